@@ -0,0 +1,8 @@
+-- Personal access tokens given with /token, sealed with GITHUB_USER_TOKEN_KEY (see tokens.rs).
+-- Only the operator's database keeps them, keyed by Telegram id, whichever tenant's chat the
+-- user linked in; tenant databases never get this table.
+CREATE TABLE IF NOT EXISTS github_tokens (
+    telegram_id BIGINT PRIMARY KEY,
+    github_login TEXT NOT NULL,
+    token TEXT NOT NULL
+)
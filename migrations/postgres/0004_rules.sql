@@ -0,0 +1,15 @@
+-- Per-chat notification rules managed with /rules, see rules.rs
+CREATE TABLE rules (
+    id BIGSERIAL PRIMARY KEY,
+    chat_id BIGINT NOT NULL,
+    rule TEXT NOT NULL,
+    created_by TEXT NOT NULL
+);
+
+-- Cards a rule has already fired for; cleared once the condition stops holding
+CREATE TABLE rule_hits (
+    rule_id BIGINT NOT NULL,
+    chat_id BIGINT NOT NULL,
+    message_id TEXT NOT NULL,
+    PRIMARY KEY (rule_id, chat_id, message_id)
+);
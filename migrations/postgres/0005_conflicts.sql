@@ -0,0 +1,2 @@
+-- 1 = GitHub reports merge conflicts with the base branch
+ALTER TABLE messages ADD COLUMN conflicts BIGINT NOT NULL DEFAULT 0;
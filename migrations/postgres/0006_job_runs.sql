@@ -0,0 +1,7 @@
+-- When a periodic per-chat job (e.g. cleanup suggestions) last ran
+CREATE TABLE job_runs (
+    chat_id BIGINT NOT NULL,
+    job TEXT NOT NULL,
+    last_run BIGINT NOT NULL,
+    PRIMARY KEY (chat_id, job)
+);
@@ -0,0 +1,2 @@
+-- JSON list of the PR's GitHub labels, shown on the card
+ALTER TABLE messages ADD COLUMN labels TEXT;
@@ -0,0 +1,11 @@
+-- The one card each chat has per PR. A claim is inserted before the card is posted and
+-- `message_id` is filled in right after, so a retry finds the claim instead of posting again.
+CREATE TABLE pr_tracking (
+    chat_id BIGINT NOT NULL,
+    repo_owner TEXT NOT NULL,
+    repo_name TEXT NOT NULL,
+    pr_number BIGINT NOT NULL,
+    message_id TEXT NOT NULL DEFAULT '',
+    claimed_at BIGINT NOT NULL,
+    PRIMARY KEY (chat_id, repo_owner, repo_name, pr_number)
+);
@@ -0,0 +1,2 @@
+-- Head commit of the PR at the last sync; a new one after a change request starts a re-review
+ALTER TABLE messages ADD COLUMN head_sha TEXT;
@@ -0,0 +1,2 @@
+-- Open review conversations, when the base branch requires resolving them before merge
+ALTER TABLE messages ADD COLUMN unresolved_threads BIGINT;
@@ -0,0 +1,10 @@
+-- When each tracked PR last changed on GitHub and was last synced, so quiet PRs can be
+-- synced less often across restarts
+CREATE TABLE pr_activity (
+    repo_owner TEXT NOT NULL,
+    repo_name TEXT NOT NULL,
+    pr_number BIGINT NOT NULL,
+    last_active BIGINT NOT NULL,
+    last_synced BIGINT NOT NULL,
+    PRIMARY KEY (repo_owner, repo_name, pr_number)
+);
@@ -0,0 +1,9 @@
+-- GitHub accounts linked to Telegram users with /link. `telegram_name` is how the user
+-- appears on cards (their @username, or first name without one).
+CREATE TABLE users (
+    telegram_id BIGINT PRIMARY KEY,
+    telegram_name TEXT NOT NULL,
+    telegram_username TEXT,
+    github_login TEXT NOT NULL UNIQUE,
+    linked_at BIGINT NOT NULL
+);
@@ -0,0 +1,3 @@
+-- JSON list of the GitHub reviewers requested on the PR at the last sync, so only new requests
+-- notify anyone. NULL until the first sync of cards created before this column existed.
+ALTER TABLE messages ADD COLUMN requested_reviewers TEXT;
@@ -0,0 +1,4 @@
+-- JSON list of people on the card (author's GitHub login or reviewers' names) who left the chat,
+-- and who took the PR over with /takeover after its author left
+ALTER TABLE messages ADD COLUMN departed TEXT;
+ALTER TABLE messages ADD COLUMN owner TEXT;
@@ -0,0 +1,5 @@
+-- Chats where reactions can't reach the bot get buttons on their cards instead
+CREATE TABLE chat_settings (
+    chat_id BIGINT PRIMARY KEY,
+    button_mode BIGINT NOT NULL DEFAULT 0
+);
@@ -0,0 +1,9 @@
+-- Per-chat reviewer pools of a repository; new PRs go to the member assigned least recently
+CREATE TABLE reviewer_pools (
+    chat_id BIGINT NOT NULL,
+    repo_owner TEXT NOT NULL,
+    repo_name TEXT NOT NULL,
+    username TEXT NOT NULL,
+    last_assigned BIGINT,
+    PRIMARY KEY (chat_id, repo_owner, repo_name, username)
+);
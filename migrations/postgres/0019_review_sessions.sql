@@ -0,0 +1,2 @@
+-- JSON list of running review sessions, timed while REVIEW_TIMEBOX_MINUTES is set
+ALTER TABLE messages ADD COLUMN review_sessions TEXT;
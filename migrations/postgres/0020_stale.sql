@@ -0,0 +1,4 @@
+-- Last GitHub activity (commits, reviews, comments) while the PR is stale, and when its
+-- author was last nudged about it
+ALTER TABLE messages ADD COLUMN stale_since BIGINT;
+ALTER TABLE messages ADD COLUMN last_nudged_at BIGINT;
@@ -0,0 +1,2 @@
+-- The pinned overview message the bot keeps current in each chat
+ALTER TABLE chat_settings ADD COLUMN dashboard_message_id TEXT;
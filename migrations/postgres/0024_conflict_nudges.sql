@@ -0,0 +1,3 @@
+-- When the PR last became conflicted, and its head commit when the author was last asked to rebase
+ALTER TABLE messages ADD COLUMN conflicted_since BIGINT;
+ALTER TABLE messages ADD COLUMN conflict_nudged_sha TEXT;
@@ -0,0 +1,3 @@
+-- Forum topic a repository's cards are posted in, and the topic each card landed in
+ALTER TABLE repositories ADD COLUMN thread_id BIGINT;
+ALTER TABLE messages ADD COLUMN thread_id BIGINT;
@@ -0,0 +1,2 @@
+-- Drafts remembered but not announced yet, see HOLD_DRAFTS
+ALTER TABLE seen_prs ADD COLUMN held BIGINT NOT NULL DEFAULT 0;
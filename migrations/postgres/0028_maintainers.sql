@@ -0,0 +1,2 @@
+-- Two-stage approval: who counts as a maintainer on the card, see [repos] maintainers
+ALTER TABLE messages ADD COLUMN maintainers TEXT;
@@ -0,0 +1,9 @@
+-- Chats routed from the config only get announcements once someone confirms the code posted there
+ALTER TABLE chat_settings ADD COLUMN verified BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE chat_settings ADD COLUMN verification_code TEXT;
+
+-- Chats that were already receiving announcements stay verified
+UPDATE chat_settings SET verified = 1 WHERE chat_id IN (SELECT chat_id FROM repositories);
+INSERT INTO chat_settings (chat_id, verified)
+    SELECT DISTINCT chat_id, 1 FROM repositories
+    WHERE chat_id NOT IN (SELECT chat_id FROM chat_settings);
@@ -0,0 +1,9 @@
+-- GitHub REST responses kept for conditional requests: unchanged ones come back as 304,
+-- which doesn't count against the rate limit
+CREATE TABLE http_cache (
+    path TEXT PRIMARY KEY,
+    etag TEXT,
+    last_modified TEXT,
+    body TEXT NOT NULL,
+    stored_at BIGINT NOT NULL
+);
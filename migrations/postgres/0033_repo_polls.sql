@@ -0,0 +1,7 @@
+-- When each repository was last polled for new PRs, so a restart resumes from there
+CREATE TABLE repo_polls (
+    owner TEXT NOT NULL,
+    name TEXT NOT NULL,
+    last_check BIGINT NOT NULL,
+    PRIMARY KEY (owner, name)
+);
@@ -0,0 +1,10 @@
+-- Per-chat ticket patterns managed with /tickets, see tickets.rs
+CREATE TABLE ticket_trackers (
+    id BIGSERIAL PRIMARY KEY,
+    chat_id BIGINT NOT NULL,
+    pattern TEXT NOT NULL,
+    url_template TEXT NOT NULL
+);
+
+-- Tickets found in the PR's title and description, linked on the card
+ALTER TABLE messages ADD COLUMN tickets TEXT;
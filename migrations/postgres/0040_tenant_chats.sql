@@ -0,0 +1,6 @@
+-- Which tenant serves a chat, in the operator's database; unbound chats are the operator's.
+-- See tenants.rs
+CREATE TABLE tenant_chats (
+    chat_id BIGINT PRIMARY KEY,
+    tenant_id TEXT NOT NULL
+);
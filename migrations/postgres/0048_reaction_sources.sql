@@ -0,0 +1,2 @@
+-- Where an entry came from: 'telegram' (reactions, commands, buttons) or 'github' (a submitted review)
+ALTER TABLE reactions ADD COLUMN source TEXT NOT NULL DEFAULT 'telegram';
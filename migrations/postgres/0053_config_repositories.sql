@@ -0,0 +1,4 @@
+-- 1 = subscribed because the configuration routes the repository to the chat (REPOSITORIES,
+-- REPO_CHATS). Dropping a route from the configuration only removes these, never what a chat
+-- tracked itself. Subscriptions from before can't be told apart and count as the chat's own.
+ALTER TABLE repositories ADD COLUMN from_config BIGINT NOT NULL DEFAULT 0;
@@ -0,0 +1,86 @@
+-- Baseline schema. Every statement is IF NOT EXISTS so databases created before
+-- versioned migrations (already upgraded by Db::upgrade_legacy) adopt it unchanged.
+
+CREATE TABLE IF NOT EXISTS repositories (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    chat_id BIGINT NOT NULL,
+    owner TEXT NOT NULL,
+    name TEXT NOT NULL,
+    UNIQUE (chat_id, owner, name)
+);
+
+CREATE TABLE IF NOT EXISTS messages (
+    message_id TEXT NOT NULL,
+    chat_id BIGINT NOT NULL,
+    pr_url TEXT NOT NULL,
+    title TEXT NOT NULL,
+    author TEXT NOT NULL,
+    repo_owner TEXT NOT NULL,
+    repo_name TEXT NOT NULL,
+    pr_number BIGINT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'awaiting_review', -- see lifecycle::PrStatus
+    review_round BIGINT NOT NULL DEFAULT 0,
+    status_since BIGINT NOT NULL DEFAULT 0,
+    snoozed_until BIGINT,
+    gates TEXT,
+    PRIMARY KEY (message_id, chat_id)
+);
+
+CREATE TABLE IF NOT EXISTS reactions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    message_id TEXT NOT NULL,
+    chat_id BIGINT NOT NULL,
+    username TEXT NOT NULL,
+    reaction_type TEXT NOT NULL, -- 'reviewer', 'approval', 'changes_requested', 'comment'
+    UNIQUE (message_id, chat_id, username, reaction_type)
+);
+
+-- PRs already announced, to avoid reposting after a restart. key: chat_id:owner/repo#number
+CREATE TABLE IF NOT EXISTS seen_prs (
+    key TEXT PRIMARY KEY,
+    seen_at BIGINT NOT NULL
+);
+
+-- Append-only log of notable things that happened to tracked PRs
+CREATE TABLE IF NOT EXISTS events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    chat_id BIGINT NOT NULL,
+    message_id TEXT NOT NULL,
+    repo_owner TEXT NOT NULL,
+    repo_name TEXT NOT NULL,
+    pr_number BIGINT NOT NULL,
+    username TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    detail TEXT NOT NULL,
+    created_at BIGINT NOT NULL
+);
+
+-- Multi-PR cards for bursts of PRs opened by a single author
+CREATE TABLE IF NOT EXISTS pr_groups (
+    message_id TEXT NOT NULL,
+    chat_id BIGINT NOT NULL,
+    author TEXT NOT NULL,
+    created_at BIGINT NOT NULL,
+    PRIMARY KEY (message_id, chat_id)
+);
+
+CREATE TABLE IF NOT EXISTS group_items (
+    message_id TEXT NOT NULL,
+    chat_id BIGINT NOT NULL,
+    pr_url TEXT NOT NULL,
+    title TEXT NOT NULL,
+    repo_owner TEXT NOT NULL,
+    repo_name TEXT NOT NULL,
+    pr_number BIGINT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'awaiting_review',
+    PRIMARY KEY (message_id, chat_id, repo_owner, repo_name, pr_number)
+);
+
+-- Required checks rendered as per-gate badges on a repository's cards
+CREATE TABLE IF NOT EXISTS check_gates (
+    owner TEXT NOT NULL,
+    name TEXT NOT NULL,
+    check_name TEXT NOT NULL,
+    critical BIGINT NOT NULL DEFAULT 0, -- 1 = notify the card when it fails
+    PRIMARY KEY (owner, name, check_name)
+);
@@ -0,0 +1,2 @@
+-- Security-sensitive PRs need a security reviewer's approval before they are ready to merge
+ALTER TABLE messages ADD COLUMN security BIGINT NOT NULL DEFAULT 0;
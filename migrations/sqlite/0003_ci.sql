@@ -0,0 +1,2 @@
+-- Summary of every CI check on the PR's head commit, as JSON
+ALTER TABLE messages ADD COLUMN ci TEXT;
@@ -0,0 +1,18 @@
+-- Merged PRs, kept for statistics after their cards are gone. `source` is empty for this
+-- instance's own merges and the peer's name for entries pulled from a federation feed.
+CREATE TABLE merge_archive (
+    source TEXT NOT NULL DEFAULT '',
+    chat_id BIGINT NOT NULL,
+    repo_owner TEXT NOT NULL,
+    repo_name TEXT NOT NULL,
+    pr_number BIGINT NOT NULL,
+    title TEXT NOT NULL,
+    author TEXT NOT NULL,
+    opened_at BIGINT NOT NULL,
+    merged_at BIGINT NOT NULL,
+    review_rounds BIGINT NOT NULL,
+    approvals BIGINT NOT NULL,
+    PRIMARY KEY (source, chat_id, repo_owner, repo_name, pr_number)
+);
+
+CREATE INDEX merge_archive_merged_at ON merge_archive (merged_at);
@@ -0,0 +1,2 @@
+-- When the chat was last reminded that the PR is waiting for a reviewer or an approval
+ALTER TABLE messages ADD COLUMN last_reminded_at BIGINT;
@@ -0,0 +1,2 @@
+-- The weekly review leaderboard scans a chat's recent review events
+CREATE INDEX events_chat_created ON events (chat_id, created_at);
@@ -0,0 +1,7 @@
+-- Leads get a morning DM triaging their chat's PRs
+CREATE TABLE chat_leads (
+    chat_id BIGINT NOT NULL,
+    user_id BIGINT NOT NULL,
+    name TEXT NOT NULL,
+    PRIMARY KEY (chat_id, user_id)
+);
@@ -0,0 +1,8 @@
+-- Named /list filters, per user per chat
+CREATE TABLE saved_filters (
+    chat_id BIGINT NOT NULL,
+    user_id BIGINT NOT NULL,
+    name TEXT NOT NULL,
+    query TEXT NOT NULL,
+    PRIMARY KEY (chat_id, user_id, name)
+);
@@ -0,0 +1,7 @@
+-- GitHub search queries whose new matches a chat gets announced, managed with /search
+CREATE TABLE searches (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    chat_id BIGINT NOT NULL,
+    query TEXT NOT NULL,
+    created_by TEXT NOT NULL
+);
@@ -0,0 +1,6 @@
+-- Telegram updates already handled, claimed before handling so a redelivered one is skipped;
+-- the highest id is where long polling resumes after a restart
+CREATE TABLE telegram_updates (
+    update_id BIGINT PRIMARY KEY,
+    processed_at BIGINT NOT NULL
+);
@@ -0,0 +1,2 @@
+-- Approvals that came with comments, and whether the author acknowledged them
+ALTER TABLE messages ADD COLUMN suggestions TEXT;
@@ -0,0 +1,12 @@
+-- What the bot's GitHub access allows on each repository, probed when it's tracked:
+-- 1 allowed, 0 denied, NULL when it couldn't be told
+CREATE TABLE repo_capabilities (
+    owner TEXT NOT NULL,
+    name TEXT NOT NULL,
+    list_prs BIGINT,
+    read_reviews BIGINT,
+    read_checks BIGINT,
+    write_labels BIGINT,
+    checked_at BIGINT NOT NULL,
+    PRIMARY KEY (owner, name)
+);
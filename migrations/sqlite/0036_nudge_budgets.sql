@@ -0,0 +1,21 @@
+-- A chat's own NUDGE_BUDGET; NULL follows the configured one
+ALTER TABLE chat_settings ADD COLUMN nudge_budget BIGINT;
+
+-- Reminder mentions each user got in a chat on a UTC day (YYYY-MM-DD)
+CREATE TABLE nudges (
+    chat_id BIGINT NOT NULL,
+    username TEXT NOT NULL,
+    day TEXT NOT NULL,
+    sent BIGINT NOT NULL,
+    PRIMARY KEY (chat_id, username, day)
+);
+
+-- Reminders past a user's budget, posted together once their day is over
+CREATE TABLE held_nudges (
+    chat_id BIGINT NOT NULL,
+    username TEXT NOT NULL,
+    day TEXT NOT NULL,
+    line TEXT NOT NULL,
+    held_at BIGINT NOT NULL
+);
+CREATE INDEX held_nudges_day ON held_nudges (day);
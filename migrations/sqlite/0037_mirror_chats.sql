@@ -0,0 +1,15 @@
+-- Chats getting read-only copies of another chat's cards, replaced from MIRROR_CHATS on startup
+CREATE TABLE mirror_chats (
+    source_chat_id BIGINT NOT NULL,
+    chat_id BIGINT NOT NULL,
+    PRIMARY KEY (source_chat_id, chat_id)
+);
+
+-- The copy of a card in each mirror chat
+CREATE TABLE mirror_cards (
+    source_chat_id BIGINT NOT NULL,
+    source_message_id TEXT NOT NULL,
+    chat_id BIGINT NOT NULL,
+    message_id TEXT NOT NULL,
+    PRIMARY KEY (source_chat_id, source_message_id, chat_id)
+);
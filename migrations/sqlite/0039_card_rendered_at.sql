@@ -0,0 +1,2 @@
+-- When the card's text was last rendered, for re-rendering elapsed times, see rerender.rs
+ALTER TABLE messages ADD COLUMN rendered_at BIGINT;
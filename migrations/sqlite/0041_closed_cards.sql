@@ -0,0 +1,11 @@
+-- A chat's own CLOSED_CARDS, see /onclose; NULL follows the repository's or the configured one
+ALTER TABLE chat_settings ADD COLUMN closed_cards TEXT;
+
+-- Cards of closed PRs kept for a while before they're deleted, and mirror copies of them
+CREATE TABLE kept_cards (
+    chat_id BIGINT NOT NULL,
+    message_id TEXT NOT NULL,
+    delete_at BIGINT NOT NULL,
+    PRIMARY KEY (chat_id, message_id)
+);
+CREATE INDEX kept_cards_delete_at ON kept_cards (delete_at);
@@ -0,0 +1,3 @@
+-- Cards of GitHub issues, with TRACK_ISSUES, share the machinery of PR cards
+ALTER TABLE messages ADD COLUMN item_type TEXT NOT NULL DEFAULT 'pr';
+ALTER TABLE messages ADD COLUMN assignees TEXT; -- JSON list of GitHub logins, issues only
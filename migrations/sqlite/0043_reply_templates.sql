@@ -0,0 +1,8 @@
+-- A chat's own bodies for the canned GitHub comments of /nitpick, /needs-tests and /needs-docs;
+-- an empty body posts nothing
+CREATE TABLE reply_templates (
+    chat_id BIGINT NOT NULL,
+    name TEXT NOT NULL,
+    body TEXT NOT NULL,
+    PRIMARY KEY (chat_id, name)
+);
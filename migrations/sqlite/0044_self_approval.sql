@@ -0,0 +1,2 @@
+-- 1 = authors may approve their own PRs in the chat, for solo-maintainer repositories; see /selfapproval
+ALTER TABLE chat_settings ADD COLUMN self_approval BIGINT NOT NULL DEFAULT 0;
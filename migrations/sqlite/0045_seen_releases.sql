@@ -0,0 +1,8 @@
+-- Releases already announced in a chat with ANNOUNCE_RELEASES, so none is announced twice
+CREATE TABLE seen_releases (
+    chat_id BIGINT NOT NULL,
+    repo TEXT NOT NULL, -- owner/repo
+    tag TEXT NOT NULL,
+    seen_at BIGINT NOT NULL,
+    PRIMARY KEY (chat_id, repo, tag)
+);
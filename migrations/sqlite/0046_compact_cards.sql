@@ -0,0 +1,4 @@
+-- Cards shown as one line after COMPACT_AFTER_DAYS without changes, see compact.rs
+ALTER TABLE messages ADD COLUMN settled_key TEXT; -- the card's state as last seen by the compaction pass
+ALTER TABLE messages ADD COLUMN settled_since BIGINT; -- since when that state is unchanged
+ALTER TABLE messages ADD COLUMN compact BIGINT NOT NULL DEFAULT 0; -- 1 = compact while the state still matches
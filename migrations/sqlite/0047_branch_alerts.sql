@@ -0,0 +1,9 @@
+-- Open alerts about failing checks on a tracked repository's default branch, with MAIN_BRANCH_ALERTS
+CREATE TABLE branch_alerts (
+    chat_id BIGINT NOT NULL,
+    repo TEXT NOT NULL, -- owner/repo
+    sha TEXT NOT NULL, -- the failing commit the alert shows
+    message_id BIGINT NOT NULL,
+    failing_since BIGINT NOT NULL,
+    PRIMARY KEY (chat_id, repo)
+);
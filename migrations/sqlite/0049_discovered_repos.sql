@@ -0,0 +1,7 @@
+-- Repositories of GITHUB_ORGS already found and tracked, so one untracked later isn't added again
+CREATE TABLE discovered_repos (
+    owner TEXT NOT NULL,
+    name TEXT NOT NULL,
+    discovered_at BIGINT NOT NULL,
+    PRIMARY KEY (owner, name)
+);
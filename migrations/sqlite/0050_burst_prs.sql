@@ -0,0 +1,10 @@
+-- New PRs waiting out PR_GROUP_HOLD_MINUTES for more by the same author
+CREATE TABLE burst_prs (
+    chat_id BIGINT NOT NULL,
+    author TEXT NOT NULL,
+    repo_owner TEXT NOT NULL,
+    repo_name TEXT NOT NULL,
+    pr_number BIGINT NOT NULL,
+    opened_at BIGINT NOT NULL,
+    PRIMARY KEY (chat_id, repo_owner, repo_name, pr_number)
+);
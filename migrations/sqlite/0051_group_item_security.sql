@@ -0,0 +1,2 @@
+-- Grouped PRs that need a security review, badged like their own cards would be
+ALTER TABLE group_items ADD COLUMN security BIGINT NOT NULL DEFAULT 0;
@@ -0,0 +1,18 @@
+-- Every reviewer assignment of the round-robin, which picks from this history
+CREATE TABLE reviewer_assignments (
+    chat_id BIGINT NOT NULL,
+    repo_owner TEXT NOT NULL,
+    repo_name TEXT NOT NULL,
+    pr_number BIGINT NOT NULL,
+    username TEXT NOT NULL,
+    assigned_at BIGINT NOT NULL,
+    PRIMARY KEY (chat_id, repo_owner, repo_name, pr_number, username)
+);
+CREATE INDEX reviewer_assignments_by_user ON reviewer_assignments (chat_id, username, assigned_at);
+
+-- Each member's last assignment carries over, under PR 0, so the rotation goes on where it was
+INSERT INTO reviewer_assignments (chat_id, repo_owner, repo_name, pr_number, username, assigned_at)
+SELECT chat_id, repo_owner, repo_name, 0, username, last_assigned
+FROM reviewer_pools WHERE last_assigned IS NOT NULL;
+
+ALTER TABLE reviewer_pools DROP COLUMN last_assigned;
@@ -0,0 +1,101 @@
+use crate::sender::{Retried, Throttle, GROUP_SEND_INTERVAL};
+use chrono::Utc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use teloxide::prelude::*;
+use teloxide::types::{MessageId, ThreadId};
+use tokio::sync::mpsc;
+use tokio::time::{timeout_at, Duration, Instant};
+use tracing::error;
+
+/// Lines arriving within this window after the first one are sent as a single message.
+const BATCH_WINDOW: Duration = Duration::from_secs(10);
+/// Lines beyond this backlog are dropped (and counted) instead of delaying the bot.
+const QUEUE_CAPACITY: usize = 500;
+/// Telegram caps messages at 4096 characters.
+const MAX_MESSAGE_LEN: usize = 4000;
+
+/// Mirrors what the bot does as compact lines into an audit chat or forum topic.
+/// The default value is disabled and ignores everything it's given.
+#[derive(Clone, Default)]
+pub struct Audit {
+    tx: Option<mpsc::Sender<String>>,
+    dropped: Arc<AtomicUsize>,
+}
+
+impl Audit {
+    /// Starts the background sender for `chat_id`, posting into `thread_id` when it's a forum topic.
+    pub fn spawn(bot: Bot, chat_id: i64, thread_id: Option<i32>) -> Self {
+        let (tx, rx) = mpsc::channel(QUEUE_CAPACITY);
+        let dropped = Arc::new(AtomicUsize::new(0));
+        tokio::spawn(run(bot, ChatId(chat_id), thread_id, rx, dropped.clone()));
+        Self {
+            tx: Some(tx),
+            dropped,
+        }
+    }
+
+    /// Queues a line without waiting; it's dropped when the backlog is full.
+    pub fn record(&self, line: impl Into<String>) {
+        let Some(tx) = &self.tx else {
+            return;
+        };
+        let line = format!("{} {}", Utc::now().format("%H:%M:%S"), line.into());
+        if tx.try_send(line).is_err() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// How a card is referred to in audit lines, e.g. "owner/repo#12 in -1001234567890".
+pub fn card(repo: &str, pr_number: impl std::fmt::Display, chat_id: i64) -> String {
+    format!("{}#{} in {}", repo, pr_number, chat_id)
+}
+
+async fn run(
+    bot: Bot,
+    chat_id: ChatId,
+    thread_id: Option<i32>,
+    mut rx: mpsc::Receiver<String>,
+    dropped: Arc<AtomicUsize>,
+) {
+    let mut throttle = Throttle::new(GROUP_SEND_INTERVAL);
+    while let Some(first) = rx.recv().await {
+        let mut lines = vec![first];
+        let deadline = Instant::now() + BATCH_WINDOW;
+        while let Ok(Some(line)) = timeout_at(deadline, rx.recv()).await {
+            lines.push(line);
+        }
+        let lost = dropped.swap(0, Ordering::Relaxed);
+        if lost > 0 {
+            lines.push(format!("… {} line(s) dropped", lost));
+        }
+
+        for text in pack(&lines) {
+            throttle.wait().await;
+            let mut request = bot.send_message(chat_id, text).disable_notification(true);
+            if let Some(thread_id) = thread_id {
+                request = request.message_thread_id(ThreadId(MessageId(thread_id)));
+            }
+            if let Err(e) = request.retried().await {
+                error!("Failed to send audit log: {}", e);
+            }
+        }
+    }
+}
+
+/// Joins lines into as few messages as fit Telegram's length limit.
+fn pack(lines: &[String]) -> Vec<String> {
+    let mut messages: Vec<String> = Vec::new();
+    for line in lines {
+        let line: String = line.chars().take(MAX_MESSAGE_LEN).collect();
+        match messages.last_mut() {
+            Some(last) if last.len() + 1 + line.len() <= MAX_MESSAGE_LEN => {
+                last.push('\n');
+                last.push_str(&line);
+            }
+            _ => messages.push(line),
+        }
+    }
+    messages
+}
@@ -0,0 +1,245 @@
+use crate::bulk;
+use crate::checks::{self, CiSummary};
+use crate::db::{BranchAlert, RepoCapabilities};
+use crate::handlers;
+use crate::monitor::Monitor;
+use crate::onboarding;
+use crate::sender::Retried;
+use chrono::Utc;
+use octocrab::models::repos::RepoCommit;
+use std::collections::HashMap;
+use teloxide::prelude::*;
+use teloxide::types::{MessageId, ParseMode};
+use teloxide::utils::html;
+use tracing::{error, info};
+
+const CHECK_INTERVAL: tokio::time::Duration = tokio::time::Duration::from_secs(10 * 60);
+
+/// Watches the default branch of every tracked repository with `MAIN_BRANCH_ALERTS`: once checks
+/// fail on its latest commit, an alert is posted in the repository's chats, kept up to date while
+/// later commits still fail, and edited into an all-clear once a commit passes again.
+pub async fn run(monitor: Monitor) {
+    // Heads whose checks all finished, by repository; they aren't looked at again
+    let mut settled: HashMap<(String, String), String> = HashMap::new();
+    loop {
+        check(&monitor, &mut settled).await;
+        tokio::time::sleep(CHECK_INTERVAL).await;
+    }
+}
+
+async fn check(monitor: &Monitor, settled: &mut HashMap<(String, String), String>) {
+    if !monitor.config().main_branch_alerts || monitor.github.sync_paused_until().await.is_some() {
+        return;
+    }
+    let subscriptions = monitor.state.get_subscriptions().await.unwrap_or_default();
+    for ((owner, repo), chats) in subscriptions {
+        if monitor.is_ignored(&owner, &repo)
+            || RepoCapabilities::denies(
+                onboarding::of(&monitor.state, &owner, &repo)
+                    .await
+                    .read_checks,
+            )
+        {
+            continue;
+        }
+        let head = match monitor.github.get_default_branch_head(&owner, &repo).await {
+            Ok(Some(head)) => head,
+            Ok(None) => continue,
+            Err(e) => {
+                error!(
+                    "Failed to fetch the default branch of {}/{}: {}",
+                    owner, repo, e
+                );
+                continue;
+            }
+        };
+        let key = (owner.clone(), repo.clone());
+        if settled.get(&key) == Some(&head.sha) {
+            continue;
+        }
+        let summary = match checks::summarize(&monitor.github, &owner, &repo, &head.sha).await {
+            Ok(summary) => summary,
+            Err(e) => {
+                error!(
+                    "Failed to fetch the checks of {}/{}@{}: {}",
+                    owner, repo, head.sha, e
+                );
+                continue;
+            }
+        };
+        // A commit without checks says nothing about the branch
+        let Some(summary) = summary else {
+            settled.insert(key, head.sha.clone());
+            continue;
+        };
+        if summary.pending == 0 {
+            settled.insert(key, head.sha.clone());
+        }
+        for &chat_id in &chats {
+            if summary.failed > 0 {
+                alert(monitor, chat_id, &owner, &repo, &head, &summary).await;
+            } else if summary.pending == 0 {
+                clear(monitor, chat_id, &owner, &repo, &head).await;
+            }
+        }
+    }
+}
+
+/// Posts the alert, or moves an open one to the newest failing commit.
+async fn alert(
+    monitor: &Monitor,
+    chat_id: i64,
+    owner: &str,
+    repo: &str,
+    head: &RepoCommit,
+    summary: &CiSummary,
+) {
+    let full_name = format!("{}/{}", owner, repo);
+    let open = match monitor.state.get_branch_alert(chat_id, &full_name).await {
+        Ok(open) => open,
+        Err(e) => {
+            error!(
+                "Failed to load the branch alert of {} in {}: {}",
+                full_name, chat_id, e
+            );
+            return;
+        }
+    };
+    let failing_since = open
+        .as_ref()
+        .map_or(Utc::now().timestamp(), |a| a.failing_since);
+    let text = format!(
+        "🔥 <b>{}</b>: checks fail on the default branch\n{}\n\n{}",
+        html::escape(&full_name),
+        summary.render(),
+        describe_commit(head)
+    );
+
+    let message_id = match open {
+        Some(open) => {
+            let edited = monitor
+                .bot
+                .edit_message_text(ChatId(chat_id), MessageId(open.message_id as i32), &text)
+                .parse_mode(ParseMode::Html)
+                .link_preview_options(handlers::no_link_preview())
+                .retried()
+                .await;
+            if let Err(e) = edited {
+                // Unchanged counts of the same commit leave the text as it was
+                if open.sha != head.sha {
+                    error!(
+                        "Failed to update the branch alert of {} in {}: {}",
+                        full_name, chat_id, e
+                    );
+                }
+            }
+            open.message_id
+        }
+        None => {
+            info!(
+                "Alerting {} about the default branch of {}",
+                chat_id, full_name
+            );
+            match monitor
+                .bot
+                .send_message(ChatId(chat_id), &text)
+                .parse_mode(ParseMode::Html)
+                .link_preview_options(handlers::no_link_preview())
+                .retried()
+                .await
+            {
+                Ok(sent) => {
+                    monitor.state.audit(format!(
+                        "notified {}: default branch of {} failing",
+                        chat_id, full_name
+                    ));
+                    sent.id.0 as i64
+                }
+                Err(e) => {
+                    error!(
+                        "Failed to send the branch alert of {} to {}: {}",
+                        full_name, chat_id, e
+                    );
+                    return;
+                }
+            }
+        }
+    };
+    let alert = BranchAlert {
+        sha: head.sha.clone(),
+        message_id,
+        failing_since,
+    };
+    if let Err(e) = monitor
+        .state
+        .set_branch_alert(chat_id, &full_name, &alert)
+        .await
+    {
+        error!(
+            "Failed to save the branch alert of {} in {}: {}",
+            full_name, chat_id, e
+        );
+    }
+}
+
+/// Edits an open alert into an all-clear once the branch passes again.
+async fn clear(monitor: &Monitor, chat_id: i64, owner: &str, repo: &str, head: &RepoCommit) {
+    let full_name = format!("{}/{}", owner, repo);
+    let Ok(Some(open)) = monitor.state.get_branch_alert(chat_id, &full_name).await else {
+        return;
+    };
+    let text = format!(
+        "✅ <b>{}</b>: the default branch passes again, after failing for {}\n\n{}",
+        html::escape(&full_name),
+        bulk::format_duration(Utc::now().timestamp() - open.failing_since),
+        describe_commit(head)
+    );
+    info!(
+        "The default branch of {} passes again in {}",
+        full_name, chat_id
+    );
+    if let Err(e) = monitor
+        .bot
+        .edit_message_text(ChatId(chat_id), MessageId(open.message_id as i32), text)
+        .parse_mode(ParseMode::Html)
+        .link_preview_options(handlers::no_link_preview())
+        .retried()
+        .await
+    {
+        error!(
+            "Failed to clear the branch alert of {} in {}: {}",
+            full_name, chat_id, e
+        );
+    }
+    if let Err(e) = monitor.state.remove_branch_alert(chat_id, &full_name).await {
+        error!(
+            "Failed to remove the branch alert of {} in {}: {}",
+            full_name, chat_id, e
+        );
+        return;
+    }
+    monitor.state.audit(format!(
+        "edited {}: default branch of {} passes again",
+        chat_id, full_name
+    ));
+}
+
+/// "<a>1a2b3c4</a> Fix the build — alice", with the first line of the commit message.
+fn describe_commit(commit: &RepoCommit) -> String {
+    let title = commit.commit.message.lines().next().unwrap_or_default();
+    let mut text = format!(
+        "<a href=\"{}\">{}</a> {}",
+        commit.html_url,
+        &commit.sha[..commit.sha.len().min(7)],
+        html::escape(title)
+    );
+    let author = commit
+        .author
+        .as_ref()
+        .map(|a| a.login.clone())
+        .or_else(|| commit.commit.author.as_ref().map(|a| a.name.clone()));
+    if let Some(author) = author {
+        text.push_str(&format!(" — {}", html::escape(&author)));
+    }
+    text
+}
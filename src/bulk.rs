@@ -0,0 +1,311 @@
+use crate::audit;
+use crate::db::{ArchivedMerge, Event};
+use crate::handlers::no_link_preview;
+use crate::lifecycle::{PrEvent, PrStatus};
+use crate::monitor::Monitor;
+use crate::sender::{Retried, Throttle, GROUP_SEND_INTERVAL};
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use octocrab::models::pulls::ReviewState;
+use std::collections::HashSet;
+use teloxide::prelude::*;
+use teloxide::types::{MessageId, ParseMode};
+use tracing::error;
+
+/// Snoozes every active card of `owner/repo` in the chat until `until`.
+pub async fn snooze_all(
+    monitor: &Monitor,
+    chat_id: ChatId,
+    owner: &str,
+    repo: &str,
+    until: DateTime<Utc>,
+) -> usize {
+    let msgs = monitor
+        .state
+        .get_active_messages_for_repo(owner, repo)
+        .await
+        .unwrap_or_default();
+
+    let mut throttle = Throttle::new(GROUP_SEND_INTERVAL);
+    let mut count = 0;
+    for m in msgs.into_iter().filter(|m| m.chat_id == chat_id.0) {
+        let Ok(Some(mut data)) = monitor
+            .state
+            .get_pr_data(m.message_id.clone(), m.chat_id)
+            .await
+        else {
+            continue;
+        };
+
+        data.apply(PrEvent::Snoozed);
+        data.snoozed_until = Some(until.timestamp());
+        if let Err(e) = monitor
+            .state
+            .update_pr_data(m.message_id.clone(), data.clone())
+            .await
+        {
+            error!("Failed to snooze {}#{}: {}", data.repo, data.pr_number, e);
+            continue;
+        }
+
+        throttle.wait().await;
+        crate::handlers::edit_card(
+            &monitor.bot,
+            &monitor.state,
+            chat_id,
+            MessageId(m.message_id.parse().unwrap_or(0)),
+            &data,
+        )
+        .await
+        .ok();
+        monitor.state.audit(format!(
+            "edited {}: /snoozeall",
+            audit::card(&data.repo, data.pr_number, chat_id.0)
+        ));
+        count += 1;
+    }
+    count
+}
+
+/// Re-syncs every active card of the chat with GitHub.
+pub async fn refresh_all(monitor: &Monitor, chat_id: ChatId) -> usize {
+    let msgs = monitor
+        .state
+        .get_all_active_messages()
+        .await
+        .unwrap_or_default();
+
+    let mut throttle = Throttle::new(GROUP_SEND_INTERVAL);
+    let mut count = 0;
+    for m in msgs.into_iter().filter(|m| m.chat_id == chat_id.0) {
+        throttle.wait().await;
+        monitor.sync_message(m).await;
+        count += 1;
+    }
+    count
+}
+
+/// Compacts cards marked merged before `cutoff` into a one-line summary and stops tracking them.
+pub async fn archive_merged(monitor: &Monitor, chat_id: ChatId, cutoff: DateTime<Utc>) -> usize {
+    let msgs = monitor
+        .state
+        .get_chat_messages(chat_id.0)
+        .await
+        .unwrap_or_default();
+
+    let mut throttle = Throttle::new(GROUP_SEND_INTERVAL);
+    let mut count = 0;
+    for m in msgs {
+        if PrStatus::parse(&m.status) != PrStatus::Merged || m.status_since >= cutoff.timestamp() {
+            continue;
+        }
+
+        throttle.wait().await;
+        let text = format!(
+            "💯 <s>{}/{}#{} {}</s> — merged, archived",
+            m.repo_owner, m.repo_name, m.pr_number, m.title
+        );
+        monitor
+            .bot
+            .edit_message_text(chat_id, MessageId(m.message_id.parse().unwrap_or(0)), text)
+            .parse_mode(ParseMode::Html)
+            .link_preview_options(no_link_preview())
+            .retried()
+            .await
+            .ok();
+        monitor.state.audit(format!(
+            "archived {}: /archiveall",
+            audit::card(
+                &format!("{}/{}", m.repo_owner, m.repo_name),
+                m.pr_number,
+                chat_id.0
+            )
+        ));
+
+        match monitor.state.remove_message(&m.message_id, m.chat_id).await {
+            Ok(()) => count += 1,
+            Err(e) => error!("Failed to remove archived message from DB: {}", e),
+        }
+    }
+    count
+}
+
+/// Imports the history of `owner/repo`'s PRs opened since `since` into the chat's events
+/// (opened, reviewed, approved, merged) and merge archive, without touching any card.
+/// Returns how many PRs and events were imported.
+pub async fn backfill(
+    monitor: &Monitor,
+    chat_id: ChatId,
+    owner: &str,
+    repo: &str,
+    since: DateTime<Utc>,
+) -> Result<(usize, usize)> {
+    let prs = monitor
+        .github
+        .get_prs_opened_since(owner, repo, since)
+        .await?;
+    let identities = monitor
+        .state
+        .get_github_identities()
+        .await
+        .unwrap_or_default();
+    // Linked users count under their Telegram name, like their live events
+    let name = |login: &str| {
+        identities
+            .get(&login.to_lowercase())
+            .cloned()
+            .unwrap_or_else(|| login.to_string())
+    };
+
+    let mut events = Vec::new();
+    let event = |pr_number: u64, username: String, kind: &str, at: DateTime<Utc>| Event {
+        chat_id: chat_id.0,
+        message_id: String::new(),
+        repo_owner: owner.to_string(),
+        repo_name: repo.to_string(),
+        pr_number: pr_number as i64,
+        username,
+        kind: kind.to_string(),
+        detail: String::new(),
+        created_at: at.timestamp(),
+    };
+    for pr in &prs {
+        let author = pr
+            .user
+            .as_ref()
+            .map(|u| u.login.clone())
+            .unwrap_or_default();
+        if let Some(opened_at) = pr.created_at {
+            events.push(event(pr.number, author.clone(), "opened", opened_at));
+        }
+
+        let reviews = match monitor.github.get_pr_reviews(owner, repo, pr.number).await {
+            Ok(reviews) => reviews,
+            Err(e) => {
+                error!(
+                    "Failed to fetch reviews of {}/{}#{}: {}",
+                    owner, repo, pr.number, e
+                );
+                Vec::new()
+            }
+        };
+        let mut approvers = HashSet::new();
+        for review in reviews {
+            let (Some(user), Some(at)) = (review.user, review.submitted_at) else {
+                continue;
+            };
+            let kind = match review.state {
+                Some(ReviewState::Approved) => {
+                    approvers.insert(user.login.clone());
+                    "approved"
+                }
+                Some(ReviewState::ChangesRequested | ReviewState::Commented) => "reviewed",
+                _ => continue,
+            };
+            events.push(event(pr.number, name(&user.login), kind, at));
+        }
+
+        if let Some(merged_at) = pr.merged_at {
+            events.push(event(pr.number, author.clone(), "merged", merged_at));
+            let entry = ArchivedMerge {
+                source: String::new(),
+                chat_id: chat_id.0,
+                repo_owner: owner.to_string(),
+                repo_name: repo.to_string(),
+                pr_number: pr.number as i64,
+                title: pr.title.clone().unwrap_or_default(),
+                author,
+                opened_at: pr.created_at.map_or(0, |t| t.timestamp()),
+                merged_at: merged_at.timestamp(),
+                review_rounds: 1,
+                approvals: approvers.len() as i64,
+            };
+            if let Err(e) = monitor.state.archive_merge(&entry).await {
+                error!("Failed to archive merge: {}", e);
+            }
+        }
+    }
+
+    monitor
+        .state
+        .import_events(chat_id.0, owner, repo, &events)
+        .await?;
+    monitor.state.audit(format!(
+        "backfilled {}/{} in {}: {} PRs, {} events",
+        owner,
+        repo,
+        chat_id,
+        prs.len(),
+        events.len()
+    ));
+    Ok((prs.len(), events.len()))
+}
+
+/// Renders seconds as whole hours, or days from two days on.
+pub fn format_duration(secs: i64) -> String {
+    let hours = secs.max(0) / 3600;
+    if hours >= 48 {
+        format!("{}d", hours / 24)
+    } else {
+        format!("{}h", hours)
+    }
+}
+
+/// The longest duration `parse_duration` accepts. Callers add it to or subtract it from the
+/// current time, which panics far short of what `Duration` itself can hold.
+const MAX_DURATION_DAYS: i64 = 10 * 365;
+
+/// Parses durations like `30m`, `12h`, `2d` or `1w`. Negative values and ones over ten years
+/// are rejected.
+pub fn parse_duration(s: &str) -> Option<Duration> {
+    let unit = s.chars().last()?;
+    let value: i64 = s[..s.len() - unit.len_utf8()].parse().ok()?;
+    if value < 0 {
+        return None;
+    }
+    let duration = match unit {
+        'm' => Duration::try_minutes(value),
+        'h' => Duration::try_hours(value),
+        'd' => Duration::try_days(value),
+        'w' => Duration::try_weeks(value),
+        _ => None,
+    }?;
+    (duration <= Duration::days(MAX_DURATION_DAYS)).then_some(duration)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations_parse_in_every_unit() {
+        assert_eq!(parse_duration("30m"), Some(Duration::minutes(30)));
+        assert_eq!(parse_duration("12h"), Some(Duration::hours(12)));
+        assert_eq!(parse_duration("2d"), Some(Duration::days(2)));
+        assert_eq!(parse_duration("1w"), Some(Duration::weeks(1)));
+        assert_eq!(parse_duration("0d"), Some(Duration::zero()));
+        assert_eq!(parse_duration("0m"), Some(Duration::zero()));
+    }
+
+    #[test]
+    fn out_of_range_durations_are_rejected() {
+        assert_eq!(parse_duration("3650d"), Some(Duration::days(3650)));
+        assert_eq!(parse_duration("3651d"), None);
+        assert_eq!(parse_duration("522w"), None);
+        // Would panic once added to the current time
+        assert_eq!(parse_duration("15000000000w"), None);
+        assert_eq!(parse_duration("16000000000w"), None);
+        assert_eq!(parse_duration("9223372036854775807m"), None);
+        assert_eq!(parse_duration("9223372036854775808d"), None);
+    }
+
+    #[test]
+    fn garbage_is_rejected() {
+        for input in [
+            "", "d", "5", "5x", "5D", "-5d", "5 d", " 5d", "5.5h", "1d2h", "d5", "5dd", "5é", "é",
+            "５d",
+        ] {
+            assert_eq!(parse_duration(input), None, "{:?}", input);
+        }
+    }
+}
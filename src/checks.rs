@@ -0,0 +1,173 @@
+use crate::db::CheckGate;
+use crate::github::GithubClient;
+use anyhow::Result;
+use octocrab::models::checks::CheckRun;
+use octocrab::models::StatusState;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Latest result of a configured check on the PR's head commit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GateState {
+    Pending,
+    Passed,
+    Failed,
+}
+
+impl GateState {
+    /// Maps a check-run conclusion; runs without one are still queued or in progress.
+    fn from_conclusion(conclusion: Option<&str>) -> Self {
+        match conclusion {
+            None => Self::Pending,
+            Some("success" | "neutral" | "skipped") => Self::Passed,
+            Some(_) => Self::Failed,
+        }
+    }
+
+    pub const fn badge(self) -> &'static str {
+        match self {
+            Self::Pending => "⏳",
+            Self::Passed => "✅",
+            Self::Failed => "❌",
+        }
+    }
+}
+
+/// Counts of every check on a commit, from both check runs and commit statuses.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CiSummary {
+    pub passed: u32,
+    pub failed: u32,
+    pub pending: u32,
+}
+
+impl CiSummary {
+    fn add(&mut self, state: GateState) {
+        match state {
+            GateState::Passed => self.passed += 1,
+            GateState::Failed => self.failed += 1,
+            GateState::Pending => self.pending += 1,
+        }
+    }
+
+    /// Renders as "✅ 7 passed / ❌ 1 failed / ⏳ 2 pending", leaving out empty counts.
+    pub fn render(&self) -> String {
+        [
+            (self.passed, GateState::Passed, "passed"),
+            (self.failed, GateState::Failed, "failed"),
+            (self.pending, GateState::Pending, "pending"),
+        ]
+        .iter()
+        .filter(|(count, _, _)| *count > 0)
+        .map(|(count, state, label)| format!("{} {} {}", state.badge(), count, label))
+        .collect::<Vec<_>>()
+        .join(" / ")
+    }
+}
+
+/// Summarizes all checks of `sha`, or `None` when the commit has none.
+///
+/// Re-run check runs only count once, with their newest result.
+pub async fn summarize(
+    github: &GithubClient,
+    owner: &str,
+    repo: &str,
+    sha: &str,
+) -> Result<Option<CiSummary>> {
+    let runs = github.get_check_runs(owner, repo, sha).await?;
+    let statuses = github.get_combined_status(owner, repo, sha).await?;
+
+    let mut latest: HashMap<&str, &CheckRun> = HashMap::new();
+    for run in &runs {
+        let entry = latest.entry(run.name.as_str()).or_insert(run);
+        if run.id.0 > entry.id.0 {
+            *entry = run;
+        }
+    }
+
+    let mut summary = CiSummary::default();
+    for run in latest.values() {
+        summary.add(GateState::from_conclusion(run.conclusion.as_deref()));
+    }
+    // The combined status already keeps only the latest status per context
+    for status in &statuses.statuses {
+        summary.add(match status.state {
+            StatusState::Success => GateState::Passed,
+            StatusState::Pending => GateState::Pending,
+            _ => GateState::Failed,
+        });
+    }
+
+    Ok((summary != CiSummary::default()).then_some(summary))
+}
+
+/// Resolves every configured gate against the check runs of `sha`.
+///
+/// A gate without a matching run counts as pending; when a check was re-run, the newest run wins.
+pub async fn evaluate(
+    github: &GithubClient,
+    owner: &str,
+    repo: &str,
+    sha: &str,
+    gates: &[CheckGate],
+) -> Result<Vec<(String, GateState)>> {
+    let runs = github.get_check_runs(owner, repo, sha).await?;
+
+    Ok(gates
+        .iter()
+        .map(|gate| {
+            let state = runs
+                .iter()
+                .filter(|r| r.name == gate.check_name)
+                .max_by_key(|r| r.id.0)
+                .map_or(GateState::Pending, |r| {
+                    GateState::from_conclusion(r.conclusion.as_deref())
+                });
+            (gate.check_name.clone(), state)
+        })
+        .collect())
+}
+
+/// Critical gates that failed in `after` but hadn't failed in `before`.
+pub fn newly_failed(
+    gates: &[CheckGate],
+    before: &[(String, GateState)],
+    after: &[(String, GateState)],
+) -> Vec<String> {
+    let failed = |list: &[(String, GateState)], name: &str| {
+        list.iter()
+            .any(|(n, s)| n == name && *s == GateState::Failed)
+    };
+    gates
+        .iter()
+        .filter(|g| g.critical != 0)
+        .filter(|g| failed(after, &g.check_name) && !failed(before, &g.check_name))
+        .map(|g| g.check_name.clone())
+        .collect()
+}
+
+/// Renders gates as "✅ build · ❌ lint · ⏳ e2e".
+pub fn render(gates: &[(String, GateState)]) -> String {
+    gates
+        .iter()
+        .map(|(name, state)| format!("{} {}", state.badge(), name))
+        .collect::<Vec<_>>()
+        .join(" · ")
+}
+
+/// Parses `/gates` arguments like `build lint! e2e`, where `!` marks a critical gate.
+pub fn parse_gates(args: &[&str]) -> Vec<CheckGate> {
+    args.iter()
+        .filter(|a| !a.is_empty() && **a != "!")
+        .map(|a| match a.strip_suffix('!') {
+            Some(name) => CheckGate {
+                check_name: name.to_string(),
+                critical: 1,
+            },
+            None => CheckGate {
+                check_name: a.to_string(),
+                critical: 0,
+            },
+        })
+        .collect()
+}
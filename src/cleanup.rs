@@ -0,0 +1,145 @@
+use crate::monitor::Monitor;
+use crate::sender::Retried;
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+use teloxide::prelude::*;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardButtonKind, InlineKeyboardMarkup};
+use tracing::{error, info};
+
+/// Callback data prefix of the "Untrack" buttons, followed by `owner/repo`.
+pub const UNTRACK_CALLBACK: &str = "untrack:";
+
+const JOB: &str = "cleanup_suggestions";
+/// A chat gets at most one suggestion per this many days.
+const SUGGESTION_INTERVAL_DAYS: i64 = 30;
+const CHECK_INTERVAL: tokio::time::Duration = tokio::time::Duration::from_secs(6 * 60 * 60);
+
+/// Periodically suggests untracking repositories without recent PR activity.
+pub async fn run(monitor: Monitor) {
+    loop {
+        suggest(&monitor).await;
+        tokio::time::sleep(CHECK_INTERVAL).await;
+    }
+}
+
+async fn suggest(monitor: &Monitor) {
+    let months = monitor.config().inactive_repo_months;
+    if months == 0 {
+        return;
+    }
+    let now = Utc::now();
+    let cutoff = now - Duration::days(30 * months as i64);
+
+    let mut chats: HashMap<i64, Vec<(String, String)>> = HashMap::new();
+    for (repo, subscribers) in monitor.state.get_subscriptions().await.unwrap_or_default() {
+        for chat_id in subscribers {
+            chats.entry(chat_id).or_default().push(repo.clone());
+        }
+    }
+
+    // A repository shared by several chats is looked up once
+    let mut last_activity: HashMap<(String, String), Option<DateTime<Utc>>> = HashMap::new();
+    for (chat_id, repos) in chats {
+        let last_run = monitor.state.get_job_run(chat_id, JOB).await.ok().flatten();
+        if last_run
+            .is_some_and(|t| t > (now - Duration::days(SUGGESTION_INTERVAL_DAYS)).timestamp())
+        {
+            continue;
+        }
+
+        let mut inactive = Vec::new();
+        for repo in repos {
+            let last = match last_activity.get(&repo) {
+                Some(last) => *last,
+                None => match monitor.github.get_last_pr_activity(&repo.0, &repo.1).await {
+                    Ok(last) => {
+                        last_activity.insert(repo.clone(), last);
+                        last
+                    }
+                    Err(e) => {
+                        error!("Failed to check activity of {}/{}: {}", repo.0, repo.1, e);
+                        continue;
+                    }
+                },
+            };
+            // Repositories without any PR yet may just have been added; leave them be
+            if let Some(last) = last.filter(|t| *t < cutoff) {
+                inactive.push((repo, last));
+            }
+        }
+
+        monitor
+            .state
+            .set_job_run(chat_id, JOB, now.timestamp())
+            .await
+            .ok();
+        if inactive.is_empty() {
+            continue;
+        }
+
+        info!(
+            "Suggesting to untrack {} inactive repositories in {}",
+            inactive.len(),
+            chat_id
+        );
+        let lines: Vec<String> = inactive
+            .iter()
+            .map(|((owner, name), last)| {
+                format!(
+                    "• {}/{} — last PR activity {}",
+                    owner,
+                    name,
+                    last.format("%Y-%m-%d")
+                )
+            })
+            .collect();
+        let text = format!(
+            "🧹 These repositories had no PR activity for {}+ months:\n{}\n\nUntracking them keeps polling and GitHub rate-limit use down. Chat admins can tap to untrack:",
+            months,
+            lines.join("\n")
+        );
+        let keyboard = InlineKeyboardMarkup::new(
+            inactive
+                .iter()
+                .map(|((owner, name), _)| format!("{}{}/{}", UNTRACK_CALLBACK, owner, name))
+                // Telegram caps callback data at 64 bytes
+                .filter(|data| data.len() <= 64)
+                .map(|data| {
+                    let label = format!("Untrack {}", &data[UNTRACK_CALLBACK.len()..]);
+                    vec![InlineKeyboardButton::callback(label, data)]
+                }),
+        );
+
+        match monitor
+            .bot
+            .send_message(ChatId(chat_id), text)
+            .reply_markup(keyboard)
+            .retried()
+            .await
+        {
+            Ok(_) => monitor.state.audit(format!(
+                "suggested untracking {} repositories in {}: inactive {}+ months",
+                inactive.len(),
+                chat_id,
+                months
+            )),
+            Err(e) => error!("Failed to send cleanup suggestion: {}", e),
+        }
+    }
+}
+
+/// The keyboard of a suggestion without the button that carried `data`.
+pub fn without_button(keyboard: &InlineKeyboardMarkup, data: &str) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(
+        keyboard
+            .inline_keyboard
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .filter(|b| !matches!(&b.kind, InlineKeyboardButtonKind::CallbackData(d) if d == data))
+                    .cloned()
+                    .collect::<Vec<_>>()
+            })
+            .filter(|row| !row.is_empty()),
+    )
+}
@@ -0,0 +1,267 @@
+use crate::audit;
+use crate::handlers;
+use crate::monitor::Monitor;
+use crate::sender::Retried;
+use crate::state::PrData;
+use regex::Regex;
+use teloxide::prelude::*;
+use teloxide::types::{MessageId, ParseMode, ReplyParameters};
+use teloxide::utils::html;
+use tracing::error;
+
+/// A line of a CODEOWNERS file: the paths it matches and who owns them (`user`, `org/team`).
+struct Rule {
+    pattern: Regex,
+    owners: Vec<String>,
+}
+
+/// The rules of a CODEOWNERS file. Email owners can't be mentioned and are left out.
+fn parse(text: &str) -> Vec<Rule> {
+    text.lines()
+        .filter_map(|line| {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let mut words = line.split_whitespace();
+            let pattern = to_regex(words.next()?)?;
+            let owners = words
+                .filter_map(|w| w.strip_prefix('@'))
+                .map(str::to_string)
+                .collect();
+            Some(Rule { pattern, owners })
+        })
+        .collect()
+}
+
+/// Gitignore-style pattern to a regex over repository paths: patterns with a slash other than
+/// a trailing one are anchored at the root, others match at any depth, and a match on a
+/// directory covers everything beneath it.
+fn to_regex(pattern: &str) -> Option<Regex> {
+    let anchored = pattern.trim_end_matches('/').contains('/');
+    let pattern = pattern.trim_start_matches('/');
+    let directory = pattern.ends_with('/');
+    let pattern = pattern.trim_end_matches('/');
+    let mut re = String::from(if anchored { "^" } else { "^(?:.*/)?" });
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    re.push_str("(?:.*/)?");
+                } else {
+                    re.push_str(".*");
+                }
+            }
+            '*' => re.push_str("[^/]*"),
+            '?' => re.push_str("[^/]"),
+            c => re.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    re.push_str(if directory { "/.*$" } else { "(?:/.*)?$" });
+    Regex::new(&re).ok()
+}
+
+/// Owners of the changed files, each file owned by the last rule matching it (as on GitHub).
+fn owners(rules: &[Rule], files: &[String]) -> Vec<String> {
+    let mut owners: Vec<String> = Vec::new();
+    for file in files {
+        let Some(rule) = rules.iter().rev().find(|r| r.pattern.is_match(file)) else {
+            continue;
+        };
+        for owner in &rule.owners {
+            if !owners.iter().any(|o| o.eq_ignore_ascii_case(owner)) {
+                owners.push(owner.clone());
+            }
+        }
+    }
+    owners
+}
+
+/// With `PING_CODE_OWNERS`, replies under a new PR's card naming the code owners of the files
+/// it changes: linked users (`/link`) and linked members of owning teams are mentioned, other
+/// owners are named. The author isn't pinged about their own PR.
+pub async fn ping(monitor: &Monitor, data: &PrData, message_id: MessageId) {
+    if !monitor.config().ping_code_owners {
+        return;
+    }
+    let Some((owner, repo)) = data.repo.split_once('/') else {
+        return;
+    };
+    let rules = match monitor.github.get_codeowners(owner, repo).await {
+        Ok(Some(text)) => parse(&text),
+        Ok(None) => return,
+        Err(e) => {
+            error!("Failed to fetch the CODEOWNERS of {}: {}", data.repo, e);
+            return;
+        }
+    };
+    if rules.is_empty() {
+        return;
+    }
+    let files = match monitor
+        .github
+        .get_pr_files(owner, repo, data.pr_number)
+        .await
+    {
+        Ok(files) => files,
+        Err(e) => {
+            error!(
+                "Failed to fetch the files of {}#{}: {}",
+                data.repo, data.pr_number, e
+            );
+            return;
+        }
+    };
+    let code_owners: Vec<String> = owners(&rules, &files)
+        .into_iter()
+        .filter(|o| !o.eq_ignore_ascii_case(&data.author))
+        .collect();
+    if code_owners.is_empty() {
+        return;
+    }
+
+    let mut parts = Vec::new();
+    for code_owner in &code_owners {
+        let part = match code_owner.split_once('/') {
+            Some((org, team)) => {
+                let members: Vec<String> = monitor
+                    .github
+                    .get_team_members(org, team)
+                    .await
+                    .into_iter()
+                    .filter(|m| !m.eq_ignore_ascii_case(&data.author))
+                    .collect();
+                let linked = monitor
+                    .state
+                    .find_linked_users(&members)
+                    .await
+                    .unwrap_or_default();
+                let mentions: Vec<String> = linked.iter().map(handlers::mention).collect();
+                if mentions.is_empty() {
+                    html::escape(code_owner)
+                } else {
+                    format!("{} ({})", html::escape(code_owner), mentions.join(" "))
+                }
+            }
+            None => {
+                let linked = monitor
+                    .state
+                    .find_linked_users(std::slice::from_ref(code_owner))
+                    .await
+                    .unwrap_or_default();
+                match linked.first() {
+                    Some(link) => handlers::mention(link),
+                    None => html::escape(code_owner),
+                }
+            }
+        };
+        parts.push(part);
+    }
+
+    match monitor
+        .bot
+        .send_message(
+            ChatId(data.chat_id),
+            format!("🗂 Code owners: {}", parts.join(", ")),
+        )
+        .reply_parameters(ReplyParameters::new(message_id))
+        .parse_mode(ParseMode::Html)
+        .link_preview_options(handlers::no_link_preview())
+        .retried()
+        .await
+    {
+        Ok(_) => monitor.state.audit(format!(
+            "notified {}: code owners {}",
+            audit::card(&data.repo, data.pr_number, data.chat_id),
+            code_owners.join(", ")
+        )),
+        Err(e) => error!("Failed to mention the code owners: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn owners_of(codeowners: &str, files: &[&str]) -> Vec<String> {
+        let files: Vec<String> = files.iter().map(|f| f.to_string()).collect();
+        owners(&parse(codeowners), &files)
+    }
+
+    fn is_match(pattern: &str, path: &str) -> bool {
+        to_regex(pattern).unwrap().is_match(path)
+    }
+
+    #[test]
+    fn unanchored_patterns_match_at_any_depth() {
+        assert!(is_match("*.rs", "main.rs"));
+        assert!(is_match("*.rs", "src/bin/main.rs"));
+        assert!(!is_match("*.rs", "main.rsx"));
+        assert!(is_match("docs", "docs/index.md"));
+        assert!(is_match("docs", "src/docs/index.md"));
+        assert!(is_match("docs/", "src/docs/index.md"));
+        assert!(is_match("Cargo.?oml", "Cargo.toml"));
+        assert!(!is_match("Cargo.?oml", "Cargo.oml"));
+    }
+
+    #[test]
+    fn patterns_with_a_slash_are_anchored() {
+        assert!(is_match("/docs", "docs/index.md"));
+        assert!(!is_match("/docs", "src/docs/index.md"));
+        assert!(is_match("apps/web", "apps/web/index.ts"));
+        assert!(!is_match("apps/web", "legacy/apps/web/index.ts"));
+        assert!(is_match("src/*.rs", "src/main.rs"));
+        assert!(!is_match("src/*.rs", "src/bin/main.rs"));
+    }
+
+    #[test]
+    fn directory_patterns_cover_only_what_is_beneath() {
+        assert!(is_match("/build/", "build/out.o"));
+        assert!(is_match("/build/", "build/debug/out.o"));
+        assert!(!is_match("/build/", "build"));
+        assert!(is_match("/build", "build"));
+    }
+
+    #[test]
+    fn double_stars_cross_directories() {
+        assert!(is_match("**/logs", "logs/today.log"));
+        assert!(is_match("**/logs", "var/app/logs/today.log"));
+        assert!(is_match("src/**/test.rs", "src/test.rs"));
+        assert!(is_match("src/**/test.rs", "src/a/b/test.rs"));
+        assert!(!is_match("src/**/test.rs", "lib/a/test.rs"));
+        assert!(is_match("/vendor/**", "vendor/a/b.c"));
+    }
+
+    #[test]
+    fn the_last_matching_rule_wins() {
+        let codeowners = "* @everyone\n/src/ @core\n/src/net/ @net\n";
+        assert_eq!(owners_of(codeowners, &["README.md"]), vec!["everyone"]);
+        assert_eq!(owners_of(codeowners, &["src/lib.rs"]), vec!["core"]);
+        assert_eq!(owners_of(codeowners, &["src/net/peer.rs"]), vec!["net"]);
+        assert_eq!(
+            owners_of(codeowners, &["src/net/peer.rs", "src/lib.rs", "README.md"]),
+            vec!["net", "core", "everyone"]
+        );
+    }
+
+    #[test]
+    fn a_rule_without_owners_unowns_its_files() {
+        let codeowners = "* @everyone\n/vendor/\n";
+        assert!(owners_of(codeowners, &["vendor/dep.rs"]).is_empty());
+        assert_eq!(owners_of(codeowners, &["main.rs"]), vec!["everyone"]);
+    }
+
+    #[test]
+    fn teams_are_kept_and_emails_are_left_out() {
+        let codeowners = "\
+# Owners of the docs
+/docs/ @near/docs-team docs@near.org @Alice # and a reviewer
+*.md   @alice
+";
+        assert_eq!(
+            owners_of(codeowners, &["docs/intro.txt", "CHANGELOG.md"]),
+            vec!["near/docs-team", "Alice"]
+        );
+        assert!(owners_of(codeowners, &["src/main.rs"]).is_empty());
+    }
+}
@@ -0,0 +1,162 @@
+use crate::audit;
+use crate::handlers;
+use crate::monitor::Monitor;
+use crate::state::PrData;
+use chrono::Utc;
+use teloxide::prelude::*;
+use teloxide::types::MessageId;
+use teloxide::utils::html;
+use tracing::{error, info};
+
+const CHECK_INTERVAL: tokio::time::Duration = tokio::time::Duration::from_secs(60 * 60);
+const DAY: i64 = 24 * 60 * 60;
+
+/// Periodically shows cards without state changes for `COMPACT_AFTER_DAYS` as one line. A card
+/// expands again as soon as its state changes, since it's only rendered compact while the state
+/// still matches the one it was compacted in.
+pub async fn run(monitor: Monitor) {
+    loop {
+        compact(&monitor).await;
+        tokio::time::sleep(CHECK_INTERVAL).await;
+    }
+}
+
+/// What counts as a change of the card's state: its status, review lists, labels, title,
+/// assignees and new commits. CI runs and elapsed times don't.
+pub fn state_key(data: &PrData) -> String {
+    serde_json::to_string(&(
+        data.status.as_str(),
+        &data.reviewers,
+        &data.approvals,
+        &data.changes_requested,
+        &data.comments,
+        &data.labels,
+        &data.title,
+        &data.assignees,
+        &data.head_sha,
+        data.conflicts,
+    ))
+    .unwrap_or_default()
+}
+
+/// Whether the card is rendered as one line.
+pub fn is_compact(data: &PrData) -> bool {
+    data.compacted
+        .as_ref()
+        .is_some_and(|key| *key == state_key(data))
+}
+
+/// Security-sensitive PRs, those carrying one of `PRIORITY_LABELS` and those waiting on a
+/// re-review stay expanded.
+fn is_high_priority(data: &PrData, priority_labels: &[String]) -> bool {
+    data.security
+        || data.is_re_review()
+        || data
+            .labels
+            .iter()
+            .any(|l| priority_labels.iter().any(|p| p.eq_ignore_ascii_case(l)))
+}
+
+/// The one-line card, e.g. "🟡 acme/api#42 Fix the login flow — alice · ❤️1 👍2".
+pub fn render(data: &PrData) -> String {
+    let mut text = format!(
+        "{} <a href=\"{}\">{}#{}</a> {} — {}",
+        data.status.badge(),
+        data.pr_url,
+        data.repo,
+        data.pr_number,
+        html::escape(&data.title),
+        html::escape(&data.author)
+    );
+    let summary = handlers::review_summary(data);
+    if !summary.is_empty() {
+        text.push_str(&format!(" · {}", summary));
+    }
+    text
+}
+
+async fn compact(monitor: &Monitor) {
+    let config = monitor.config();
+    if config.compact_after_days == 0 {
+        return;
+    }
+    let threshold = config.compact_after_days as i64 * DAY;
+    let now = Utc::now().timestamp();
+
+    let messages = match monitor.state.get_all_active_messages().await {
+        Ok(messages) => messages,
+        Err(e) => {
+            error!("Failed to load cards for compaction: {}", e);
+            return;
+        }
+    };
+    for msg in messages {
+        let Ok(Some(mut data)) = monitor
+            .state
+            .get_pr_data(msg.message_id.clone(), msg.chat_id)
+            .await
+        else {
+            continue;
+        };
+        let key = state_key(&data);
+        let card = audit::card(&data.repo, data.pr_number, msg.chat_id);
+        let message_id = MessageId(msg.message_id.parse().unwrap_or(0));
+
+        // A changed card starts settling again; it was expanded when it changed. Cards seen
+        // for the first time count as unchanged since their last status change.
+        if msg.settled_key.as_deref() != Some(key.as_str()) {
+            let since = match msg.settled_key {
+                Some(_) => now,
+                None => data.status_since,
+            };
+            if let Err(e) = monitor
+                .state
+                .set_settled(&msg.message_id, msg.chat_id, &key, since, false)
+                .await
+            {
+                error!("Failed to record the state of {}: {}", card, e);
+            }
+            continue;
+        }
+
+        let quiet = now - msg.settled_since.unwrap_or(now) >= threshold;
+        let compact = quiet && !is_high_priority(&data, &config.priority_labels);
+        if compact == (msg.compact != 0) {
+            continue;
+        }
+        if let Err(e) = monitor
+            .state
+            .set_settled(
+                &msg.message_id,
+                msg.chat_id,
+                &key,
+                msg.settled_since.unwrap_or(now),
+                compact,
+            )
+            .await
+        {
+            error!("Failed to save the compaction of {}: {}", card, e);
+            continue;
+        }
+        data.compacted = compact.then_some(key);
+        info!(
+            "{} {}",
+            if compact { "Compacting" } else { "Expanding" },
+            card
+        );
+        handlers::edit_card(
+            &monitor.bot,
+            &monitor.state,
+            ChatId(msg.chat_id),
+            message_id,
+            &data,
+        )
+        .await
+        .ok();
+        monitor.state.audit(format!(
+            "edited {}: {}",
+            card,
+            if compact { "compacted" } else { "expanded" }
+        ));
+    }
+}
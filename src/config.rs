@@ -1,71 +1,1183 @@
-use anyhow::Result;
+use crate::checks;
+use crate::db::CheckGate;
+use crate::filter::Query;
+use crate::github;
+use crate::reactions::{Precedence, ReactionMap};
+use crate::security::{self, SecurityPolicy};
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{NaiveTime, Weekday};
 use dotenv::dotenv;
+use octocrab::models::pulls::PullRequest;
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
 use std::env;
+use std::path::Path;
+
+pub const GITHUB_WEB_URL: &str = "https://github.com";
 
 #[derive(Clone, Debug)]
 pub struct Config {
     pub telegram_bot_token: String,
-    pub github_token: String,
+    pub github_auth: GithubAuth,
+    pub github_api_url: Option<String>, // REST API base for GitHub Enterprise, e.g. https://github.example.com/api/v3
+    pub github_web_url: String, // where PR links point, https://github.com unless on GitHub Enterprise
     pub chat_id: i64,
+    pub database_url: String,
     pub repositories: Vec<(String, String)>, // (owner, repo)
     pub ignored_repositories: Vec<(String, String)>, // (owner, repo) - for repos we want to track interactive messages but not auto-post new PRs
+    pub github_orgs: Vec<String>, // organizations whose repositories are tracked in the default chat as they're created
+    pub org_repo_topics: Vec<String>, // with github_orgs, only repositories with one of these topics (empty = any)
+    pub org_repo_names: Vec<Regex>, // with github_orgs, only repositories whose name matches (empty = any)
+    pub skip_authors: Vec<String>, // GitHub logins whose new PRs are never announced, e.g. dependabot[bot]
+    pub chat_verification_codes: Vec<String>, // codes the bot posted in new chats, confirming them
+    pub mirror_chats: Vec<(i64, i64)>, // (source chat, mirror chat): read-only copies of the source's cards
+    pub admin_user_ids: Vec<i64>, // Telegram users who may /track, /untrack and /reload (empty = no restriction)
+    pub tenants: Vec<(String, String)>, // (id, config file) of the isolated tenants this process also serves
+    pub tenant: Option<(String, String)>, // (id, config file) when this is a tenant's configuration
+    pub repo_options: Vec<RepoOptions>,
+    pub group_threshold: usize, // group an author's new PRs into one card when there are more than this many (0 = never)
+    pub group_window_minutes: i64,
+    pub group_hold_minutes: i64, // new PRs wait this long for more by the same author (0 = announce right away)
+    pub poll_interval_secs: Option<u64>, // defaults depend on whether the GitHub webhook is enabled
+    pub quiet_sync_every: u32, // PRs without activity in the last hour are synced every this many polls
+    pub webhook_listen_addr: Option<String>, // e.g. "0.0.0.0:8080"; polling is used when unset
+    pub webhook_secret: Option<String>, // set whenever webhook_listen_addr is
+    pub user_token_key: Option<[u8; 32]>, // seals the tokens users give with /token; /token is off when unset
+    pub telegram_webhook_url: Option<String>, // public URL Telegram posts updates to; long polling when unset
+    pub telegram_webhook_listen_addr: String,
+    pub telegram_webhook_secret: Option<String>,
+    pub error_reply_ttl_secs: u64, // failed-command replies are deleted after this long (0 = keep)
+    pub ready_ping_reviewers: bool, // mention the reviewers when a draft becomes ready for review
+    pub hold_drafts: bool,         // announce new drafts only once they're ready for review
+    pub closed_cards: ClosedCards, // what happens to cards of merged or closed PRs
+    pub hold_suggested_approvals: bool, // approvals with comments count once the author sends /ack
+    pub track_issues: bool,        // announce new issues too and follow them like PRs
+    pub issue_labels: Vec<String>, // announce only issues with one of these labels (empty = any)
+    pub announce_releases: bool,   // announce new releases of the tracked repositories
+    pub main_branch_alerts: bool, // alert while checks fail on a tracked repository's default branch
+    pub ping_code_owners: bool,   // mention the CODEOWNERS of a new PR's files under its card
+    pub inactive_repo_months: u32, // suggest untracking repos without PR activity for this long (0 = never)
+    pub review_sla_hours: u32, // remind the chat of PRs waiting this long for a reviewer or an approval (0 = never)
+    pub fairness_report_day: Option<u32>, // post the monthly review fairness report on this day of the month (UTC); never when unset
+    pub review_timebox_minutes: u32, // time reviews from ❤ or /review and act once this runs out (0 = never)
+    pub review_timebox_release: bool, // release the claim instead of pinging the reviewer
+    pub nudge_budget: u32, // mentions per user and day from reminders before the rest wait for the day's summary (0 = unlimited)
+    pub stale_after_days: u32, // flag PRs without commits, reviews or comments for this long (0 = never)
+    pub compact_after_days: u32, // show cards unchanged for this long as one line (0 = never)
+    pub priority_labels: Vec<String>, // cards with one of these labels are never compacted
+    pub rerender_interval_hours: u32, // refresh the elapsed times shown on cards at most this often (0 = never)
+    pub conflict_nudge_hours: u32, // ask the author to rebase once the PR has conflicted this long (0 = never)
+    pub conflict_nudge_text: Option<String>, // replaces the default nudge; {author}, {base} and {duration} are filled in
+    pub digest_time: Option<NaiveTime>, // post the daily digest at this time (UTC); never when unset
+    pub digest_filter: Option<Query>,   // only PRs matching this filter make the digest
+    pub lead_inbox_time: Option<NaiveTime>, // DM leads their triage at this time (UTC); never when unset
+    pub leaderboard_day: Option<Weekday>, // post the weekly review leaderboard on this day (UTC); never when unset
+    pub drift_report_day: Option<Weekday>, // post the weekly process drift report on this day (UTC); never when unset
+    pub federation_instance: String,       // how this instance names itself in its feed
+    pub federation_secret: Option<String>, // signs our feed and verifies the peers'
+    pub federation_listen_addr: Option<String>, // serve the merge archive feed here
+    pub federation_peers: Vec<(String, String)>, // (name, feed URL) of instances to aggregate
+    pub audit_chat_id: Option<i64>,        // mirror the bot's actions into this chat
+    pub audit_thread_id: Option<i32>,      // forum topic within the audit chat
+    pub metrics_listen_addr: Option<String>, // serve Prometheus metrics at /metrics here
+    pub health_listen_addr: Option<String>, // serve the health check at /healthz here
+    pub cycle_report_chat_id: Option<i64>, // post a report of every polling cycle here
+    pub cycle_report_thread_id: Option<i32>, // forum topic within the report chat
+    pub demo_chat_id: Option<i64>,         // replay scripted fake PRs in this chat
+    pub demo_step_secs: u64,               // pause between the demo's steps
+    pub reactions: ReactionMap,
+    pub reaction_precedence: Precedence, // which review mark wins when a user reacts with several
+    pub security: SecurityPolicy,
+}
+
+/// Settings from a `[repos."owner/repo"]` table of the config file, or a repository named in
+/// `REPO_CHATS` only.
+#[derive(Clone, Debug)]
+pub struct RepoOptions {
+    pub owner: String,
+    pub name: String,
+    pub chats: Vec<i64>, // subscribed on startup (`chats` and `REPO_CHATS`), in addition to the default chat
+    pub gates: Option<Vec<CheckGate>>, // replaces the `/gates` setting on startup when present
+    pub filter: PrFilter,
+    pub skip_authors: Vec<String>, // in addition to the global `SKIP_AUTHORS`
+    pub maintainers: Vec<String>, // logins or org/team; PRs then need a peer's and a maintainer's approval
+    pub closed_cards: Option<ClosedCards>, // in place of `CLOSED_CARDS`
+}
+
+/// How the bot authenticates to GitHub.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GithubAuth {
+    Token(String), // personal access token
+    /// A GitHub App installation, whose short-lived tokens are minted and refreshed as needed
+    App {
+        app_id: u64,
+        private_key: String,          // PEM
+        installation_id: Option<u64>, // the app's first installation when unset
+    },
+}
+
+/// Which new PRs of a repository get announced, by their GitHub labels and base branch.
+#[derive(Clone, Debug, Default)]
+pub struct PrFilter {
+    pub only: Vec<String>, // announce only PRs with one of these labels (empty = any)
+    pub skip: Vec<String>, // never announce PRs with one of these labels
+    pub base_branches: Vec<Regex>, // announce only PRs into a matching branch (empty = any)
+}
+
+impl PrFilter {
+    pub fn allows(&self, pr: &PullRequest) -> bool {
+        let labels = github::labels(pr);
+        let has = |wanted: &[String]| {
+            labels
+                .iter()
+                .any(|l| wanted.iter().any(|w| w.eq_ignore_ascii_case(l)))
+        };
+        let base = &pr.base.ref_field;
+        (self.only.is_empty() || has(&self.only))
+            && !has(&self.skip)
+            && (self.base_branches.is_empty()
+                || self.base_branches.iter().any(|b| b.is_match(base)))
+    }
+}
+
+/// What happens to a card once its PR is merged or closed, by `CLOSED_CARDS`, a repository's
+/// `closed_cards` or a chat's `/onclose`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ClosedCards {
+    /// Deleted, or struck out where Telegram no longer allows deleting it
+    #[default]
+    Delete,
+    /// Edited into a summary of who merged it and when
+    Archive,
+    /// The summary posted to this chat, the card deleted
+    Move(i64),
+    /// Struck out, and deleted this many days later
+    Keep(u32),
+}
+
+impl ClosedCards {
+    /// Parses "delete", "archive", "move:<chat id>" or "keep:<days>".
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim().to_ascii_lowercase();
+        match value.split_once(':') {
+            None if value == "delete" => Some(Self::Delete),
+            None if value == "archive" => Some(Self::Archive),
+            Some(("move", chat_id)) => chat_id.trim().parse().ok().map(Self::Move),
+            Some(("keep", days)) => days
+                .trim()
+                .trim_end_matches('d')
+                .parse()
+                .ok()
+                .filter(|d| *d > 0)
+                .map(Self::Keep),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for ClosedCards {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Delete => write!(f, "delete"),
+            Self::Archive => write!(f, "archive"),
+            Self::Move(chat_id) => write!(f, "move:{}", chat_id),
+            Self::Keep(days) => write!(f, "keep:{}", days),
+        }
+    }
+}
+
+/// Layout of `config.toml`. Every key is optional and the matching env var wins when set.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileConfig {
+    database_url: Option<String>,
+    poll_interval_seconds: Option<u64>,
+    quiet_sync_every: Option<u32>,
+    inactive_repo_months: Option<u32>,
+    review_sla_hours: Option<u32>,
+    review_timebox_minutes: Option<u32>,
+    review_timebox_action: Option<String>,
+    nudge_budget: Option<u32>,
+    stale_after_days: Option<u32>,
+    compact_after_days: Option<u32>,
+    priority_labels: Vec<String>,
+    rerender_interval_hours: Option<u32>,
+    conflict_nudge_hours: Option<u32>,
+    conflict_nudge_text: Option<String>,
+    digest_time: Option<String>,
+    digest_filter: Option<String>,
+    lead_inbox_time: Option<String>,
+    leaderboard_day: Option<String>,
+    drift_report_day: Option<String>,
+    fairness_report_day: Option<u32>,
+    ready_ping_reviewers: Option<bool>,
+    hold_drafts: Option<bool>,
+    closed_cards: Option<String>,
+    hold_suggested_approvals: Option<bool>,
+    track_issues: Option<bool>,
+    issue_labels: Vec<String>,
+    announce_releases: Option<bool>,
+    main_branch_alerts: Option<bool>,
+    ping_code_owners: Option<bool>,
+    error_reply_ttl_seconds: Option<u64>,
+    metrics_listen_addr: Option<String>,
+    health_listen_addr: Option<String>,
+    repositories: Vec<String>,
+    ignored_repositories: Vec<String>,
+    github_orgs: Vec<String>,
+    org_repo_topics: Vec<String>,
+    org_repo_names: Vec<String>,
+    skip_authors: Vec<String>,
+    chat_verification_codes: Vec<String>,
+    mirror_chats: Vec<(i64, i64)>,
+    repo_chats: Vec<(String, i64)>,
+    admin_user_ids: Vec<i64>,
+    tenants: BTreeMap<String, String>, // id -> config file
+    telegram: TelegramSection,
+    github: GithubSection,
+    grouping: GroupingSection,
+    reactions: BTreeMap<String, String>,
+    reaction_precedence: Option<String>,
+    security: SecuritySection,
+    audit: AuditSection,
+    cycle_report: CycleReportSection,
+    federation: FederationSection,
+    demo: DemoSection,
+    repos: BTreeMap<String, RepoSection>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct TelegramSection {
+    bot_token: Option<String>,
+    chat_id: Option<i64>,
+    webhook_url: Option<String>,
+    webhook_listen_addr: Option<String>,
+    webhook_secret: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct GithubSection {
+    token: Option<String>,
+    app_id: Option<u64>,
+    app_private_key: Option<String>,
+    app_private_key_file: Option<String>,
+    app_installation_id: Option<u64>,
+    api_url: Option<String>,
+    web_url: Option<String>,
+    webhook_listen_addr: Option<String>,
+    webhook_secret: Option<String>,
+    user_token_key: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct GroupingSection {
+    threshold: Option<usize>,
+    window_minutes: Option<i64>,
+    hold_minutes: Option<i64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct SecuritySection {
+    labels: Option<Vec<String>>,
+    paths: Option<Vec<String>>,
+    reviewers: Option<Vec<String>>,
+    chat_id: Option<i64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct AuditSection {
+    chat_id: Option<i64>,
+    thread_id: Option<i32>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct CycleReportSection {
+    chat_id: Option<i64>,
+    thread_id: Option<i32>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct DemoSection {
+    chat_id: Option<i64>,
+    step_seconds: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FederationSection {
+    instance: Option<String>,
+    secret: Option<String>,
+    listen_addr: Option<String>,
+    peers: BTreeMap<String, String>, // name -> feed URL
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RepoSection {
+    chats: Vec<i64>,
+    gates: Option<Vec<String>>,
+    ignored: bool,
+    only_labels: Vec<String>,
+    skip_labels: Vec<String>,
+    skip_authors: Vec<String>,
+    base_branches: Vec<String>,
+    maintainers: Vec<String>,
+    closed_cards: Option<String>,
+}
+
+impl FileConfig {
+    /// Flattens the file into the env var names it stands in for, so both share one parser.
+    fn settings(&self) -> HashMap<&'static str, String> {
+        let mut out = HashMap::new();
+        let mut put = |name: &'static str, value: Option<String>| {
+            if let Some(value) = value {
+                out.insert(name, value);
+            }
+        };
+        let joined = |list: &[String]| (!list.is_empty()).then(|| list.join(","));
+
+        put("TELEGRAM_BOT_TOKEN", self.telegram.bot_token.clone());
+        put(
+            "TELEGRAM_CHAT_ID",
+            self.telegram.chat_id.map(|v| v.to_string()),
+        );
+        put("TELEGRAM_WEBHOOK_URL", self.telegram.webhook_url.clone());
+        put(
+            "TELEGRAM_WEBHOOK_LISTEN_ADDR",
+            self.telegram.webhook_listen_addr.clone(),
+        );
+        put(
+            "TELEGRAM_WEBHOOK_SECRET",
+            self.telegram.webhook_secret.clone(),
+        );
+        put("GITHUB_TOKEN", self.github.token.clone());
+        put("GITHUB_APP_ID", self.github.app_id.map(|v| v.to_string()));
+        put(
+            "GITHUB_APP_PRIVATE_KEY",
+            self.github.app_private_key.clone(),
+        );
+        put(
+            "GITHUB_APP_PRIVATE_KEY_FILE",
+            self.github.app_private_key_file.clone(),
+        );
+        put(
+            "GITHUB_APP_INSTALLATION_ID",
+            self.github.app_installation_id.map(|v| v.to_string()),
+        );
+        put("GITHUB_API_URL", self.github.api_url.clone());
+        put("GITHUB_WEB_URL", self.github.web_url.clone());
+        put(
+            "WEBHOOK_LISTEN_ADDR",
+            self.github.webhook_listen_addr.clone(),
+        );
+        put("GITHUB_WEBHOOK_SECRET", self.github.webhook_secret.clone());
+        put("GITHUB_USER_TOKEN_KEY", self.github.user_token_key.clone());
+        put("DATABASE_URL", self.database_url.clone());
+        put(
+            "POLL_INTERVAL_SECONDS",
+            self.poll_interval_seconds.map(|v| v.to_string()),
+        );
+        put(
+            "QUIET_SYNC_EVERY",
+            self.quiet_sync_every.map(|v| v.to_string()),
+        );
+        put(
+            "INACTIVE_REPO_MONTHS",
+            self.inactive_repo_months.map(|v| v.to_string()),
+        );
+        put(
+            "REVIEW_SLA_HOURS",
+            self.review_sla_hours.map(|v| v.to_string()),
+        );
+        put(
+            "REVIEW_TIMEBOX_MINUTES",
+            self.review_timebox_minutes.map(|v| v.to_string()),
+        );
+        put("REVIEW_TIMEBOX_ACTION", self.review_timebox_action.clone());
+        put("NUDGE_BUDGET", self.nudge_budget.map(|v| v.to_string()));
+        put(
+            "STALE_AFTER_DAYS",
+            self.stale_after_days.map(|v| v.to_string()),
+        );
+        put(
+            "COMPACT_AFTER_DAYS",
+            self.compact_after_days.map(|v| v.to_string()),
+        );
+        put("PRIORITY_LABELS", joined(&self.priority_labels));
+        put(
+            "RERENDER_INTERVAL_HOURS",
+            self.rerender_interval_hours.map(|v| v.to_string()),
+        );
+        put(
+            "CONFLICT_NUDGE_HOURS",
+            self.conflict_nudge_hours.map(|v| v.to_string()),
+        );
+        put("CONFLICT_NUDGE_TEXT", self.conflict_nudge_text.clone());
+        put("DIGEST_TIME", self.digest_time.clone());
+        put("DIGEST_FILTER", self.digest_filter.clone());
+        put("LEAD_INBOX_TIME", self.lead_inbox_time.clone());
+        put("LEADERBOARD_DAY", self.leaderboard_day.clone());
+        put("DRIFT_REPORT_DAY", self.drift_report_day.clone());
+        put(
+            "FAIRNESS_REPORT_DAY",
+            self.fairness_report_day.map(|v| v.to_string()),
+        );
+        put(
+            "READY_PING_REVIEWERS",
+            self.ready_ping_reviewers.map(|v| v.to_string()),
+        );
+        put("HOLD_DRAFTS", self.hold_drafts.map(|v| v.to_string()));
+        put("CLOSED_CARDS", self.closed_cards.clone());
+        put(
+            "HOLD_SUGGESTED_APPROVALS",
+            self.hold_suggested_approvals.map(|v| v.to_string()),
+        );
+        put("TRACK_ISSUES", self.track_issues.map(|v| v.to_string()));
+        put("ISSUE_LABELS", joined(&self.issue_labels));
+        put(
+            "ANNOUNCE_RELEASES",
+            self.announce_releases.map(|v| v.to_string()),
+        );
+        put(
+            "MAIN_BRANCH_ALERTS",
+            self.main_branch_alerts.map(|v| v.to_string()),
+        );
+        put(
+            "PING_CODE_OWNERS",
+            self.ping_code_owners.map(|v| v.to_string()),
+        );
+        put(
+            "ERROR_REPLY_TTL_SECONDS",
+            self.error_reply_ttl_seconds.map(|v| v.to_string()),
+        );
+        put("METRICS_LISTEN_ADDR", self.metrics_listen_addr.clone());
+        put("HEALTH_LISTEN_ADDR", self.health_listen_addr.clone());
+        put("GITHUB_REPOS", joined(&self.repositories));
+        put("GITHUB_IGNORED_REPOS", joined(&self.ignored_repositories));
+        put("GITHUB_ORGS", joined(&self.github_orgs));
+        put("ORG_REPO_TOPICS", joined(&self.org_repo_topics));
+        put("ORG_REPO_NAMES", joined(&self.org_repo_names));
+        put("SKIP_AUTHORS", joined(&self.skip_authors));
+        put(
+            "CHAT_VERIFICATION_CODES",
+            joined(&self.chat_verification_codes),
+        );
+        put(
+            "MIRROR_CHATS",
+            (!self.mirror_chats.is_empty()).then(|| {
+                self.mirror_chats
+                    .iter()
+                    .map(|(source, mirror)| format!("{}={}", source, mirror))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            }),
+        );
+        put(
+            "REPO_CHATS",
+            (!self.repo_chats.is_empty()).then(|| {
+                self.repo_chats
+                    .iter()
+                    .map(|(repo, chat)| format!("{}={}", repo, chat))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            }),
+        );
+        put(
+            "ADMIN_USER_IDS",
+            (!self.admin_user_ids.is_empty()).then(|| {
+                self.admin_user_ids
+                    .iter()
+                    .map(|id| id.to_string())
+                    .collect::<Vec<_>>()
+                    .join(",")
+            }),
+        );
+        put(
+            "TENANTS",
+            (!self.tenants.is_empty()).then(|| {
+                self.tenants
+                    .iter()
+                    .map(|(id, path)| format!("{}={}", id, path))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            }),
+        );
+        put(
+            "PR_GROUP_THRESHOLD",
+            self.grouping.threshold.map(|v| v.to_string()),
+        );
+        put(
+            "PR_GROUP_WINDOW_MINUTES",
+            self.grouping.window_minutes.map(|v| v.to_string()),
+        );
+        put(
+            "PR_GROUP_HOLD_MINUTES",
+            self.grouping.hold_minutes.map(|v| v.to_string()),
+        );
+        put(
+            "REACTION_EMOJIS",
+            (!self.reactions.is_empty()).then(|| {
+                self.reactions
+                    .iter()
+                    .map(|(action, emojis)| format!("{}={}", action, emojis))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            }),
+        );
+        put("REACTION_PRECEDENCE", self.reaction_precedence.clone());
+        // Lists are kept even when empty: `labels = []` disables label detection like an empty env var
+        put(
+            "SECURITY_LABELS",
+            self.security.labels.as_ref().map(|l| l.join(",")),
+        );
+        put(
+            "SECURITY_PATHS",
+            self.security.paths.as_ref().map(|l| l.join(",")),
+        );
+        put(
+            "SECURITY_REVIEWERS",
+            self.security.reviewers.as_ref().map(|l| l.join(",")),
+        );
+        put(
+            "SECURITY_CHAT_ID",
+            self.security.chat_id.map(|v| v.to_string()),
+        );
+        put("FEDERATION_INSTANCE", self.federation.instance.clone());
+        put("FEDERATION_SECRET", self.federation.secret.clone());
+        put(
+            "FEDERATION_LISTEN_ADDR",
+            self.federation.listen_addr.clone(),
+        );
+        put(
+            "FEDERATION_PEERS",
+            (!self.federation.peers.is_empty()).then(|| {
+                self.federation
+                    .peers
+                    .iter()
+                    .map(|(name, url)| format!("{}={}", name, url))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            }),
+        );
+        put("AUDIT_CHAT_ID", self.audit.chat_id.map(|v| v.to_string()));
+        put(
+            "AUDIT_THREAD_ID",
+            self.audit.thread_id.map(|v| v.to_string()),
+        );
+        put(
+            "CYCLE_REPORT_CHAT_ID",
+            self.cycle_report.chat_id.map(|v| v.to_string()),
+        );
+        put(
+            "CYCLE_REPORT_THREAD_ID",
+            self.cycle_report.thread_id.map(|v| v.to_string()),
+        );
+        put("DEMO_CHAT_ID", self.demo.chat_id.map(|v| v.to_string()));
+        put(
+            "DEMO_STEP_SECONDS",
+            self.demo.step_seconds.map(|v| v.to_string()),
+        );
+        out
+    }
+
+    fn repo_options(&self) -> Result<Vec<RepoOptions>> {
+        self.repos
+            .iter()
+            .map(|(key, section)| {
+                let (owner, name) = parse_repo(key)
+                    .ok_or_else(|| anyhow!("Invalid repository in [repos]: {}", key))?;
+                let gates = section.gates.as_ref().map(|gates| {
+                    checks::parse_gates(&gates.iter().map(String::as_str).collect::<Vec<_>>())
+                });
+                let base_branches = section
+                    .base_branches
+                    .iter()
+                    .map(|b| security::glob_to_regex(b))
+                    .collect::<Result<_>>()
+                    .with_context(|| format!("Invalid base_branches of {}", key))?;
+                let closed_cards = section
+                    .closed_cards
+                    .as_deref()
+                    .map(|v| {
+                        ClosedCards::parse(v)
+                            .ok_or_else(|| anyhow!("Invalid closed_cards of {}: {}", key, v))
+                    })
+                    .transpose()?;
+                Ok(RepoOptions {
+                    owner,
+                    name,
+                    chats: section.chats.clone(),
+                    gates,
+                    filter: PrFilter {
+                        only: section.only_labels.clone(),
+                        skip: section.skip_labels.clone(),
+                        base_branches,
+                    },
+                    skip_authors: section.skip_authors.clone(),
+                    maintainers: section.maintainers.clone(),
+                    closed_cards,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Looks settings up by env var name, falling back to the config file. Tenants' settings only
+/// come from their file.
+pub struct Settings {
+    file: HashMap<&'static str, String>,
+    env: bool,
+}
+
+impl Settings {
+    pub fn get(&self, name: &str) -> Option<String> {
+        self.env
+            .then(|| env::var(name).ok())
+            .flatten()
+            .or_else(|| self.file.get(name).cloned())
+    }
 }
 
 impl Config {
-    pub fn from_env() -> Result<Self> {
+    /// Loads `CONFIG_FILE` (default `config.toml`, skipped when absent) with env vars as overrides.
+    pub fn load() -> Result<Self> {
         dotenv().ok();
 
+        let path = env::var("CONFIG_FILE").ok();
+        let file = match &path {
+            Some(path) => read_file(Path::new(path))?,
+            None if Path::new("config.toml").exists() => read_file(Path::new("config.toml"))?,
+            None => FileConfig::default(),
+        };
+        Self::from_file(file, true)
+    }
+
+    /// Loads a tenant's config file. Env vars are the operator's, so they don't apply, and the
+    /// tenant shares the operator's Telegram bot.
+    pub fn load_tenant(id: &str, path: &str, telegram_bot_token: &str) -> Result<Self> {
+        let mut file = read_file(Path::new(path))?;
+        if !file.tenants.is_empty() {
+            bail!("Tenant {} can't have tenants of its own", id);
+        }
+        file.telegram.bot_token = Some(telegram_bot_token.to_string());
+        let mut config = Self::from_file(file, false)?;
+        config.tenant = Some((id.to_string(), path.to_string()));
+        Ok(config)
+    }
+
+    fn from_file(file: FileConfig, env: bool) -> Result<Self> {
+        let settings = Settings {
+            file: file.settings(),
+            env,
+        };
+        let get = |name: &str| settings.get(name);
+
         let telegram_bot_token =
-            env::var("TELEGRAM_BOT_TOKEN").expect("TELEGRAM_BOT_TOKEN must be set");
-        let github_token = env::var("GITHUB_TOKEN").expect("GITHUB_TOKEN must be set");
-        let chat_id = env::var("TELEGRAM_CHAT_ID")
-            .expect("TELEGRAM_CHAT_ID must be set")
+            get("TELEGRAM_BOT_TOKEN").context("TELEGRAM_BOT_TOKEN must be set")?;
+        let github_auth = match get("GITHUB_APP_ID").filter(|v| !v.is_empty()) {
+            Some(app_id) => {
+                let app_id = app_id
+                    .parse::<u64>()
+                    .context("GITHUB_APP_ID must be the app's numeric id")?;
+                let private_key = match get("GITHUB_APP_PRIVATE_KEY").filter(|v| !v.is_empty()) {
+                    Some(key) => key.replace("\\n", "\n"),
+                    None => {
+                        let path = get("GITHUB_APP_PRIVATE_KEY_FILE").context(
+                            "GITHUB_APP_PRIVATE_KEY or GITHUB_APP_PRIVATE_KEY_FILE must be set with GITHUB_APP_ID",
+                        )?;
+                        std::fs::read_to_string(&path)
+                            .with_context(|| format!("Failed to read {}", path))?
+                    }
+                };
+                jsonwebtoken::EncodingKey::from_rsa_pem(private_key.as_bytes())
+                    .context("The GitHub App private key must be the PEM file GitHub generated")?;
+                let installation_id = get("GITHUB_APP_INSTALLATION_ID")
+                    .filter(|v| !v.is_empty())
+                    .map(|v| v.parse::<u64>())
+                    .transpose()
+                    .context("GITHUB_APP_INSTALLATION_ID must be a number")?;
+                GithubAuth::App {
+                    app_id,
+                    private_key,
+                    installation_id,
+                }
+            }
+            None => GithubAuth::Token(
+                get("GITHUB_TOKEN").context("GITHUB_TOKEN or GITHUB_APP_ID must be set")?,
+            ),
+        };
+        let url = |name: &str| -> Result<Option<String>> {
+            let Some(value) = get(name).filter(|v| !v.is_empty()) else {
+                return Ok(None);
+            };
+            url::Url::parse(&value).with_context(|| format!("{} must be a URL", name))?;
+            Ok(Some(value.trim_end_matches('/').to_string()))
+        };
+        let github_web_url = url("GITHUB_WEB_URL")?.unwrap_or_else(|| GITHUB_WEB_URL.to_string());
+        // GitHub Enterprise serves the REST API under /api/v3 of the web host
+        let github_api_url = url("GITHUB_API_URL")?.or_else(|| {
+            (github_web_url != GITHUB_WEB_URL).then(|| format!("{}/api/v3", github_web_url))
+        });
+
+        let chat_id = get("TELEGRAM_CHAT_ID")
+            .context("TELEGRAM_CHAT_ID must be set")?
             .parse::<i64>()
-            .expect("TELEGRAM_CHAT_ID must be a valid integer");
-
-        let repositories = env::var("GITHUB_REPOS")
-            .map(|repos_str| {
-                repos_str
-                    .split(',')
-                    .map(|s| {
-                        let parts: Vec<&str> = s.split('/').collect();
-                        if parts.len() != 2 {
-                            // Don't panic here, just skip invalid or log
-                            eprintln!("Invalid repository format: {}", s);
-                            ("".to_string(), "".to_string())
-                        } else {
-                            (parts[0].to_string(), parts[1].to_string())
-                        }
-                    })
-                    .filter(|(o, r)| !o.is_empty() && !r.is_empty())
-                    .collect()
-            })
+            .context("TELEGRAM_CHAT_ID must be a valid integer")?;
+
+        let database_url = get("DATABASE_URL").unwrap_or_else(|| "sqlite:bot.db".to_string());
+
+        let repositories = get("GITHUB_REPOS")
+            .map(|repos_str| parse_repo_list(&repos_str, "repository"))
+            .unwrap_or_default();
+
+        let mut ignored_repositories: Vec<(String, String)> = get("GITHUB_IGNORED_REPOS")
+            .map(|repos_str| parse_repo_list(&repos_str, "ignored repository"))
             .unwrap_or_default();
+        for (key, section) in &file.repos {
+            if section.ignored {
+                if let Some(repo) = parse_repo(key) {
+                    if !ignored_repositories.contains(&repo) {
+                        ignored_repositories.push(repo);
+                    }
+                }
+            }
+        }
+
+        let list = |name: &str| -> Vec<String> {
+            get(name)
+                .unwrap_or_default()
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect()
+        };
+        let github_orgs = list("GITHUB_ORGS");
+        let org_repo_topics = list("ORG_REPO_TOPICS")
+            .into_iter()
+            .map(|t| t.to_lowercase())
+            .collect();
+        let org_repo_names = list("ORG_REPO_NAMES")
+            .iter()
+            .map(|n| security::glob_to_regex(n))
+            .collect::<Result<_>>()
+            .context("Invalid ORG_REPO_NAMES")?;
+
+        let mut repo_options = file.repo_options()?;
+        // Routes add to the chats of the repository's [repos] table
+        for route in get("REPO_CHATS")
+            .unwrap_or_default()
+            .split(',')
+            .filter(|s| !s.trim().is_empty())
+        {
+            let ((owner, name), chat_id) = route
+                .split_once('=')
+                .and_then(|(repo, chat)| Some((parse_repo(repo)?, chat.trim().parse().ok()?)))
+                .ok_or_else(|| {
+                    anyhow!("Invalid route \"{}\", expected owner/repo=chat_id", route)
+                })?;
+            let index = match repo_options.iter().position(|o| {
+                o.owner.eq_ignore_ascii_case(&owner) && o.name.eq_ignore_ascii_case(&name)
+            }) {
+                Some(index) => index,
+                None => {
+                    repo_options.push(RepoOptions {
+                        owner,
+                        name,
+                        chats: vec![],
+                        gates: None,
+                        filter: PrFilter::default(),
+                        skip_authors: vec![],
+                        maintainers: vec![],
+                        closed_cards: None,
+                    });
+                    repo_options.len() - 1
+                }
+            };
+            if !repo_options[index].chats.contains(&chat_id) {
+                repo_options[index].chats.push(chat_id);
+            }
+        }
+
+        let skip_authors = get("SKIP_AUTHORS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect();
 
-        let ignored_repositories = env::var("GITHUB_IGNORED_REPOS")
-            .map(|repos_str| {
-                repos_str
-                    .split(',')
-                    .map(|s| {
-                        let parts: Vec<&str> = s.split('/').collect();
-                        if parts.len() != 2 {
-                            eprintln!("Invalid ignored repository format: {}", s);
-                            ("".to_string(), "".to_string())
-                        } else {
-                            (parts[0].to_string(), parts[1].to_string())
-                        }
+        let chat_verification_codes = get("CHAT_VERIFICATION_CODES")
+            .unwrap_or_default()
+            .split(',')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect();
+
+        let mirror_chats = get("MIRROR_CHATS")
+            .unwrap_or_default()
+            .split(',')
+            .filter(|s| !s.trim().is_empty())
+            .map(|s| {
+                s.split_once('=')
+                    .and_then(|(source, mirror)| {
+                        Some((source.trim().parse().ok()?, mirror.trim().parse().ok()?))
+                    })
+                    .filter(|(source, mirror)| source != mirror)
+                    .ok_or_else(|| {
+                        anyhow!(
+                            "Invalid mirror \"{}\", expected source_chat_id=mirror_chat_id",
+                            s
+                        )
                     })
-                    .filter(|(o, r)| !o.is_empty() && !r.is_empty())
-                    .collect()
             })
-            .unwrap_or_default();
+            .collect::<Result<Vec<_>>>()?;
+
+        let admin_user_ids = get("ADMIN_USER_IDS")
+            .unwrap_or_default()
+            .split(',')
+            .filter(|s| !s.trim().is_empty())
+            .map(|s| {
+                s.trim()
+                    .parse()
+                    .map_err(|_| anyhow!("Invalid admin user id \"{}\"", s))
+            })
+            .collect::<Result<Vec<i64>>>()?;
+
+        let tenants = get("TENANTS")
+            .unwrap_or_default()
+            .split(',')
+            .filter(|s| !s.trim().is_empty())
+            .map(|s| match s.split_once('=') {
+                Some((id, path)) if is_tenant_id(id.trim()) && !path.trim().is_empty() => {
+                    Ok((id.trim().to_string(), path.trim().to_string()))
+                }
+                _ => Err(anyhow!(
+                    "Invalid tenant \"{}\", expected id=config_file with a lowercase id",
+                    s
+                )),
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let group_threshold = get("PR_GROUP_THRESHOLD")
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or(3);
+
+        let group_window_minutes = get("PR_GROUP_WINDOW_MINUTES")
+            .and_then(|v| v.parse::<i64>().ok())
+            .unwrap_or(15);
+
+        let group_hold_minutes = get("PR_GROUP_HOLD_MINUTES")
+            .and_then(|v| v.parse::<i64>().ok())
+            .filter(|v| *v >= 0)
+            .unwrap_or(0);
+
+        let poll_interval_secs = get("POLL_INTERVAL_SECONDS")
+            .and_then(|v| v.parse::<u64>().ok())
+            .filter(|v| *v > 0);
+
+        let quiet_sync_every = get("QUIET_SYNC_EVERY")
+            .and_then(|v| v.parse::<u32>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(10);
+
+        let webhook_listen_addr = get("WEBHOOK_LISTEN_ADDR").filter(|v| !v.is_empty());
+        let webhook_secret = get("GITHUB_WEBHOOK_SECRET").filter(|v| !v.is_empty());
+        // Unsigned deliveries would let anyone who reaches the port force syncs of whole
+        // repositories and drain the GitHub rate limit
+        if webhook_listen_addr.is_some() && webhook_secret.is_none() {
+            bail!("WEBHOOK_LISTEN_ADDR needs GITHUB_WEBHOOK_SECRET to verify deliveries");
+        }
+
+        let user_token_key = match get("GITHUB_USER_TOKEN_KEY").filter(|v| !v.is_empty()) {
+            Some(v) => Some(
+                hex::decode(v.trim())
+                    .ok()
+                    .and_then(|key| <[u8; 32]>::try_from(key).ok())
+                    .ok_or_else(|| {
+                        anyhow!("GITHUB_USER_TOKEN_KEY must be 64 hex digits (32 bytes)")
+                    })?,
+            ),
+            None => None,
+        };
+
+        let telegram_webhook_url = get("TELEGRAM_WEBHOOK_URL").filter(|v| !v.is_empty());
+        let telegram_webhook_listen_addr =
+            get("TELEGRAM_WEBHOOK_LISTEN_ADDR").unwrap_or_else(|| "0.0.0.0:8443".to_string());
+        let telegram_webhook_secret = get("TELEGRAM_WEBHOOK_SECRET").filter(|v| !v.is_empty());
+
+        let error_reply_ttl_secs = get("ERROR_REPLY_TTL_SECONDS")
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(20);
+
+        let inactive_repo_months = get("INACTIVE_REPO_MONTHS")
+            .and_then(|v| v.parse::<u32>().ok())
+            .unwrap_or(3);
+
+        let review_sla_hours = get("REVIEW_SLA_HOURS")
+            .and_then(|v| v.parse::<u32>().ok())
+            .unwrap_or(0);
+
+        let review_timebox_minutes = get("REVIEW_TIMEBOX_MINUTES")
+            .and_then(|v| v.parse::<u32>().ok())
+            .unwrap_or(0);
+        let review_timebox_release =
+            get("REVIEW_TIMEBOX_ACTION").is_some_and(|v| v.eq_ignore_ascii_case("release"));
+
+        let nudge_budget = get("NUDGE_BUDGET")
+            .and_then(|v| v.parse::<u32>().ok())
+            .unwrap_or(0);
+
+        let stale_after_days = get("STALE_AFTER_DAYS")
+            .and_then(|v| v.parse::<u32>().ok())
+            .unwrap_or(0);
+        let compact_after_days = get("COMPACT_AFTER_DAYS")
+            .and_then(|v| v.parse::<u32>().ok())
+            .unwrap_or(0);
+        let priority_labels = get("PRIORITY_LABELS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect();
+
+        let rerender_interval_hours = get("RERENDER_INTERVAL_HOURS")
+            .and_then(|v| v.parse::<u32>().ok())
+            .unwrap_or(6);
+
+        let conflict_nudge_hours = get("CONFLICT_NUDGE_HOURS")
+            .and_then(|v| v.parse::<u32>().ok())
+            .unwrap_or(0);
+        let conflict_nudge_text = get("CONFLICT_NUDGE_TEXT")
+            .filter(|v| !v.is_empty())
+            .map(|v| v.replace("\\n", "\n"));
+
+        let digest_time = get("DIGEST_TIME")
+            .filter(|v| !v.is_empty())
+            .map(|v| NaiveTime::parse_from_str(&v, "%H:%M"))
+            .transpose()
+            .context("DIGEST_TIME must be a time like 09:00")?;
+        let digest_filter = get("DIGEST_FILTER")
+            .filter(|v| !v.trim().is_empty())
+            .map(|v| Query::parse(&v))
+            .transpose()
+            .context("DIGEST_FILTER must be a filter like status:approved -label:wip")?;
+        if digest_filter.as_ref().is_some_and(Query::uses_me) {
+            bail!("DIGEST_FILTER can't use \"me\", the digest is posted to the whole chat");
+        }
+
+        let lead_inbox_time = get("LEAD_INBOX_TIME")
+            .filter(|v| !v.is_empty())
+            .map(|v| NaiveTime::parse_from_str(&v, "%H:%M"))
+            .transpose()
+            .context("LEAD_INBOX_TIME must be a time like 08:30")?;
+
+        let leaderboard_day = get("LEADERBOARD_DAY")
+            .filter(|v| !v.is_empty())
+            .map(|v| v.parse::<Weekday>())
+            .transpose()
+            .map_err(|_| anyhow!("LEADERBOARD_DAY must be a weekday like monday"))?;
+        let drift_report_day = get("DRIFT_REPORT_DAY")
+            .filter(|v| !v.is_empty())
+            .map(|v| v.parse::<Weekday>())
+            .transpose()
+            .map_err(|_| anyhow!("DRIFT_REPORT_DAY must be a weekday like monday"))?;
+        // Every month has the 28th
+        let fairness_report_day = match get("FAIRNESS_REPORT_DAY").filter(|v| !v.is_empty()) {
+            Some(v) => match v.parse::<u32>() {
+                Ok(day @ 1..=28) => Some(day),
+                _ => bail!("FAIRNESS_REPORT_DAY must be a day of the month from 1 to 28"),
+            },
+            None => None,
+        };
+
+        let ready_ping_reviewers = get("READY_PING_REVIEWERS")
+            .is_some_and(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes"));
+        let hold_drafts = get("HOLD_DRAFTS")
+            .is_some_and(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes"));
+        let closed_cards = match get("CLOSED_CARDS").filter(|v| !v.is_empty()) {
+            Some(v) => ClosedCards::parse(&v).ok_or_else(|| {
+                anyhow!("CLOSED_CARDS must be delete, archive, move:<chat id> or keep:<days>")
+            })?,
+            None => ClosedCards::Delete,
+        };
+        let hold_suggested_approvals = get("HOLD_SUGGESTED_APPROVALS")
+            .is_some_and(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes"));
+        let track_issues = get("TRACK_ISSUES")
+            .is_some_and(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes"));
+        let issue_labels = get("ISSUE_LABELS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect();
+        let announce_releases = get("ANNOUNCE_RELEASES")
+            .is_some_and(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes"));
+        let main_branch_alerts = get("MAIN_BRANCH_ALERTS")
+            .is_some_and(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes"));
+        let ping_code_owners = get("PING_CODE_OWNERS")
+            .is_some_and(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes"));
+
+        let federation_instance =
+            get("FEDERATION_INSTANCE").unwrap_or_else(|| "default".to_string());
+        let federation_secret = get("FEDERATION_SECRET").filter(|v| !v.is_empty());
+        let federation_listen_addr = get("FEDERATION_LISTEN_ADDR").filter(|v| !v.is_empty());
+        let metrics_listen_addr = get("METRICS_LISTEN_ADDR").filter(|v| !v.is_empty());
+        let health_listen_addr = get("HEALTH_LISTEN_ADDR").filter(|v| !v.is_empty());
+        let federation_peers = get("FEDERATION_PEERS")
+            .unwrap_or_default()
+            .split(',')
+            .filter(|s| !s.trim().is_empty())
+            .map(|s| match s.split_once('=') {
+                Some((name, url)) if !name.trim().is_empty() => {
+                    Ok((name.trim().to_string(), url.trim().to_string()))
+                }
+                _ => Err(anyhow!(
+                    "Invalid federation peer \"{}\", expected name=url",
+                    s
+                )),
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let audit_chat_id = get("AUDIT_CHAT_ID").and_then(|v| v.parse::<i64>().ok());
+        let audit_thread_id = get("AUDIT_THREAD_ID").and_then(|v| v.parse::<i32>().ok());
+
+        let cycle_report_chat_id = get("CYCLE_REPORT_CHAT_ID").and_then(|v| v.parse::<i64>().ok());
+        let cycle_report_thread_id =
+            get("CYCLE_REPORT_THREAD_ID").and_then(|v| v.parse::<i32>().ok());
+
+        let demo_chat_id = get("DEMO_CHAT_ID").and_then(|v| v.parse::<i64>().ok());
+        let demo_step_secs = get("DEMO_STEP_SECONDS")
+            .and_then(|v| v.parse::<u64>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(30);
+
+        let reactions = ReactionMap::parse(&get("REACTION_EMOJIS").unwrap_or_default())?;
+        let reaction_precedence =
+            Precedence::parse(&get("REACTION_PRECEDENCE").unwrap_or_default())?;
+
+        let security = SecurityPolicy::from_settings(&settings)?;
 
         Ok(Self {
             telegram_bot_token,
-            github_token,
+            github_auth,
+            github_api_url,
+            github_web_url,
             chat_id,
+            database_url,
             repositories,
             ignored_repositories,
+            skip_authors,
+            chat_verification_codes,
+            mirror_chats,
+            admin_user_ids,
+            tenants,
+            tenant: None,
+            repo_options,
+            group_threshold,
+            group_window_minutes,
+            group_hold_minutes,
+            poll_interval_secs,
+            quiet_sync_every,
+            webhook_listen_addr,
+            webhook_secret,
+            user_token_key,
+            telegram_webhook_url,
+            telegram_webhook_listen_addr,
+            telegram_webhook_secret,
+            error_reply_ttl_secs,
+            inactive_repo_months,
+            review_sla_hours,
+            review_timebox_minutes,
+            review_timebox_release,
+            nudge_budget,
+            stale_after_days,
+            compact_after_days,
+            priority_labels,
+            github_orgs,
+            org_repo_topics,
+            org_repo_names,
+            rerender_interval_hours,
+            conflict_nudge_hours,
+            conflict_nudge_text,
+            digest_time,
+            digest_filter,
+            lead_inbox_time,
+            leaderboard_day,
+            drift_report_day,
+            fairness_report_day,
+            ready_ping_reviewers,
+            hold_drafts,
+            closed_cards,
+            hold_suggested_approvals,
+            track_issues,
+            issue_labels,
+            announce_releases,
+            main_branch_alerts,
+            ping_code_owners,
+            federation_instance,
+            federation_secret,
+            federation_listen_addr,
+            metrics_listen_addr,
+            health_listen_addr,
+            federation_peers,
+            audit_chat_id,
+            audit_thread_id,
+            cycle_report_chat_id,
+            cycle_report_thread_id,
+            demo_chat_id,
+            demo_step_secs,
+            reactions,
+            reaction_precedence,
+            security,
         })
     }
 }
+
+fn read_file(path: &Path) -> Result<FileConfig> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    match toml::from_str(&text) {
+        Ok(file) => Ok(file),
+        Err(e) => bail!("Invalid config file {}: {}", path.display(), e),
+    }
+}
+
+/// Tenant ids are typed in `/tenant <id>`: lowercase letters, digits, `-` and `_`. "leave"
+/// is the command's own.
+fn is_tenant_id(id: &str) -> bool {
+    !id.is_empty()
+        && id != "leave"
+        && id
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+}
+
+fn parse_repo(s: &str) -> Option<(String, String)> {
+    match s.trim().split_once('/') {
+        Some((owner, repo)) if !owner.is_empty() && !repo.is_empty() && !repo.contains('/') => {
+            Some((owner.to_string(), repo.to_string()))
+        }
+        _ => None,
+    }
+}
+
+fn parse_repo_list(list: &str, what: &str) -> Vec<(String, String)> {
+    list.split(',')
+        .filter(|s| !s.trim().is_empty())
+        .filter_map(|s| {
+            let repo = parse_repo(s);
+            if repo.is_none() {
+                // Don't panic here, just skip invalid or log
+                eprintln!("Invalid {} format: {}", what, s);
+            }
+            repo
+        })
+        .collect()
+}
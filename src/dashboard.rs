@@ -0,0 +1,115 @@
+use crate::handlers;
+use crate::lifecycle::PrStatus;
+use crate::monitor::Monitor;
+use crate::sender::Retried;
+use crate::state::StateManager;
+use std::collections::HashMap;
+use teloxide::prelude::*;
+use teloxide::types::{MessageId, ParseMode};
+use teloxide::{ApiError, RequestError};
+use tracing::{error, info};
+
+const REFRESH_INTERVAL: tokio::time::Duration = tokio::time::Duration::from_secs(60);
+/// PRs listed on a dashboard, keeping it under Telegram's message size limit.
+const ROW_LIMIT: usize = 40;
+const TITLE_LIMIT: usize = 40;
+
+/// Keeps every chat's pinned dashboard in line with its open PRs. Dashboards are only
+/// edited when their content changed.
+pub async fn run(monitor: Monitor) {
+    let mut shown: HashMap<i64, String> = HashMap::new();
+    loop {
+        let dashboards = match monitor.state.get_dashboards().await {
+            Ok(dashboards) => dashboards,
+            Err(e) => {
+                error!("Failed to load dashboards: {}", e);
+                Vec::new()
+            }
+        };
+        for (chat_id, message_id) in dashboards {
+            let text = dashboard_text(&monitor.state, chat_id).await;
+            if shown.get(&chat_id) == Some(&text) {
+                continue;
+            }
+            match monitor
+                .bot
+                .edit_message_text(
+                    ChatId(chat_id),
+                    MessageId(message_id.parse().unwrap_or(0)),
+                    text.clone(),
+                )
+                .parse_mode(ParseMode::Html)
+                .link_preview_options(handlers::no_link_preview())
+                .retried()
+                .await
+            {
+                Ok(_) | Err(RequestError::Api(ApiError::MessageNotModified)) => {
+                    shown.insert(chat_id, text);
+                }
+                Err(RequestError::Api(ApiError::MessageToEditNotFound)) => {
+                    info!("Dashboard of {} was deleted, forgetting it", chat_id);
+                    monitor.state.set_dashboard(chat_id, None).await.ok();
+                    shown.remove(&chat_id);
+                }
+                Err(e) => error!("Failed to update the dashboard of {}: {}", chat_id, e),
+            }
+        }
+        tokio::time::sleep(REFRESH_INTERVAL).await;
+    }
+}
+
+/// Where a status is listed on the dashboard: what needs the team first.
+fn order(status: PrStatus) -> u8 {
+    match status {
+        PrStatus::AwaitingReview => 0,
+        PrStatus::ChangesRequested => 1,
+        PrStatus::InReview => 2,
+        PrStatus::Approved => 3,
+        PrStatus::ReadyToMerge => 4,
+        PrStatus::Blocked => 5,
+        PrStatus::Draft => 6,
+        PrStatus::Snoozed => 7,
+        PrStatus::Merged | PrStatus::Closed => 8,
+    }
+}
+
+/// One line per open PR of the chat, most in need of attention first.
+pub async fn dashboard_text(state: &StateManager, chat_id: i64) -> String {
+    let mut rows = Vec::new();
+    for m in state.get_chat_messages(chat_id).await.unwrap_or_default() {
+        let Ok(Some(data)) = state.get_pr_data(m.message_id.clone(), chat_id).await else {
+            continue;
+        };
+        if data.status.is_terminal() {
+            continue;
+        }
+        rows.push((m.message_id, data));
+    }
+    rows.sort_by_key(|(_, d)| (order(d.status), d.status_since));
+
+    let mut text = format!("📌 <b>Open PRs ({})</b>\n", rows.len());
+    if rows.is_empty() {
+        text.push_str("\nNothing open right now 🎉");
+    }
+    for (message_id, data) in rows.iter().take(ROW_LIMIT) {
+        let link =
+            handlers::message_link(chat_id, message_id).unwrap_or_else(|| data.pr_url.clone());
+        let mut title: String = data.title.chars().take(TITLE_LIMIT).collect();
+        if data.title.chars().count() > TITLE_LIMIT {
+            title.push('…');
+        }
+        text.push_str(&format!(
+            "{} <a href=\"{}\">{}#{}</a> {} {}\n",
+            data.status.badge(),
+            link,
+            data.repo,
+            data.pr_number,
+            teloxide::utils::html::escape(&title),
+            handlers::review_summary(data)
+        ));
+    }
+    if rows.len() > ROW_LIMIT {
+        text.push_str(&format!("…and {} more\n", rows.len() - ROW_LIMIT));
+    }
+    text
+}
@@ -1,16 +1,35 @@
+use crate::metrics::TimedPool;
+use crate::storage::{self, Storage};
 use anyhow::Result;
-use sqlx::{sqlite::SqlitePool, FromRow, Row};
+use serde::{Deserialize, Serialize};
+use sqlx::{any::AnyPoolOptions, Executor, FromRow, Row};
+use std::borrow::Cow;
+use tracing::{info, warn};
+
+/// Stands in for an erased user in the events history.
+pub const FORMER_MEMBER: &str = "former member";
 
 #[derive(Clone)]
 pub struct Db {
-    pool: SqlitePool,
+    pool: TimedPool,
+    storage: &'static dyn Storage,
+    safe_mode: Option<SchemaVersions>,
+}
+
+/// The schema version of a database a newer build migrated, and the newest this build knows.
+/// Versions are those of the migrations, recorded in `_sqlx_migrations`.
+#[derive(Clone, Copy, Debug)]
+pub struct SchemaVersions {
+    pub database: i64,
+    pub build: i64,
 }
 
 #[derive(FromRow, Debug)]
 pub struct TrackedRepo {
-    pub id: i64,
+    pub chat_id: i64,
     pub owner: String,
     pub name: String,
+    pub thread_id: Option<i64>, // forum topic for the repository's cards, set with /track
 }
 
 #[derive(FromRow, Debug)]
@@ -23,98 +42,590 @@ pub struct PrMessage {
     pub repo_owner: String,
     pub repo_name: String,
     pub pr_number: i64,
-    pub is_merged: bool,
-    pub is_draft: bool,
-    pub re_review_requested: bool,
+    pub status: String, // see lifecycle::PrStatus
+    pub review_round: i64,
+    pub status_since: i64,
+    pub snoozed_until: Option<i64>,
+    pub gates: Option<String>,  // JSON of the last evaluated check gates
+    pub security: i64,          // 1 = needs a security reviewer's approval
+    pub ci: Option<String>,     // JSON of the last CI summary
+    pub conflicts: i64,         // 1 = has merge conflicts
+    pub labels: Option<String>, // JSON list of GitHub labels
+    pub head_sha: Option<String>,
+    pub unresolved_threads: Option<i64>, // NULL when the base branch doesn't require resolution
+    pub requested_reviewers: Option<String>, // JSON list of GitHub logins and org/team teams
+    pub departed: Option<String>,        // JSON list of people on the card who left the chat
+    pub owner: Option<String>,           // who took the PR over after its author left
+    pub last_reminded_at: Option<i64>,   // set by reminders.rs only, never by saving the card
+    pub review_sessions: Option<String>, // JSON list of running review sessions, see sessions.rs
+    pub stale_since: Option<i64>,        // last GitHub activity, while the PR is stale
+    pub last_nudged_at: Option<i64>,     // set by stale.rs only, never by saving the card
+    pub conflicted_since: Option<i64>,   // when the PR last became conflicted, while it is
+    pub conflict_nudged_sha: Option<String>, // head commit when the author was asked to rebase
+    pub thread_id: Option<i64>,          // forum topic the card was posted in
+    pub maintainers: Option<String>, // JSON list, when the repository needs a maintainer's approval
+    pub suggestions: Option<String>, // JSON list of approvals with comments, see state::Suggestion
+    pub tickets: Option<String>,     // JSON list of linked ticket ids, see tickets.rs
+    pub rendered_at: Option<i64>,    // set by edit_card only, never by saving the card
+    pub item_type: String,           // see state::ItemType
+    pub assignees: Option<String>,   // JSON list of GitHub logins, issues only
+    pub settled_key: Option<String>, // set by compact.rs only, never by saving the card
+    pub settled_since: Option<i64>,  // set by compact.rs only, never by saving the card
+    pub compact: i64,                // 1 = shown as one line while the state matches settled_key
+}
+
+/// A Telegram user's GitHub account, linked with `/link`.
+#[derive(FromRow, Debug, Clone)]
+pub struct UserLink {
+    pub telegram_id: i64,
+    pub telegram_name: String, // as the user appears in the cards' review lists
+    pub telegram_username: Option<String>,
+    pub github_login: String,
+    pub linked_at: i64,
+}
+
+/// How recently a tracked PR changed on GitHub, for adaptive polling.
+#[derive(FromRow, Debug)]
+pub struct PrActivity {
+    pub repo_owner: String,
+    pub repo_name: String,
+    pub pr_number: i64,
+    pub last_active: i64, // the PR's `updated_at` at the last sync
+    pub last_synced: i64,
+}
+
+/// A named check run whose result is shown on the cards of a repository.
+#[derive(FromRow, Debug, Clone)]
+pub struct CheckGate {
+    pub check_name: String,
+    pub critical: i64, // 1 = notify the card when it fails
+}
+
+/// A `/tickets` entry: ticket ids matching `pattern` link to `url_template` with `{id}` filled in.
+#[derive(FromRow, Debug, Clone)]
+pub struct TicketTracker {
+    pub id: i64,
+    pub pattern: String,
+    pub url_template: String,
+}
+
+/// An alert about failing checks on a repository's default branch, see branch_health.rs.
+#[derive(FromRow, Debug, Clone)]
+pub struct BranchAlert {
+    pub sha: String,
+    pub message_id: i64,
+    pub failing_since: i64,
+}
+
+/// A `/rules` entry; `rule` is the text it was added with, see rules.rs.
+#[derive(FromRow, Debug, Clone)]
+pub struct StoredRule {
+    pub id: i64,
+    pub rule: String,
+    pub created_by: String,
+}
+
+/// A GitHub response with the validators GitHub sent for it, see `GithubClient::get_revalidated`.
+#[derive(FromRow, Debug, Clone)]
+pub struct CachedResponse {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub body: String,
+}
+
+/// What the bot's GitHub access allows on a repository, see onboarding.rs. Each flag is 1 or
+/// 0, or NULL when the probe couldn't tell.
+#[derive(FromRow, Debug, Clone, Default)]
+pub struct RepoCapabilities {
+    pub list_prs: Option<i64>,
+    pub read_reviews: Option<i64>,
+    pub read_checks: Option<i64>,
+    pub write_labels: Option<i64>,
+}
+
+impl RepoCapabilities {
+    /// Whether the probe found the capability missing; unknown ones are still attempted.
+    pub fn denies(flag: Option<i64>) -> bool {
+        flag == Some(0)
+    }
+}
+
+/// A `/search` subscription: new PRs matching `query` on GitHub are announced in the chat.
+#[derive(FromRow, Debug, Clone)]
+pub struct StoredSearch {
+    pub id: i64,
+    pub chat_id: i64,
+    pub query: String,
+    pub created_by: String,
+}
+
+/// Days of assignment history the round-robin weighs, so someone back from a week off isn't
+/// handed every new PR until their count catches up with everyone else's.
+pub const ASSIGNMENT_WINDOW_DAYS: i64 = 7;
+
+/// A member of a repository's reviewer pool in a chat, see `/pool`.
+#[derive(FromRow, Debug, Clone)]
+pub struct PoolMember {
+    pub username: String,
+    pub recent_assignments: i64, // in any of the chat's pools within ASSIGNMENT_WINDOW_DAYS
+    pub open_reviews: i64, // cards in the chat not yet merged or closed they're a ❤️ reviewer of
+    pub last_assigned: Option<i64>, // in this repository's pool
+}
+
+impl PoolMember {
+    /// Lower goes first: recent assignments plus open reviews, then who waited longest.
+    pub fn rank(&self) -> (i64, i64) {
+        (
+            self.recent_assignments + self.open_reviews,
+            self.last_assigned.unwrap_or(0),
+        )
+    }
+}
+
+/// How cards in a chat take review actions. Persisted as `chat_settings.button_mode`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ButtonMode {
+    Reactions = 0,
+    /// Buttons because reactions can't reach the bot; undone once they can again
+    Fallback = 1,
+    /// Buttons chosen with `/buttons on`; kept whether reactions work or not
+    Chosen = 2,
+}
+
+/// A chat's lead, who gets the morning inbox DM, see `/lead`.
+#[derive(FromRow, Debug, Clone)]
+pub struct Lead {
+    pub chat_id: i64,
+    pub user_id: i64,
+    pub name: String,
+}
+
+/// A reminder held back because its user's nudge budget ran out, see nudges.rs.
+#[derive(FromRow, Debug, Clone)]
+pub struct HeldNudge {
+    pub chat_id: i64,
+    pub username: String,
+    pub day: String,
+    pub line: String,
+}
+
+/// A merged PR kept in `merge_archive`; also the entry format of federation feeds.
+#[derive(FromRow, Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedMerge {
+    #[serde(skip)]
+    pub source: String, // "" for this instance, else the federation peer's name
+    pub chat_id: i64,
+    pub repo_owner: String,
+    pub repo_name: String,
+    pub pr_number: i64,
+    pub title: String,
+    pub author: String,
+    pub opened_at: i64,
+    pub merged_at: i64,
+    pub review_rounds: i64,
+    pub approvals: i64,
+}
+
+/// A position in a source's merge archive, which is ordered by merge time and then by the
+/// merge's key, so paging doesn't stall on many merges within one second.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveCursor {
+    pub merged_at: i64,
+    pub chat_id: i64,
+    pub repo_owner: String,
+    pub repo_name: String,
+    pub pr_number: i64,
+}
+
+impl ArchivedMerge {
+    pub fn cursor(&self) -> ArchiveCursor {
+        ArchiveCursor {
+            merged_at: self.merged_at,
+            chat_id: self.chat_id,
+            repo_owner: self.repo_owner.clone(),
+            repo_name: self.repo_name.clone(),
+            pr_number: self.pr_number,
+        }
+    }
+}
+
+#[derive(FromRow, Debug, Clone)]
+pub struct PrGroup {
+    pub message_id: String,
+    pub chat_id: i64,
+    pub author: String,
+    pub created_at: i64,
+}
+
+#[derive(FromRow, Debug, Clone)]
+pub struct GroupItem {
+    pub pr_url: String,
+    pub title: String,
+    pub repo_owner: String,
+    pub repo_name: String,
+    pub pr_number: i64,
+    pub status: String, // see lifecycle::PrStatus
+    pub security: i64,  // 1 when security-sensitive, see security.rs
+}
+
+/// A new PR held back by `PR_GROUP_HOLD_MINUTES`, see groups.rs.
+#[derive(FromRow, Debug, Clone)]
+pub struct BurstPr {
+    pub chat_id: i64,
+    pub author: String,
+    pub repo_owner: String,
+    pub repo_name: String,
+    pub pr_number: i64,
+    pub opened_at: i64,
+}
+
+#[derive(FromRow, Debug, Clone)]
+pub struct Event {
+    pub chat_id: i64,
+    pub message_id: String,
+    pub repo_owner: String,
+    pub repo_name: String,
+    pub pr_number: i64,
+    pub username: String,
+    pub kind: String,
+    pub detail: String,
+    pub created_at: i64,
 }
 
 impl Db {
-    pub async fn new(database_url: &str) -> Result<Self> {
-        let pool = SqlitePool::connect(database_url).await?;
-        let db = Self { pool };
-        db.init().await?;
+    /// Connects and migrates the database. The `operator`'s also gets the tables that belong to
+    /// the whole process rather than to a tenant, see `Storage::operator_schema`.
+    pub async fn new(database_url: &str, operator: bool) -> Result<Self> {
+        sqlx::any::install_default_drivers();
+        let pool = AnyPoolOptions::new().connect(database_url).await?;
+        let storage = storage::for_url(database_url);
+        let db = Self {
+            pool: TimedPool(pool),
+            storage,
+            safe_mode: None,
+        };
+
+        // After a rollback the schema can be ahead of this build, whose writes could then
+        // corrupt what it doesn't know about; it's only read until the newer build is back
+        let versions = SchemaVersions {
+            database: db.schema_version().await?,
+            build: storage
+                .migrator()
+                .iter()
+                .map(|m| m.version)
+                .max()
+                .unwrap_or(0),
+        };
+        if versions.database > versions.build {
+            warn!(
+                "Database schema version {} is newer than this build's {}; starting in read-only safe mode",
+                versions.database, versions.build
+            );
+            db.close().await;
+            let pool = AnyPoolOptions::new()
+                .after_connect(move |conn, _| {
+                    Box::pin(async move {
+                        conn.execute(storage.read_only_statement()).await?;
+                        Ok(())
+                    })
+                })
+                .connect(database_url)
+                .await?;
+            return Ok(Self {
+                pool: TimedPool(pool),
+                storage,
+                safe_mode: Some(versions),
+            });
+        }
+
+        db.init(operator).await?;
         Ok(db)
     }
 
-    async fn init(&self) -> Result<()> {
-        sqlx::query(
-            "CREATE TABLE IF NOT EXISTS repositories (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                owner TEXT NOT NULL,
-                name TEXT NOT NULL,
-                UNIQUE(owner, name)
-            )",
-        )
+    /// The schema versions when the database is newer than this build, which then only reads it.
+    pub fn safe_mode(&self) -> Option<SchemaVersions> {
+        self.safe_mode
+    }
+
+    /// The newest migration applied to the database; 0 for a new one.
+    async fn schema_version(&self) -> Result<i64> {
+        if !self.table_exists("_sqlx_migrations").await? {
+            return Ok(0);
+        }
+        let version: Option<i64> = sqlx::query_scalar("SELECT MAX(version) FROM _sqlx_migrations")
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(version.unwrap_or(0))
+    }
+
+    /// Checks the database answers, for the health check.
+    pub async fn ping(&self) -> Result<()> {
+        sqlx::query("SELECT 1").execute(&self.pool).await?;
+        Ok(())
+    }
+
+    /// Waits for running queries to finish and closes every connection.
+    pub async fn close(&self) {
+        self.pool.close().await
+    }
+
+    /// Rewrites `?` placeholders into the form the storage engine expects.
+    fn sql<'a>(&self, query: &'a str) -> Cow<'a, str> {
+        self.storage.sql(query)
+    }
+
+    async fn init(&self, operator: bool) -> Result<()> {
+        // Databases from before versioned migrations are first brought up to the baseline;
+        // the initial migration only uses IF NOT EXISTS, so it then just records them.
+        if self.table_exists("messages").await? && !self.table_exists("_sqlx_migrations").await? {
+            info!("Upgrading a database created before versioned migrations...");
+            self.upgrade_legacy().await?;
+        }
+
+        self.storage.migrator().run(&self.pool.0).await?;
+        if operator {
+            sqlx::query(self.storage.operator_schema())
+                .execute(&self.pool)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Applies the schema changes made before `migrations/` existed, in their original order.
+    async fn upgrade_legacy(&self) -> Result<()> {
+        // Repositories used to be global; rebuild the table so the unique key includes the chat.
+        // Legacy rows get chat 0, which `claim_unassigned_repositories` hands to the default chat.
+        if self
+            .add_column_if_missing("repositories", "chat_id", "BIGINT NOT NULL DEFAULT 0")
+            .await?
+        {
+            let mut tx = self.pool.begin().await?;
+            sqlx::query(&format!(
+                "CREATE TABLE repositories_new (
+                    {},
+                    chat_id BIGINT NOT NULL,
+                    owner TEXT NOT NULL,
+                    name TEXT NOT NULL,
+                    UNIQUE(chat_id, owner, name)
+                )",
+                self.storage.id_column()
+            ))
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query(
+                "INSERT INTO repositories_new (chat_id, owner, name)
+                 SELECT chat_id, owner, name FROM repositories",
+            )
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query("DROP TABLE repositories")
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("ALTER TABLE repositories_new RENAME TO repositories")
+                .execute(&mut *tx)
+                .await?;
+            tx.commit().await?;
+        }
+
+        // Lifecycle status replaces the is_merged/is_draft/re_review_requested flags.
+        // The old columns stay in place for existing databases but are no longer written.
+        if self
+            .add_column_if_missing("messages", "status", "TEXT")
+            .await?
+        {
+            sqlx::query(
+                "UPDATE messages SET status = CASE
+                    WHEN is_merged THEN 'merged'
+                    WHEN is_draft THEN 'draft'
+                    ELSE 'awaiting_review'
+                 END",
+            )
+            .execute(&self.pool)
+            .await?;
+        }
+        if self
+            .add_column_if_missing("messages", "review_round", "BIGINT NOT NULL DEFAULT 0")
+            .await?
+        {
+            sqlx::query("UPDATE messages SET review_round = 1 WHERE re_review_requested")
+                .execute(&self.pool)
+                .await?;
+        }
+
+        if self
+            .add_column_if_missing("messages", "status_since", "BIGINT NOT NULL DEFAULT 0")
+            .await?
+        {
+            sqlx::query(&self.sql("UPDATE messages SET status_since = ?"))
+                .bind(chrono::Utc::now().timestamp())
+                .execute(&self.pool)
+                .await?;
+        }
+        self.add_column_if_missing("messages", "snoozed_until", "BIGINT")
+            .await?;
+        self.add_column_if_missing("messages", "gates", "TEXT")
+            .await?;
+
+        Ok(())
+    }
+
+    async fn table_exists(&self, table: &str) -> Result<bool> {
+        let count: i64 = sqlx::query_scalar(&self.sql(self.storage.table_count_query()))
+            .bind(table)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(count > 0)
+    }
+
+    /// Adds a column to an existing table, returning whether it was actually added.
+    async fn add_column_if_missing(&self, table: &str, column: &str, decl: &str) -> Result<bool> {
+        let exists: i64 = sqlx::query_scalar(&self.sql(&self.storage.column_count_query(table)))
+            .bind(column)
+            .fetch_one(&self.pool)
+            .await?;
+
+        if exists > 0 {
+            return Ok(false);
+        }
+
+        sqlx::query(&format!(
+            "ALTER TABLE {} ADD COLUMN {} {}",
+            table, column, decl
+        ))
         .execute(&self.pool)
         .await?;
+        Ok(true)
+    }
 
-        sqlx::query(
-            "CREATE TABLE IF NOT EXISTS messages (
-                message_id TEXT NOT NULL,
-                chat_id INTEGER NOT NULL,
-                pr_url TEXT NOT NULL,
-                title TEXT NOT NULL,
-                author TEXT NOT NULL,
-                repo_owner TEXT NOT NULL,
-                repo_name TEXT NOT NULL,
-                pr_number INTEGER NOT NULL,
-                is_merged BOOLEAN DEFAULT 0,
-                is_draft BOOLEAN DEFAULT 0,
-                re_review_requested BOOLEAN DEFAULT 0,
-                PRIMARY KEY (message_id, chat_id)
-            )",
-        )
+    /// Subscribes the chat to the repository as its own, even where the configuration had.
+    pub async fn add_repository(&self, chat_id: i64, owner: &str, name: &str) -> Result<()> {
+        sqlx::query(&self.sql(
+            "INSERT INTO repositories (chat_id, owner, name) VALUES (?, ?, ?)
+             ON CONFLICT (chat_id, owner, name) DO UPDATE SET from_config = 0",
+        ))
+        .bind(chat_id)
+        .bind(owner)
+        .bind(name)
         .execute(&self.pool)
         .await?;
+        Ok(())
+    }
 
-        sqlx::query(
-            "CREATE TABLE IF NOT EXISTS reactions (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                message_id TEXT NOT NULL,
-                chat_id INTEGER NOT NULL,
-                username TEXT NOT NULL,
-                reaction_type TEXT NOT NULL, -- 'reviewer', 'approval', 'comment'
-                UNIQUE(message_id, chat_id, username, reaction_type)
-            )",
-        )
+    /// Subscribes the chat to a repository the configuration routes there, unless it is already.
+    pub async fn add_config_repository(&self, chat_id: i64, owner: &str, name: &str) -> Result<()> {
+        sqlx::query(&self.sql(
+            "INSERT INTO repositories (chat_id, owner, name, from_config) VALUES (?, ?, ?, 1)
+             ON CONFLICT DO NOTHING",
+        ))
+        .bind(chat_id)
+        .bind(owner)
+        .bind(name)
         .execute(&self.pool)
         .await?;
+        Ok(())
+    }
 
-        // Table to track seen PRs globally to avoid reposting if we restart
-        // key: owner/repo#number
-        sqlx::query(
-            "CREATE TABLE IF NOT EXISTS seen_prs (
-                key TEXT PRIMARY KEY,
-                seen_at INTEGER NOT NULL
-             )",
-        )
+    /// Removes a subscription the configuration made, in any spelling of the repository.
+    pub async fn remove_config_repository(
+        &self,
+        chat_id: i64,
+        owner: &str,
+        name: &str,
+    ) -> Result<bool> {
+        let result = sqlx::query(&self.sql(
+            "DELETE FROM repositories WHERE chat_id = ? AND LOWER(owner) = LOWER(?)
+             AND LOWER(name) = LOWER(?) AND from_config = 1",
+        ))
+        .bind(chat_id)
+        .bind(owner)
+        .bind(name)
         .execute(&self.pool)
         .await?;
+        Ok(result.rows_affected() > 0)
+    }
 
+    /// Routes the repository's new cards in `chat_id` to a forum topic, or back to the
+    /// chat's general topic with `None`.
+    pub async fn set_repository_topic(
+        &self,
+        chat_id: i64,
+        owner: &str,
+        name: &str,
+        thread_id: Option<i32>,
+    ) -> Result<()> {
+        sqlx::query(&self.sql(
+            "UPDATE repositories SET thread_id = ? WHERE chat_id = ? AND owner = ? AND name = ?",
+        ))
+        .bind(thread_id.map(i64::from))
+        .bind(chat_id)
+        .bind(owner)
+        .bind(name)
+        .execute(&self.pool)
+        .await?;
         Ok(())
     }
 
-    pub async fn add_repository(&self, owner: &str, name: &str) -> Result<()> {
-        sqlx::query("INSERT OR IGNORE INTO repositories (owner, name) VALUES (?, ?)")
-            .bind(owner)
-            .bind(name)
-            .execute(&self.pool)
-            .await?;
-        Ok(())
+    pub async fn remove_repository(&self, chat_id: i64, owner: &str, name: &str) -> Result<bool> {
+        let result = sqlx::query(
+            &self.sql("DELETE FROM repositories WHERE chat_id = ? AND owner = ? AND name = ?"),
+        )
+        .bind(chat_id)
+        .bind(owner)
+        .bind(name)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
     }
 
     pub async fn get_repositories(&self) -> Result<Vec<TrackedRepo>> {
-        let repos = sqlx::query_as::<_, TrackedRepo>("SELECT * FROM repositories")
-            .fetch_all(&self.pool)
-            .await?;
+        let repos = sqlx::query_as::<_, TrackedRepo>(
+            "SELECT chat_id, owner, name, thread_id FROM repositories ORDER BY owner, name",
+        )
+        .fetch_all(&self.pool)
+        .await?;
         Ok(repos)
     }
 
+    pub async fn claim_unassigned_repositories(&self, chat_id: i64) -> Result<()> {
+        // Drop legacy rows the chat already has a subscription for, then claim the rest
+        sqlx::query(&self.sql(
+            "DELETE FROM repositories WHERE chat_id = 0 AND EXISTS (
+                SELECT 1 FROM repositories r
+                WHERE r.chat_id = ? AND r.owner = repositories.owner AND r.name = repositories.name
+             )",
+        ))
+        .bind(chat_id)
+        .execute(&self.pool)
+        .await?;
+        sqlx::query(&self.sql("UPDATE repositories SET chat_id = ? WHERE chat_id = 0"))
+            .bind(chat_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
     pub async fn save_pr_message(&self, msg: &PrMessage) -> Result<()> {
         sqlx::query(
-            "INSERT OR REPLACE INTO messages 
-            (message_id, chat_id, pr_url, title, author, repo_owner, repo_name, pr_number, is_merged, is_draft, re_review_requested)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            &self.sql("INSERT INTO messages
+            (message_id, chat_id, pr_url, title, author, repo_owner, repo_name, pr_number, status, review_round, status_since, snoozed_until, gates, security, ci, conflicts, labels, head_sha, unresolved_threads, requested_reviewers, departed, owner, review_sessions, stale_since, conflicted_since, conflict_nudged_sha, thread_id, maintainers, suggestions, tickets, item_type, assignees)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT (message_id, chat_id) DO UPDATE SET
+                pr_url = excluded.pr_url, title = excluded.title, author = excluded.author,
+                repo_owner = excluded.repo_owner, repo_name = excluded.repo_name,
+                pr_number = excluded.pr_number, status = excluded.status,
+                review_round = excluded.review_round, status_since = excluded.status_since,
+                snoozed_until = excluded.snoozed_until, gates = excluded.gates,
+                security = excluded.security, ci = excluded.ci, conflicts = excluded.conflicts,
+                labels = excluded.labels, head_sha = excluded.head_sha,
+                unresolved_threads = excluded.unresolved_threads,
+                requested_reviewers = excluded.requested_reviewers,
+                departed = excluded.departed, owner = excluded.owner,
+                review_sessions = excluded.review_sessions, stale_since = excluded.stale_since,
+                conflicted_since = excluded.conflicted_since,
+                conflict_nudged_sha = excluded.conflict_nudged_sha,
+                thread_id = excluded.thread_id, maintainers = excluded.maintainers,
+                suggestions = excluded.suggestions, tickets = excluded.tickets,
+                item_type = excluded.item_type, assignees = excluded.assignees"),
         )
         .bind(&msg.message_id)
         .bind(msg.chat_id)
@@ -124,21 +635,86 @@ impl Db {
         .bind(&msg.repo_owner)
         .bind(&msg.repo_name)
         .bind(msg.pr_number)
-        .bind(msg.is_merged)
-        .bind(msg.is_draft)
-        .bind(msg.re_review_requested)
+        .bind(&msg.status)
+        .bind(msg.review_round)
+        .bind(msg.status_since)
+        .bind(msg.snoozed_until)
+        .bind(&msg.gates)
+        .bind(msg.security)
+        .bind(&msg.ci)
+        .bind(msg.conflicts)
+        .bind(&msg.labels)
+        .bind(&msg.head_sha)
+        .bind(msg.unresolved_threads)
+        .bind(&msg.requested_reviewers)
+        .bind(&msg.departed)
+        .bind(&msg.owner)
+        .bind(&msg.review_sessions)
+        .bind(msg.stale_since)
+        .bind(msg.conflicted_since)
+        .bind(&msg.conflict_nudged_sha)
+        .bind(msg.thread_id)
+        .bind(&msg.maintainers)
+        .bind(&msg.suggestions)
+        .bind(&msg.tickets)
+        .bind(&msg.item_type)
+        .bind(&msg.assignees)
         .execute(&self.pool)
         .await?;
         Ok(())
     }
 
+    /// Replaces the gates of a repository; an empty list removes them.
+    pub async fn set_check_gates(
+        &self,
+        owner: &str,
+        name: &str,
+        gates: &[CheckGate],
+    ) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(&self.sql("DELETE FROM check_gates WHERE owner = ? AND name = ?"))
+            .bind(owner)
+            .bind(name)
+            .execute(&mut *tx)
+            .await?;
+
+        for gate in gates {
+            sqlx::query(&self.sql(
+                "INSERT INTO check_gates (owner, name, check_name, critical) VALUES (?, ?, ?, ?)
+                 ON CONFLICT DO NOTHING",
+            ))
+            .bind(owner)
+            .bind(name)
+            .bind(&gate.check_name)
+            .bind(gate.critical)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn get_check_gates(&self, owner: &str, name: &str) -> Result<Vec<CheckGate>> {
+        let gates = sqlx::query_as::<_, CheckGate>(&self.sql(
+            "SELECT check_name, critical FROM check_gates WHERE owner = ? AND name = ?
+             ORDER BY check_name",
+        ))
+        .bind(owner)
+        .bind(name)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(gates)
+    }
+
     pub async fn get_pr_message(
         &self,
         message_id: &str,
         chat_id: i64,
     ) -> Result<Option<PrMessage>> {
         let msg = sqlx::query_as::<_, PrMessage>(
-            "SELECT * FROM messages WHERE message_id = ? AND chat_id = ?",
+            &self.sql("SELECT * FROM messages WHERE message_id = ? AND chat_id = ?"),
         )
         .bind(message_id)
         .bind(chat_id)
@@ -147,44 +723,44 @@ impl Db {
         Ok(msg)
     }
 
+    /// Replaces the card's reviewer claims and its verdicts, (reaction type, users) each.
     pub async fn update_reactions(
         &self,
         message_id: &str,
         chat_id: i64,
         reviewers: &[String],
-        approvals: &[String],
-        changes_requested: &[String],
-        comments: &[String],
+        verdicts: [(&str, &[String]); 3],
+        from_github: &[String],
     ) -> Result<()> {
         // Transactional update
         let mut tx = self.pool.begin().await?;
+        let source = |user: &String| {
+            if from_github.contains(user) {
+                "github"
+            } else {
+                "telegram"
+            }
+        };
 
         // Clear existing for this message
-        sqlx::query("DELETE FROM reactions WHERE message_id = ? AND chat_id = ?")
+        sqlx::query(&self.sql("DELETE FROM reactions WHERE message_id = ? AND chat_id = ?"))
             .bind(message_id)
             .bind(chat_id)
             .execute(&mut *tx)
             .await?;
 
+        // Claims to be reviewing only ever come from Telegram
         for user in reviewers {
-            sqlx::query("INSERT INTO reactions (message_id, chat_id, username, reaction_type) VALUES (?, ?, ?, 'reviewer')")
-                .bind(message_id).bind(chat_id).bind(user)
-                .execute(&mut *tx).await?;
-        }
-        for user in approvals {
-            sqlx::query("INSERT INTO reactions (message_id, chat_id, username, reaction_type) VALUES (?, ?, ?, 'approval')")
-                .bind(message_id).bind(chat_id).bind(user)
-                .execute(&mut *tx).await?;
-        }
-        for user in changes_requested {
-            sqlx::query("INSERT INTO reactions (message_id, chat_id, username, reaction_type) VALUES (?, ?, ?, 'changes_requested')")
+            sqlx::query(&self.sql("INSERT INTO reactions (message_id, chat_id, username, reaction_type) VALUES (?, ?, ?, 'reviewer')"))
                 .bind(message_id).bind(chat_id).bind(user)
                 .execute(&mut *tx).await?;
         }
-        for user in comments {
-            sqlx::query("INSERT INTO reactions (message_id, chat_id, username, reaction_type) VALUES (?, ?, ?, 'comment')")
-                .bind(message_id).bind(chat_id).bind(user)
-                .execute(&mut *tx).await?;
+        for (reaction_type, users) in verdicts {
+            for user in users {
+                sqlx::query(&self.sql("INSERT INTO reactions (message_id, chat_id, username, reaction_type, source) VALUES (?, ?, ?, ?, ?)"))
+                    .bind(message_id).bind(chat_id).bind(user).bind(reaction_type).bind(source(user))
+                    .execute(&mut *tx).await?;
+            }
         }
 
         tx.commit().await?;
@@ -196,9 +772,9 @@ impl Db {
         message_id: &str,
         chat_id: i64,
     ) -> Result<(Vec<String>, Vec<String>, Vec<String>, Vec<String>)> {
-        let rows = sqlx::query(
+        let rows = sqlx::query(&self.sql(
             "SELECT username, reaction_type FROM reactions WHERE message_id = ? AND chat_id = ?",
-        )
+        ))
         .bind(message_id)
         .bind(chat_id)
         .fetch_all(&self.pool)
@@ -224,42 +800,1785 @@ impl Db {
         Ok((reviewers, approvals, changes_requested, comments))
     }
 
+    /// Who has an entry on the card that came from their GitHub review.
+    pub async fn get_github_sourced(&self, message_id: &str, chat_id: i64) -> Result<Vec<String>> {
+        let users = sqlx::query_scalar(&self.sql(
+            "SELECT DISTINCT username FROM reactions
+             WHERE message_id = ? AND chat_id = ? AND source = 'github'",
+        ))
+        .bind(message_id)
+        .bind(chat_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(users)
+    }
+
     pub async fn is_pr_seen(&self, key: &str) -> Result<bool> {
-        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM seen_prs WHERE key = ?")
-            .bind(key)
-            .fetch_one(&self.pool)
-            .await?;
+        let count: i64 =
+            sqlx::query_scalar(&self.sql("SELECT COUNT(*) FROM seen_prs WHERE key = ?"))
+                .bind(key)
+                .fetch_one(&self.pool)
+                .await?;
         Ok(count > 0)
     }
 
     pub async fn mark_pr_seen(&self, key: &str) -> Result<()> {
-        sqlx::query("INSERT OR IGNORE INTO seen_prs (key, seen_at) VALUES (?, ?)")
-            .bind(key)
-            .bind(chrono::Utc::now().timestamp())
-            .execute(&self.pool)
-            .await?;
+        sqlx::query(
+            &self.sql("INSERT INTO seen_prs (key, seen_at) VALUES (?, ?) ON CONFLICT DO NOTHING"),
+        )
+        .bind(key)
+        .bind(chrono::Utc::now().timestamp())
+        .execute(&self.pool)
+        .await?;
         Ok(())
     }
 
-    pub async fn get_all_active_messages(&self) -> Result<Vec<PrMessage>> {
-        let msgs = sqlx::query_as::<_, PrMessage>("SELECT * FROM messages WHERE is_merged = 0")
-            .fetch_all(&self.pool)
-            .await?;
-        Ok(msgs)
+    /// Remembers a release as announced in the chat. Returns whether it wasn't already.
+    pub async fn mark_release_seen(&self, chat_id: i64, repo: &str, tag: &str) -> Result<bool> {
+        let inserted = sqlx::query(&self.sql(
+            "INSERT INTO seen_releases (chat_id, repo, tag, seen_at) VALUES (?, ?, ?, ?)
+             ON CONFLICT DO NOTHING",
+        ))
+        .bind(chat_id)
+        .bind(repo)
+        .bind(tag)
+        .bind(chrono::Utc::now().timestamp())
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+        Ok(inserted > 0)
     }
 
-    pub async fn remove_message(&self, message_id: &str, chat_id: i64) -> Result<()> {
-        let mut tx = self.pool.begin().await?;
+    /// Records an organization's repository as found. False when it already was.
+    pub async fn mark_repo_discovered(&self, owner: &str, name: &str) -> Result<bool> {
+        let inserted = sqlx::query(&self.sql(
+            "INSERT INTO discovered_repos (owner, name, discovered_at) VALUES (?, ?, ?)
+             ON CONFLICT DO NOTHING",
+        ))
+        .bind(owner)
+        .bind(name)
+        .bind(chrono::Utc::now().timestamp())
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+        Ok(inserted > 0)
+    }
 
-        // Delete reactions first (FK like behavior)
-        sqlx::query("DELETE FROM reactions WHERE message_id = ? AND chat_id = ?")
+    /// Remembers a PR as seen but held back from announcing until it's released.
+    pub async fn hold_pr(&self, key: &str) -> Result<()> {
+        sqlx::query(&self.sql(
+            "INSERT INTO seen_prs (key, seen_at, held) VALUES (?, ?, 1) ON CONFLICT DO NOTHING",
+        ))
+        .bind(key)
+        .bind(chrono::Utc::now().timestamp())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn get_held_prs(&self) -> Result<Vec<String>> {
+        let keys = sqlx::query_scalar(&self.sql("SELECT key FROM seen_prs WHERE held = 1"))
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(keys)
+    }
+
+    /// Stops holding a PR back; it stays seen. Returns whether it was held.
+    pub async fn release_pr(&self, key: &str) -> Result<bool> {
+        let released =
+            sqlx::query(&self.sql("UPDATE seen_prs SET held = 0 WHERE key = ? AND held = 1"))
+                .bind(key)
+                .execute(&self.pool)
+                .await?
+                .rows_affected();
+        Ok(released > 0)
+    }
+
+    /// Claims the chat's card for a PR. Returns false when the chat already has one (or a
+    /// claim that's still being posted), in which case nothing must be posted.
+    pub async fn claim_card(
+        &self,
+        chat_id: i64,
+        owner: &str,
+        repo: &str,
+        pr_number: i64,
+    ) -> Result<bool> {
+        let result = sqlx::query(&self.sql(
+            "INSERT INTO pr_tracking (chat_id, repo_owner, repo_name, pr_number, claimed_at)
+             VALUES (?, ?, ?, ?, ?) ON CONFLICT DO NOTHING",
+        ))
+        .bind(chat_id)
+        .bind(owner)
+        .bind(repo)
+        .bind(pr_number)
+        .bind(chrono::Utc::now().timestamp())
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn set_card_message(
+        &self,
+        chat_id: i64,
+        owner: &str,
+        repo: &str,
+        pr_number: i64,
+        message_id: &str,
+    ) -> Result<()> {
+        sqlx::query(&self.sql(
+            "UPDATE pr_tracking SET message_id = ?
+             WHERE chat_id = ? AND repo_owner = ? AND repo_name = ? AND pr_number = ?",
+        ))
+        .bind(message_id)
+        .bind(chat_id)
+        .bind(owner)
+        .bind(repo)
+        .bind(pr_number)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Drops a claim whose card couldn't be posted.
+    pub async fn release_card(
+        &self,
+        chat_id: i64,
+        owner: &str,
+        repo: &str,
+        pr_number: i64,
+    ) -> Result<()> {
+        sqlx::query(&self.sql(
+            "DELETE FROM pr_tracking
+             WHERE chat_id = ? AND repo_owner = ? AND repo_name = ? AND pr_number = ?",
+        ))
+        .bind(chat_id)
+        .bind(owner)
+        .bind(repo)
+        .bind(pr_number)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Message id of the chat's card for a PR; empty while the card is being posted.
+    pub async fn find_card(
+        &self,
+        chat_id: i64,
+        owner: &str,
+        repo: &str,
+        pr_number: i64,
+    ) -> Result<Option<String>> {
+        let message_id = sqlx::query_scalar(&self.sql(
+            "SELECT message_id FROM pr_tracking
+             WHERE chat_id = ? AND repo_owner = ? AND repo_name = ? AND pr_number = ?",
+        ))
+        .bind(chat_id)
+        .bind(owner)
+        .bind(repo)
+        .bind(pr_number)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(message_id)
+    }
+
+    /// Drops claims that never got a message id, left behind by a crash before posting.
+    pub async fn release_stale_claims(&self, before: i64) -> Result<u64> {
+        let result = sqlx::query(
+            &self.sql("DELETE FROM pr_tracking WHERE message_id = '' AND claimed_at < ?"),
+        )
+        .bind(before)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Registers cards created before `pr_tracking` existed.
+    pub async fn backfill_card_tracking(&self) -> Result<()> {
+        // `WHERE true` keeps SQLite from reading ON CONFLICT as part of the SELECT
+        sqlx::query(&self.sql(
+            "INSERT INTO pr_tracking (chat_id, repo_owner, repo_name, pr_number, message_id, claimed_at)
+             SELECT chat_id, repo_owner, repo_name, pr_number, message_id, ?
+             FROM messages WHERE true
+             ON CONFLICT DO NOTHING",
+        ))
+        .bind(chrono::Utc::now().timestamp())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn get_all_messages(&self) -> Result<Vec<PrMessage>> {
+        let msgs = sqlx::query_as::<_, PrMessage>("SELECT * FROM messages")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(msgs)
+    }
+
+    pub async fn get_all_active_messages(&self) -> Result<Vec<PrMessage>> {
+        let msgs = sqlx::query_as::<_, PrMessage>(
+            "SELECT * FROM messages WHERE status NOT IN ('merged', 'closed')",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(msgs)
+    }
+
+    pub async fn get_chat_messages(&self, chat_id: i64) -> Result<Vec<PrMessage>> {
+        let msgs =
+            sqlx::query_as::<_, PrMessage>(&self.sql("SELECT * FROM messages WHERE chat_id = ?"))
+                .bind(chat_id)
+                .fetch_all(&self.pool)
+                .await?;
+        Ok(msgs)
+    }
+
+    pub async fn get_messages_for_pr(
+        &self,
+        owner: &str,
+        repo: &str,
+        pr_number: i64,
+    ) -> Result<Vec<PrMessage>> {
+        let msgs = sqlx::query_as::<_, PrMessage>(&self.sql(
+            "SELECT * FROM messages WHERE repo_owner = ? AND repo_name = ? AND pr_number = ?",
+        ))
+        .bind(owner)
+        .bind(repo)
+        .bind(pr_number)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(msgs)
+    }
+
+    pub async fn get_active_messages_for_repo(
+        &self,
+        owner: &str,
+        repo: &str,
+    ) -> Result<Vec<PrMessage>> {
+        let msgs = sqlx::query_as::<_, PrMessage>(&self.sql(
+            "SELECT * FROM messages WHERE repo_owner = ? AND repo_name = ?
+             AND status NOT IN ('merged', 'closed')",
+        ))
+        .bind(owner)
+        .bind(repo)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(msgs)
+    }
+
+    pub async fn record_event(&self, event: &Event) -> Result<()> {
+        sqlx::query(
+            &self.sql("INSERT INTO events
+            (chat_id, message_id, repo_owner, repo_name, pr_number, username, kind, detail, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)"),
+        )
+        .bind(event.chat_id)
+        .bind(&event.message_id)
+        .bind(&event.repo_owner)
+        .bind(&event.repo_name)
+        .bind(event.pr_number)
+        .bind(&event.username)
+        .bind(&event.kind)
+        .bind(&event.detail)
+        .bind(event.created_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Replaces the events imported for a repository in a chat, see `/backfill`. Imported
+    /// events have no card, so an empty `message_id`.
+    pub async fn import_events(
+        &self,
+        chat_id: i64,
+        owner: &str,
+        repo: &str,
+        events: &[Event],
+    ) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(&self.sql(
+            "DELETE FROM events
+             WHERE chat_id = ? AND repo_owner = ? AND repo_name = ? AND message_id = ''",
+        ))
+        .bind(chat_id)
+        .bind(owner)
+        .bind(repo)
+        .execute(&mut *tx)
+        .await?;
+
+        for event in events {
+            sqlx::query(&self.sql(
+                "INSERT INTO events
+                (chat_id, message_id, repo_owner, repo_name, pr_number, username, kind, detail, created_at)
+                VALUES (?, '', ?, ?, ?, ?, ?, ?, ?)",
+            ))
+            .bind(chat_id)
+            .bind(owner)
+            .bind(repo)
+            .bind(event.pr_number)
+            .bind(&event.username)
+            .bind(&event.kind)
+            .bind(&event.detail)
+            .bind(event.created_at)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Details of a chat's events of one kind since `since`.
+    pub async fn get_event_details(
+        &self,
+        chat_id: i64,
+        kind: &str,
+        since: i64,
+    ) -> Result<Vec<String>> {
+        let details =
+            sqlx::query_scalar::<_, String>(&self.sql(
+                "SELECT detail FROM events WHERE chat_id = ? AND kind = ? AND created_at >= ?",
+            ))
+            .bind(chat_id)
+            .bind(kind)
+            .bind(since)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(details)
+    }
+
+    /// (username, detail) of a chat's events of `kind` since `since`.
+    pub async fn get_events_since(
+        &self,
+        chat_id: i64,
+        kind: &str,
+        since: i64,
+    ) -> Result<Vec<(String, String)>> {
+        let events = sqlx::query_as::<_, (String, String)>(&self.sql(
+            "SELECT username, detail FROM events WHERE chat_id = ? AND kind = ? AND created_at >= ?",
+        ))
+        .bind(chat_id)
+        .bind(kind)
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(events)
+    }
+
+    /// (username, detail) of a card's events of `kind`, oldest first.
+    pub async fn get_card_events(
+        &self,
+        chat_id: i64,
+        message_id: &str,
+        kind: &str,
+    ) -> Result<Vec<(String, String)>> {
+        let events = sqlx::query_as::<_, (String, String)>(&self.sql(
+            "SELECT username, detail FROM events WHERE chat_id = ? AND message_id = ? AND kind = ?
+             ORDER BY created_at",
+        ))
+        .bind(chat_id)
+        .bind(message_id)
+        .bind(kind)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(events)
+    }
+
+    /// How many of a chat's PRs were opened, reviewed, approved and merged since `since`,
+    /// as (kind, count).
+    pub async fn get_activity_counts(
+        &self,
+        chat_id: i64,
+        since: i64,
+    ) -> Result<Vec<(String, i64)>> {
+        let counts = sqlx::query_as::<_, (String, i64)>(&self.sql(
+            "SELECT kind, COUNT(DISTINCT repo_owner || '/' || repo_name || '#' || pr_number)
+             FROM events
+             WHERE chat_id = ? AND created_at >= ?
+                AND kind IN ('opened', 'reviewed', 'approved', 'merged')
+             GROUP BY kind",
+        ))
+        .bind(chat_id)
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(counts)
+    }
+
+    /// Per user, how many of a chat's PRs they reviewed and approved since `since`.
+    /// Reviewing or approving one PR several times counts once.
+    pub async fn get_review_counts(
+        &self,
+        chat_id: i64,
+        since: i64,
+    ) -> Result<Vec<(String, i64, i64)>> {
+        let counts = sqlx::query_as::<_, (String, i64, i64)>(&self.sql(
+            "SELECT username,
+                COUNT(DISTINCT repo_owner || '/' || repo_name || '#' || pr_number),
+                COUNT(DISTINCT CASE WHEN kind = 'approved'
+                    THEN repo_owner || '/' || repo_name || '#' || pr_number END)
+             FROM events
+             WHERE chat_id = ? AND created_at >= ? AND kind IN ('reviewed', 'approved')
+                AND username <> ?
+             GROUP BY username",
+        ))
+        .bind(chat_id)
+        .bind(since)
+        .bind(FORMER_MEMBER)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(counts)
+    }
+
+    /// (PR, reviewer, author) of every review of a chat's PRs between `since` and `until`,
+    /// the author being who opened the PR. Reviewing one PR several times counts once.
+    pub async fn get_review_pairs(
+        &self,
+        chat_id: i64,
+        since: i64,
+        until: i64,
+    ) -> Result<Vec<(String, String, String)>> {
+        let pairs = sqlx::query_as::<_, (String, String, String)>(&self.sql(
+            "SELECT DISTINCT e.repo_owner || '/' || e.repo_name || '#' || e.pr_number,
+                e.username, o.username
+             FROM events e
+             JOIN events o ON o.chat_id = e.chat_id AND o.repo_owner = e.repo_owner
+                AND o.repo_name = e.repo_name AND o.pr_number = e.pr_number AND o.kind = 'opened'
+             WHERE e.chat_id = ? AND e.created_at >= ? AND e.created_at < ?
+                AND e.kind IN ('reviewed', 'approved') AND e.username <> ?",
+        ))
+        .bind(chat_id)
+        .bind(since)
+        .bind(until)
+        .bind(FORMER_MEMBER)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(pairs)
+    }
+
+    pub async fn archive_merge(&self, entry: &ArchivedMerge) -> Result<()> {
+        sqlx::query(&self.sql(
+            "INSERT INTO merge_archive
+            (source, chat_id, repo_owner, repo_name, pr_number, title, author, opened_at, merged_at, review_rounds, approvals)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT (source, chat_id, repo_owner, repo_name, pr_number) DO UPDATE SET
+                title = excluded.title, author = excluded.author, opened_at = excluded.opened_at,
+                merged_at = excluded.merged_at, review_rounds = excluded.review_rounds,
+                approvals = excluded.approvals",
+        ))
+        .bind(&entry.source)
+        .bind(entry.chat_id)
+        .bind(&entry.repo_owner)
+        .bind(&entry.repo_name)
+        .bind(entry.pr_number)
+        .bind(&entry.title)
+        .bind(&entry.author)
+        .bind(entry.opened_at)
+        .bind(entry.merged_at)
+        .bind(entry.review_rounds)
+        .bind(entry.approvals)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Archived merges of `source` after `after`, or merged at or after `since` without a
+    /// cursor, in cursor order.
+    pub async fn get_archived_merges(
+        &self,
+        source: &str,
+        since: i64,
+        after: Option<&ArchiveCursor>,
+        limit: i64,
+    ) -> Result<Vec<ArchivedMerge>> {
+        const ORDER: &str = "ORDER BY merged_at, chat_id, repo_owner, repo_name, pr_number LIMIT ?";
+        let entries = match after {
+            Some(after) => {
+                sqlx::query_as::<_, ArchivedMerge>(&self.sql(&format!(
+                    "SELECT * FROM merge_archive WHERE source = ?
+                     AND (merged_at, chat_id, repo_owner, repo_name, pr_number) > (?, ?, ?, ?, ?)
+                     {}",
+                    ORDER
+                )))
+                .bind(source)
+                .bind(after.merged_at)
+                .bind(after.chat_id)
+                .bind(&after.repo_owner)
+                .bind(&after.repo_name)
+                .bind(after.pr_number)
+                .bind(limit)
+                .fetch_all(&self.pool)
+                .await?
+            }
+            None => {
+                sqlx::query_as::<_, ArchivedMerge>(&self.sql(&format!(
+                    "SELECT * FROM merge_archive WHERE source = ? AND merged_at >= ? {}",
+                    ORDER
+                )))
+                .bind(source)
+                .bind(since)
+                .bind(limit)
+                .fetch_all(&self.pool)
+                .await?
+            }
+        };
+        Ok(entries)
+    }
+
+    /// Archived merges of every source merged at or after `since`.
+    pub async fn get_all_archived_merges(&self, since: i64) -> Result<Vec<ArchivedMerge>> {
+        let entries = sqlx::query_as::<_, ArchivedMerge>(
+            &self.sql("SELECT * FROM merge_archive WHERE merged_at >= ?"),
+        )
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(entries)
+    }
+
+    /// Newest merge time stored for `source`, to resume pulling its feed from there.
+    pub async fn get_last_archived_merge(&self, source: &str) -> Result<Option<i64>> {
+        let last = sqlx::query_scalar(
+            &self.sql("SELECT MAX(merged_at) FROM merge_archive WHERE source = ?"),
+        )
+        .bind(source)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(last)
+    }
+
+    /// When `job` last ran for the chat, as a unix timestamp.
+    pub async fn link_user(&self, link: &UserLink) -> Result<()> {
+        sqlx::query(&self.sql(
+            "INSERT INTO users (telegram_id, telegram_name, telegram_username, github_login, linked_at)
+             VALUES (?, ?, ?, ?, ?)
+             ON CONFLICT (telegram_id) DO UPDATE SET
+                telegram_name = excluded.telegram_name,
+                telegram_username = excluded.telegram_username,
+                github_login = excluded.github_login, linked_at = excluded.linked_at",
+        ))
+        .bind(link.telegram_id)
+        .bind(&link.telegram_name)
+        .bind(&link.telegram_username)
+        .bind(&link.github_login)
+        .bind(link.linked_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Stores a user's sealed token (see tokens.rs), replacing the one they gave before. Only
+    /// the operator's database has tokens, see `Db::new`.
+    pub async fn set_github_token(
+        &self,
+        telegram_id: i64,
+        login: &str,
+        sealed: &str,
+    ) -> Result<()> {
+        sqlx::query(&self.sql(
+            "INSERT INTO github_tokens (telegram_id, github_login, token) VALUES (?, ?, ?)
+             ON CONFLICT (telegram_id) DO UPDATE SET
+                github_login = excluded.github_login, token = excluded.token",
+        ))
+        .bind(telegram_id)
+        .bind(login)
+        .bind(sealed)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Forgets a user's token, returning whether there was one.
+    pub async fn remove_github_token(&self, telegram_id: i64) -> Result<bool> {
+        let result = sqlx::query(&self.sql("DELETE FROM github_tokens WHERE telegram_id = ?"))
+            .bind(telegram_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// The GitHub login and sealed token of a user.
+    pub async fn get_github_token(&self, telegram_id: i64) -> Result<Option<(String, String)>> {
+        let token = sqlx::query_as::<_, (String, String)>(
+            &self.sql("SELECT github_login, token FROM github_tokens WHERE telegram_id = ?"),
+        )
+        .bind(telegram_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(token)
+    }
+
+    pub async fn unlink_user(&self, telegram_id: i64) -> Result<bool> {
+        let result = sqlx::query(&self.sql("DELETE FROM users WHERE telegram_id = ?"))
+            .bind(telegram_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn get_user_link(&self, telegram_id: i64) -> Result<Option<UserLink>> {
+        let link =
+            sqlx::query_as::<_, UserLink>(&self.sql("SELECT * FROM users WHERE telegram_id = ?"))
+                .bind(telegram_id)
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(link)
+    }
+
+    /// GitHub logins are case-insensitive.
+    pub async fn get_user_by_github(&self, login: &str) -> Result<Option<UserLink>> {
+        let link = sqlx::query_as::<_, UserLink>(
+            &self.sql("SELECT * FROM users WHERE LOWER(github_login) = LOWER(?)"),
+        )
+        .bind(login)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(link)
+    }
+
+    pub async fn get_user_links(&self) -> Result<Vec<UserLink>> {
+        let links = sqlx::query_as::<_, UserLink>("SELECT * FROM users")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(links)
+    }
+
+    pub async fn get_pr_activity(&self) -> Result<Vec<PrActivity>> {
+        let rows = sqlx::query_as::<_, PrActivity>("SELECT * FROM pr_activity")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows)
+    }
+
+    pub async fn set_pr_activity(&self, activity: &PrActivity) -> Result<()> {
+        sqlx::query(&self.sql(
+            "INSERT INTO pr_activity (repo_owner, repo_name, pr_number, last_active, last_synced)
+             VALUES (?, ?, ?, ?, ?)
+             ON CONFLICT (repo_owner, repo_name, pr_number) DO UPDATE SET
+                last_active = excluded.last_active, last_synced = excluded.last_synced",
+        ))
+        .bind(&activity.repo_owner)
+        .bind(&activity.repo_name)
+        .bind(activity.pr_number)
+        .bind(activity.last_active)
+        .bind(activity.last_synced)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Forgets PRs that no longer have a card anywhere.
+    pub async fn prune_pr_activity(&self) -> Result<()> {
+        sqlx::query(
+            "DELETE FROM pr_activity WHERE NOT EXISTS (
+                SELECT 1 FROM messages m WHERE m.repo_owner = pr_activity.repo_owner
+                AND m.repo_name = pr_activity.repo_name AND m.pr_number = pr_activity.pr_number
+            )",
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Records the update as handled; false when it already was.
+    pub async fn claim_update(&self, update_id: i64) -> Result<bool> {
+        let result = sqlx::query(&self.sql(
+            "INSERT INTO telegram_updates (update_id, processed_at) VALUES (?, ?)
+             ON CONFLICT (update_id) DO NOTHING",
+        ))
+        .bind(update_id)
+        .bind(chrono::Utc::now().timestamp())
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn get_last_update_id(&self) -> Result<Option<i64>> {
+        let id = sqlx::query_scalar("SELECT MAX(update_id) FROM telegram_updates")
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(id)
+    }
+
+    /// Forgets updates handled before `before`, keeping the latest so polling can resume from it.
+    pub async fn prune_updates(&self, before: i64) -> Result<()> {
+        sqlx::query(&self.sql(
+            "DELETE FROM telegram_updates WHERE processed_at < ?
+             AND update_id < (SELECT MAX(update_id) FROM telegram_updates)",
+        ))
+        .bind(before)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn get_cached_response(&self, path: &str) -> Result<Option<CachedResponse>> {
+        let cached = sqlx::query_as::<_, CachedResponse>(
+            &self.sql("SELECT etag, last_modified, body FROM http_cache WHERE path = ?"),
+        )
+        .bind(path)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(cached)
+    }
+
+    pub async fn cache_response(&self, path: &str, response: &CachedResponse) -> Result<()> {
+        sqlx::query(&self.sql(
+            "INSERT INTO http_cache (path, etag, last_modified, body, stored_at) VALUES (?, ?, ?, ?, ?)
+             ON CONFLICT (path) DO UPDATE SET etag = excluded.etag,
+             last_modified = excluded.last_modified, body = excluded.body, stored_at = excluded.stored_at",
+        ))
+        .bind(path)
+        .bind(&response.etag)
+        .bind(&response.last_modified)
+        .bind(&response.body)
+        .bind(chrono::Utc::now().timestamp())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Drops cached responses stored before `before`, such as those of PRs no longer tracked.
+    pub async fn prune_response_cache(&self, before: i64) -> Result<()> {
+        sqlx::query(&self.sql("DELETE FROM http_cache WHERE stored_at < ?"))
+            .bind(before)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn get_job_run(&self, chat_id: i64, job: &str) -> Result<Option<i64>> {
+        let last_run = sqlx::query_scalar(
+            &self.sql("SELECT last_run FROM job_runs WHERE chat_id = ? AND job = ?"),
+        )
+        .bind(chat_id)
+        .bind(job)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(last_run)
+    }
+
+    pub async fn set_job_run(&self, chat_id: i64, job: &str, last_run: i64) -> Result<()> {
+        sqlx::query(&self.sql(
+            "INSERT INTO job_runs (chat_id, job, last_run) VALUES (?, ?, ?)
+             ON CONFLICT (chat_id, job) DO UPDATE SET last_run = excluded.last_run",
+        ))
+        .bind(chat_id)
+        .bind(job)
+        .bind(last_run)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn get_repo_capabilities(
+        &self,
+        owner: &str,
+        name: &str,
+    ) -> Result<Option<RepoCapabilities>> {
+        let capabilities = sqlx::query_as::<_, RepoCapabilities>(&self.sql(
+            "SELECT list_prs, read_reviews, read_checks, write_labels FROM repo_capabilities
+             WHERE owner = ? AND name = ?",
+        ))
+        .bind(owner)
+        .bind(name)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(capabilities)
+    }
+
+    pub async fn set_repo_capabilities(
+        &self,
+        owner: &str,
+        name: &str,
+        capabilities: &RepoCapabilities,
+    ) -> Result<()> {
+        sqlx::query(&self.sql(
+            "INSERT INTO repo_capabilities
+             (owner, name, list_prs, read_reviews, read_checks, write_labels, checked_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?)
+             ON CONFLICT (owner, name) DO UPDATE SET list_prs = excluded.list_prs,
+             read_reviews = excluded.read_reviews, read_checks = excluded.read_checks,
+             write_labels = excluded.write_labels, checked_at = excluded.checked_at",
+        ))
+        .bind(owner)
+        .bind(name)
+        .bind(capabilities.list_prs)
+        .bind(capabilities.read_reviews)
+        .bind(capabilities.read_checks)
+        .bind(capabilities.write_labels)
+        .bind(chrono::Utc::now().timestamp())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn get_last_poll(&self, owner: &str, name: &str) -> Result<Option<i64>> {
+        let last_check = sqlx::query_scalar(
+            &self.sql("SELECT last_check FROM repo_polls WHERE owner = ? AND name = ?"),
+        )
+        .bind(owner)
+        .bind(name)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(last_check)
+    }
+
+    pub async fn set_last_poll(&self, owner: &str, name: &str, last_check: i64) -> Result<()> {
+        sqlx::query(&self.sql(
+            "INSERT INTO repo_polls (owner, name, last_check) VALUES (?, ?, ?)
+             ON CONFLICT (owner, name) DO UPDATE SET last_check = excluded.last_check",
+        ))
+        .bind(owner)
+        .bind(name)
+        .bind(last_check)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn set_last_reminded(&self, message_id: &str, chat_id: i64, at: i64) -> Result<()> {
+        sqlx::query(
+            &self.sql(
+                "UPDATE messages SET last_reminded_at = ? WHERE message_id = ? AND chat_id = ?",
+            ),
+        )
+        .bind(at)
+        .bind(message_id)
+        .bind(chat_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn set_last_nudged(&self, message_id: &str, chat_id: i64, at: i64) -> Result<()> {
+        sqlx::query(
+            &self
+                .sql("UPDATE messages SET last_nudged_at = ? WHERE message_id = ? AND chat_id = ?"),
+        )
+        .bind(at)
+        .bind(message_id)
+        .bind(chat_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Records the card's state as seen by the compaction pass, since when it's unchanged and
+    /// whether the card is shown compact in it.
+    pub async fn set_settled(
+        &self,
+        message_id: &str,
+        chat_id: i64,
+        key: &str,
+        since: i64,
+        compact: bool,
+    ) -> Result<()> {
+        sqlx::query(&self.sql(
+            "UPDATE messages SET settled_key = ?, settled_since = ?, compact = ?
+             WHERE message_id = ? AND chat_id = ?",
+        ))
+        .bind(key)
+        .bind(since)
+        .bind(compact as i64)
+        .bind(message_id)
+        .bind(chat_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn set_rendered_at(&self, message_id: &str, chat_id: i64, at: i64) -> Result<()> {
+        sqlx::query(
+            &self.sql("UPDATE messages SET rendered_at = ? WHERE message_id = ? AND chat_id = ?"),
+        )
+        .bind(at)
+        .bind(message_id)
+        .bind(chat_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn get_button_mode(&self, chat_id: i64) -> Result<ButtonMode> {
+        let button_mode: Option<i64> = sqlx::query_scalar(
+            &self.sql("SELECT button_mode FROM chat_settings WHERE chat_id = ?"),
+        )
+        .bind(chat_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(match button_mode {
+            Some(1) => ButtonMode::Fallback,
+            Some(2) => ButtonMode::Chosen,
+            _ => ButtonMode::Reactions,
+        })
+    }
+
+    pub async fn set_button_mode(&self, chat_id: i64, mode: ButtonMode) -> Result<()> {
+        sqlx::query(&self.sql(
+            "INSERT INTO chat_settings (chat_id, button_mode) VALUES (?, ?)
+             ON CONFLICT (chat_id) DO UPDATE SET button_mode = excluded.button_mode",
+        ))
+        .bind(chat_id)
+        .bind(mode as i64)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn get_dashboard(&self, chat_id: i64) -> Result<Option<String>> {
+        let message_id: Option<Option<String>> = sqlx::query_scalar(
+            &self.sql("SELECT dashboard_message_id FROM chat_settings WHERE chat_id = ?"),
+        )
+        .bind(chat_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(message_id.flatten())
+    }
+
+    /// (chat_id, message_id) of every chat's dashboard.
+    pub async fn get_dashboards(&self) -> Result<Vec<(i64, String)>> {
+        let dashboards = sqlx::query_as::<_, (i64, String)>(&self.sql(
+            "SELECT chat_id, dashboard_message_id FROM chat_settings
+             WHERE dashboard_message_id IS NOT NULL",
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(dashboards)
+    }
+
+    pub async fn set_dashboard(&self, chat_id: i64, message_id: Option<&str>) -> Result<()> {
+        sqlx::query(&self.sql(
+            "INSERT INTO chat_settings (chat_id, dashboard_message_id) VALUES (?, ?)
+             ON CONFLICT (chat_id) DO UPDATE SET dashboard_message_id = excluded.dashboard_message_id",
+        ))
+        .bind(chat_id)
+        .bind(message_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// The chat's own nudge budget, if it set one with `/nudges`.
+    pub async fn get_nudge_budget(&self, chat_id: i64) -> Result<Option<u32>> {
+        let budget: Option<Option<i64>> = sqlx::query_scalar(
+            &self.sql("SELECT nudge_budget FROM chat_settings WHERE chat_id = ?"),
+        )
+        .bind(chat_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(budget.flatten().map(|b| b as u32))
+    }
+
+    pub async fn set_nudge_budget(&self, chat_id: i64, budget: Option<u32>) -> Result<()> {
+        sqlx::query(&self.sql(
+            "INSERT INTO chat_settings (chat_id, nudge_budget) VALUES (?, ?)
+             ON CONFLICT (chat_id) DO UPDATE SET nudge_budget = excluded.nudge_budget",
+        ))
+        .bind(chat_id)
+        .bind(budget.map(|b| b as i64))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// The chat's own policy for cards of closed PRs, if it set one with `/onclose`.
+    pub async fn get_closed_cards(&self, chat_id: i64) -> Result<Option<String>> {
+        let policy: Option<Option<String>> = sqlx::query_scalar(
+            &self.sql("SELECT closed_cards FROM chat_settings WHERE chat_id = ?"),
+        )
+        .bind(chat_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(policy.flatten())
+    }
+
+    pub async fn set_closed_cards(&self, chat_id: i64, policy: Option<&str>) -> Result<()> {
+        sqlx::query(&self.sql(
+            "INSERT INTO chat_settings (chat_id, closed_cards) VALUES (?, ?)
+             ON CONFLICT (chat_id) DO UPDATE SET closed_cards = excluded.closed_cards",
+        ))
+        .bind(chat_id)
+        .bind(policy)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn get_self_approval(&self, chat_id: i64) -> Result<bool> {
+        let allowed: Option<i64> = sqlx::query_scalar(
+            &self.sql("SELECT self_approval FROM chat_settings WHERE chat_id = ?"),
+        )
+        .bind(chat_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(allowed.unwrap_or(0) != 0)
+    }
+
+    pub async fn set_self_approval(&self, chat_id: i64, allowed: bool) -> Result<()> {
+        sqlx::query(&self.sql(
+            "INSERT INTO chat_settings (chat_id, self_approval) VALUES (?, ?)
+             ON CONFLICT (chat_id) DO UPDATE SET self_approval = excluded.self_approval",
+        ))
+        .bind(chat_id)
+        .bind(allowed as i64)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Schedules a kept card, or a copy of one, for deletion at `delete_at`.
+    pub async fn add_kept_card(
+        &self,
+        chat_id: i64,
+        message_id: &str,
+        delete_at: i64,
+    ) -> Result<()> {
+        sqlx::query(&self.sql(
+            "INSERT INTO kept_cards (chat_id, message_id, delete_at) VALUES (?, ?, ?)
+             ON CONFLICT (chat_id, message_id) DO UPDATE SET delete_at = excluded.delete_at",
+        ))
+        .bind(chat_id)
+        .bind(message_id)
+        .bind(delete_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// (chat_id, message_id) of the kept cards due for deletion by `now`.
+    pub async fn get_due_kept_cards(&self, now: i64) -> Result<Vec<(i64, String)>> {
+        let cards = sqlx::query_as::<_, (i64, String)>(
+            &self.sql("SELECT chat_id, message_id FROM kept_cards WHERE delete_at <= ?"),
+        )
+        .bind(now)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(cards)
+    }
+
+    pub async fn remove_kept_card(&self, chat_id: i64, message_id: &str) -> Result<()> {
+        sqlx::query(&self.sql("DELETE FROM kept_cards WHERE chat_id = ? AND message_id = ?"))
+            .bind(chat_id)
+            .bind(message_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn get_chat_tenant(&self, chat_id: i64) -> Result<Option<String>> {
+        let tenant_id =
+            sqlx::query_scalar(&self.sql("SELECT tenant_id FROM tenant_chats WHERE chat_id = ?"))
+                .bind(chat_id)
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(tenant_id)
+    }
+
+    /// Binds the chat to a tenant, or back to the operator with `None`.
+    pub async fn set_chat_tenant(&self, chat_id: i64, tenant_id: Option<&str>) -> Result<()> {
+        match tenant_id {
+            Some(tenant_id) => {
+                sqlx::query(&self.sql(
+                    "INSERT INTO tenant_chats (chat_id, tenant_id) VALUES (?, ?)
+                     ON CONFLICT (chat_id) DO UPDATE SET tenant_id = excluded.tenant_id",
+                ))
+                .bind(chat_id)
+                .bind(tenant_id)
+                .execute(&self.pool)
+                .await?;
+            }
+            None => {
+                sqlx::query(&self.sql("DELETE FROM tenant_chats WHERE chat_id = ?"))
+                    .bind(chat_id)
+                    .execute(&self.pool)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Counts a reminder mention of `username` on `day` unless that would exceed `budget`.
+    /// Returns whether it was counted, i.e. the user may be mentioned.
+    pub async fn take_nudge(
+        &self,
+        chat_id: i64,
+        username: &str,
+        day: &str,
+        budget: u32,
+    ) -> Result<bool> {
+        let taken = sqlx::query(&self.sql(
+            "INSERT INTO nudges (chat_id, username, day, sent) VALUES (?, ?, ?, 1)
+             ON CONFLICT (chat_id, username, day) DO UPDATE SET sent = nudges.sent + 1
+             WHERE nudges.sent < ?",
+        ))
+        .bind(chat_id)
+        .bind(username)
+        .bind(day)
+        .bind(budget as i64)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+        Ok(taken > 0)
+    }
+
+    pub async fn hold_nudge(
+        &self,
+        chat_id: i64,
+        username: &str,
+        day: &str,
+        line: &str,
+    ) -> Result<()> {
+        sqlx::query(&self.sql(
+            "INSERT INTO held_nudges (chat_id, username, day, line, held_at) VALUES (?, ?, ?, ?, ?)",
+        ))
+        .bind(chat_id)
+        .bind(username)
+        .bind(day)
+        .bind(line)
+        .bind(chrono::Utc::now().timestamp())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Held reminders of days before `day`, in the order they were held.
+    pub async fn get_held_nudges(&self, day: &str) -> Result<Vec<HeldNudge>> {
+        let held = sqlx::query_as::<_, HeldNudge>(&self.sql(
+            "SELECT chat_id, username, day, line FROM held_nudges WHERE day < ?
+             ORDER BY chat_id, day, username, held_at",
+        ))
+        .bind(day)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(held)
+    }
+
+    pub async fn delete_held_nudges(&self, chat_id: i64, day: &str) -> Result<()> {
+        sqlx::query(&self.sql("DELETE FROM held_nudges WHERE chat_id = ? AND day = ?"))
+            .bind(chat_id)
+            .bind(day)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Drops the counts of days before `day`; budgets start over every day.
+    pub async fn prune_nudges(&self, day: &str) -> Result<()> {
+        sqlx::query(&self.sql("DELETE FROM nudges WHERE day < ?"))
+            .bind(day)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Replaces every mirror with `mirrors`, as (source chat, mirror chat).
+    pub async fn set_mirror_chats(&self, mirrors: &[(i64, i64)]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(&self.sql("DELETE FROM mirror_chats"))
+            .execute(&mut *tx)
+            .await?;
+
+        for (source_chat_id, chat_id) in mirrors {
+            sqlx::query(&self.sql(
+                "INSERT INTO mirror_chats (source_chat_id, chat_id) VALUES (?, ?)
+                 ON CONFLICT DO NOTHING",
+            ))
+            .bind(source_chat_id)
+            .bind(chat_id)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// The chats mirroring `source_chat_id`'s cards.
+    pub async fn get_mirror_chats(&self, source_chat_id: i64) -> Result<Vec<i64>> {
+        let chats = sqlx::query_scalar(
+            &self.sql("SELECT chat_id FROM mirror_chats WHERE source_chat_id = ? ORDER BY chat_id"),
+        )
+        .bind(source_chat_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(chats)
+    }
+
+    pub async fn is_mirror_chat(&self, chat_id: i64) -> Result<bool> {
+        let found: Option<i64> =
+            sqlx::query_scalar(&self.sql("SELECT chat_id FROM mirror_chats WHERE chat_id = ?"))
+                .bind(chat_id)
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(found.is_some())
+    }
+
+    /// The copies of a card, as (mirror chat, message id).
+    pub async fn get_mirror_cards(
+        &self,
+        source_chat_id: i64,
+        source_message_id: &str,
+    ) -> Result<Vec<(i64, String)>> {
+        let cards = sqlx::query_as(&self.sql(
+            "SELECT chat_id, message_id FROM mirror_cards
+             WHERE source_chat_id = ? AND source_message_id = ?",
+        ))
+        .bind(source_chat_id)
+        .bind(source_message_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(cards)
+    }
+
+    pub async fn add_mirror_card(
+        &self,
+        source_chat_id: i64,
+        source_message_id: &str,
+        chat_id: i64,
+        message_id: &str,
+    ) -> Result<()> {
+        sqlx::query(&self.sql(
+            "INSERT INTO mirror_cards (source_chat_id, source_message_id, chat_id, message_id)
+             VALUES (?, ?, ?, ?)
+             ON CONFLICT (source_chat_id, source_message_id, chat_id)
+             DO UPDATE SET message_id = excluded.message_id",
+        ))
+        .bind(source_chat_id)
+        .bind(source_message_id)
+        .bind(chat_id)
+        .bind(message_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn delete_mirror_cards(
+        &self,
+        source_chat_id: i64,
+        source_message_id: &str,
+    ) -> Result<()> {
+        sqlx::query(
+            &self
+                .sql("DELETE FROM mirror_cards WHERE source_chat_id = ? AND source_message_id = ?"),
+        )
+        .bind(source_chat_id)
+        .bind(source_message_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn is_chat_verified(&self, chat_id: i64) -> Result<bool> {
+        let verified: Option<i64> =
+            sqlx::query_scalar(&self.sql("SELECT verified FROM chat_settings WHERE chat_id = ?"))
+                .bind(chat_id)
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(verified == Some(1))
+    }
+
+    pub async fn get_verification_code(&self, chat_id: i64) -> Result<Option<String>> {
+        let code: Option<Option<String>> = sqlx::query_scalar(
+            &self.sql("SELECT verification_code FROM chat_settings WHERE chat_id = ?"),
+        )
+        .bind(chat_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(code.flatten())
+    }
+
+    pub async fn set_verification_code(&self, chat_id: i64, code: &str) -> Result<()> {
+        sqlx::query(&self.sql(
+            "INSERT INTO chat_settings (chat_id, verification_code) VALUES (?, ?)
+             ON CONFLICT (chat_id) DO UPDATE SET verification_code = excluded.verification_code",
+        ))
+        .bind(chat_id)
+        .bind(code)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// The unverified chat a verification code was posted in.
+    pub async fn find_chat_by_code(&self, code: &str) -> Result<Option<i64>> {
+        let chat_id =
+            sqlx::query_scalar(&self.sql(
+                "SELECT chat_id FROM chat_settings WHERE verification_code = ? AND verified = 0",
+            ))
+            .bind(code)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(chat_id)
+    }
+
+    /// Marks a chat verified, returning whether it wasn't already.
+    pub async fn set_chat_verified(&self, chat_id: i64) -> Result<bool> {
+        let was_verified = self.is_chat_verified(chat_id).await?;
+        sqlx::query(&self.sql(
+            "INSERT INTO chat_settings (chat_id, verified) VALUES (?, 1)
+             ON CONFLICT (chat_id) DO UPDATE SET verified = 1, verification_code = NULL",
+        ))
+        .bind(chat_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(!was_verified)
+    }
+
+    /// The pool's members with their assignments since `since` and their open reviews.
+    pub async fn get_reviewer_pool(
+        &self,
+        chat_id: i64,
+        owner: &str,
+        repo: &str,
+        since: i64,
+    ) -> Result<Vec<PoolMember>> {
+        let members = sqlx::query_as::<_, PoolMember>(&self.sql(
+            "SELECT p.username,
+                (SELECT COUNT(*) FROM reviewer_assignments a
+                 WHERE a.chat_id = p.chat_id AND a.username = p.username
+                    AND a.assigned_at >= ?) AS recent_assignments,
+                (SELECT COUNT(*) FROM reactions r
+                 JOIN messages m ON m.message_id = r.message_id AND m.chat_id = r.chat_id
+                 WHERE r.chat_id = p.chat_id AND r.username = p.username
+                    AND r.reaction_type = 'reviewer'
+                    AND m.status NOT IN ('merged', 'closed')) AS open_reviews,
+                (SELECT MAX(a.assigned_at) FROM reviewer_assignments a
+                 WHERE a.chat_id = p.chat_id AND a.repo_owner = p.repo_owner
+                    AND a.repo_name = p.repo_name AND a.username = p.username) AS last_assigned
+             FROM reviewer_pools p
+             WHERE p.chat_id = ? AND p.repo_owner = ? AND p.repo_name = ?
+             ORDER BY p.username",
+        ))
+        .bind(since)
+        .bind(chat_id)
+        .bind(owner)
+        .bind(repo)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(members)
+    }
+
+    pub async fn add_pool_member(
+        &self,
+        chat_id: i64,
+        owner: &str,
+        repo: &str,
+        username: &str,
+    ) -> Result<()> {
+        sqlx::query(&self.sql(
+            "INSERT INTO reviewer_pools (chat_id, repo_owner, repo_name, username) VALUES (?, ?, ?, ?)
+             ON CONFLICT (chat_id, repo_owner, repo_name, username) DO NOTHING",
+        ))
+        .bind(chat_id)
+        .bind(owner)
+        .bind(repo)
+        .bind(username)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Removes a member from the pool, returning whether they were in it.
+    pub async fn remove_pool_member(
+        &self,
+        chat_id: i64,
+        owner: &str,
+        repo: &str,
+        username: &str,
+    ) -> Result<bool> {
+        let removed = sqlx::query(&self.sql(
+            "DELETE FROM reviewer_pools
+             WHERE chat_id = ? AND repo_owner = ? AND repo_name = ? AND username = ?",
+        ))
+        .bind(chat_id)
+        .bind(owner)
+        .bind(repo)
+        .bind(username)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+        Ok(removed > 0)
+    }
+
+    /// Records that the PR was assigned to the pool member.
+    pub async fn record_assignment(
+        &self,
+        chat_id: i64,
+        owner: &str,
+        repo: &str,
+        pr_number: i64,
+        username: &str,
+        at: i64,
+    ) -> Result<()> {
+        sqlx::query(&self.sql(
+            "INSERT INTO reviewer_assignments
+             (chat_id, repo_owner, repo_name, pr_number, username, assigned_at)
+             VALUES (?, ?, ?, ?, ?, ?) ON CONFLICT DO NOTHING",
+        ))
+        .bind(chat_id)
+        .bind(owner)
+        .bind(repo)
+        .bind(pr_number)
+        .bind(username)
+        .bind(at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn add_lead(&self, lead: &Lead) -> Result<()> {
+        sqlx::query(&self.sql(
+            "INSERT INTO chat_leads (chat_id, user_id, name) VALUES (?, ?, ?)
+             ON CONFLICT (chat_id, user_id) DO UPDATE SET name = excluded.name",
+        ))
+        .bind(lead.chat_id)
+        .bind(lead.user_id)
+        .bind(&lead.name)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Removes a lead, returning whether they were one.
+    pub async fn remove_lead(&self, chat_id: i64, user_id: i64) -> Result<bool> {
+        let removed =
+            sqlx::query(&self.sql("DELETE FROM chat_leads WHERE chat_id = ? AND user_id = ?"))
+                .bind(chat_id)
+                .bind(user_id)
+                .execute(&self.pool)
+                .await?
+                .rows_affected();
+        Ok(removed > 0)
+    }
+
+    pub async fn get_leads(&self, chat_id: i64) -> Result<Vec<Lead>> {
+        let leads = sqlx::query_as::<_, Lead>(
+            &self.sql("SELECT * FROM chat_leads WHERE chat_id = ? ORDER BY name"),
+        )
+        .bind(chat_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(leads)
+    }
+
+    pub async fn get_all_leads(&self) -> Result<Vec<Lead>> {
+        let leads = sqlx::query_as::<_, Lead>(&self.sql("SELECT * FROM chat_leads"))
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(leads)
+    }
+
+    /// Saves a user's named filter, replacing one of the same name.
+    pub async fn save_filter(
+        &self,
+        chat_id: i64,
+        user_id: i64,
+        name: &str,
+        query: &str,
+    ) -> Result<()> {
+        sqlx::query(&self.sql(
+            "INSERT INTO saved_filters (chat_id, user_id, name, query) VALUES (?, ?, ?, ?)
+             ON CONFLICT (chat_id, user_id, name) DO UPDATE SET query = excluded.query",
+        ))
+        .bind(chat_id)
+        .bind(user_id)
+        .bind(name)
+        .bind(query)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Removes a saved filter, returning whether there was one.
+    pub async fn remove_filter(&self, chat_id: i64, user_id: i64, name: &str) -> Result<bool> {
+        let removed = sqlx::query(
+            &self.sql("DELETE FROM saved_filters WHERE chat_id = ? AND user_id = ? AND name = ?"),
+        )
+        .bind(chat_id)
+        .bind(user_id)
+        .bind(name)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+        Ok(removed > 0)
+    }
+
+    /// A user's saved filters in a chat as (name, query), by name.
+    pub async fn get_filters(&self, chat_id: i64, user_id: i64) -> Result<Vec<(String, String)>> {
+        let filters = sqlx::query_as(&self.sql(
+            "SELECT name, query FROM saved_filters WHERE chat_id = ? AND user_id = ? ORDER BY name",
+        ))
+        .bind(chat_id)
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(filters)
+    }
+
+    pub async fn add_rule(&self, chat_id: i64, rule: &str, created_by: &str) -> Result<()> {
+        sqlx::query(&self.sql("INSERT INTO rules (chat_id, rule, created_by) VALUES (?, ?, ?)"))
+            .bind(chat_id)
+            .bind(rule)
+            .bind(created_by)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn get_rules(&self, chat_id: i64) -> Result<Vec<StoredRule>> {
+        let rules = sqlx::query_as::<_, StoredRule>(
+            &self.sql("SELECT id, rule, created_by FROM rules WHERE chat_id = ? ORDER BY id"),
+        )
+        .bind(chat_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rules)
+    }
+
+    /// Removes a rule of the chat, returning whether it existed.
+    pub async fn add_search(&self, chat_id: i64, query: &str, created_by: &str) -> Result<()> {
+        sqlx::query(
+            &self.sql("INSERT INTO searches (chat_id, query, created_by) VALUES (?, ?, ?)"),
+        )
+        .bind(chat_id)
+        .bind(query)
+        .bind(created_by)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn get_searches(&self, chat_id: i64) -> Result<Vec<StoredSearch>> {
+        let searches = sqlx::query_as::<_, StoredSearch>(&self.sql(
+            "SELECT id, chat_id, query, created_by FROM searches WHERE chat_id = ? ORDER BY id",
+        ))
+        .bind(chat_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(searches)
+    }
+
+    /// Every chat's searches, for the poller.
+    pub async fn get_all_searches(&self) -> Result<Vec<StoredSearch>> {
+        let searches = sqlx::query_as::<_, StoredSearch>(
+            "SELECT id, chat_id, query, created_by FROM searches ORDER BY id",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(searches)
+    }
+
+    pub async fn remove_search(&self, chat_id: i64, id: i64) -> Result<bool> {
+        let removed = sqlx::query(&self.sql("DELETE FROM searches WHERE id = ? AND chat_id = ?"))
+            .bind(id)
+            .bind(chat_id)
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+        Ok(removed > 0)
+    }
+
+    pub async fn remove_rule(&self, chat_id: i64, id: i64) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+        let removed = sqlx::query(&self.sql("DELETE FROM rules WHERE id = ? AND chat_id = ?"))
+            .bind(id)
+            .bind(chat_id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        sqlx::query(&self.sql("DELETE FROM rule_hits WHERE rule_id = ? AND chat_id = ?"))
+            .bind(id)
+            .bind(chat_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(removed > 0)
+    }
+
+    pub async fn add_ticket_tracker(
+        &self,
+        chat_id: i64,
+        pattern: &str,
+        url_template: &str,
+    ) -> Result<()> {
+        sqlx::query(
+            &self.sql(
+                "INSERT INTO ticket_trackers (chat_id, pattern, url_template) VALUES (?, ?, ?)",
+            ),
+        )
+        .bind(chat_id)
+        .bind(pattern)
+        .bind(url_template)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn get_ticket_trackers(&self, chat_id: i64) -> Result<Vec<TicketTracker>> {
+        let trackers = sqlx::query_as::<_, TicketTracker>(&self.sql(
+            "SELECT id, pattern, url_template FROM ticket_trackers WHERE chat_id = ? ORDER BY id",
+        ))
+        .bind(chat_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(trackers)
+    }
+
+    /// Removes a ticket tracker of the chat, returning whether it existed.
+    pub async fn remove_ticket_tracker(&self, chat_id: i64, id: i64) -> Result<bool> {
+        let removed =
+            sqlx::query(&self.sql("DELETE FROM ticket_trackers WHERE id = ? AND chat_id = ?"))
+                .bind(id)
+                .bind(chat_id)
+                .execute(&self.pool)
+                .await?
+                .rows_affected();
+        Ok(removed > 0)
+    }
+
+    pub async fn get_branch_alert(&self, chat_id: i64, repo: &str) -> Result<Option<BranchAlert>> {
+        let alert = sqlx::query_as::<_, BranchAlert>(&self.sql(
+            "SELECT sha, message_id, failing_since FROM branch_alerts WHERE chat_id = ? AND repo = ?",
+        ))
+        .bind(chat_id)
+        .bind(repo)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(alert)
+    }
+
+    pub async fn set_branch_alert(
+        &self,
+        chat_id: i64,
+        repo: &str,
+        alert: &BranchAlert,
+    ) -> Result<()> {
+        sqlx::query(&self.sql(
+            "INSERT INTO branch_alerts (chat_id, repo, sha, message_id, failing_since)
+             VALUES (?, ?, ?, ?, ?)
+             ON CONFLICT (chat_id, repo) DO UPDATE SET
+                sha = excluded.sha, message_id = excluded.message_id,
+                failing_since = excluded.failing_since",
+        ))
+        .bind(chat_id)
+        .bind(repo)
+        .bind(&alert.sha)
+        .bind(alert.message_id)
+        .bind(alert.failing_since)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn remove_branch_alert(&self, chat_id: i64, repo: &str) -> Result<()> {
+        sqlx::query(&self.sql("DELETE FROM branch_alerts WHERE chat_id = ? AND repo = ?"))
+            .bind(chat_id)
+            .bind(repo)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// The chat's own template bodies, name -> body.
+    pub async fn get_reply_templates(&self, chat_id: i64) -> Result<Vec<(String, String)>> {
+        let rows = sqlx::query(
+            &self.sql("SELECT name, body FROM reply_templates WHERE chat_id = ? ORDER BY name"),
+        )
+        .bind(chat_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .iter()
+            .map(|row| (row.get("name"), row.get("body")))
+            .collect())
+    }
+
+    pub async fn set_reply_template(&self, chat_id: i64, name: &str, body: &str) -> Result<()> {
+        sqlx::query(&self.sql(
+            "INSERT INTO reply_templates (chat_id, name, body) VALUES (?, ?, ?)
+             ON CONFLICT (chat_id, name) DO UPDATE SET body = excluded.body",
+        ))
+        .bind(chat_id)
+        .bind(name)
+        .bind(body)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Drops the chat's own body of a template, returning whether it had one.
+    pub async fn remove_reply_template(&self, chat_id: i64, name: &str) -> Result<bool> {
+        let removed =
+            sqlx::query(&self.sql("DELETE FROM reply_templates WHERE chat_id = ? AND name = ?"))
+                .bind(chat_id)
+                .bind(name)
+                .execute(&self.pool)
+                .await?
+                .rows_affected();
+        Ok(removed > 0)
+    }
+
+    pub async fn is_rule_hit(&self, rule_id: i64, chat_id: i64, message_id: &str) -> Result<bool> {
+        let count: i64 = sqlx::query_scalar(&self.sql(
+            "SELECT COUNT(*) FROM rule_hits WHERE rule_id = ? AND chat_id = ? AND message_id = ?",
+        ))
+        .bind(rule_id)
+        .bind(chat_id)
+        .bind(message_id)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(count > 0)
+    }
+
+    /// Records that a rule fired for a card, or clears it so the rule can fire again.
+    pub async fn set_rule_hit(
+        &self,
+        rule_id: i64,
+        chat_id: i64,
+        message_id: &str,
+        hit: bool,
+    ) -> Result<()> {
+        let query = if hit {
+            "INSERT INTO rule_hits (rule_id, chat_id, message_id) VALUES (?, ?, ?)
+             ON CONFLICT DO NOTHING"
+        } else {
+            "DELETE FROM rule_hits WHERE rule_id = ? AND chat_id = ? AND message_id = ?"
+        };
+        sqlx::query(&self.sql(query))
+            .bind(rule_id)
+            .bind(chat_id)
+            .bind(message_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn remove_message(&self, message_id: &str, chat_id: i64) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        // Delete reactions first (FK like behavior)
+        sqlx::query(&self.sql("DELETE FROM reactions WHERE message_id = ? AND chat_id = ?"))
+            .bind(message_id)
+            .bind(chat_id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query(&self.sql("DELETE FROM rule_hits WHERE message_id = ? AND chat_id = ?"))
+            .bind(message_id)
+            .bind(chat_id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query(&self.sql("DELETE FROM pr_tracking WHERE message_id = ? AND chat_id = ?"))
             .bind(message_id)
             .bind(chat_id)
             .execute(&mut *tx)
             .await?;
 
         // Delete message
-        sqlx::query("DELETE FROM messages WHERE message_id = ? AND chat_id = ?")
+        sqlx::query(&self.sql("DELETE FROM messages WHERE message_id = ? AND chat_id = ?"))
             .bind(message_id)
             .bind(chat_id)
             .execute(&mut *tx)
@@ -268,4 +2587,379 @@ impl Db {
         tx.commit().await?;
         Ok(())
     }
+
+    /// Erases a user: drops their review marks and anonymises their event history.
+    /// Returns the cards (message_id, chat_id) that showed one of their marks.
+    pub async fn forget_user(&self, username: &str) -> Result<Vec<(String, i64)>> {
+        let mut tx = self.pool.begin().await?;
+
+        let cards: Vec<(String, i64)> = sqlx::query_as(
+            &self.sql("SELECT DISTINCT message_id, chat_id FROM reactions WHERE username = ?"),
+        )
+        .bind(username)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        sqlx::query(&self.sql("DELETE FROM reactions WHERE username = ?"))
+            .bind(username)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query(&self.sql("UPDATE events SET username = ? WHERE username = ?"))
+            .bind(FORMER_MEMBER)
+            .bind(username)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query(&self.sql("DELETE FROM users WHERE telegram_name = ?"))
+            .bind(username)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query(&self.sql("DELETE FROM reviewer_pools WHERE username = ?"))
+            .bind(username)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query(&self.sql("DELETE FROM reviewer_assignments WHERE username = ?"))
+            .bind(username)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query(&self.sql("DELETE FROM chat_leads WHERE name = ?"))
+            .bind(username)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(cards)
+    }
+
+    /// Deletes everything stored for a chat. Returns how many cards were dropped.
+    pub async fn wipe_chat(&self, chat_id: i64) -> Result<u64> {
+        let mut tx = self.pool.begin().await?;
+
+        for table in [
+            "reactions",
+            "events",
+            "group_items",
+            "pr_groups",
+            "repositories",
+            "rules",
+            "rule_hits",
+            "job_runs",
+            "pr_tracking",
+            "chat_settings",
+            "reviewer_pools",
+            "reviewer_assignments",
+            "chat_leads",
+            "saved_filters",
+            "searches",
+            "nudges",
+            "held_nudges",
+            "mirror_cards",
+            "ticket_trackers",
+            "reply_templates",
+            "seen_releases",
+            "branch_alerts",
+            "burst_prs",
+        ] {
+            sqlx::query(&self.sql(&format!("DELETE FROM {} WHERE chat_id = ?", table)))
+                .bind(chat_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        sqlx::query(&self.sql("DELETE FROM merge_archive WHERE source = '' AND chat_id = ?"))
+            .bind(chat_id)
+            .execute(&mut *tx)
+            .await?;
+
+        // Copies of the chat's cards elsewhere aren't updated anymore
+        sqlx::query(&self.sql("DELETE FROM mirror_cards WHERE source_chat_id = ?"))
+            .bind(chat_id)
+            .execute(&mut *tx)
+            .await?;
+
+        let cards = sqlx::query(&self.sql("DELETE FROM messages WHERE chat_id = ?"))
+            .bind(chat_id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+
+        // seen_prs keys are "chat_id:owner/repo#number"
+        sqlx::query(&self.sql("DELETE FROM seen_prs WHERE key LIKE ?"))
+            .bind(format!("{}:%", chat_id))
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(cards)
+    }
+
+    pub async fn save_group(&self, group: &PrGroup, items: &[GroupItem]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(&self.sql(
+            "INSERT INTO pr_groups (message_id, chat_id, author, created_at) VALUES (?, ?, ?, ?)
+             ON CONFLICT (message_id, chat_id) DO UPDATE SET
+                author = excluded.author, created_at = excluded.created_at",
+        ))
+        .bind(&group.message_id)
+        .bind(group.chat_id)
+        .bind(&group.author)
+        .bind(group.created_at)
+        .execute(&mut *tx)
+        .await?;
+
+        for item in items {
+            sqlx::query(&self.sql(
+                "INSERT INTO group_items
+                (message_id, chat_id, pr_url, title, repo_owner, repo_name, pr_number, status,
+                 security)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+                ON CONFLICT (message_id, chat_id, repo_owner, repo_name, pr_number) DO UPDATE SET
+                    pr_url = excluded.pr_url, title = excluded.title, status = excluded.status",
+            ))
+            .bind(&group.message_id)
+            .bind(group.chat_id)
+            .bind(&item.pr_url)
+            .bind(&item.title)
+            .bind(&item.repo_owner)
+            .bind(&item.repo_name)
+            .bind(item.pr_number)
+            .bind(&item.status)
+            .bind(item.security)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn get_group(&self, message_id: &str, chat_id: i64) -> Result<Option<PrGroup>> {
+        let group = sqlx::query_as::<_, PrGroup>(
+            &self.sql("SELECT * FROM pr_groups WHERE message_id = ? AND chat_id = ?"),
+        )
+        .bind(message_id)
+        .bind(chat_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(group)
+    }
+
+    pub async fn get_all_groups(&self) -> Result<Vec<PrGroup>> {
+        let groups = sqlx::query_as::<_, PrGroup>("SELECT * FROM pr_groups")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(groups)
+    }
+
+    pub async fn find_recent_group(
+        &self,
+        chat_id: i64,
+        author: &str,
+        since: i64,
+    ) -> Result<Option<PrGroup>> {
+        let group = sqlx::query_as::<_, PrGroup>(&self.sql(
+            "SELECT * FROM pr_groups WHERE chat_id = ? AND author = ? AND created_at >= ?
+             ORDER BY created_at DESC LIMIT 1",
+        ))
+        .bind(chat_id)
+        .bind(author)
+        .bind(since)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(group)
+    }
+
+    pub async fn get_group_items(&self, message_id: &str, chat_id: i64) -> Result<Vec<GroupItem>> {
+        let items = sqlx::query_as::<_, GroupItem>(&self.sql(
+            "SELECT * FROM group_items WHERE message_id = ? AND chat_id = ?
+             ORDER BY repo_owner, repo_name, pr_number",
+        ))
+        .bind(message_id)
+        .bind(chat_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(items)
+    }
+
+    /// Holds a new PR back for a burst of its author's; holding it again changes nothing.
+    pub async fn hold_burst_pr(&self, pr: &BurstPr) -> Result<()> {
+        sqlx::query(&self.sql(
+            "INSERT INTO burst_prs (chat_id, author, repo_owner, repo_name, pr_number, opened_at)
+             VALUES (?, ?, ?, ?, ?, ?) ON CONFLICT DO NOTHING",
+        ))
+        .bind(pr.chat_id)
+        .bind(&pr.author)
+        .bind(&pr.repo_owner)
+        .bind(&pr.repo_name)
+        .bind(pr.pr_number)
+        .bind(pr.opened_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Every held PR, oldest first.
+    pub async fn get_burst_prs(&self) -> Result<Vec<BurstPr>> {
+        let prs = sqlx::query_as::<_, BurstPr>(
+            "SELECT chat_id, author, repo_owner, repo_name, pr_number, opened_at FROM burst_prs
+             ORDER BY opened_at",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(prs)
+    }
+
+    /// Stops holding a PR back. Returns whether it was held, so only one caller announces it.
+    pub async fn release_burst_pr(&self, pr: &BurstPr) -> Result<bool> {
+        let released = sqlx::query(&self.sql(
+            "DELETE FROM burst_prs
+             WHERE chat_id = ? AND repo_owner = ? AND repo_name = ? AND pr_number = ?",
+        ))
+        .bind(pr.chat_id)
+        .bind(&pr.repo_owner)
+        .bind(&pr.repo_name)
+        .bind(pr.pr_number)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+        Ok(released > 0)
+    }
+
+    pub async fn remove_group(&self, message_id: &str, chat_id: i64) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(&self.sql("DELETE FROM group_items WHERE message_id = ? AND chat_id = ?"))
+            .bind(message_id)
+            .bind(chat_id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query(&self.sql("DELETE FROM pr_groups WHERE message_id = ? AND chat_id = ?"))
+            .bind(message_id)
+            .bind(chat_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh, migrated SQLite database in the temp directory, the operator's unless it's a
+    /// tenant's.
+    async fn temp_db(name: &str, operator: bool) -> Db {
+        let path = std::env::temp_dir().join(format!("tg-bot-{}-{}.db", name, std::process::id()));
+        std::fs::remove_file(&path).ok();
+        Db::new(&format!("sqlite:{}?mode=rwc", path.display()), operator)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn only_the_operators_database_keeps_tokens() {
+        let operator = temp_db("operator", true).await;
+        assert!(operator.table_exists("github_tokens").await.unwrap());
+        operator
+            .set_github_token(7, "alice", "v1:00:00")
+            .await
+            .unwrap();
+        assert_eq!(
+            operator.get_github_token(7).await.unwrap(),
+            Some(("alice".to_string(), "v1:00:00".to_string()))
+        );
+
+        let tenant = temp_db("tenant", false).await;
+        assert!(!tenant.table_exists("github_tokens").await.unwrap());
+        // Tokens were never kept in plain text next to the links
+        let plain: i64 =
+            sqlx::query_scalar(&tenant.sql(&tenant.storage.column_count_query("users")))
+                .bind("github_token")
+                .fetch_one(&tenant.pool)
+                .await
+                .unwrap();
+        assert_eq!(plain, 0);
+    }
+
+    #[tokio::test]
+    async fn dropped_routes_only_remove_what_the_config_subscribed() {
+        let db = temp_db("config-routes", true).await;
+        db.add_config_repository(1, "Near", "Core").await.unwrap();
+        db.add_config_repository(2, "near", "core").await.unwrap();
+        // Chat 2 tracked it itself as well
+        db.add_repository(2, "near", "core").await.unwrap();
+
+        assert!(db
+            .remove_config_repository(1, "near", "core")
+            .await
+            .unwrap());
+        assert!(!db
+            .remove_config_repository(2, "near", "core")
+            .await
+            .unwrap());
+        let chats: Vec<i64> = db
+            .get_repositories()
+            .await
+            .unwrap()
+            .iter()
+            .map(|r| r.chat_id)
+            .collect();
+        assert_eq!(chats, vec![2]);
+    }
+
+    #[tokio::test]
+    async fn merged_and_closed_cards_arent_open_reviews() {
+        let db = temp_db("open-reviews", true).await;
+        for username in ["alice", "bob"] {
+            db.add_pool_member(1, "near", "core", username)
+                .await
+                .unwrap();
+        }
+        for (message_id, status, reviewer) in [
+            ("10", "in_review", "alice"),
+            ("11", "merged", "bob"),
+            ("12", "closed", "bob"),
+        ] {
+            sqlx::query(&db.sql(
+                "INSERT INTO messages
+                 (message_id, chat_id, pr_url, title, author, repo_owner, repo_name, pr_number, status)
+                 VALUES (?, 1, '', '', 'carol', 'near', 'core', ?, ?)",
+            ))
+            .bind(message_id)
+            .bind(message_id.parse::<i64>().unwrap())
+            .bind(status)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+            let none: &[String] = &[];
+            db.update_reactions(
+                message_id,
+                1,
+                &[reviewer.to_string()],
+                [
+                    ("approval", none),
+                    ("changes_requested", none),
+                    ("comment", none),
+                ],
+                none,
+            )
+            .await
+            .unwrap();
+        }
+
+        let pool = db.get_reviewer_pool(1, "near", "core", 0).await.unwrap();
+        let open: Vec<(&str, i64)> = pool
+            .iter()
+            .map(|m| (m.username.as_str(), m.open_reviews))
+            .collect();
+        assert_eq!(open, vec![("alice", 1), ("bob", 0)]);
+    }
 }
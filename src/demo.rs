@@ -0,0 +1,217 @@
+use crate::checks::CiSummary;
+use crate::handlers;
+use crate::lifecycle::{PrEvent, PrStatus};
+use crate::monitor::Monitor;
+use crate::reactions::ReactionAction;
+use crate::sender::Retried;
+use crate::state::PrData;
+use chrono::Utc;
+use teloxide::prelude::*;
+use teloxide::types::{ParseMode, ReplyParameters};
+use tracing::{error, info};
+
+const REPO: &str = "demo/widgets";
+/// Steps to wait after a round before replaying it.
+const ROUND_PAUSE: u32 = 10;
+
+/// One scripted change to a demo card.
+enum Step {
+    /// A chat member reacts to the card (or replies with the command when the emoji is off)
+    React(usize, &'static str, ReactionAction),
+    /// A review submitted on GitHub: approval or changes requested
+    GithubReview(usize, &'static str, bool),
+    /// Anything else the GitHub sync would report
+    Event(usize, PrEvent, &'static str),
+}
+
+const SCRIPT: [Step; 12] = [
+    Step::React(0, "dave", ReactionAction::Review),
+    Step::React(1, "erin", ReactionAction::Review),
+    Step::GithubReview(1, "erin", false),
+    Step::React(0, "dave", ReactionAction::Approve),
+    Step::Event(
+        0,
+        PrEvent::MergeReady(true),
+        "CI passed and there are no conflicts",
+    ),
+    Step::React(1, "bob", ReactionAction::ReReview),
+    Step::React(2, "carol", ReactionAction::Draft),
+    Step::React(2, "frank", ReactionAction::Review),
+    Step::React(2, "frank", ReactionAction::GiveUp),
+    Step::React(0, "alice", ReactionAction::Merge),
+    Step::GithubReview(1, "erin", true),
+    Step::React(2, "dave", ReactionAction::Comment),
+];
+
+/// Replays a scripted round of fake PRs in `DEMO_CHAT_ID`, one step every `DEMO_STEP_SECONDS`,
+/// so a team can try the cards without connecting a repository. The cards aren't tracked:
+/// reactions to them and GitHub syncs leave them alone.
+pub async fn run(monitor: Monitor) {
+    loop {
+        let config = monitor.config();
+        let step = tokio::time::Duration::from_secs(config.demo_step_secs);
+        if let Some(chat_id) = config.demo_chat_id {
+            play_round(&monitor, ChatId(chat_id)).await;
+            tokio::time::sleep(step * ROUND_PAUSE).await;
+        } else {
+            tokio::time::sleep(step).await;
+        }
+    }
+}
+
+async fn play_round(monitor: &Monitor, chat_id: ChatId) {
+    info!("Starting a demo round in {}", chat_id);
+    let mut cards = Vec::new();
+    for data in fake_prs(chat_id.0) {
+        let mut request = monitor
+            .bot
+            .send_message(chat_id, handlers::generate_message_text(&data))
+            .parse_mode(ParseMode::Html)
+            .link_preview_options(handlers::no_link_preview());
+        if let Some(buttons) = handlers::card_buttons(&monitor.state, chat_id.0).await {
+            request = request.reply_markup(buttons);
+        }
+        match request.retried().await {
+            Ok(sent) => cards.push((sent.id, data)),
+            Err(e) => {
+                error!("Failed to post a demo card to {}: {}", chat_id, e);
+                return;
+            }
+        }
+    }
+
+    for step in &SCRIPT {
+        let config = monitor.config();
+        tokio::time::sleep(tokio::time::Duration::from_secs(config.demo_step_secs)).await;
+        // The demo was turned off or moved by /reload
+        if config.demo_chat_id != Some(chat_id.0) {
+            return;
+        }
+
+        let (index, narration) = match step {
+            Step::React(index, user, action) => {
+                let how = match config.reactions.emoji(*action) {
+                    Some(emoji) => format!("reacted {}", emoji),
+                    None => format!("replied /{}", action.name()),
+                };
+                handlers::apply_action(&mut cards[*index].1, *action, user, 0);
+                (*index, format!("{} {}: {}", user, how, describe(*action)))
+            }
+            Step::GithubReview(index, user, approved) => {
+                let data = &mut cards[*index].1;
+                let user = user.to_string();
+                data.approvals.retain(|u| u != &user);
+                data.changes_requested.retain(|u| u != &user);
+                if *approved {
+                    data.approvals.push(user.clone());
+                } else {
+                    data.changes_requested.push(user.clone());
+                }
+                data.apply(PrEvent::ReviewsChanged);
+                let verdict = if *approved {
+                    "approved"
+                } else {
+                    "requested changes"
+                };
+                (*index, format!("{} {} on GitHub", user, verdict))
+            }
+            Step::Event(index, event, what) => {
+                cards[*index].1.apply(*event);
+                (*index, what.to_string())
+            }
+        };
+
+        let (message_id, data) = &cards[index];
+        if let Err(e) =
+            handlers::edit_card(&monitor.bot, &monitor.state, chat_id, *message_id, data).await
+        {
+            error!("Failed to edit a demo card in {}: {}", chat_id, e);
+        }
+        monitor
+            .bot
+            .send_message(chat_id, format!("🎬 {}", narration))
+            .reply_parameters(ReplyParameters::new(*message_id))
+            .disable_notification(true)
+            .retried()
+            .await
+            .ok();
+    }
+}
+
+/// What the demo narration says an action does.
+fn describe(action: ReactionAction) -> &'static str {
+    match action {
+        ReactionAction::Review => "starts reviewing",
+        ReactionAction::Approve => "approves",
+        ReactionAction::RequestChanges => "requests changes",
+        ReactionAction::Comment => "leaves comments",
+        ReactionAction::GiveUp => "gives up the review",
+        ReactionAction::Merge => "marks it merged",
+        ReactionAction::ReReview => "addressed the feedback, asks for a re-review",
+        ReactionAction::Draft => "marks it ready for review",
+    }
+}
+
+fn fake_prs(chat_id: i64) -> [PrData; 3] {
+    [
+        fake_pr(
+            chat_id,
+            101,
+            "Retry failed webhook deliveries",
+            "alice",
+            PrStatus::AwaitingReview,
+            &["enhancement"],
+            Some(CiSummary {
+                passed: 12,
+                failed: 0,
+                pending: 0,
+            }),
+        ),
+        fake_pr(
+            chat_id,
+            102,
+            "Move sessions to Redis",
+            "bob",
+            PrStatus::AwaitingReview,
+            &["backend"],
+            Some(CiSummary {
+                passed: 9,
+                failed: 0,
+                pending: 3,
+            }),
+        ),
+        fake_pr(
+            chat_id,
+            103,
+            "WIP: dark mode for the settings page",
+            "carol",
+            PrStatus::Draft,
+            &[],
+            None,
+        ),
+    ]
+}
+
+fn fake_pr(
+    chat_id: i64,
+    pr_number: u64,
+    title: &str,
+    author: &str,
+    status: PrStatus,
+    labels: &[&str],
+    ci: Option<CiSummary>,
+) -> PrData {
+    PrData {
+        pr_url: format!("https://github.com/{}/pull/{}", REPO, pr_number),
+        title: title.to_string(),
+        author: author.to_string(),
+        repo: REPO.to_string(),
+        pr_number,
+        status,
+        status_since: Utc::now().timestamp(),
+        ci,
+        labels: labels.iter().map(|l| l.to_string()).collect(),
+        chat_id,
+        ..Default::default()
+    }
+}
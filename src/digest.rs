@@ -0,0 +1,147 @@
+use crate::filter::Viewer;
+use crate::handlers;
+use crate::lifecycle::PrStatus;
+use crate::monitor::Monitor;
+use crate::sender::Retried;
+use crate::state::PrData;
+use chrono::Utc;
+use std::collections::BTreeMap;
+use teloxide::prelude::*;
+use teloxide::types::ParseMode;
+use tracing::{error, info};
+
+const JOB: &str = "daily_digest";
+const CHECK_INTERVAL: tokio::time::Duration = tokio::time::Duration::from_secs(5 * 60);
+/// PRs listed per section, keeping the digest under Telegram's message size limit.
+const SECTION_LIMIT: usize = 15;
+
+/// Posts a summary of the open PRs in every chat once a day at `DIGEST_TIME` (UTC).
+pub async fn run(monitor: Monitor) {
+    loop {
+        send_due(&monitor).await;
+        tokio::time::sleep(CHECK_INTERVAL).await;
+    }
+}
+
+async fn send_due(monitor: &Monitor) {
+    let config = monitor.config();
+    let Some(time) = config.digest_time else {
+        return;
+    };
+    let now = Utc::now();
+    let scheduled = now.date_naive().and_time(time).and_utc();
+    if now < scheduled {
+        return;
+    }
+
+    let mut chats: BTreeMap<i64, Vec<Card>> = BTreeMap::new();
+    for msg in monitor
+        .state
+        .get_all_active_messages()
+        .await
+        .unwrap_or_default()
+    {
+        if let Ok(Some(data)) = monitor
+            .state
+            .get_pr_data(msg.message_id.clone(), msg.chat_id)
+            .await
+        {
+            if let Some(filter) = &config.digest_filter {
+                if !filter.matches(&data, &Viewer::default()) {
+                    continue;
+                }
+            }
+            chats.entry(msg.chat_id).or_default().push(Card {
+                message_id: msg.message_id,
+                data,
+            });
+        }
+    }
+
+    for (chat_id, cards) in chats {
+        let last_run = monitor.state.get_job_run(chat_id, JOB).await.ok().flatten();
+        if last_run.is_some_and(|t| t >= scheduled.timestamp()) {
+            continue;
+        }
+        monitor
+            .state
+            .set_job_run(chat_id, JOB, now.timestamp())
+            .await
+            .ok();
+
+        let Some(text) = digest_text(chat_id, &cards) else {
+            continue;
+        };
+        info!("Sending the daily digest to {}", chat_id);
+        match monitor
+            .bot
+            .send_message(ChatId(chat_id), text)
+            .parse_mode(ParseMode::Html)
+            .link_preview_options(handlers::no_link_preview())
+            .disable_notification(true)
+            .retried()
+            .await
+        {
+            Ok(_) => monitor.state.audit(format!(
+                "notified {}: daily digest of {} PRs",
+                chat_id,
+                cards.len()
+            )),
+            Err(e) => error!("Failed to send the daily digest to {}: {}", chat_id, e),
+        }
+    }
+}
+
+/// The digest of a chat's open PRs, or `None` when none needs attention.
+fn digest_text(chat_id: i64, cards: &[Card]) -> Option<String> {
+    let sections: [(&str, Vec<&Card>); 3] = [
+        (
+            "👀 Needs a reviewer",
+            cards
+                .iter()
+                .filter(|c| c.data.status == PrStatus::AwaitingReview && !c.data.is_re_review())
+                .collect(),
+        ),
+        (
+            "🙏 Awaiting re-review",
+            cards.iter().filter(|c| c.data.is_re_review()).collect(),
+        ),
+        (
+            "✅ Approved, not merged",
+            cards
+                .iter()
+                .filter(|c| matches!(c.data.status, PrStatus::Approved | PrStatus::ReadyToMerge))
+                .collect(),
+        ),
+    ];
+    if sections.iter().all(|(_, cards)| cards.is_empty()) {
+        return None;
+    }
+
+    let mut text = format!("📰 <b>Daily digest</b>: {} open PRs\n", cards.len());
+    for (title, section) in sections.iter().filter(|(_, s)| !s.is_empty()) {
+        text.push_str(&format!("\n<b>{} ({})</b>\n", title, section.len()));
+        for card in section.iter().take(SECTION_LIMIT) {
+            let data = &card.data;
+            // Link back to the card where there is one, so reactions are a tap away
+            let link = handlers::message_link(chat_id, &card.message_id)
+                .unwrap_or_else(|| data.pr_url.clone());
+            text.push_str(&format!(
+                "• <a href=\"{}\">{}#{}</a> {}\n",
+                link,
+                data.repo,
+                data.pr_number,
+                teloxide::utils::html::escape(&data.title)
+            ));
+        }
+        if section.len() > SECTION_LIMIT {
+            text.push_str(&format!("…and {} more\n", section.len() - SECTION_LIMIT));
+        }
+    }
+    Some(text)
+}
+
+struct Card {
+    message_id: String,
+    data: PrData,
+}
@@ -0,0 +1,118 @@
+use crate::config::Config;
+use crate::monitor::Monitor;
+use crate::onboarding;
+use crate::sender::Retried;
+use octocrab::models::Repository;
+use teloxide::prelude::*;
+use tracing::{error, info};
+
+const CHECK_INTERVAL: tokio::time::Duration = tokio::time::Duration::from_secs(60 * 60);
+/// Repositories named in one announcement at most, so a first pass over a large
+/// organization doesn't flood the chat.
+const NAMES_LIMIT: usize = 20;
+
+/// Tracks the repositories of `GITHUB_ORGS` in the default chat as they're created, checking
+/// every hour. Each repository is added once: one untracked later, ignored or filtered out by
+/// `ORG_REPO_TOPICS` / `ORG_REPO_NAMES` stays out.
+pub async fn run(monitor: Monitor) {
+    loop {
+        discover(&monitor).await;
+        tokio::time::sleep(CHECK_INTERVAL).await;
+    }
+}
+
+/// Whether the repository passes the topic and name filters.
+fn wanted(config: &Config, repo: &Repository) -> bool {
+    let topics = repo.topics.as_deref().unwrap_or_default();
+    (config.org_repo_topics.is_empty()
+        || topics
+            .iter()
+            .any(|t| config.org_repo_topics.contains(&t.to_lowercase())))
+        && (config.org_repo_names.is_empty()
+            || config.org_repo_names.iter().any(|n| n.is_match(&repo.name)))
+}
+
+async fn discover(monitor: &Monitor) {
+    let config = monitor.config();
+    if config.github_orgs.is_empty() || monitor.github.sync_paused_until().await.is_some() {
+        return;
+    }
+    for org in &config.github_orgs {
+        let repos = match monitor.github.get_org_repos(org).await {
+            Ok(repos) => repos,
+            Err(e) => {
+                error!("Failed to list the repositories of {}: {}", org, e);
+                monitor.counters.error();
+                continue;
+            }
+        };
+
+        let mut added = Vec::new();
+        for repo in repos {
+            let owner = repo.owner.as_ref().map_or(org.clone(), |o| o.login.clone());
+            if !wanted(&config, &repo) || monitor.is_ignored(&owner, &repo.name) {
+                continue;
+            }
+            match monitor.state.mark_repo_discovered(&owner, &repo.name).await {
+                Ok(true) => {}
+                Ok(false) => continue,
+                Err(e) => {
+                    error!("Failed to record {}/{}: {}", owner, repo.name, e);
+                    continue;
+                }
+            }
+            if let Err(e) = monitor
+                .state
+                .add_repository(config.chat_id, &owner, &repo.name)
+                .await
+            {
+                error!("Failed to track {}/{}: {}", owner, repo.name, e);
+                continue;
+            }
+            // Features the bot's access doesn't allow are skipped from the first poll on
+            onboarding::probe(&monitor.github, &monitor.state, &owner, &repo.name).await;
+            info!("Discovered {}/{} in {}", owner, repo.name, org);
+            monitor.state.audit(format!(
+                "tracked {}/{} in {}: discovered in {}",
+                owner, repo.name, config.chat_id, org
+            ));
+            added.push(format!("{}/{}", owner, repo.name));
+        }
+
+        if added.is_empty() {
+            continue;
+        }
+        let mut text = format!(
+            "📡 Now tracking {} new {} of {}: {}",
+            added.len(),
+            if added.len() == 1 {
+                "repository"
+            } else {
+                "repositories"
+            },
+            org,
+            added
+                .iter()
+                .take(NAMES_LIMIT)
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        if added.len() > NAMES_LIMIT {
+            text.push_str(&format!(" …and {} more", added.len() - NAMES_LIMIT));
+        }
+        text.push_str(". /untrack owner/repo keeps one out.");
+        if let Err(e) = monitor
+            .bot
+            .send_message(ChatId(config.chat_id), text)
+            .disable_notification(true)
+            .retried()
+            .await
+        {
+            error!(
+                "Failed to announce the new repositories of {} in {}: {}",
+                org, config.chat_id, e
+            );
+        }
+    }
+}
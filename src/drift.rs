@@ -0,0 +1,284 @@
+use crate::github::SubmittedReview;
+use crate::monitor::Monitor;
+use crate::reactions::ReactionAction;
+use crate::sender::Retried;
+use crate::state::{PrData, StateManager};
+use chrono::{Datelike, Duration, Utc};
+use octocrab::models::pulls::ReviewState;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use teloxide::prelude::*;
+use tracing::{error, info};
+
+const JOB: &str = "drift_report";
+const CHECK_INTERVAL: tokio::time::Duration = tokio::time::Duration::from_secs(60 * 60);
+/// Event of a review mark set from Telegram; the detail is the mark's action name.
+const DECLARED: &str = "declared";
+/// Event of how a Telegram mark held up once the PR closed, detail "approve>none" and alike.
+const OUTCOME: &str = "declared_outcome";
+/// Review marks set in Telegram that GitHub can confirm.
+const MARKS: [ReactionAction; 4] = [
+    ReactionAction::Review,
+    ReactionAction::Approve,
+    ReactionAction::RequestChanges,
+    ReactionAction::Comment,
+];
+/// Names listed per mark as not followed up on GitHub.
+const NAMES_LIMIT: usize = 10;
+
+/// Posts every chat's process drift report for the past 30 days every `DRIFT_REPORT_DAY` (UTC).
+pub async fn run(monitor: Monitor) {
+    loop {
+        post_due(&monitor).await;
+        tokio::time::sleep(CHECK_INTERVAL).await;
+    }
+}
+
+fn marked(data: &PrData, mark: ReactionAction) -> &[String] {
+    match mark {
+        ReactionAction::Review => &data.reviewers,
+        ReactionAction::Approve => &data.approvals,
+        ReactionAction::RequestChanges => &data.changes_requested,
+        _ => &data.comments,
+    }
+}
+
+/// Records the review marks `username` newly set on a card from Telegram, to compare them
+/// with GitHub once the PR closes.
+pub async fn record_declared(
+    state: &StateManager,
+    message_id: &str,
+    before: &PrData,
+    after: &PrData,
+    username: &str,
+) {
+    let username = username.to_string();
+    for mark in MARKS {
+        if marked(after, mark).contains(&username) && !marked(before, mark).contains(&username) {
+            state
+                .record_event(after, message_id, &username, DECLARED, mark.name())
+                .await
+                .ok();
+        }
+    }
+}
+
+/// Compares the Telegram marks of a closing card with the reviews submitted on GitHub and
+/// records how each held up. `identities` maps lowercased GitHub logins to linked names.
+pub async fn record_outcomes(
+    state: &StateManager,
+    data: &PrData,
+    message_id: &str,
+    reviews: &[SubmittedReview],
+    identities: &HashMap<String, String>,
+) {
+    let declared: BTreeSet<(String, String)> = match state
+        .get_card_events(data.chat_id, message_id, DECLARED)
+        .await
+    {
+        Ok(declared) => declared.into_iter().collect(),
+        Err(e) => {
+            error!(
+                "Failed to load the marks of {}#{}: {}",
+                data.repo, data.pr_number, e
+            );
+            return;
+        }
+    };
+    if declared.is_empty() {
+        return;
+    }
+
+    // The strongest review each user submitted, by lowercased name
+    let mut submitted: HashMap<String, &'static str> = HashMap::new();
+    for review in reviews {
+        let outcome = match review.state {
+            ReviewState::Approved => "approved",
+            ReviewState::ChangesRequested => "changes_requested",
+            ReviewState::Commented => "commented",
+            _ => continue,
+        };
+        let login = review.login.to_lowercase();
+        let name = identities
+            .get(&login)
+            .map(|n| n.to_lowercase())
+            .unwrap_or(login);
+        let best = submitted.entry(name).or_insert(outcome);
+        if strength(outcome) > strength(best) {
+            *best = outcome;
+        }
+    }
+
+    for (username, mark) in declared {
+        let outcome = submitted
+            .get(&username.to_lowercase())
+            .copied()
+            .unwrap_or("none");
+        state
+            .record_event(
+                data,
+                message_id,
+                &username,
+                OUTCOME,
+                &format!("{}>{}", mark, outcome),
+            )
+            .await
+            .ok();
+    }
+}
+
+fn strength(outcome: &str) -> u8 {
+    match outcome {
+        "approved" => 3,
+        "changes_requested" => 2,
+        "commented" => 1,
+        _ => 0,
+    }
+}
+
+/// How the chat's Telegram marks on PRs closed since `since` held up on GitHub, or `None`
+/// when no such PR had marks.
+pub async fn report_text(
+    state: &StateManager,
+    chat_id: i64,
+    since: i64,
+    days: i64,
+) -> Option<String> {
+    let outcomes = match state.get_events_since(chat_id, OUTCOME, since).await {
+        Ok(outcomes) => outcomes,
+        Err(e) => {
+            error!("Failed to load mark outcomes of {}: {}", chat_id, e);
+            return None;
+        }
+    };
+
+    let mut sections = Vec::new();
+    for mark in MARKS {
+        // (confirmed, other review, no review) and who didn't follow up, per mark
+        let (mut confirmed, mut other, mut missing) = (0, 0, 0);
+        let mut unconfirmed: BTreeMap<String, usize> = BTreeMap::new();
+        for (username, detail) in &outcomes {
+            let Some((declared, outcome)) = detail.split_once('>') else {
+                continue;
+            };
+            if declared != mark.name() {
+                continue;
+            }
+            let is_confirmed = match mark {
+                ReactionAction::Approve => outcome == "approved",
+                ReactionAction::RequestChanges => outcome == "changes_requested",
+                _ => outcome != "none",
+            };
+            if is_confirmed {
+                confirmed += 1;
+                continue;
+            }
+            if outcome == "none" {
+                missing += 1;
+            } else {
+                other += 1;
+            }
+            *unconfirmed.entry(username.clone()).or_default() += 1;
+        }
+        let total = confirmed + other + missing;
+        if total == 0 {
+            continue;
+        }
+
+        let on_github = match mark {
+            ReactionAction::Approve => "approved",
+            ReactionAction::RequestChanges => "requested changes",
+            _ => "reviewed",
+        };
+        let mut section = format!(
+            "{}: {} — {} {} on GitHub ({}%)",
+            mark.button_label(),
+            total,
+            confirmed,
+            on_github,
+            confirmed * 100 / total
+        );
+        if other > 0 {
+            section.push_str(&format!(", {} reviewed otherwise", other));
+        }
+        if missing > 0 {
+            section.push_str(&format!(", {} never reviewed", missing));
+        }
+        if !unconfirmed.is_empty() {
+            let mut names: Vec<(String, usize)> = unconfirmed.into_iter().collect();
+            names.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+            let listed: Vec<String> = names
+                .iter()
+                .take(NAMES_LIMIT)
+                .map(|(name, n)| match n {
+                    1 => name.clone(),
+                    n => format!("{} ×{}", name, n),
+                })
+                .collect();
+            section.push_str(&format!(
+                "\n   Not {} on GitHub: {}",
+                on_github,
+                listed.join(", ")
+            ));
+            if names.len() > NAMES_LIMIT {
+                section.push_str(&format!(" and {} more", names.len() - NAMES_LIMIT));
+            }
+        }
+        sections.push(section);
+    }
+    if sections.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "🧭 Process drift, last {} days\nHow the review marks set here held up on GitHub by the time their PRs closed:\n\n{}",
+        days,
+        sections.join("\n")
+    ))
+}
+
+async fn post_due(monitor: &Monitor) {
+    let Some(day) = monitor.config().drift_report_day else {
+        return;
+    };
+    let now = Utc::now();
+    if now.weekday() != day {
+        return;
+    }
+
+    let mut chats: BTreeSet<i64> = BTreeSet::new();
+    for (_, subscribers) in monitor.state.get_subscriptions().await.unwrap_or_default() {
+        chats.extend(subscribers);
+    }
+    let since = (now - Duration::days(30)).timestamp();
+    for chat_id in chats {
+        // Already posted today
+        let last_run = monitor.state.get_job_run(chat_id, JOB).await.ok().flatten();
+        if last_run.is_some_and(|t| t > (now - Duration::days(6)).timestamp()) {
+            continue;
+        }
+        monitor
+            .state
+            .set_job_run(chat_id, JOB, now.timestamp())
+            .await
+            .ok();
+
+        let Some(text) = report_text(&monitor.state, chat_id, since, 30).await else {
+            continue;
+        };
+        info!("Posting the process drift report to {}", chat_id);
+        match monitor
+            .bot
+            .send_message(ChatId(chat_id), text)
+            .disable_notification(true)
+            .retried()
+            .await
+        {
+            Ok(_) => monitor
+                .state
+                .audit(format!("notified {}: process drift report", chat_id)),
+            Err(e) => error!(
+                "Failed to send the process drift report to {}: {}",
+                chat_id, e
+            ),
+        }
+    }
+}
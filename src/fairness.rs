@@ -0,0 +1,168 @@
+use crate::db::FORMER_MEMBER;
+use crate::monitor::Monitor;
+use crate::sender::Retried;
+use crate::state::StateManager;
+use chrono::{Datelike, Duration, Utc};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use teloxide::prelude::*;
+use tracing::{error, info};
+
+const JOB: &str = "fairness_report";
+const CHECK_INTERVAL: tokio::time::Duration = tokio::time::Duration::from_secs(60 * 60);
+/// People listed at most, those leaning on others the most first.
+const PEOPLE_LIMIT: usize = 20;
+/// One side at least this many times the other counts as an imbalance...
+const IMBALANCE_RATIO: i64 = 3;
+/// ...once the larger side has at least this many reviews, so a quiet month doesn't count.
+const IMBALANCE_MIN: i64 = 5;
+
+/// Posts every chat's review fairness report for the past 30 days every month on
+/// `FAIRNESS_REPORT_DAY` (UTC).
+pub async fn run(monitor: Monitor) {
+    loop {
+        post_due(&monitor).await;
+        tokio::time::sleep(CHECK_INTERVAL).await;
+    }
+}
+
+/// Per person, (reviews given, reviews received) between `since` and `until`. Authors are
+/// named by their GitHub login, so linked users (`/link`) are counted under their card name.
+async fn counts(
+    state: &StateManager,
+    identities: &HashMap<String, String>,
+    chat_id: i64,
+    since: i64,
+    until: i64,
+) -> anyhow::Result<BTreeMap<String, (i64, i64)>> {
+    let name = |name: String| {
+        identities
+            .get(&name.to_lowercase())
+            .cloned()
+            .unwrap_or(name)
+    };
+    let pairs: BTreeSet<(String, String, String)> = state
+        .get_review_pairs(chat_id, since, until)
+        .await?
+        .into_iter()
+        .map(|(pr, reviewer, author)| (pr, name(reviewer), name(author)))
+        .collect();
+
+    let mut counts: BTreeMap<String, (i64, i64)> = BTreeMap::new();
+    for (_, reviewer, author) in pairs {
+        // Reviewing your own PR gives nobody anything
+        if reviewer == author || author == FORMER_MEMBER {
+            continue;
+        }
+        counts.entry(reviewer).or_default().0 += 1;
+        counts.entry(author).or_default().1 += 1;
+    }
+    Ok(counts)
+}
+
+fn imbalanced((given, received): (i64, i64)) -> bool {
+    let (low, high) = (given.min(received), given.max(received));
+    high >= IMBALANCE_MIN && high >= low * IMBALANCE_RATIO
+}
+
+/// The report for the `days` before now, `None` when nobody reviewed anything. Imbalances
+/// that also showed over the `days` before are marked chronic.
+pub async fn report_text(state: &StateManager, chat_id: i64, days: i64) -> Option<String> {
+    let now = Utc::now();
+    let since = (now - Duration::days(days)).timestamp();
+    let before = (now - Duration::days(2 * days)).timestamp();
+    let identities = state.get_github_identities().await.unwrap_or_default();
+    let current = match counts(state, &identities, chat_id, since, now.timestamp()).await {
+        Ok(counts) => counts,
+        Err(e) => {
+            error!("Failed to load the reviews of {}: {}", chat_id, e);
+            return None;
+        }
+    };
+    if current.is_empty() {
+        return None;
+    }
+    let previous = counts(state, &identities, chat_id, before, since)
+        .await
+        .unwrap_or_default();
+
+    let mut people: Vec<(String, (i64, i64))> = current.into_iter().collect();
+    people.sort_by(|a, b| {
+        (b.1 .1 - b.1 .0)
+            .cmp(&(a.1 .1 - a.1 .0))
+            .then(a.0.cmp(&b.0))
+    });
+    let lines: Vec<String> = people
+        .iter()
+        .take(PEOPLE_LIMIT)
+        .map(|(name, (given, received))| {
+            let mark = if !imbalanced((*given, *received)) {
+                "▫️"
+            } else if previous.get(name).is_some_and(|c| imbalanced(*c)) {
+                "🔴"
+            } else {
+                "🟠"
+            };
+            format!("{} {} — gave {}, received {}", mark, name, given, received)
+        })
+        .collect();
+    let mut text = format!(
+        "⚖️ Review fairness, last {} days\nReviews given on others' PRs vs received on one's own, those leaning on others the most first:\n\n{}",
+        days,
+        lines.join("\n")
+    );
+    if people.len() > PEOPLE_LIMIT {
+        text.push_str(&format!("\n…and {} more", people.len() - PEOPLE_LIMIT));
+    }
+    text.push_str(&format!(
+        "\n\n🟠 one side {}× the other or more · 🔴 the {} days before too",
+        IMBALANCE_RATIO, days
+    ));
+    Some(text)
+}
+
+async fn post_due(monitor: &Monitor) {
+    let Some(day) = monitor.config().fairness_report_day else {
+        return;
+    };
+    let now = Utc::now();
+    if now.day() != day {
+        return;
+    }
+
+    let mut chats: BTreeSet<i64> = BTreeSet::new();
+    for (_, subscribers) in monitor.state.get_subscriptions().await.unwrap_or_default() {
+        chats.extend(subscribers);
+    }
+    for chat_id in chats {
+        // Already posted today
+        let last_run = monitor.state.get_job_run(chat_id, JOB).await.ok().flatten();
+        if last_run.is_some_and(|t| t > (now - Duration::days(2)).timestamp()) {
+            continue;
+        }
+        monitor
+            .state
+            .set_job_run(chat_id, JOB, now.timestamp())
+            .await
+            .ok();
+
+        let Some(text) = report_text(&monitor.state, chat_id, 30).await else {
+            continue;
+        };
+        info!("Posting the review fairness report to {}", chat_id);
+        match monitor
+            .bot
+            .send_message(ChatId(chat_id), text)
+            .disable_notification(true)
+            .retried()
+            .await
+        {
+            Ok(_) => monitor
+                .state
+                .audit(format!("notified {}: review fairness report", chat_id)),
+            Err(e) => error!(
+                "Failed to send the review fairness report to {}: {}",
+                chat_id, e
+            ),
+        }
+    }
+}
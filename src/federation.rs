@@ -0,0 +1,321 @@
+use crate::bulk;
+use crate::db::{ArchiveCursor, ArchivedMerge};
+use crate::state::StateManager;
+use anyhow::{bail, Result};
+use axum::extract::{RawQuery, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use tokio::time::{sleep, Duration};
+use tracing::{error, info, warn};
+
+pub const FEED_PATH: &str = "/federation/archive";
+/// Carries `sha256=<hex hmac>` of the query on requests and of the body on responses.
+const SIGNATURE_HEADER: &str = "x-federation-signature";
+const PAGE_SIZE: i64 = 500;
+/// How far a request's signed `ts` may be from the feed's clock, so a captured request can't be
+/// replayed later.
+const MAX_REQUEST_AGE_SECS: i64 = 5 * 60;
+const PULL_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// What an instance publishes at `FEED_PATH`: its own archived merges in cursor order.
+#[derive(Serialize, Deserialize)]
+struct Feed {
+    instance: String,
+    entries: Vec<ArchivedMerge>,
+}
+
+struct FeedState {
+    state: Arc<StateManager>,
+    instance: String,
+    secret: String,
+}
+
+/// Router serving this instance's merge archive at `GET /federation/archive?since=<unix time>`,
+/// continued past the last entry of a page with `&chat=…&repo=owner/repo&pr=…`. Every request
+/// carries the unix time it was signed at in `ts`.
+pub fn router(state: Arc<StateManager>, instance: String, secret: String) -> Router {
+    Router::new()
+        .route(FEED_PATH, get(serve_feed))
+        .with_state(Arc::new(FeedState {
+            state,
+            instance,
+            secret,
+        }))
+}
+
+fn sign(secret: &str, data: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(data);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// The feed query signed at `now` for the entries since `since`, or for the page after `cursor`.
+fn feed_query(since: i64, cursor: Option<&ArchiveCursor>, now: i64) -> String {
+    let mut query = url::form_urlencoded::Serializer::new(String::new());
+    match cursor {
+        Some(cursor) => query
+            .append_pair("since", &cursor.merged_at.to_string())
+            .append_pair("chat", &cursor.chat_id.to_string())
+            .append_pair(
+                "repo",
+                &format!("{}/{}", cursor.repo_owner, cursor.repo_name),
+            )
+            .append_pair("pr", &cursor.pr_number.to_string()),
+        None => query.append_pair("since", &since.to_string()),
+    };
+    query.append_pair("ts", &now.to_string()).finish()
+}
+
+/// The `since` time, the cursor to continue after when the query continues a page, and the time
+/// the request was signed at.
+fn parse_query(query: &str) -> (i64, Option<ArchiveCursor>, Option<i64>) {
+    let params: HashMap<String, String> = url::form_urlencoded::parse(query.as_bytes())
+        .into_owned()
+        .collect();
+    let since = params
+        .get("since")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0);
+    let signed_at = params.get("ts").and_then(|v| v.parse().ok());
+    (since, parse_cursor(&params, since), signed_at)
+}
+
+/// Whether a request signed at `signed_at` is recent enough to serve at `now`.
+fn is_fresh(signed_at: Option<i64>, now: i64) -> bool {
+    signed_at.is_some_and(|ts| (now - ts).abs() <= MAX_REQUEST_AGE_SECS)
+}
+
+fn parse_cursor(params: &HashMap<String, String>, merged_at: i64) -> Option<ArchiveCursor> {
+    let (repo_owner, repo_name) = params.get("repo")?.split_once('/')?;
+    Some(ArchiveCursor {
+        merged_at,
+        chat_id: params.get("chat")?.parse().ok()?,
+        repo_owner: repo_owner.to_string(),
+        repo_name: repo_name.to_string(),
+        pr_number: params.get("pr")?.parse().ok()?,
+    })
+}
+
+fn verify(secret: &str, data: &[u8], signature: &str) -> bool {
+    let Some(signature) = signature
+        .strip_prefix("sha256=")
+        .and_then(|v| hex::decode(v).ok())
+    else {
+        return false;
+    };
+    let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(secret.as_bytes()) else {
+        return false;
+    };
+    mac.update(data);
+    mac.verify_slice(&signature).is_ok()
+}
+
+async fn serve_feed(
+    State(ctx): State<Arc<FeedState>>,
+    RawQuery(query): RawQuery,
+    headers: HeaderMap,
+) -> Response {
+    let query = query.unwrap_or_default();
+    let signed = headers
+        .get(SIGNATURE_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|s| verify(&ctx.secret, query.as_bytes(), s));
+    if !signed {
+        warn!("Rejected federation feed request with a bad signature");
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+
+    let (since, after, signed_at) = parse_query(&query);
+    if !is_fresh(signed_at, chrono::Utc::now().timestamp()) {
+        warn!("Rejected a stale or undated federation feed request");
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    let entries = match ctx
+        .state
+        .get_archived_merges("", since, after.as_ref(), PAGE_SIZE)
+        .await
+    {
+        Ok(entries) => entries,
+        Err(e) => {
+            error!("Failed to load merge archive: {}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    let body = match serde_json::to_vec(&Feed {
+        instance: ctx.instance.clone(),
+        entries,
+    }) {
+        Ok(body) => body,
+        Err(e) => {
+            error!("Failed to encode federation feed: {}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    let signature = sign(&ctx.secret, &body);
+    (
+        [
+            ("content-type", "application/json".to_string()),
+            (SIGNATURE_HEADER, signature),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+/// Periodically copies the peers' archived merges into the local archive, tagged with their name.
+pub async fn pull_peers(state: Arc<StateManager>, peers: Vec<(String, String)>, secret: String) {
+    let client = reqwest::Client::new();
+    loop {
+        for (name, url) in &peers {
+            match pull_peer(&client, &state, name, url, &secret).await {
+                Ok(0) => {}
+                Ok(count) => info!("Pulled {} archived merges from {}", count, name),
+                Err(e) => error!("Failed to pull federation feed of {}: {}", name, e),
+            }
+        }
+        sleep(PULL_INTERVAL).await;
+    }
+}
+
+async fn pull_peer(
+    client: &reqwest::Client,
+    state: &StateManager,
+    name: &str,
+    url: &str,
+    secret: &str,
+) -> Result<usize> {
+    let since = state.get_last_archived_merge(name).await?.unwrap_or(0);
+    let mut after: Option<ArchiveCursor> = None;
+    let mut total = 0;
+    loop {
+        let query = feed_query(since, after.as_ref(), chrono::Utc::now().timestamp());
+        let response = client
+            .get(format!("{}?{}", url, query))
+            .header(SIGNATURE_HEADER, sign(secret, query.as_bytes()))
+            .send()
+            .await?
+            .error_for_status()?;
+        let signature = response
+            .headers()
+            .get(SIGNATURE_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string)
+            .unwrap_or_default();
+        let body = response.bytes().await?;
+        if !verify(secret, &body, &signature) {
+            bail!("the feed's signature doesn't match FEDERATION_SECRET");
+        }
+
+        let feed: Feed = serde_json::from_slice(&body)?;
+        let count = feed.entries.len();
+        let Some(last) = feed.entries.last().map(ArchivedMerge::cursor) else {
+            return Ok(total);
+        };
+        for mut entry in feed.entries {
+            entry.source = name.to_string();
+            state.archive_merge(&entry).await?;
+        }
+        total += count;
+
+        // A peer that doesn't know cursors serves the same page again
+        if count < PAGE_SIZE as usize || after.as_ref() == Some(&last) {
+            return Ok(total);
+        }
+        after = Some(last);
+    }
+}
+
+/// Per-instance merge statistics over the archive, as shown by `/orgstats`.
+pub async fn org_stats_text(state: &StateManager, window: chrono::Duration) -> Result<String> {
+    let since = (chrono::Utc::now() - window).timestamp();
+    let mut sources: BTreeMap<String, Vec<ArchivedMerge>> = BTreeMap::new();
+    for entry in state.get_all_archived_merges(since).await? {
+        sources.entry(entry.source.clone()).or_default().push(entry);
+    }
+    if sources.is_empty() {
+        return Ok(format!(
+            "No merges archived in the last {} days.",
+            window.num_days()
+        ));
+    }
+
+    let mut lines = vec![format!(
+        "📊 <b>Merges in the last {} days</b>",
+        window.num_days()
+    )];
+    let mut total = 0;
+    for (source, entries) in &sources {
+        let mut durations: Vec<i64> = entries.iter().map(|e| e.merged_at - e.opened_at).collect();
+        durations.sort_unstable();
+        let median = durations[durations.len() / 2];
+        let rounds =
+            entries.iter().map(|e| e.review_rounds).sum::<i64>() as f64 / entries.len() as f64;
+        let name = if source.is_empty() {
+            "this instance"
+        } else {
+            source.as_str()
+        };
+        lines.push(format!(
+            "• <b>{}</b>: {} merged, median {} to merge, {:.1} review rounds",
+            teloxide::utils::html::escape(name),
+            entries.len(),
+            bulk::format_duration(median),
+            rounds
+        ));
+        total += entries.len();
+    }
+    if sources.len() > 1 {
+        lines.push(format!("Total: {} merged", total));
+    }
+    Ok(lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursors_survive_the_query_string() {
+        let cursor = ArchiveCursor {
+            merged_at: 1_700_000_000,
+            chat_id: -1001234567890,
+            repo_owner: "near".to_string(),
+            repo_name: "nearcore.rs".to_string(),
+            pr_number: 42,
+        };
+        assert_eq!(
+            parse_query(&feed_query(0, Some(&cursor), 1_800_000_000)),
+            (1_700_000_000, Some(cursor), Some(1_800_000_000))
+        );
+    }
+
+    #[test]
+    fn a_bare_since_starts_a_page_without_a_cursor() {
+        assert_eq!(
+            parse_query(&feed_query(1_700_000_000, None, 1_800_000_000)),
+            (1_700_000_000, None, Some(1_800_000_000))
+        );
+        assert_eq!(parse_query("since=5&chat=1&pr=2"), (5, None, None));
+        assert_eq!(parse_query(""), (0, None, None));
+    }
+
+    #[test]
+    fn only_recently_signed_requests_are_served() {
+        let now = 1_800_000_000;
+        assert!(is_fresh(Some(now), now));
+        assert!(is_fresh(Some(now - MAX_REQUEST_AGE_SECS), now));
+        assert!(is_fresh(Some(now + 30), now));
+        assert!(!is_fresh(Some(now - MAX_REQUEST_AGE_SECS - 1), now));
+        assert!(!is_fresh(Some(now + MAX_REQUEST_AGE_SECS + 1), now));
+        assert!(!is_fresh(None, now));
+    }
+}
@@ -0,0 +1,249 @@
+use crate::lifecycle::PrStatus;
+use crate::state::PrData;
+use anyhow::{anyhow, bail, Result};
+use std::fmt;
+
+/// A PR query shared by `/list`, `/find` and `DIGEST_FILTER`, e.g.
+/// `author:me status:changes_requested,in_review -label:wip parser`.
+///
+/// Terms are ANDed, comma-separated values of one term are ORed, a leading `-` negates a
+/// term and bare words must all appear in the title.
+#[derive(Clone, Debug)]
+pub struct Query {
+    terms: Vec<Term>,
+    text: String,
+}
+
+#[derive(Clone, Debug)]
+struct Term {
+    field: Field,
+    values: Vec<String>,
+    negated: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Field {
+    Author,
+    Reviewer, // anyone with a mark on the card
+    Status,
+    Repo,
+    Label,
+    Title,
+}
+
+/// Who `me` stands for: the asking user's name on cards and their linked GitHub login.
+#[derive(Default)]
+pub struct Viewer {
+    pub names: Vec<String>,
+}
+
+impl Field {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "author" => Some(Self::Author),
+            "reviewer" => Some(Self::Reviewer),
+            "status" => Some(Self::Status),
+            "repo" => Some(Self::Repo),
+            "label" => Some(Self::Label),
+            _ => None,
+        }
+    }
+}
+
+impl Term {
+    fn parse(word: &str) -> Result<Self> {
+        let (negated, word) = match word.strip_prefix('-') {
+            Some(rest) if !rest.is_empty() => (true, rest),
+            _ => (false, word),
+        };
+        let Some((key, raw)) = word.split_once(':') else {
+            return Ok(Self {
+                field: Field::Title,
+                values: vec![word.to_lowercase()],
+                negated,
+            });
+        };
+        let field = Field::parse(&key.to_lowercase()).ok_or_else(|| {
+            anyhow!(
+                "unknown filter \"{}\", try author, reviewer, status, repo or label",
+                key
+            )
+        })?;
+        let values: Vec<String> = raw
+            .split(',')
+            .map(|v| v.trim_start_matches('@').to_lowercase())
+            .filter(|v| !v.is_empty())
+            .collect();
+        if values.is_empty() {
+            bail!("\"{}:\" needs a value", key);
+        }
+        if field == Field::Status {
+            for v in &values {
+                if v != "open" && PrStatus::parse(v).as_str() != v {
+                    bail!("unknown status \"{}\"", v);
+                }
+            }
+        }
+        Ok(Self {
+            field,
+            values,
+            negated,
+        })
+    }
+
+    fn holds(&self, data: &PrData, viewer: &Viewer) -> bool {
+        let is = |name: &str, value: &str| {
+            if value == "me" {
+                viewer.names.iter().any(|n| n.eq_ignore_ascii_case(name))
+            } else {
+                name.eq_ignore_ascii_case(value)
+            }
+        };
+        let any = self.values.iter().any(|value| match self.field {
+            Field::Author => is(&data.author, value),
+            Field::Reviewer => data
+                .reviewers
+                .iter()
+                .chain(&data.approvals)
+                .chain(&data.changes_requested)
+                .chain(&data.comments)
+                .any(|name| is(name, value)),
+            Field::Status if value == "open" => !data.status.is_terminal(),
+            Field::Status => data.status.as_str() == value,
+            // Either owner/repo or just the repository's name
+            Field::Repo => {
+                data.repo.eq_ignore_ascii_case(value)
+                    || data
+                        .repo
+                        .split_once('/')
+                        .is_some_and(|(_, name)| name.eq_ignore_ascii_case(value))
+            }
+            Field::Label => data.labels.iter().any(|l| l.eq_ignore_ascii_case(value)),
+            Field::Title => data.title.to_lowercase().contains(value.as_str()),
+        });
+        any != self.negated
+    }
+}
+
+impl Query {
+    pub fn parse(text: &str) -> Result<Self> {
+        let terms = text
+            .split_whitespace()
+            .map(Term::parse)
+            .collect::<Result<Vec<_>>>()?;
+        if terms.is_empty() {
+            bail!("the filter is empty");
+        }
+        Ok(Self {
+            terms,
+            text: text.split_whitespace().collect::<Vec<_>>().join(" "),
+        })
+    }
+
+    /// Whether the query refers to the asking user, so only makes sense for a person.
+    pub fn uses_me(&self) -> bool {
+        self.terms
+            .iter()
+            .any(|t| t.field != Field::Title && t.values.iter().any(|v| v == "me"))
+    }
+
+    pub fn matches(&self, data: &PrData, viewer: &Viewer) -> bool {
+        self.terms.iter().all(|t| t.holds(data, viewer))
+    }
+}
+
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data() -> PrData {
+        PrData {
+            title: "Faster Parser for configs".to_string(),
+            author: "alice".to_string(),
+            repo: "near/core".to_string(),
+            reviewers: vec!["bob".to_string()],
+            changes_requested: vec!["carol".to_string()],
+            status: PrStatus::ChangesRequested,
+            labels: vec!["WIP".to_string(), "parser".to_string()],
+            ..Default::default()
+        }
+    }
+
+    fn matches(query: &str, viewer: &[&str]) -> bool {
+        let viewer = Viewer {
+            names: viewer.iter().map(|n| n.to_string()).collect(),
+        };
+        Query::parse(query).unwrap().matches(&data(), &viewer)
+    }
+
+    fn error(query: &str) -> String {
+        Query::parse(query).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn terms_are_anded() {
+        assert!(matches("author:alice repo:core", &[]));
+        assert!(!matches("author:alice repo:nearcore", &[]));
+        assert!(matches("repo:NEAR/core reviewer:@carol", &[]));
+    }
+
+    #[test]
+    fn comma_lists_are_ored() {
+        assert!(matches("status:in_review,changes_requested", &[]));
+        assert!(!matches("status:in_review,approved", &[]));
+        assert!(matches("author:bob,alice", &[]));
+        assert!(matches("status:open", &[]));
+        assert!(matches("label:docs,,wip", &[]));
+    }
+
+    #[test]
+    fn negation_inverts_a_term() {
+        assert!(!matches("-label:wip", &[]));
+        assert!(matches("-label:docs", &[]));
+        assert!(!matches("-author:bob,alice", &[]));
+        assert!(!matches("-faster", &[]));
+        assert!(matches("-slower", &[]));
+    }
+
+    #[test]
+    fn me_stands_for_the_viewer() {
+        let query = Query::parse("author:me").unwrap();
+        assert!(query.uses_me());
+        assert!(!Query::parse("me").unwrap().uses_me());
+        assert!(matches("author:me", &["Alice"]));
+        assert!(!matches("author:me", &["bob"]));
+        assert!(!matches("author:me", &[]));
+        assert!(matches("reviewer:me", &["bob"]));
+    }
+
+    #[test]
+    fn free_text_must_all_appear_in_the_title() {
+        assert!(matches("parser CONFIGS", &[]));
+        assert!(!matches("parser lexer", &[]));
+        assert!(matches("author:alice faster", &[]));
+        // A lone dash is a word, not a negation
+        assert!(!matches("-", &[]));
+    }
+
+    #[test]
+    fn malformed_queries_are_rejected() {
+        assert!(error("colour:red").contains("unknown filter \"colour\""));
+        assert!(error("Author:alice colour:red").contains("unknown filter"));
+        assert!(error("label:").contains("\"label:\" needs a value"));
+        assert!(error("-author:,@").contains("needs a value"));
+        assert!(error("status:pending").contains("unknown status \"pending\""));
+        assert!(error("   ").contains("empty"));
+    }
+
+    #[test]
+    fn the_query_displays_normalized() {
+        let query = Query::parse("  author:me   -label:wip  parser ").unwrap();
+        assert_eq!(query.to_string(), "author:me -label:wip parser");
+    }
+}
@@ -1,40 +1,583 @@
-use anyhow::Result;
+use crate::config::{GithubAuth, PrFilter};
+use crate::db::{CachedResponse, Db};
+use crate::metrics;
+use anyhow::{anyhow, Result};
+use axum::http::header::{HeaderMap, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use axum::http::StatusCode;
 use chrono::{DateTime, Utc};
-use octocrab::{models::pulls::PullRequest, Octocrab};
-use std::collections::HashSet;
+use octocrab::models::issues::Issue;
+use octocrab::models::pulls::{MergeableState, PullRequest, Review, ReviewAction, ReviewState};
+use octocrab::models::repos::{Content, Release, RepoCommit};
+use octocrab::models::{AppId, InstallationId, IssueState, Repository};
+use octocrab::Octocrab;
+use serde::de::DeserializeOwned;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::future::Future;
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{error, info, instrument, warn};
+
+/// Branch protection rarely changes, so it's looked up at most this often per branch.
+const PROTECTION_CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// (owner, repo, branch) -> (looked up at, requires conversation resolution)
+type ProtectionCache = HashMap<(String, String, String), (Instant, bool)>;
+/// (org, team) -> (looked up at, member logins)
+type TeamCache = HashMap<(String, String), (Instant, Vec<String>)>;
+
+/// PRs fetched per GraphQL query by `get_pr_snapshots`. Each brings up to 100 labels,
+/// review requests and reviews, which keeps a query well under GitHub's node limit.
+const SNAPSHOT_BATCH: usize = 25;
+
+/// (owner, repo, number) of a PR.
+pub type PrKey = (String, String, u64);
+
+/// What the sync needs to know about a PR, from one GraphQL batch or from REST.
+#[derive(Clone, Debug)]
+pub struct PrSnapshot {
+    pub closed: bool, // merged PRs are closed too
+    pub draft: bool,
+    pub title: String,
+    pub body: String, // the description, for ticket ids
+    pub labels: Vec<String>,
+    pub head_sha: String,
+    pub base_ref: String,
+    pub requested_reviewers: Vec<String>, // logins of requested users, then requested teams as org/team
+    pub mergeable: Option<bool>,          // unknown while GitHub is still computing it
+    pub mergeable_state: Option<MergeableState>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub merged_at: Option<DateTime<Utc>>,
+    pub merged_by: Option<String>, // login of whoever merged it
+    /// Human reviews in the order they were submitted; `None` when they couldn't be fetched
+    pub reviews: Option<Vec<SubmittedReview>>,
+}
+
+#[derive(Clone, Debug)]
+pub struct SubmittedReview {
+    pub id: u64,
+    pub login: String,
+    pub state: ReviewState,
+    pub commit_id: Option<String>,
+    pub with_comments: bool, // has a summary or inline comments
+}
+
+impl From<&PullRequest> for PrSnapshot {
+    fn from(pr: &PullRequest) -> Self {
+        Self {
+            closed: matches!(pr.state, Some(IssueState::Closed)),
+            draft: pr.draft.unwrap_or(false),
+            title: pr.title.clone().unwrap_or_default(),
+            body: pr.body.clone().unwrap_or_default(),
+            labels: labels(pr),
+            head_sha: pr.head.sha.clone(),
+            base_ref: pr.base.ref_field.clone(),
+            requested_reviewers: requested_reviewers(pr),
+            mergeable: pr.mergeable,
+            mergeable_state: pr.mergeable_state.clone(),
+            created_at: pr.created_at,
+            updated_at: pr.updated_at,
+            merged_at: pr.merged_at,
+            merged_by: pr.merged_by.as_ref().map(|u| u.login.clone()),
+            reviews: None,
+        }
+    }
+}
+
+/// An issue has no head, reviews or merge state; the snapshot carries what the cleanup of its
+/// card needs.
+impl From<&Issue> for PrSnapshot {
+    fn from(issue: &Issue) -> Self {
+        Self {
+            closed: issue.state == IssueState::Closed,
+            draft: false,
+            title: issue.title.clone(),
+            body: issue.body.clone().unwrap_or_default(),
+            labels: issue.labels.iter().map(|l| l.name.clone()).collect(),
+            head_sha: String::new(),
+            base_ref: String::new(),
+            requested_reviewers: vec![],
+            mergeable: None,
+            mergeable_state: None,
+            created_at: Some(issue.created_at),
+            updated_at: Some(issue.updated_at),
+            merged_at: None,
+            merged_by: None,
+            reviews: None,
+        }
+    }
+}
+
+impl SubmittedReview {
+    fn from_rest(review: Review) -> Option<Self> {
+        // Inline comments aren't listed with REST reviews, only the summary
+        Some(Self {
+            id: review.id.0,
+            login: review.user?.login,
+            state: review.state?,
+            commit_id: review.commit_id,
+            with_comments: review.body.is_some_and(|b| !b.trim().is_empty()),
+        })
+    }
+}
+
+/// The fields of `PrSnapshot`, for GraphQL.
+const SNAPSHOT_FRAGMENT: &str = "fragment Snapshot on PullRequest {
+    state isDraft title body createdAt updatedAt mergedAt mergeable mergeStateStatus
+    baseRefName headRefOid mergedBy { login }
+    labels(first: 100) { nodes { name } }
+    reviewRequests(first: 100) {
+        nodes { requestedReviewer { ... on User { login } ... on Team { combinedSlug } } }
+    }
+    reviews(first: 100) {
+        nodes { databaseId state body author { login __typename } commit { oid } comments { totalCount } }
+    }
+}";
+
+impl PrSnapshot {
+    fn from_graphql(pr: &serde_json::Value) -> Option<Self> {
+        let time = |field: &str| {
+            pr[field]
+                .as_str()
+                .and_then(|t| t.parse::<DateTime<Utc>>().ok())
+        };
+        let names = |connection: &serde_json::Value, field: &str| -> Vec<String> {
+            connection["nodes"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|node| node.pointer(field)?.as_str().map(str::to_string))
+                .collect()
+        };
+        let reviews = pr["reviews"]["nodes"]
+            .as_array()?
+            .iter()
+            .filter(|r| {
+                let author = &r["author"];
+                author["__typename"] != "Bot"
+                    && author["login"]
+                        .as_str()
+                        .is_some_and(|login| !login.ends_with("[bot]"))
+            })
+            .filter_map(|r| {
+                Some(SubmittedReview {
+                    id: r["databaseId"].as_u64()?,
+                    login: r["author"]["login"].as_str()?.to_string(),
+                    state: serde_json::from_value(r["state"].clone()).ok()?,
+                    commit_id: r["commit"]["oid"].as_str().map(str::to_string),
+                    with_comments: r["body"].as_str().is_some_and(|b| !b.trim().is_empty())
+                        || r["comments"]["totalCount"].as_u64().is_some_and(|n| n > 0),
+                })
+            })
+            .collect();
+        Some(Self {
+            closed: pr["state"].as_str()? != "OPEN",
+            draft: pr["isDraft"].as_bool().unwrap_or(false),
+            title: pr["title"].as_str()?.to_string(),
+            body: pr["body"].as_str().unwrap_or_default().to_string(),
+            labels: names(&pr["labels"], "/name"),
+            head_sha: pr["headRefOid"].as_str()?.to_string(),
+            base_ref: pr["baseRefName"].as_str()?.to_string(),
+            requested_reviewers: [
+                names(&pr["reviewRequests"], "/requestedReviewer/login"),
+                names(&pr["reviewRequests"], "/requestedReviewer/combinedSlug"),
+            ]
+            .concat(),
+            mergeable: match pr["mergeable"].as_str() {
+                Some("MERGEABLE") => Some(true),
+                Some("CONFLICTING") => Some(false),
+                _ => None,
+            },
+            mergeable_state: pr["mergeStateStatus"]
+                .as_str()
+                .and_then(|s| serde_json::from_value(s.to_lowercase().into()).ok()),
+            created_at: time("createdAt"),
+            updated_at: time("updatedAt"),
+            merged_at: time("mergedAt"),
+            merged_by: pr["mergedBy"]["login"].as_str().map(str::to_string),
+            reviews: Some(reviews),
+        })
+    }
+}
+
+/// Cached responses not stored again for this long are dropped; an active one costs a full
+/// request at most this often, while those of closed PRs don't linger.
+const RESPONSE_CACHE_TTL: chrono::Duration = chrono::Duration::days(7);
+
+/// Share of each rate limit kept for chat commands and webhooks: the background sync pauses
+/// once less than this is left.
+const RATE_LIMIT_RESERVE_DIVISOR: usize = 20; // 5%
+
+/// Installation tokens last an hour; the GraphQL client's is replaced a bit before that.
+const GRAPHQL_TOKEN_TTL: Duration = Duration::from_secs(50 * 60);
+
+/// GitHub Enterprise serves GraphQL at `/api/graphql`, beside the REST API's `/api/v3`
+/// rather than under it where octocrab would send it, so it needs a client of its own.
+enum EnterpriseGraphql {
+    Token(Octocrab),
+    App(Box<InstallationGraphql>),
+}
+
+/// Rebuilt with a fresh installation token of the app when the last one is about to expire.
+struct InstallationGraphql {
+    base_uri: String,
+    app: Octocrab,
+    installation: InstallationId,
+    client: tokio::sync::Mutex<Option<(Instant, Octocrab)>>,
+}
 
 #[derive(Clone)]
 pub struct GithubClient {
     client: Arc<Octocrab>,
     // simple in-memory cache of seen PR IDs to avoid duplicates if we poll frequently
     seen_prs: Arc<Mutex<HashSet<u64>>>,
+    conversation_resolution: Arc<Mutex<ProtectionCache>>,
+    teams: Arc<Mutex<TeamCache>>,
+    graphql: Option<Arc<EnterpriseGraphql>>,
+    /// Where responses are kept for conditional requests, see `get_revalidated`
+    cache: Option<Db>,
+    /// Rate limit resource (core, graphql or search) -> when it resets, while it's used up
+    exhausted: Arc<Mutex<HashMap<&'static str, DateTime<Utc>>>>,
+    /// REST API base of a GitHub Enterprise server, for clients acting as a user
+    api_url: Option<String>,
+}
+
+/// A GitHub call that wasn't made, or was rejected, because its rate limit is used up.
+#[derive(Debug)]
+pub struct RateLimited {
+    pub resource: &'static str,
+    pub resets_at: DateTime<Utc>,
+}
+
+impl fmt::Display for RateLimited {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "GitHub {} rate limit is used up until {} UTC",
+            self.resource,
+            self.resets_at.format("%H:%M")
+        )
+    }
+}
+
+impl std::error::Error for RateLimited {}
+
+/// The rate limit an endpoint counts against.
+fn rate_resource(endpoint: &str) -> &'static str {
+    match endpoint {
+        "graphql" => "graphql",
+        "search.issues" => "search",
+        _ => "core",
+    }
 }
 
 impl GithubClient {
-    pub fn new(token: String) -> Result<Self> {
-        let client = Octocrab::builder().personal_token(token).build()?;
+    /// `api_url` is the REST API base of a GitHub Enterprise server, api.github.com when `None`.
+    pub async fn new(auth: &GithubAuth, api_url: Option<&str>) -> Result<Self> {
+        let builder = || -> Result<_> {
+            Ok(match api_url {
+                Some(url) => Octocrab::builder().base_uri(url)?,
+                None => Octocrab::builder(),
+            })
+        };
+        let graphql_base = api_url
+            .and_then(|url| url.strip_suffix("/v3"))
+            .map(str::to_string);
+        let (client, graphql) = match auth {
+            GithubAuth::Token(token) => {
+                let client = builder()?.personal_token(token.clone()).build()?;
+                let graphql = match graphql_base {
+                    Some(base_uri) => Some(EnterpriseGraphql::Token(
+                        Octocrab::builder()
+                            .base_uri(base_uri)?
+                            .personal_token(token.clone())
+                            .build()?,
+                    )),
+                    None => None,
+                };
+                (client, graphql)
+            }
+            GithubAuth::App {
+                app_id,
+                private_key,
+                installation_id,
+            } => {
+                let key = jsonwebtoken::EncodingKey::from_rsa_pem(private_key.as_bytes())?;
+                let app = builder()?.app(AppId(*app_id), key).build()?;
+                let installation = match installation_id {
+                    Some(id) => InstallationId(*id),
+                    None => {
+                        metrics::github("apps.installations", app.apps().installations().send())
+                            .await?
+                            .items
+                            .first()
+                            .ok_or_else(|| anyhow!("The GitHub App isn't installed anywhere"))?
+                            .id
+                    }
+                };
+                info!(
+                    "Authenticating as installation {} of GitHub App {}",
+                    installation, app_id
+                );
+                // Octocrab mints an installation token on first use and renews it before it expires
+                let client = app.installation(installation)?;
+                let graphql = graphql_base.map(|base_uri| {
+                    EnterpriseGraphql::App(Box::new(InstallationGraphql {
+                        base_uri,
+                        app,
+                        installation,
+                        client: tokio::sync::Mutex::new(None),
+                    }))
+                });
+                (client, graphql)
+            }
+        };
+        if let Some(url) = api_url {
+            info!("Using the GitHub Enterprise API at {}", url);
+        }
         Ok(Self {
             client: Arc::new(client),
             seen_prs: Arc::new(Mutex::new(HashSet::new())),
+            conversation_resolution: Arc::new(Mutex::new(HashMap::new())),
+            teams: Arc::new(Mutex::new(HashMap::new())),
+            graphql: graphql.map(Arc::new),
+            cache: None,
+            exhausted: Arc::new(Mutex::new(HashMap::new())),
+            api_url: api_url.map(str::to_string),
         })
     }
 
+    /// A client acting as the user whose personal access token it is. Its calls count
+    /// against the user's rate limit, not the bot's.
+    fn user_client(&self, token: &str) -> Result<Octocrab> {
+        let builder = match &self.api_url {
+            Some(url) => Octocrab::builder().base_uri(url.as_str())?,
+            None => Octocrab::builder(),
+        };
+        Ok(builder.personal_token(token.to_string()).build()?)
+    }
+
+    /// Runs a GitHub call through `metrics::github`, unless its rate limit is known to be used
+    /// up; calls then fail with `RateLimited` without spending a request until it resets.
+    async fn call<T>(
+        &self,
+        endpoint: &'static str,
+        call: impl Future<Output = Result<T, octocrab::Error>>,
+    ) -> Result<T> {
+        let resource = rate_resource(endpoint);
+        if let Some(resets_at) = self.exhausted_until(resource) {
+            return Err(RateLimited {
+                resource,
+                resets_at,
+            }
+            .into());
+        }
+        match metrics::github(endpoint, call).await {
+            Ok(value) => Ok(value),
+            Err(e) if metrics::is_rate_limit(&e) => {
+                // The error doesn't say when the limit resets; asking doesn't cost a request.
+                // Secondary limits leave the budget alone and usually lift within a minute.
+                let resets_at = match self.get_rate_limit().await {
+                    Ok(rate) => {
+                        let rate = match resource {
+                            "graphql" => rate.resources.graphql.unwrap_or(rate.resources.core),
+                            "search" => rate.resources.search,
+                            _ => rate.resources.core,
+                        };
+                        (rate.remaining == 0)
+                            .then(|| DateTime::from_timestamp(rate.reset as i64, 0))
+                            .flatten()
+                    }
+                    Err(_) => None,
+                }
+                .unwrap_or_else(|| Utc::now() + chrono::Duration::minutes(1));
+                warn!(
+                    "GitHub {} rate limit is used up, pausing those calls until {}",
+                    resource, resets_at
+                );
+                self.exhausted.lock().unwrap().insert(resource, resets_at);
+                Err(RateLimited {
+                    resource,
+                    resets_at,
+                }
+                .into())
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn exhausted_until(&self, resource: &str) -> Option<DateTime<Utc>> {
+        let mut exhausted = self.exhausted.lock().unwrap();
+        match exhausted.get(resource) {
+            Some(resets_at) if *resets_at > Utc::now() => Some(*resets_at),
+            Some(_) => {
+                exhausted.remove(resource);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Notes a used-up limit from the `x-ratelimit-*` headers GitHub sends with every response.
+    fn note_rate_headers(&self, headers: &HeaderMap) {
+        let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+        let resource = match header("x-ratelimit-resource") {
+            Some("graphql") => "graphql",
+            Some("search") => "search",
+            _ => "core",
+        };
+        if let Some(remaining) = header("x-ratelimit-remaining").and_then(|v| v.parse().ok()) {
+            metrics::github_rate_remaining(resource, remaining);
+        }
+        if header("x-ratelimit-remaining") != Some("0") {
+            return;
+        }
+        if let Some(resets_at) = header("x-ratelimit-reset")
+            .and_then(|v| v.parse().ok())
+            .and_then(|t| DateTime::from_timestamp(t, 0))
+        {
+            self.exhausted.lock().unwrap().insert(resource, resets_at);
+        }
+    }
+
+    /// When the core or GraphQL limit is down to its reserve, the time the sync can resume.
+    /// Checking doesn't count against the limit.
+    pub async fn sync_paused_until(&self) -> Option<DateTime<Utc>> {
+        let rate = self.get_rate_limit().await.ok()?;
+        [Some(rate.resources.core), rate.resources.graphql]
+            .into_iter()
+            .flatten()
+            .filter(|r| r.remaining < r.limit / RATE_LIMIT_RESERVE_DIVISOR)
+            .filter_map(|r| DateTime::from_timestamp(r.reset as i64, 0))
+            .max()
+    }
+
+    /// Revalidates PR and PR list requests against responses stored in `db`.
+    pub fn with_response_cache(mut self, db: Db) -> Self {
+        self.cache = Some(db);
+        self
+    }
+
+    /// GETs `path`, sending the validators of its cached response. When GitHub answers 304
+    /// Not Modified, which doesn't count against the rate limit, the cached body is used.
+    async fn get_revalidated<T: DeserializeOwned>(
+        &self,
+        endpoint: &'static str,
+        path: &str,
+    ) -> Result<T> {
+        let cached = match &self.cache {
+            Some(db) => db.get_cached_response(path).await.unwrap_or_else(|e| {
+                error!("Failed to read the cached response of {}: {}", path, e);
+                None
+            }),
+            None => None,
+        };
+        let mut headers = HeaderMap::new();
+        if let Some(cached) = &cached {
+            if let Some(etag) = cached.etag.as_deref().and_then(|v| v.parse().ok()) {
+                headers.insert(IF_NONE_MATCH, etag);
+            }
+            if let Some(date) = cached.last_modified.as_deref().and_then(|v| v.parse().ok()) {
+                headers.insert(IF_MODIFIED_SINCE, date);
+            }
+        }
+
+        let response = self
+            .call(endpoint, async {
+                let response = self.client._get_with_headers(path, Some(headers)).await?;
+                self.note_rate_headers(response.headers());
+                if response.status() == StatusCode::NOT_MODIFIED {
+                    return Ok(None);
+                }
+                octocrab::map_github_error(response).await.map(Some)
+            })
+            .await?;
+        let response = match (response, cached) {
+            (Some(response), _) => response,
+            (None, Some(cached)) => return Ok(serde_json::from_str(&cached.body)?),
+            (None, None) => anyhow::bail!("GitHub answered 304 for {} without validators", path),
+        };
+
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string)
+        };
+        let etag = header(ETAG);
+        let last_modified = header(LAST_MODIFIED);
+        let body = self.client.body_to_string(response).await?;
+        let value = serde_json::from_str(&body)?;
+        if let (Some(db), true) = (&self.cache, etag.is_some() || last_modified.is_some()) {
+            let stored = CachedResponse {
+                etag,
+                last_modified,
+                body,
+            };
+            if let Err(e) = db.cache_response(path, &stored).await {
+                error!("Failed to cache the response of {}: {}", path, e);
+            }
+        }
+        Ok(value)
+    }
+
+    pub async fn prune_response_cache(&self) {
+        if let Some(db) = &self.cache {
+            let before = (Utc::now() - RESPONSE_CACHE_TTL).timestamp();
+            db.prune_response_cache(before).await.ok();
+        }
+    }
+
+    async fn graphql(&self, query: &serde_json::Value) -> Result<serde_json::Value> {
+        let client = match self.graphql.as_deref() {
+            None => self.client.as_ref().clone(),
+            Some(EnterpriseGraphql::Token(client)) => client.clone(),
+            Some(EnterpriseGraphql::App(installation)) => {
+                let mut cached = installation.client.lock().await;
+                match &*cached {
+                    Some((minted, client)) if minted.elapsed() < GRAPHQL_TOKEN_TTL => {
+                        client.clone()
+                    }
+                    _ => {
+                        let (_, token) = metrics::github(
+                            "apps.installation_token",
+                            installation
+                                .app
+                                .installation_and_token(installation.installation),
+                        )
+                        .await?;
+                        let client = Octocrab::builder()
+                            .base_uri(installation.base_uri.as_str())?
+                            .personal_token(token)
+                            .build()?;
+                        *cached = Some((Instant::now(), client.clone()));
+                        client
+                    }
+                }
+            }
+        };
+        self.call("graphql", client.graphql(query)).await
+    }
+
+    #[instrument(name = "poll_repo", skip_all, fields(repo = %format_args!("{}/{}", owner, repo)))]
     pub async fn get_new_prs(
         &self,
         owner: &str,
         repo: &str,
         since: DateTime<Utc>,
+        filter: &PrFilter,
     ) -> Result<Vec<PullRequest>> {
-        let issues = self
-            .client
-            .pulls(owner, repo)
-            .list()
-            .sort(octocrab::params::pulls::Sort::Created)
-            .direction(octocrab::params::Direction::Descending)
-            .state(octocrab::params::State::Open)
-            .per_page(10) // fetching few latest
-            .send()
+        // Only the few latest; unchanged lists come back as 304
+        let issues: Vec<PullRequest> = self
+            .get_revalidated(
+                "pulls.list",
+                &format!(
+                    "/repos/{}/{}/pulls?state=open&sort=created&direction=desc&per_page=10",
+                    owner, repo
+                ),
+            )
             .await?;
 
         let mut new_prs = Vec::new();
@@ -42,7 +585,7 @@ impl GithubClient {
 
         for pr in issues {
             if let Some(created_at) = pr.created_at {
-                if created_at > since && !seen.contains(&pr.id.0) {
+                if created_at > since && !seen.contains(&pr.id.0) && filter.allows(&pr) {
                     seen.insert(pr.id.0);
                     new_prs.push(pr);
                 }
@@ -52,29 +595,661 @@ impl GithubClient {
         Ok(new_prs)
     }
 
+    /// Open issues created after `since` with any of `labels`, or any issue when there are none.
+    /// GitHub lists PRs among issues; those are left out.
+    pub async fn get_new_issues(
+        &self,
+        owner: &str,
+        repo: &str,
+        since: DateTime<Utc>,
+        labels: &[String],
+    ) -> Result<Vec<Issue>> {
+        // Only the few latest; unchanged lists come back as 304
+        let issues: Vec<Issue> = self
+            .get_revalidated(
+                "issues.list",
+                &format!(
+                    "/repos/{}/{}/issues?state=open&sort=created&direction=desc&per_page=10",
+                    owner, repo
+                ),
+            )
+            .await?;
+        Ok(issues
+            .into_iter()
+            .filter(|issue| issue.pull_request.is_none() && issue.created_at > since)
+            .filter(|issue| {
+                labels.is_empty()
+                    || issue
+                        .labels
+                        .iter()
+                        .any(|l| labels.iter().any(|f| f.eq_ignore_ascii_case(&l.name)))
+            })
+            .collect())
+    }
+
+    /// Releases published after `since`, oldest first. Drafts aren't published yet.
+    pub async fn get_new_releases(
+        &self,
+        owner: &str,
+        repo: &str,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<Release>> {
+        // Only the few latest; unchanged lists come back as 304
+        let releases: Vec<Release> = self
+            .get_revalidated(
+                "repos.list_releases",
+                &format!("/repos/{}/{}/releases?per_page=10", owner, repo),
+            )
+            .await?;
+        let mut releases: Vec<Release> = releases
+            .into_iter()
+            .filter(|r| !r.draft && r.published_at.is_some_and(|at| at > since))
+            .collect();
+        releases.sort_by_key(|r| r.published_at);
+        Ok(releases)
+    }
+
+    pub async fn get_issue(&self, owner: &str, repo: &str, number: u64) -> Result<Issue> {
+        self.get_revalidated(
+            "issues.get",
+            &format!("/repos/{}/{}/issues/{}", owner, repo, number),
+        )
+        .await
+    }
+
     pub async fn get_pr_details(
         &self,
         owner: &str,
         repo: &str,
         pr_number: u64,
     ) -> Result<PullRequest> {
-        Ok(self.client.pulls(owner, repo).get(pr_number).await?)
+        self.get_revalidated(
+            "pulls.get",
+            &format!("/repos/{}/{}/pulls/{}", owner, repo, pr_number),
+        )
+        .await
     }
 
-    pub async fn get_pr_reviews(
+    pub async fn get_repo(&self, owner: &str, repo: &str) -> Result<octocrab::models::Repository> {
+        self.call("repos.get", self.client.repos(owner, repo).get())
+            .await
+    }
+
+    /// Paths of every file the PR changes.
+    pub async fn get_pr_files(
         &self,
         owner: &str,
         repo: &str,
         pr_number: u64,
-    ) -> Result<Vec<octocrab::models::pulls::Review>> {
+    ) -> Result<Vec<String>> {
+        let page = self
+            .call(
+                "pulls.files",
+                self.client.pulls(owner, repo).list_files(pr_number),
+            )
+            .await?;
+        let files = self
+            .call("pulls.files", self.client.all_pages(page))
+            .await?;
+        Ok(files.into_iter().map(|f| f.filename).collect())
+    }
+
+    /// The latest commit on the repository's default branch.
+    pub async fn get_default_branch_head(
+        &self,
+        owner: &str,
+        repo: &str,
+    ) -> Result<Option<RepoCommit>> {
+        // Unchanged heads come back as 304
+        let commits: Vec<RepoCommit> = self
+            .get_revalidated(
+                "repos.list_commits",
+                &format!("/repos/{}/{}/commits?per_page=1", owner, repo),
+            )
+            .await?;
+        Ok(commits.into_iter().next())
+    }
+
+    pub async fn get_check_runs(
+        &self,
+        owner: &str,
+        repo: &str,
+        sha: &str,
+    ) -> Result<Vec<octocrab::models::checks::CheckRun>> {
         Ok(self
-            .client
-            .pulls(owner, repo)
-            .list_reviews(pr_number)
-            .per_page(100)
-            .send()
+            .call(
+                "checks.runs",
+                self.client
+                    .checks(owner, repo)
+                    .list_check_runs_for_git_ref(octocrab::params::repos::Commitish(
+                        sha.to_string(),
+                    ))
+                    .per_page(100)
+                    .send(),
+            )
             .await?
-            .take_items()
+            .check_runs)
+    }
+
+    /// Commit statuses of `sha`, the pre-Checks API some CI services still report through.
+    pub async fn get_combined_status(
+        &self,
+        owner: &str,
+        repo: &str,
+        sha: &str,
+    ) -> Result<octocrab::models::CombinedStatus> {
+        self.call(
+            "commits.status",
+            self.client.get(
+                format!("/repos/{}/{}/commits/{}/status", owner, repo, sha),
+                None::<&()>,
+            ),
+        )
+        .await
+    }
+
+    /// When the most recently updated PR of the repository (open or closed) was last touched.
+    pub async fn get_last_pr_activity(
+        &self,
+        owner: &str,
+        repo: &str,
+    ) -> Result<Option<DateTime<Utc>>> {
+        Ok(self
+            .get_latest_pr(owner, repo)
+            .await?
+            .and_then(|pr| pr.updated_at.or(pr.created_at)))
+    }
+
+    /// The most recently updated PR of the repository, open or closed.
+    pub async fn get_latest_pr(&self, owner: &str, repo: &str) -> Result<Option<PullRequest>> {
+        let page = self
+            .call(
+                "pulls.list",
+                self.client
+                    .pulls(owner, repo)
+                    .list()
+                    .state(octocrab::params::State::All)
+                    .sort(octocrab::params::pulls::Sort::Updated)
+                    .direction(octocrab::params::Direction::Descending)
+                    .per_page(1)
+                    .send(),
+            )
+            .await?;
+        Ok(page.items.into_iter().next())
+    }
+
+    /// Every PR of the repository opened since `since`, open or closed, newest first.
+    pub async fn get_prs_opened_since(
+        &self,
+        owner: &str,
+        repo: &str,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<PullRequest>> {
+        let mut page = self
+            .call(
+                "pulls.list",
+                self.client
+                    .pulls(owner, repo)
+                    .list()
+                    .state(octocrab::params::State::All)
+                    .sort(octocrab::params::pulls::Sort::Created)
+                    .direction(octocrab::params::Direction::Descending)
+                    .per_page(100)
+                    .send(),
+            )
+            .await?;
+
+        let mut prs = Vec::new();
+        loop {
+            let next = page.next.clone();
+            for pr in page.take_items() {
+                if pr.created_at.is_some_and(|t| t < since) {
+                    return Ok(prs);
+                }
+                prs.push(pr);
+            }
+            match self
+                .call("pulls.list", self.client.get_page::<PullRequest>(&next))
+                .await?
+            {
+                Some(next_page) => page = next_page,
+                None => return Ok(prs),
+            }
+        }
+    }
+
+    /// The newest open PRs matching a GitHub search, as (owner, repo, number). `is:pr is:open`
+    /// is added unless the query already says what to search.
+    pub async fn search_prs(&self, query: &str) -> Result<Vec<PrKey>> {
+        let mut query = query.to_string();
+        if !query
+            .split_whitespace()
+            .any(|t| matches!(t, "is:pr" | "type:pr"))
+        {
+            query.push_str(" is:pr");
+        }
+        if !query.split_whitespace().any(|t| {
+            matches!(t, "is:open" | "is:closed" | "is:merged" | "is:unmerged")
+                || t.starts_with("state:")
+        }) {
+            query.push_str(" is:open");
+        }
+        let page = self
+            .call(
+                "search.issues",
+                self.client
+                    .search()
+                    .issues_and_pull_requests(&query)
+                    .sort("created")
+                    .order("desc")
+                    .per_page(30)
+                    .send(),
+            )
+            .await?;
+        Ok(page
+            .items
+            .into_iter()
+            .filter(|issue| issue.pull_request.is_some())
+            .filter_map(|issue| {
+                // https://api.github.com/repos/{owner}/{repo}
+                let mut path = issue.repository_url.path_segments()?.rev();
+                let repo = path.next()?.to_string();
+                let owner = path.next()?.to_string();
+                Some((owner, repo, issue.number))
+            })
+            .collect())
+    }
+
+    /// The canonical spelling of a GitHub login, or an error when there's no such user.
+    pub async fn get_login(&self, login: &str) -> Result<String> {
+        Ok(self
+            .call("users.get", self.client.users(login).profile())
+            .await?
+            .login)
+    }
+
+    /// The login a personal access token belongs to.
+    pub async fn get_token_login(&self, token: &str) -> Result<String> {
+        let client = self.user_client(token)?;
+        Ok(metrics::github("users.current", client.current().user())
+            .await?
+            .login)
+    }
+
+    /// Submits a review of the PR as the user whose token it is.
+    pub async fn submit_review(
+        &self,
+        token: &str,
+        owner: &str,
+        repo: &str,
+        pr_number: u64,
+        action: ReviewAction,
+        body: &str,
+    ) -> Result<()> {
+        let client = self.user_client(token)?;
+        let route = format!("/repos/{}/{}/pulls/{}/reviews", owner, repo, pr_number);
+        let review = serde_json::json!({ "event": action, "body": body });
+        let _: serde_json::Value =
+            metrics::github("pulls.reviews.create", client.post(route, Some(&review))).await?;
+        Ok(())
+    }
+
+    /// Whether merging into `branch` requires every review conversation to be resolved, by
+    /// classic branch protection or a ruleset. Settings the token can't read count as off.
+    pub async fn requires_conversation_resolution(
+        &self,
+        owner: &str,
+        repo: &str,
+        branch: &str,
+    ) -> bool {
+        let key = (owner.to_string(), repo.to_string(), branch.to_string());
+        if let Some((at, required)) = self.conversation_resolution.lock().unwrap().get(&key) {
+            if at.elapsed() < PROTECTION_CACHE_TTL {
+                return *required;
+            }
+        }
+
+        // Reading classic protection needs admin rights; rulesets are readable with pull access
+        let protection: Option<serde_json::Value> = self
+            .call(
+                "branches.protection",
+                self.client.get(
+                    format!("/repos/{}/{}/branches/{}/protection", owner, repo, branch),
+                    None::<&()>,
+                ),
+            )
+            .await
+            .ok();
+        let rules: Option<serde_json::Value> = self
+            .call(
+                "rules.branches",
+                self.client.get(
+                    format!("/repos/{}/{}/rules/branches/{}", owner, repo, branch),
+                    None::<&()>,
+                ),
+            )
+            .await
+            .ok();
+        let required = protection
+            .is_some_and(|p| p["required_conversation_resolution"]["enabled"] == true)
+            || rules
+                .and_then(|r| r.as_array().cloned())
+                .is_some_and(|rules| {
+                    rules.iter().any(|rule| {
+                        rule["type"] == "pull_request"
+                            && rule["parameters"]["required_review_thread_resolution"] == true
+                    })
+                });
+
+        self.conversation_resolution
+            .lock()
+            .unwrap()
+            .insert(key, (Instant::now(), required));
+        required
+    }
+
+    /// The organization's repositories that are neither archived nor disabled.
+    pub async fn get_org_repos(&self, org: &str) -> Result<Vec<Repository>> {
+        let page = self
+            .call(
+                "orgs.repos",
+                self.client.orgs(org).list_repos().per_page(100).send(),
+            )
+            .await?;
+        let repos = self.call("orgs.repos", self.client.all_pages(page)).await?;
+        Ok(repos
+            .into_iter()
+            .filter(|r| !r.archived.unwrap_or(false) && !r.disabled.unwrap_or(false))
+            .collect())
+    }
+
+    /// The repository's CODEOWNERS file, from the first of the places GitHub looks for it.
+    pub async fn get_codeowners(&self, owner: &str, repo: &str) -> Result<Option<String>> {
+        for path in [".github/CODEOWNERS", "CODEOWNERS", "docs/CODEOWNERS"] {
+            // Unchanged files come back as 304
+            let content: Result<Content> = self
+                .get_revalidated(
+                    "repos.contents",
+                    &format!("/repos/{}/{}/contents/{}", owner, repo, path),
+                )
+                .await;
+            match content {
+                Ok(content) => return Ok(content.decoded_content()),
+                Err(e) if is_not_found(&e) => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(None)
+    }
+
+    /// Logins of a team's members, looked up at most once per `PROTECTION_CACHE_TTL`. Falls
+    /// back to the last known members (or none) when the token can't read the team.
+    pub async fn get_team_members(&self, org: &str, team: &str) -> Vec<String> {
+        let key = (org.to_string(), team.to_string());
+        if let Some((at, members)) = self.teams.lock().unwrap().get(&key) {
+            if at.elapsed() < PROTECTION_CACHE_TTL {
+                return members.clone();
+            }
+        }
+
+        let members: Vec<String> = match self
+            .call(
+                "teams.members",
+                self.client.teams(org).members(team).per_page(100).send(),
+            )
+            .await
+        {
+            Ok(page) => page.items.into_iter().map(|m| m.login).collect(),
+            Err(e) => {
+                error!("Failed to list the members of {}/{}: {}", org, team, e);
+                return self
+                    .teams
+                    .lock()
+                    .unwrap()
+                    .get(&key)
+                    .map(|(_, members)| members.clone())
+                    .unwrap_or_default();
+            }
+        };
+        self.teams
+            .lock()
+            .unwrap()
+            .insert(key, (Instant::now(), members.clone()));
+        members
+    }
+
+    /// The PR and its reviews over REST, two calls.
+    pub async fn get_pr_snapshot(
+        &self,
+        owner: &str,
+        repo: &str,
+        pr_number: u64,
+    ) -> Result<PrSnapshot> {
+        let pr = self.get_pr_details(owner, repo, pr_number).await?;
+        let mut snapshot = PrSnapshot::from(&pr);
+        snapshot.reviews = self
+            .get_pr_reviews(owner, repo, pr_number)
+            .await
+            .ok()
+            .map(|reviews| {
+                reviews
+                    .into_iter()
+                    .filter_map(SubmittedReview::from_rest)
+                    .collect()
+            });
+        Ok(snapshot)
+    }
+
+    /// Snapshots of many PRs at once, `SNAPSHOT_BATCH` per GraphQL query. PRs missing from the
+    /// result, because their batch failed or GitHub didn't return them, are left to REST.
+    pub async fn get_pr_snapshots(&self, prs: &[PrKey]) -> HashMap<PrKey, PrSnapshot> {
+        let mut snapshots = HashMap::new();
+        for batch in prs.chunks(SNAPSHOT_BATCH) {
+            // One aliased repository field per repo, holding its PRs
+            let mut repos: Vec<(&str, &str, Vec<u64>)> = Vec::new();
+            for (owner, repo, number) in batch {
+                match repos.iter_mut().find(|(o, r, _)| o == owner && r == repo) {
+                    Some((_, _, numbers)) => numbers.push(*number),
+                    None => repos.push((owner, repo, vec![*number])),
+                }
+            }
+            let mut params = Vec::new();
+            let mut fields = String::new();
+            let mut variables = serde_json::Map::new();
+            for (i, (owner, repo, numbers)) in repos.iter().enumerate() {
+                params.push(format!("$o{i}: String!, $n{i}: String!"));
+                variables.insert(format!("o{i}"), (*owner).into());
+                variables.insert(format!("n{i}"), (*repo).into());
+                fields.push_str(&format!("r{i}: repository(owner: $o{i}, name: $n{i}) {{"));
+                for number in numbers {
+                    fields.push_str(&format!(
+                        " p{number}: pullRequest(number: {number}) {{ ...Snapshot }}"
+                    ));
+                }
+                fields.push_str(" }\n");
+            }
+            let query = serde_json::json!({
+                "query": format!(
+                    "query({}) {{\n{}}}\n{}",
+                    params.join(", "),
+                    fields,
+                    SNAPSHOT_FRAGMENT
+                ),
+                "variables": variables,
+            });
+
+            let response = match self.graphql(&query).await {
+                Ok(response) => response,
+                Err(e) => {
+                    error!("Failed to fetch a batch of {} PRs: {}", batch.len(), e);
+                    continue;
+                }
+            };
+            // Deleted repositories and PRs come back as errors next to the rest of the data
+            if let Some(errors) = response["errors"].as_array() {
+                info!(
+                    "GitHub left out {} PRs of a batch: {}",
+                    errors.len(),
+                    response["errors"]
+                );
+            }
+            for (i, (owner, repo, numbers)) in repos.iter().enumerate() {
+                for number in numbers {
+                    let pr = &response["data"][format!("r{i}")][format!("p{number}")];
+                    if let Some(snapshot) = PrSnapshot::from_graphql(pr) {
+                        snapshots.insert((owner.to_string(), repo.to_string(), *number), snapshot);
+                    }
+                }
+            }
+        }
+        snapshots
+    }
+
+    /// Number of unresolved review conversations on the PR.
+    pub async fn get_unresolved_threads(
+        &self,
+        owner: &str,
+        repo: &str,
+        pr_number: u64,
+    ) -> Result<u32> {
+        let query = serde_json::json!({
+            "query": "query($owner: String!, $name: String!, $number: Int!) {
+                repository(owner: $owner, name: $name) {
+                    pullRequest(number: $number) {
+                        reviewThreads(first: 100) { nodes { isResolved } }
+                    }
+                }
+            }",
+            "variables": { "owner": owner, "name": repo, "number": pr_number },
+        });
+        let response = self.graphql(&query).await?;
+        let threads = response["data"]["repository"]["pullRequest"]["reviewThreads"]["nodes"]
+            .as_array()
+            .ok_or_else(|| anyhow::anyhow!("unexpected GraphQL response: {}", response))?;
+        Ok(threads.iter().filter(|t| t["isResolved"] == false).count() as u32)
+    }
+
+    /// When someone last pushed a commit, reviewed or commented on the PR.
+    pub async fn get_last_activity(
+        &self,
+        owner: &str,
+        repo: &str,
+        pr_number: u64,
+    ) -> Result<Option<DateTime<Utc>>> {
+        let query = serde_json::json!({
+            "query": "query($owner: String!, $name: String!, $number: Int!) {
+                repository(owner: $owner, name: $name) {
+                    pullRequest(number: $number) {
+                        createdAt
+                        commits(last: 1) { nodes { commit { committedDate } } }
+                        reviews(last: 1) { nodes { submittedAt } }
+                        comments(last: 1) { nodes { createdAt } }
+                    }
+                }
+            }",
+            "variables": { "owner": owner, "name": repo, "number": pr_number },
+        });
+        let response = self.graphql(&query).await?;
+        let pr = &response["data"]["repository"]["pullRequest"];
+        if pr.is_null() {
+            anyhow::bail!("unexpected GraphQL response: {}", response);
+        }
+        Ok([
+            &pr["createdAt"],
+            &pr["commits"]["nodes"][0]["commit"]["committedDate"],
+            &pr["reviews"]["nodes"][0]["submittedAt"],
+            &pr["comments"]["nodes"][0]["createdAt"],
+        ]
+        .into_iter()
+        .filter_map(|t| t.as_str()?.parse::<DateTime<Utc>>().ok())
+        .max())
+    }
+
+    /// The login's role on the repository: admin, maintain, write, triage, read or none.
+    pub async fn get_permission(&self, owner: &str, repo: &str, login: &str) -> Result<String> {
+        let response: serde_json::Value = self
+            .call(
+                "collaborators.permission",
+                self.client.get(
+                    format!(
+                        "/repos/{}/{}/collaborators/{}/permission",
+                        owner, repo, login
+                    ),
+                    None::<&()>,
+                ),
+            )
+            .await?;
+        Ok(response["role_name"]
+            .as_str()
+            .or(response["permission"].as_str())
+            .unwrap_or("none")
+            .to_string())
+    }
+
+    /// Replaces the PR's labels, returning the labels it has afterwards.
+    pub async fn set_labels(
+        &self,
+        owner: &str,
+        repo: &str,
+        pr_number: u64,
+        labels: &[String],
+    ) -> Result<Vec<String>> {
+        Ok(self
+            .call(
+                "issues.labels",
+                self.client
+                    .issues(owner, repo)
+                    .replace_all_labels(pr_number, labels),
+            )
+            .await?
+            .into_iter()
+            .map(|l| l.name)
+            .collect())
+    }
+
+    /// Comments on a PR or issue as the bot.
+    pub async fn post_comment(
+        &self,
+        owner: &str,
+        repo: &str,
+        number: u64,
+        body: &str,
+    ) -> Result<()> {
+        self.call(
+            "issues.comment",
+            self.client.issues(owner, repo).create_comment(number, body),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Current rate limit usage of the bot's token. Checking it doesn't count against the limit.
+    pub async fn get_rate_limit(&self) -> Result<octocrab::models::RateLimit> {
+        let rate = metrics::github("rate_limit", self.client.ratelimit().get()).await?;
+        metrics::github_rate_remaining("core", rate.resources.core.remaining as i64);
+        metrics::github_rate_remaining("search", rate.resources.search.remaining as i64);
+        if let Some(graphql) = &rate.resources.graphql {
+            metrics::github_rate_remaining("graphql", graphql.remaining as i64);
+        }
+        Ok(rate)
+    }
+
+    pub async fn get_pr_reviews(
+        &self,
+        owner: &str,
+        repo: &str,
+        pr_number: u64,
+    ) -> Result<Vec<octocrab::models::pulls::Review>> {
+        let reviews: Vec<Review> = self
+            .get_revalidated(
+                "pulls.reviews",
+                &format!(
+                    "/repos/{}/{}/pulls/{}/reviews?per_page=100",
+                    owner, repo, pr_number
+                ),
+            )
+            .await?;
+        Ok(reviews
             .into_iter()
             .filter(|review| {
                 review.user.is_some()
@@ -84,3 +1259,58 @@ impl GithubClient {
             .collect())
     }
 }
+
+/// Names of the PR's labels.
+pub fn labels(pr: &PullRequest) -> Vec<String> {
+    pr.labels.iter().flatten().map(|l| l.name.clone()).collect()
+}
+
+/// Logins of the users asked to review the PR, then the teams asked as org/team.
+pub fn requested_reviewers(pr: &PullRequest) -> Vec<String> {
+    // Only teams of the repository's organization can be asked
+    let org = pr
+        .base
+        .repo
+        .as_ref()
+        .and_then(|r| r.owner.as_ref())
+        .map(|o| o.login.clone())
+        .unwrap_or_default();
+    let users = pr
+        .requested_reviewers
+        .iter()
+        .flatten()
+        .map(|u| u.login.clone());
+    let teams = pr
+        .requested_teams
+        .iter()
+        .flatten()
+        .map(|t| format!("{}/{}", org, t.slug));
+    users.chain(teams).collect()
+}
+
+/// Whether GitHub answered 404 Not Found.
+fn is_not_found(e: &anyhow::Error) -> bool {
+    matches!(
+        e.downcast_ref::<octocrab::Error>(),
+        Some(octocrab::Error::GitHub { source, .. }) if source.status_code.as_u16() == 404
+    )
+}
+
+/// Short, user-facing reason for a failed GitHub call.
+pub fn describe_error(e: &anyhow::Error) -> String {
+    if let Some(limited) = e.downcast_ref::<RateLimited>() {
+        return format!(
+            "the bot used up its GitHub {} rate limit, try again after {} UTC",
+            limited.resource,
+            limited.resets_at.format("%H:%M")
+        );
+    }
+    match e.downcast_ref::<octocrab::Error>() {
+        Some(octocrab::Error::GitHub { source, .. }) => match source.status_code.as_u16() {
+            404 => "not found on GitHub (or the bot's token can't see it)".to_string(),
+            401 | 403 => "GitHub denied access with the bot's token".to_string(),
+            _ => format!("GitHub error: {}", source.message),
+        },
+        _ => "couldn't reach GitHub, try again later".to_string(),
+    }
+}
@@ -0,0 +1,560 @@
+use crate::config::ClosedCards;
+use crate::db::{BurstPr, GroupItem, PrGroup};
+use crate::github::GithubClient;
+use crate::handlers::{self, no_link_preview};
+use crate::lifecycle::PrStatus;
+use crate::metrics;
+use crate::monitor::{ClosingCard, Monitor};
+use crate::sender::Retried;
+use crate::state::StateManager;
+use chrono::Utc;
+use octocrab::models::pulls::{PullRequest, ReviewState};
+use std::collections::HashMap;
+use teloxide::prelude::*;
+use teloxide::types::{MessageId, ParseMode, ThreadId};
+use tracing::{error, info};
+
+/// A freshly discovered PR: (owner, repo, pr)
+pub type NewPr = (String, String, PullRequest);
+
+const FLUSH_INTERVAL: tokio::time::Duration = tokio::time::Duration::from_secs(20);
+
+/// With `PR_GROUP_HOLD_MINUTES`, announces the held PRs: an author's held PRs become one group
+/// card once there are more than `PR_GROUP_THRESHOLD` of them, and a PR still on its own when
+/// its hold runs out gets its own card.
+pub async fn run(monitor: Monitor) {
+    loop {
+        flush(&monitor).await;
+        tokio::time::sleep(FLUSH_INTERVAL).await;
+    }
+}
+
+/// Holds new PRs back for more by their author, see `run`. The hold counts from when the PR was
+/// opened, so a burst split across polls or webhook deliveries still ends up on one card.
+pub async fn hold(monitor: &Monitor, chat_id: i64, author: &str, prs: &[NewPr]) {
+    for (owner, repo, pr) in prs {
+        let held = BurstPr {
+            chat_id,
+            author: author.to_string(),
+            repo_owner: owner.clone(),
+            repo_name: repo.clone(),
+            pr_number: pr.number as i64,
+            opened_at: pr.created_at.unwrap_or_else(Utc::now).timestamp(),
+        };
+        info!(
+            "Holding {}/{}#{} in {} for more PRs by {}",
+            owner, repo, pr.number, chat_id, author
+        );
+        if let Err(e) = monitor.state.hold_burst_pr(&held).await {
+            error!("Failed to hold {}/{}#{}: {}", owner, repo, pr.number, e);
+            // Better an own card than none
+            let sensitive = monitor
+                .config()
+                .security
+                .is_sensitive(&monitor.github, owner, repo, pr)
+                .await;
+            monitor
+                .announce_pr(chat_id, owner, repo, pr.clone(), sensitive)
+                .await;
+        }
+    }
+}
+
+async fn flush(monitor: &Monitor) {
+    let held = match monitor.state.get_burst_prs().await {
+        Ok(held) => held,
+        Err(e) => {
+            error!("Failed to load held PRs: {}", e);
+            return;
+        }
+    };
+    let mut by_author: Vec<((i64, String), Vec<BurstPr>)> = Vec::new();
+    for pr in held {
+        let key = (pr.chat_id, pr.author.clone());
+        match by_author.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, list)) => list.push(pr),
+            None => by_author.push((key, vec![pr])),
+        }
+    }
+
+    let config = monitor.config();
+    let now = Utc::now().timestamp();
+    let hold = config.group_hold_minutes * 60;
+    for ((chat_id, author), held) in by_author {
+        // Everything held was opened within the hold of now, so it all counts as one burst
+        let burst = config.group_threshold > 0 && held.len() > config.group_threshold;
+        let group_since = now - config.group_window_minutes * 60;
+        let existing = monitor
+            .state
+            .find_recent_group(chat_id, &author, group_since)
+            .await
+            .unwrap_or(None);
+        if burst || existing.is_some() {
+            let prs = release(monitor, &held).await;
+            if !prs.is_empty() {
+                announce_group(monitor, chat_id, &author, prs, existing).await;
+            }
+            continue;
+        }
+
+        let due: Vec<BurstPr> = held
+            .into_iter()
+            .filter(|pr| pr.opened_at + hold <= now)
+            .collect();
+        for (owner, repo, pr) in release(monitor, &due).await {
+            let sensitive = config
+                .security
+                .is_sensitive(&monitor.github, &owner, &repo, &pr)
+                .await;
+            monitor
+                .announce_pr(chat_id, &owner, &repo, pr, sensitive)
+                .await;
+        }
+    }
+}
+
+/// Stops holding the PRs, returning those still open. Closed ones are dropped silently.
+async fn release(monitor: &Monitor, held: &[BurstPr]) -> Vec<NewPr> {
+    let mut prs = Vec::new();
+    for burst_pr in held {
+        if !monitor
+            .state
+            .release_burst_pr(burst_pr)
+            .await
+            .unwrap_or(false)
+        {
+            continue;
+        }
+        let (owner, repo) = (&burst_pr.repo_owner, &burst_pr.repo_name);
+        match monitor
+            .github
+            .get_pr_details(owner, repo, burst_pr.pr_number as u64)
+            .await
+        {
+            Ok(pr) if pr.closed_at.is_none() && pr.merged_at.is_none() => {
+                prs.push((owner.clone(), repo.clone(), pr))
+            }
+            Ok(_) => info!(
+                "Dropping held {}/{}#{}, it was closed meanwhile",
+                owner, repo, burst_pr.pr_number
+            ),
+            Err(e) => {
+                error!(
+                    "Failed to fetch held {}/{}#{}: {}",
+                    owner, repo, burst_pr.pr_number, e
+                );
+                // Held again, for the next flush
+                monitor.state.hold_burst_pr(burst_pr).await.ok();
+            }
+        }
+    }
+    prs
+}
+
+/// Buckets new PRs by author, keeping the order in which authors first appear.
+pub fn bucket_by_author(prs: Vec<NewPr>) -> Vec<(String, Vec<NewPr>)> {
+    let mut buckets: Vec<(String, Vec<NewPr>)> = Vec::new();
+    for (owner, repo, pr) in prs {
+        let author = pr
+            .user
+            .as_ref()
+            .map(|u| u.login.clone())
+            .unwrap_or("unknown".to_string());
+        match buckets.iter_mut().find(|(a, _)| a == &author) {
+            Some((_, list)) => list.push((owner, repo, pr)),
+            None => buckets.push((author, vec![(owner, repo, pr)])),
+        }
+    }
+    buckets
+}
+
+fn is_terminal(status: &str) -> bool {
+    PrStatus::parse(status).is_terminal()
+}
+
+/// Computes the lifecycle status of a grouped PR from its GitHub state and latest reviews.
+async fn item_status(github: &GithubClient, owner: &str, repo: &str, pr: &PullRequest) -> String {
+    let status = PrStatus::from_github(
+        pr.merged_at.is_some(),
+        matches!(pr.state, Some(octocrab::models::IssueState::Closed)),
+        pr.draft.unwrap_or(false),
+    );
+    if status != PrStatus::AwaitingReview {
+        return status.as_str().to_string();
+    }
+
+    // Latest review per user wins
+    let mut user_state: HashMap<String, ReviewState> = HashMap::new();
+    if let Ok(reviews) = github.get_pr_reviews(owner, repo, pr.number).await {
+        for review in reviews {
+            if let (Some(user), Some(state)) = (review.user, review.state) {
+                user_state.insert(user.login, state);
+            }
+        }
+    }
+
+    let status = if user_state
+        .values()
+        .any(|s| matches!(s, ReviewState::ChangesRequested))
+    {
+        PrStatus::ChangesRequested
+    } else if user_state
+        .values()
+        .any(|s| matches!(s, ReviewState::Approved))
+    {
+        PrStatus::Approved
+    } else {
+        PrStatus::AwaitingReview
+    };
+    status.as_str().to_string()
+}
+
+pub fn generate_group_text(group: &PrGroup, items: &[GroupItem]) -> String {
+    let mut text = format!("<b>📦 {} PRs by {}</b>\n\n", items.len(), group.author);
+    if items
+        .iter()
+        .any(|i| i.security != 0 && !is_terminal(&i.status))
+    {
+        text.push_str(handlers::SECURITY_NOTICE);
+    }
+
+    for item in items {
+        let badge = PrStatus::parse(&item.status).badge();
+        let line = item_line(item);
+        if is_terminal(&item.status) {
+            text.push_str(&format!("{} <s>{}</s>\n", badge, line));
+        } else {
+            text.push_str(&format!("{} {}\n", badge, line));
+        }
+    }
+
+    text.push_str("\nReply /split to break this into individual cards.");
+    text
+}
+
+/// The linked PR and its title, after the security badge where it applies.
+fn item_line(item: &GroupItem) -> String {
+    format!(
+        "{}<a href=\"{}\">{}/{}#{}</a> {}",
+        if item.security != 0 { "🔒 " } else { "" },
+        item.pr_url,
+        item.repo_owner,
+        item.repo_name,
+        item.pr_number,
+        item.title
+    )
+}
+
+/// What an archived group card is edited into once all its PRs closed.
+fn closed_group_summary(group: &PrGroup, items: &[GroupItem]) -> String {
+    let mut text = format!(
+        "📦 <b>{} PRs by {}</b>, all merged or closed",
+        items.len(),
+        group.author
+    );
+    for item in items {
+        text.push_str(&format!(
+            "\n{} {}",
+            PrStatus::parse(&item.status).badge(),
+            item_line(item)
+        ));
+    }
+    text
+}
+
+/// Posts a multi-PR card for `prs`, or appends them to `existing` if the author
+/// already has a group card open within the grouping window.
+pub async fn announce_group(
+    monitor: &Monitor,
+    chat_id: i64,
+    author: &str,
+    prs: Vec<NewPr>,
+    existing: Option<PrGroup>,
+) {
+    let (bot, github, state) = (&monitor.bot, &monitor.github, &monitor.state);
+    let mut new_items = Vec::new();
+    for (owner, repo, pr) in &prs {
+        let sensitive = monitor
+            .config()
+            .security
+            .is_sensitive(github, owner, repo, pr)
+            .await;
+        new_items.push(GroupItem {
+            pr_url: pr
+                .html_url
+                .as_ref()
+                .map(|u| u.to_string())
+                .unwrap_or_default(),
+            title: pr.title.clone().unwrap_or_default(),
+            repo_owner: owner.clone(),
+            repo_name: repo.clone(),
+            pr_number: pr.number as i64,
+            status: item_status(github, owner, repo, pr).await,
+            security: sensitive as i64,
+        });
+    }
+
+    match existing {
+        Some(group) => {
+            let mut items = state
+                .get_group(&group.message_id, chat_id)
+                .await
+                .ok()
+                .flatten()
+                .map(|(_, items)| items)
+                .unwrap_or_default();
+            items.extend(new_items.iter().cloned());
+
+            info!(
+                "Adding {} PR(s) by {} to existing group card {}",
+                new_items.len(),
+                author,
+                group.message_id
+            );
+
+            let text = generate_group_text(&group, &items);
+            if let Err(e) = bot
+                .edit_message_text(
+                    ChatId(chat_id),
+                    MessageId(group.message_id.parse().unwrap_or(0)),
+                    text,
+                )
+                .parse_mode(ParseMode::Html)
+                .link_preview_options(no_link_preview())
+                .retried()
+                .await
+            {
+                error!("Failed to update group card: {}", e);
+            } else {
+                metrics::prs_announced(new_items.len());
+            }
+            state.audit(format!(
+                "edited group card of {} in {}: {} new PR(s)",
+                author,
+                chat_id,
+                new_items.len()
+            ));
+
+            if let Err(e) = state.save_group(&group, &new_items).await {
+                error!("Failed to save group items: {}", e);
+            }
+        }
+        None => {
+            let mut group = PrGroup {
+                message_id: String::new(),
+                chat_id,
+                author: author.to_string(),
+                created_at: Utc::now().timestamp(),
+            };
+
+            info!("Grouping {} new PRs by {} into one card", prs.len(), author);
+
+            let text = generate_group_text(&group, &new_items);
+            let mut request = bot
+                .send_message(ChatId(chat_id), text)
+                .parse_mode(ParseMode::Html)
+                .link_preview_options(no_link_preview());
+            // A group spanning repositories goes to the topic of its first PR's repository
+            if let Some(first) = new_items.first() {
+                if let Ok(Some(thread_id)) = state
+                    .get_repository_topic(chat_id, &first.repo_owner, &first.repo_name)
+                    .await
+                {
+                    request = request.message_thread_id(ThreadId(MessageId(thread_id)));
+                }
+            }
+            match request.retried().await {
+                Ok(sent_msg) => {
+                    metrics::prs_announced(new_items.len());
+                    group.message_id = sent_msg.id.0.to_string();
+                    state.audit(format!(
+                        "created group card of {} in {}: {} new PRs",
+                        author,
+                        chat_id,
+                        new_items.len()
+                    ));
+                    if let Err(e) = state.save_group(&group, &new_items).await {
+                        error!("Failed to save group: {}", e);
+                    }
+                }
+                Err(e) => error!("Failed to send group card: {}", e),
+            }
+        }
+    }
+}
+
+/// Refreshes per-item statuses of all group cards. Cards whose PRs are all closed go the way
+/// the chat's closed cards do, see `CLOSED_CARDS`.
+pub async fn sync_groups(monitor: &Monitor) {
+    let (bot, github, state) = (&monitor.bot, &monitor.github, &monitor.state);
+    let groups = match state.get_all_groups().await {
+        Ok(groups) => groups,
+        Err(e) => {
+            error!("Failed to load group cards: {}", e);
+            return;
+        }
+    };
+
+    for group in groups {
+        let mut items = match state.get_group(&group.message_id, group.chat_id).await {
+            Ok(Some((_, items))) => items,
+            _ => continue,
+        };
+
+        let mut changed = false;
+        for item in items.iter_mut().filter(|i| !is_terminal(&i.status)) {
+            match github
+                .get_pr_details(&item.repo_owner, &item.repo_name, item.pr_number as u64)
+                .await
+            {
+                Ok(pr) => {
+                    let status = item_status(github, &item.repo_owner, &item.repo_name, &pr).await;
+                    let title = pr.title.clone().unwrap_or_default();
+                    if item.status != status || item.title != title {
+                        item.status = status;
+                        item.title = title;
+                        changed = true;
+                    }
+                }
+                Err(e) => error!(
+                    "Failed to check status for {}/{}#{}: {}",
+                    item.repo_owner, item.repo_name, item.pr_number, e
+                ),
+            }
+        }
+
+        let chat_id = ChatId(group.chat_id);
+        let message_id = MessageId(group.message_id.parse().unwrap_or(0));
+
+        if items.iter().all(|i| is_terminal(&i.status)) {
+            info!(
+                "All PRs in group card {} are closed/merged. Cleaning up...",
+                group.message_id
+            );
+            let final_text = format!("<s>{}</s>", generate_group_text(&group, &items));
+            // A group spanning repositories follows the policy of its first PR's repository
+            let policy = match items.first() {
+                Some(first) => {
+                    monitor
+                        .closed_cards(group.chat_id, &first.repo_owner, &first.repo_name)
+                        .await
+                }
+                None => ClosedCards::Delete,
+            };
+            let card = ClosingCard {
+                chat_id: group.chat_id,
+                message_id: group.message_id.clone(),
+                name: format!("group card of {} in {}", group.author, group.chat_id),
+                reason: "all PRs closed",
+            };
+            let stays = match policy {
+                ClosedCards::Archive => {
+                    let summary = closed_group_summary(&group, &items);
+                    monitor.archive_card(&card, &summary).await
+                }
+                ClosedCards::Move(archive_chat) => {
+                    let summary = closed_group_summary(&group, &items);
+                    monitor.move_card(&card, &summary, archive_chat).await;
+                    false
+                }
+                ClosedCards::Keep(days) => monitor.keep_card(&card, &final_text, days).await,
+                ClosedCards::Delete => false,
+            };
+            if !stays {
+                state.audit(format!("deleted {}: {}", card.name, card.reason));
+                if bot
+                    .delete_message(chat_id, message_id)
+                    .retried()
+                    .await
+                    .is_err()
+                {
+                    bot.edit_message_text(chat_id, message_id, final_text)
+                        .parse_mode(ParseMode::Html)
+                        .link_preview_options(no_link_preview())
+                        .retried()
+                        .await
+                        .ok();
+                }
+            }
+            if let Err(e) = state.remove_group(&group.message_id, group.chat_id).await {
+                error!("Failed to remove group from DB: {}", e);
+            }
+            continue;
+        }
+
+        if changed {
+            if let Err(e) = state.save_group(&group, &items).await {
+                error!("Failed to update group in DB: {}", e);
+                continue;
+            }
+            if let Err(e) = bot
+                .edit_message_text(chat_id, message_id, generate_group_text(&group, &items))
+                .parse_mode(ParseMode::Html)
+                .link_preview_options(no_link_preview())
+                .retried()
+                .await
+            {
+                error!("Failed to update group card in chat: {}", e);
+            }
+            state.audit(format!(
+                "edited group card of {} in {}: GitHub sync",
+                group.author, group.chat_id
+            ));
+        }
+    }
+}
+
+/// Replaces a group card with one individual tracked card per still-open PR.
+pub async fn split_group(
+    bot: &Bot,
+    github: &GithubClient,
+    state: &StateManager,
+    group: PrGroup,
+    items: Vec<GroupItem>,
+) -> ResponseResult<()> {
+    let chat_id = ChatId(group.chat_id);
+
+    for item in items.iter().filter(|i| !is_terminal(&i.status)) {
+        let mut pr_data = match github
+            .get_pr_details(&item.repo_owner, &item.repo_name, item.pr_number as u64)
+            .await
+        {
+            Ok(pr) => {
+                handlers::pr_data_from_github(&pr, &item.repo_owner, &item.repo_name, group.chat_id)
+            }
+            Err(e) => {
+                error!(
+                    "Failed to fetch PR while splitting, using cached data: {}",
+                    e
+                );
+                crate::state::PrData {
+                    pr_url: item.pr_url.clone(),
+                    title: item.title.clone(),
+                    author: group.author.clone(),
+                    repo: format!("{}/{}", item.repo_owner, item.repo_name),
+                    pr_number: item.pr_number as u64,
+                    status: PrStatus::parse(&item.status),
+                    status_since: Utc::now().timestamp(),
+                    chat_id: group.chat_id,
+                    ..Default::default()
+                }
+            }
+        };
+        pr_data.security = item.security != 0;
+        handlers::send_tracked_card(bot, state, chat_id, pr_data, "/split").await?;
+    }
+
+    bot.delete_message(chat_id, MessageId(group.message_id.parse().unwrap_or(0)))
+        .retried()
+        .await
+        .ok();
+    state.audit(format!(
+        "deleted group card of {} in {}: /split",
+        group.author, group.chat_id
+    ));
+    if let Err(e) = state.remove_group(&group.message_id, group.chat_id).await {
+        error!("Failed to remove group from DB: {}", e);
+    }
+
+    Ok(())
+}
@@ -1,15 +1,91 @@
-use crate::github::GithubClient;
-use crate::state::{PrData, StateManager};
-use log::error;
+use crate::audit;
+use crate::bulk;
+use crate::checks;
+use crate::cleanup;
+use crate::compact;
+use crate::config::ClosedCards;
+use crate::dashboard;
+use crate::db::{ButtonMode, Lead, RepoCapabilities, UserLink};
+use crate::drift;
+use crate::fairness;
+use crate::federation;
+use crate::filter::{Query, Viewer};
+use crate::github::{self, GithubClient};
+use crate::groups;
+use crate::lifecycle::{PrEvent, PrStatus};
+use crate::metrics::{self, Timed};
+use crate::mirror;
+use crate::monitor::Monitor;
+use crate::nudges;
+use crate::onboarding;
+use crate::reactions::{ReactionAction, ReactionMap};
+use crate::rules::Rule;
+use crate::sender::Retried;
+use crate::sessions;
+use crate::state::{ItemType, PrData, StateManager};
+use crate::submit;
+use crate::templates;
+use crate::tenants::Tenants;
+use crate::tickets;
+use crate::tokens;
+use crate::verification;
+use octocrab::models::issues::Issue;
+use octocrab::models::pulls::PullRequest;
+use octocrab::models::IssueState;
 use regex::Regex;
 use std::sync::Arc;
 use teloxide::prelude::*;
-use teloxide::types::{LinkPreviewOptions, MessageReactionUpdated, ParseMode, ReactionType};
+use teloxide::types::{
+    Chat, ChatMemberUpdated, InlineKeyboardButton, InlineKeyboardMarkup, KeyboardButton,
+    KeyboardMarkup, LinkPreviewOptions, MessageId, MessageReactionUpdated, ParseMode, ReactionType,
+    ReplyParameters, ThreadId, User,
+};
+use teloxide::{ApiError, RequestError};
+use tracing::{error, instrument, warn};
 
+/// Callback data prefix of the buttons on cards in button mode, followed by the action's name.
+pub const CARD_CALLBACK: &str = "card:";
+
+/// Max PRs listed by /list, keeping the reply under Telegram's message size limit.
+const LIST_LIMIT: usize = 30;
+
+/// Commands that act on the tracked card they reply to.
+const CARD_COMMANDS: &[&str] = &[
+    "/addressed",
+    "/rereview",
+    "/review",
+    "/approve",
+    "/changes",
+    "/comment",
+    "/giveup",
+    "/merge",
+    "/draft",
+    "/unblock",
+    "/block",
+    "/unsnooze",
+    "/snooze",
+    "/takeover",
+    "/label",
+    "/ack",
+    "/nitpick",
+    "/needs-tests",
+    "/needs-docs",
+];
+
+#[instrument(
+    name = "update",
+    skip_all,
+    fields(
+        kind = "reaction",
+        chat_id = update.chat.id.0,
+        message_id = update.message_id.0,
+    )
+)]
 pub async fn handle_reaction(
     bot: Bot,
     update: MessageReactionUpdated,
     state: Arc<StateManager>,
+    monitor: Monitor,
 ) -> ResponseResult<()> {
     let message_id = update.message_id;
     let chat_id = update.chat.id;
@@ -50,88 +126,123 @@ pub async fn handle_reaction(
         })
         .collect();
 
-    // specific emojis (Base characters)
-    let heart = "\u{2764}"; // ❤
-    let thumbs_up = "\u{1f44d}"; // 👍
-    let ok_hand = "\u{1f44c}"; // 👌
-    let cry = "\u{1f62d}"; // 😭
-    let hundred = "\u{1f4af}"; // 💯
-    let pray = "\u{1f64f}"; // 🙏
-    let cooking = "\u{1f373}"; // 🍳
-
-    let has_reaction =
-        |list: &[String], base: &str| -> bool { list.iter().any(|e| e.starts_with(base)) };
-
-    // Helper to update lists
-    // Iterate over old emojis to remove them
-    for emoji in &old_emojis {
-        if !new_emojis.contains(emoji) {
-            if emoji.starts_with(heart) {
-                data.reviewers.retain(|u| u != &username);
-            } else if emoji.starts_with(thumbs_up) {
-                data.approvals.retain(|u| u != &username);
-            } else if emoji.starts_with(cry) {
-                // cry removes from reviewers when ADDED, so removing cry does nothing special?
-                // Or maybe restores? For now, nothing.
-            } else if emoji.starts_with(hundred) {
-                // Managed by is_merged logic below?
-                // actually we should handle it here or below.
-                // Current logic handles toggles below.
-            } else if emoji.starts_with(cooking) || emoji.starts_with(pray) {
-                // Managed below
-            } else {
-                // It was a comment
-                data.comments.retain(|u| u != &username);
+    let config = monitor.config();
+    let map = &config.reactions;
+    let has_action = |list: &[String], action: ReactionAction| -> bool {
+        list.iter().any(|e| map.action(e) == Some(action))
+    };
+
+    // Lifecycle events are applied after the list updates, so a re-review request
+    // isn't immediately settled away by the cleared comment list
+    let before = data.clone();
+    let lists_before = (
+        data.reviewers.clone(),
+        data.approvals.clone(),
+        data.changes_requested.clone(),
+        data.comments.clone(),
+    );
+    let mut events = Vec::new();
+
+    // A user keeps one review mark: several marking emojis resolve by REACTION_PRECEDENCE
+    let held: Vec<ReactionAction> = [
+        (ReactionAction::Review, &data.reviewers),
+        (ReactionAction::Approve, &data.approvals),
+        (ReactionAction::RequestChanges, &data.changes_requested),
+        (ReactionAction::Comment, &data.comments),
+    ]
+    .into_iter()
+    .filter(|(_, list)| list.contains(&username))
+    .map(|(mark, _)| mark)
+    .collect();
+    let mut mark = map.resolve_mark(config.reaction_precedence, &held, &old_emojis, &new_emojis);
+    if mark == Some(Some(ReactionAction::Approve))
+        && !data.self_approval
+        && is_own_pr(&state, &user, &data).await
+    {
+        // The author's other marks stay as they were
+        mark = None;
+        reply_expiring(
+            &bot,
+            chat_id,
+            message_id,
+            config.error_reply_ttl_secs,
+            self_approval_refusal(&username),
+        )
+        .await?;
+    }
+    if let Some(mark) = mark {
+        data.reviewers.retain(|u| u != &username);
+        data.approvals.retain(|u| u != &username);
+        data.changes_requested.retain(|u| u != &username);
+        data.comments.retain(|u| u != &username);
+        data.from_github.retain(|u| u != &username);
+        match mark {
+            Some(ReactionAction::Review) => {
+                data.reviewers.push(username.clone());
+                if !held.contains(&ReactionAction::Review) {
+                    sessions::start(&mut data, &username, config.review_timebox_minutes);
+                }
             }
+            Some(ReactionAction::Approve) => data.approvals.push(username.clone()),
+            Some(ReactionAction::RequestChanges) => data.changes_requested.push(username.clone()),
+            Some(_) => data.comments.push(username.clone()),
+            None => {}
         }
     }
 
-    // Iterate over new emojis to add them
+    // Iterate over new emojis to apply one-off actions; giving up has no undo
+    let mut re_review_for = None;
     for emoji in &new_emojis {
         if !old_emojis.contains(emoji) {
-            if emoji.starts_with(heart) {
-                if !data.reviewers.contains(&username) {
-                    data.reviewers.push(username.clone());
-                }
-            } else if emoji.starts_with(thumbs_up) {
-                if !data.approvals.contains(&username) {
-                    data.approvals.push(username.clone());
-                }
-            } else if emoji.starts_with(cry) {
-                data.reviewers.retain(|u| u != &username);
-            } else if emoji.starts_with(hundred) {
-                data.is_merged = true;
-            } else if emoji.starts_with(cooking) {
-                data.is_draft = true;
-            } else if emoji.starts_with(pray) {
-                data.re_review_requested = true;
-                // remove comments when re-review is requested via emoji
-                data.comments.clear();
-            } else {
-                // It is a comment (including ok_hand)
-                if !data.comments.contains(&username) {
-                    data.comments.push(username.clone());
-                }
-
-                // If it is ok_hand, they reviewed it, so remove from reviewers list if they are there
-                // (Assuming "reviewer" means "committed to review" and "comment/ok_hand" means "did review")
-                if emoji.starts_with(ok_hand) {
-                    data.reviewers.retain(|u| u != &username);
+            match map.action(emoji) {
+                Some(ReactionAction::GiveUp) => data.reviewers.retain(|u| u != &username),
+                Some(ReactionAction::Merge) => events.push(PrEvent::Merged),
+                Some(ReactionAction::Draft) => events.push(PrEvent::DraftSet(true)),
+                Some(ReactionAction::ReReview) => {
+                    events.push(PrEvent::ReReviewRequested);
+                    re_review_for = Some(re_review_audience(&data));
+                    // remove comments and change requests when re-review is requested via emoji
+                    data.comments.clear();
+                    data.changes_requested.clear();
                 }
+                _ => {}
             }
         }
     }
 
-    // Handle toggles off for single-state booleans (merged, draft, re-review)
+    // Handle toggles off for single-state markers (merged, draft, re-review)
     // If specific emoji was removed
-    if has_reaction(&old_emojis, hundred) && !has_reaction(&new_emojis, hundred) {
-        data.is_merged = false;
+    if has_action(&old_emojis, ReactionAction::Merge)
+        && !has_action(&new_emojis, ReactionAction::Merge)
+    {
+        events.push(PrEvent::Reopened);
+    }
+    if has_action(&old_emojis, ReactionAction::Draft)
+        && !has_action(&new_emojis, ReactionAction::Draft)
+    {
+        events.push(PrEvent::DraftSet(false));
+    }
+    if has_action(&old_emojis, ReactionAction::ReReview)
+        && !has_action(&new_emojis, ReactionAction::ReReview)
+    {
+        events.push(PrEvent::ReReviewWithdrawn);
     }
-    if has_reaction(&old_emojis, cooking) && !has_reaction(&new_emojis, cooking) {
-        data.is_draft = false;
+
+    if (
+        &data.reviewers,
+        &data.approvals,
+        &data.changes_requested,
+        &data.comments,
+    ) != (
+        &lists_before.0,
+        &lists_before.1,
+        &lists_before.2,
+        &lists_before.3,
+    ) {
+        data.apply(PrEvent::ReviewsChanged);
     }
-    if has_reaction(&old_emojis, pray) && !has_reaction(&new_emojis, pray) {
-        data.re_review_requested = false;
+    for event in events {
+        data.apply(event);
     }
 
     // Save and Update Message
@@ -140,31 +251,356 @@ pub async fn handle_reaction(
         .await
     {
         error!("Failed to save state: {}", e);
+    } else {
+        drift::record_declared(&state, &message_id.0.to_string(), &before, &data, &username).await;
     }
 
-    let new_text = generate_message_text(&data);
+    edit_card(&bot, &state, chat_id, message_id, &data).await?;
+    state.audit(format!(
+        "edited {}: reaction by {}",
+        audit::card(&data.repo, data.pr_number, chat_id.0),
+        username
+    ));
+    if let Some(names) = re_review_for {
+        notify_linked_users(
+            &bot,
+            &state,
+            &data,
+            &message_id.0.to_string(),
+            "🙏 Re-review requested:",
+            &names,
+            Some(&username),
+        )
+        .await;
+    }
+    monitor
+        .apply_rules_after_change(&message_id.0.to_string(), &data)
+        .await;
 
-    bot.edit_message_text(chat_id, message_id, new_text)
-        .parse_mode(ParseMode::Html)
-        .link_preview_options(LinkPreviewOptions {
-            is_disabled: true,
-            url: None,
-            prefer_small_media: false,
-            prefer_large_media: false,
-            show_above_text: false,
-        })
+    Ok(())
+}
+
+/// Handles inline button taps: card buttons in button mode and the "Untrack" buttons of
+/// cleanup suggestions.
+#[instrument(
+    name = "update",
+    skip_all,
+    fields(
+        kind = "callback",
+        chat_id = query.regular_message().map(|m| m.chat.id.0),
+    )
+)]
+pub async fn handle_callback(
+    bot: Bot,
+    query: CallbackQuery,
+    state: Arc<StateManager>,
+    monitor: Monitor,
+) -> ResponseResult<()> {
+    let data = query.data.clone().unwrap_or_default();
+    if let Some(action) = data.strip_prefix(CARD_CALLBACK) {
+        return handle_card_button(&bot, &query, &state, &monitor, action).await;
+    }
+    let (Some(repo_arg), Some(message)) = (
+        data.strip_prefix(cleanup::UNTRACK_CALLBACK),
+        query.regular_message(),
+    ) else {
+        bot.answer_callback_query(&query.id).timed().await?;
+        return Ok(());
+    };
+    let Some((owner, repo)) = repo_arg.split_once('/') else {
+        bot.answer_callback_query(&query.id).timed().await?;
+        return Ok(());
+    };
+
+    if !is_chat_admin(&bot, &message.chat, Some(&query.from)).await {
+        bot.answer_callback_query(&query.id)
+            .text("Only chat admins can untrack repositories.")
+            .show_alert(true)
+            .timed()
+            .await?;
+        return Ok(());
+    }
+
+    let reply = match state
+        .remove_repository(message.chat.id.0, owner, repo)
+        .await
+    {
+        Ok(_) => {
+            state.audit(format!(
+                "untracked {}/{} in {}: cleanup suggestion, tapped by {}",
+                owner,
+                repo,
+                message.chat.id,
+                query
+                    .from
+                    .username
+                    .clone()
+                    .unwrap_or(query.from.first_name.clone())
+            ));
+            format!("Untracked {}/{}", owner, repo)
+        }
+        Err(e) => {
+            error!("Failed to untrack {}/{}: {}", owner, repo, e);
+            "Couldn't untrack the repository, try again.".to_string()
+        }
+    };
+
+    // The suggestion stays as a record; only the tapped button goes away
+    if let Some(keyboard) = message.reply_markup() {
+        bot.edit_message_reply_markup(message.chat.id, message.id)
+            .reply_markup(cleanup::without_button(keyboard, &data))
+            .timed()
+            .await
+            .ok();
+    }
+    bot.answer_callback_query(&query.id)
+        .text(reply)
+        .timed()
         .await?;
+    Ok(())
+}
+
+/// Applies a card button tap, the button-mode counterpart of a reaction.
+async fn handle_card_button(
+    bot: &Bot,
+    query: &CallbackQuery,
+    state: &StateManager,
+    monitor: &Monitor,
+    action: &str,
+) -> ResponseResult<()> {
+    let (Some(action), Some(message)) =
+        (ReactionAction::from_name(action), query.regular_message())
+    else {
+        bot.answer_callback_query(&query.id).timed().await?;
+        return Ok(());
+    };
+    let chat_id = message.chat.id;
+    let message_id = message.id.0.to_string();
+
+    let mut data = match state.get_pr_data(message_id.clone(), chat_id.0).await {
+        Ok(Some(d)) => d,
+        Ok(None) => {
+            bot.answer_callback_query(&query.id)
+                .text("This PR isn't tracked anymore.")
+                .timed()
+                .await?;
+            return Ok(());
+        }
+        Err(e) => {
+            error!("Error fetching PR data: {}", e);
+            bot.answer_callback_query(&query.id)
+                .text("Couldn't load the card, try again.")
+                .timed()
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let username = query
+        .from
+        .username
+        .clone()
+        .unwrap_or(query.from.first_name.clone());
+    if action == ReactionAction::Approve
+        && !data.self_approval
+        && is_own_pr(state, &query.from, &data).await
+    {
+        bot.answer_callback_query(&query.id)
+            .text(self_approval_refusal(&username))
+            .show_alert(true)
+            .timed()
+            .await?;
+        return Ok(());
+    }
+    let timebox = monitor.config().review_timebox_minutes;
+    let before = data.clone();
+    let (changed, re_review_for) = apply_action(&mut data, action, &username, timebox);
+    if !changed {
+        bot.answer_callback_query(&query.id).timed().await?;
+        return Ok(());
+    }
+    if let Err(e) = state.update_pr_data(message_id.clone(), data.clone()).await {
+        error!("Failed to save state: {}", e);
+        bot.answer_callback_query(&query.id)
+            .text("Couldn't save that change, try again.")
+            .timed()
+            .await?;
+        return Ok(());
+    }
+    drift::record_declared(state, &message_id, &before, &data, &username).await;
+
+    if let Err(e) = edit_card(bot, state, chat_id, message.id, &data).await {
+        error!("Failed to update card: {}", e);
+    }
+    state.audit(format!(
+        "edited {}: {} button by {}",
+        audit::card(&data.repo, data.pr_number, chat_id.0),
+        action.name(),
+        username
+    ));
+    if let Some(names) = re_review_for {
+        notify_linked_users(
+            bot,
+            state,
+            &data,
+            &message_id,
+            "🙏 Re-review requested:",
+            &names,
+            Some(&username),
+        )
+        .await;
+    }
+    monitor.apply_rules_after_change(&message_id, &data).await;
+    bot.answer_callback_query(&query.id).timed().await?;
+    Ok(())
+}
+
+/// Flags the cards of a member who left the chat so their PRs don't silently rot.
+#[instrument(name = "update", skip_all, fields(kind = "chat_member", chat_id = update.chat.id.0))]
+pub async fn handle_chat_member(
+    bot: Bot,
+    update: ChatMemberUpdated,
+    state: Arc<StateManager>,
+    monitor: Monitor,
+) -> ResponseResult<()> {
+    if !update.old_chat_member.is_present() || update.new_chat_member.is_present() {
+        return Ok(());
+    }
+    let user = &update.new_chat_member.user;
+    if user.is_bot {
+        return Ok(());
+    }
+    let chat_id = update.chat.id;
+    let name = user.username.clone().unwrap_or(user.first_name.clone());
+    // Authors are GitHub logins, so only a linked account tells us they left
+    let login = state
+        .get_user_link(user.id.0 as i64)
+        .await
+        .ok()
+        .flatten()
+        .map(|l| l.github_login);
+
+    let messages = match state.get_chat_messages(chat_id.0).await {
+        Ok(messages) => messages,
+        Err(e) => {
+            error!("Failed to load cards of {}: {}", chat_id, e);
+            return Ok(());
+        }
+    };
+    let mut flagged = Vec::new();
+    for m in messages {
+        let Ok(Some(mut data)) = state.get_pr_data(m.message_id.clone(), chat_id.0).await else {
+            continue;
+        };
+        if data.status.is_terminal() {
+            continue;
+        }
+        let mut changed = false;
+        if login
+            .as_ref()
+            .is_some_and(|l| l.eq_ignore_ascii_case(&data.author))
+            && !data.departed.contains(&data.author)
+        {
+            data.departed.push(data.author.clone());
+            changed = true;
+        }
+        if data.reviewers.contains(&name) && !data.departed.contains(&name) {
+            data.departed.push(name.clone());
+            changed = true;
+        }
+        if !changed {
+            continue;
+        }
+
+        if let Err(e) = state
+            .update_pr_data(m.message_id.clone(), data.clone())
+            .await
+        {
+            error!("Failed to save state: {}", e);
+            continue;
+        }
+        if let Ok(message_id) = m.message_id.parse() {
+            edit_card(&bot, &state, chat_id, MessageId(message_id), &data)
+                .await
+                .ok();
+        }
+        state.audit(format!(
+            "edited {}: {} left the chat",
+            audit::card(&data.repo, data.pr_number, chat_id.0),
+            name
+        ));
+        flagged.push(data);
+    }
+    if flagged.is_empty() {
+        return Ok(());
+    }
 
+    let lines: Vec<String> = flagged
+        .iter()
+        .map(|d| {
+            format!(
+                "• <a href=\"{}\">{}#{}</a> {}",
+                d.pr_url,
+                d.repo,
+                d.pr_number,
+                teloxide::utils::html::escape(&d.title)
+            )
+        })
+        .collect();
+    let admins: Vec<String> = monitor
+        .admin_usernames(chat_id)
+        .await
+        .into_iter()
+        .map(|u| format!("@{}", u))
+        .collect();
+    let text = format!(
+        "🚪 {} left the chat and was on these PRs:\n{}\n\nReply /takeover to a card to take it over. {}",
+        teloxide::utils::html::escape(&name),
+        lines.join("\n"),
+        admins.join(" ")
+    );
+    match bot
+        .send_message(chat_id, text.trim_end())
+        .parse_mode(ParseMode::Html)
+        .link_preview_options(no_link_preview())
+        .retried()
+        .await
+    {
+        Ok(_) => state.audit(format!(
+            "notified {}: {} left with {} open PR(s)",
+            chat_id,
+            name,
+            flagged.len()
+        )),
+        Err(e) => error!("Failed to report departure of {}: {}", name, e),
+    }
     Ok(())
 }
 
+#[instrument(
+    name = "update",
+    skip_all,
+    fields(
+        kind = "message",
+        chat_id = msg.chat.id.0,
+        message_id = msg.id.0,
+    )
+)]
 pub async fn handle_message(
     bot: Bot,
     msg: Message,
     state: Arc<StateManager>,
     github: Arc<GithubClient>,
+    monitor: Monitor,
+    tenants: Arc<Tenants>,
 ) -> ResponseResult<()> {
     let text = msg.text().unwrap_or("").to_string();
+    let ttl = monitor.config().error_reply_ttl_secs;
+
+    // Mirror chats only watch; reactions on their copies find no card either
+    if mirror::is_mirror(&state, msg.chat.id).await {
+        return Ok(());
+    }
 
     // Check for /upgrade command
     if text.starts_with("/upgrade") {
@@ -176,311 +612,3435 @@ pub async fn handle_message(
             // Action: Parse link from replied message, delete replied message, post new bot message with tracking.
 
             let reply_text = reply.text().unwrap_or("");
-            if let Some((owner, repo, pr_number)) = extract_pr_info(reply_text) {
-                // Fetch PR info
-                match github.get_pr_details(&owner, &repo, pr_number).await {
-                    Ok(pr) => {
-                        // Delete user message
-                        bot.delete_message(msg.chat.id, reply.id).await?;
-                        // Delete command message
-                        bot.delete_message(msg.chat.id, msg.id).await?;
-
-                        // Send new tracked message
-                        let pr_data = PrData {
-                            pr_url: pr.html_url.map(|u| u.to_string()).unwrap_or_default(),
-                            title: pr.title.unwrap_or_default(),
-                            author: pr.user.map(|u| u.login).unwrap_or("unknown".to_string()),
-                            repo: format!("{}/{}", owner, repo),
-                            pr_number,
-                            reviewers: vec![],
-                            approvals: vec![],
-                            changes_requested: vec![],
-                            comments: vec![],
-                            is_merged: pr.merged_at.is_some(),
-                            is_draft: pr.draft.unwrap_or(false),
-                            re_review_requested: false,
-                            chat_id: msg.chat.id.0,
-                        };
-
-                        let text = generate_message_text(&pr_data);
-                        let sent_msg = bot
-                            .send_message(msg.chat.id, text)
-                            .parse_mode(ParseMode::Html)
-                            .link_preview_options(LinkPreviewOptions {
-                                is_disabled: true,
-                                url: None,
-                                prefer_small_media: false,
-                                prefer_large_media: false,
-                                show_above_text: false,
-                            })
-                            .await?;
-
-                        state
-                            .add_message(sent_msg.id.0.to_string(), pr_data)
-                            .await
-                            .ok();
-
-                        // Add repo to tracking if new
-                        state.add_repository(&owner, &repo).await.ok();
+            let config = monitor.config();
+            let Some((owner, repo, pr_number, item_type)) =
+                extract_pr_info(reply_text, &config.github_web_url, config.track_issues)
+            else {
+                return reply_error(&bot, &msg, ttl, "That message has no GitHub PR link.").await;
+            };
+            if let Ok(Some(message_id)) = state
+                .find_card(msg.chat.id.0, &owner, &repo, pr_number)
+                .await
+            {
+                let reason = match message_link(msg.chat.id.0, &message_id) {
+                    Some(link) if !message_id.is_empty() => {
+                        format!(
+                            "{}/{}#{} already has a card here: {}",
+                            owner, repo, pr_number, link
+                        )
                     }
-                    Err(e) => {
-                        error!("Failed to fetch PR: {}", e);
-                        bot.send_message(msg.chat.id, "Failed to fetch PR details.")
-                            .await?;
+                    _ => format!("{}/{}#{} already has a card here.", owner, repo, pr_number),
+                };
+                return reply_error(&bot, &msg, ttl, reason).await;
+            }
+            if !may_manage_repos(&monitor, msg.from.as_ref())
+                && !is_tracked(&state, msg.chat.id.0, &owner, &repo).await
+            {
+                let reason = format!(
+                    "Sorry, {}/{} isn't tracked here and only the bot's administrators can add repositories.",
+                    owner, repo
+                );
+                return reply_error(&bot, &msg, ttl, reason).await;
+            }
+
+            // Fetch PR info
+            match card_data_from_github(&github, &owner, &repo, pr_number, item_type, msg.chat.id.0)
+                .await
+            {
+                Ok(pr_data) => {
+                    match replace_with_card(&bot, &state, reply, pr_data, "/upgrade").await? {
+                        Replacement::Replaced => {}
+                        Replacement::NotPosted => {
+                            let reason = format!(
+                                "Couldn't post a card for {}/{}#{}, try again later.",
+                                owner, repo, pr_number
+                            );
+                            return reply_error(&bot, &msg, ttl, reason).await;
+                        }
+                        Replacement::Undeletable(RequestError::Api(_)) => {
+                            return reply_error(
+                                &bot,
+                                &msg,
+                                ttl,
+                                "I can't replace that message: I need the \"Delete messages\" admin right.",
+                            )
+                            .await;
+                        }
+                        Replacement::Undeletable(_) => {
+                            return reply_error(
+                                &bot,
+                                &msg,
+                                ttl,
+                                "Couldn't replace that message, try again later.",
+                            )
+                            .await;
+                        }
                     }
+                    // Delete command message
+                    bot.delete_message(msg.chat.id, msg.id).retried().await.ok();
+
+                    // Add repo to tracking if new
+                    state
+                        .add_repository(msg.chat.id.0, &owner, &repo)
+                        .await
+                        .ok();
+                    onboarding::probe_new(&bot, &github, &state, msg.chat.id, &owner, &repo).await;
+                }
+                Err(e) => {
+                    error!("Failed to fetch PR: {}", e);
+                    let reason = format!(
+                        "Couldn't upgrade {}/{}#{}: {}.",
+                        owner,
+                        repo,
+                        pr_number,
+                        github::describe_error(&e)
+                    );
+                    return reply_error(&bot, &msg, ttl, reason).await;
                 }
             }
+        } else {
+            return reply_error(
+                &bot,
+                &msg,
+                ttl,
+                "Reply to a message with a PR link to use /upgrade.",
+            )
+            .await;
         }
         return Ok(());
     }
 
-    // Help command
-    if text.starts_with("/help") || text.starts_with("/start") {
-        let help_text = r#"
-<b>🤖 PR Monitor Bot Help</b>
+    // Split a grouped multi-PR card back into individual cards
+    if text.starts_with("/split") {
+        if let Some(reply) = msg.reply_to_message() {
+            if let Ok(Some((group, items))) = state
+                .get_group(&reply.id.0.to_string(), msg.chat.id.0)
+                .await
+            {
+                groups::split_group(&bot, &github, &state, group, items).await?;
+                bot.delete_message(msg.chat.id, msg.id).retried().await.ok();
+            }
+        }
+        return Ok(());
+    }
 
-I monitor GitHub PRs and track review status via emojis or commands.
+    // Runtime repository management
+    if (text.starts_with("/track") || text.starts_with("/untrack"))
+        && !may_manage_repos(&monitor, msg.from.as_ref())
+    {
+        return reply_error(
+            &bot,
+            &msg,
+            ttl,
+            "Sorry, only the bot's administrators can track or untrack repositories.",
+        )
+        .await;
+    }
 
-<b>Commands or Reactions (reply to tracked message):</b>
-/review - Mark as reviewing (❤️)
-/approve - Approve PR (👍)
-/comment - Add comment status (👌)
-/giveup - Unassign self (😭)
-/merge - Mark as merged (💯)
-/draft - Mark as draft (🍳)
-/addressed or /rereview - Request re-review (🙏)
+    if text.starts_with("/track") {
+        let Some((owner, repo)) = parse_repo_arg(&text, &monitor.config().github_web_url) else {
+            bot.send_message(msg.chat.id, "Usage: /track owner/repo")
+                .retried()
+                .await?;
+            return Ok(());
+        };
+        // Catch typos and repos the token can't read before polling them forever
+        if let Err(e) = github.get_repo(&owner, &repo).await {
+            let reason = format!(
+                "Can't track {}/{}: {}.",
+                owner,
+                repo,
+                github::describe_error(&e)
+            );
+            return reply_error(&bot, &msg, ttl, reason).await;
+        }
+        // Sent inside a forum topic, /track routes the repository's cards to that topic
+        let topic = msg.is_topic_message.then_some(msg.thread_id).flatten();
+        let saved = match state.add_repository(msg.chat.id.0, &owner, &repo).await {
+            // Sent from the chat itself, so it's the right one
+            Ok(()) => state.set_chat_verified(msg.chat.id.0).await.map(|_| ()),
+            Err(e) => Err(e),
+        };
+        let saved = match saved {
+            Ok(()) => {
+                state
+                    .set_repository_topic(msg.chat.id.0, &owner, &repo, topic.map(|t| t.0 .0))
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+        if let Err(e) = saved {
+            error!("Failed to track {}/{}: {}", owner, repo, e);
+            let reason = format!("Failed to track {}/{}, try again later.", owner, repo);
+            return reply_error(&bot, &msg, ttl, reason).await;
+        }
+        let checklist = onboarding::probe(&github, &state, &owner, &repo).await;
+        let mut reply = bot.send_message(
+            msg.chat.id,
+            match topic {
+                Some(_) => format!(
+                    "✅ Now tracking {}/{} in this topic\n\n{}",
+                    owner, repo, checklist
+                ),
+                None => format!("✅ Now tracking {}/{}\n\n{}", owner, repo, checklist),
+            },
+        );
+        if let Some(topic) = topic {
+            reply = reply.message_thread_id(topic);
+        }
+        reply.retried().await?;
+        return Ok(());
+    }
 
-<b>Note:</b> Review status (Approved, Changes Requested, etc.) is automatically synced from GitHub. Manual commands are useful for quick updates but GitHub state will override them on the next sync.
+    if text.starts_with("/untrack") {
+        let Some((owner, repo)) = parse_repo_arg(&text, &monitor.config().github_web_url) else {
+            bot.send_message(msg.chat.id, "Usage: /untrack owner/repo [clean]")
+                .retried()
+                .await?;
+            return Ok(());
+        };
+        let clean = text.split_whitespace().nth(2) == Some("clean");
 
-<b>General Commands:</b>
-/upgrade (reply to link) - Replace link with tracked message
-/help - Show this message
-"#;
-        bot.send_message(msg.chat.id, help_text)
-            .parse_mode(ParseMode::Html)
-            .await?;
+        let reply = match state.remove_repository(msg.chat.id.0, &owner, &repo).await {
+            Ok(true) => {
+                let mut reply = format!("🛑 Stopped tracking {}/{}", owner, repo);
+                if clean {
+                    let removed =
+                        remove_repo_messages(&bot, &state, msg.chat.id, &owner, &repo).await;
+                    reply.push_str(&format!(" and removed {} active message(s)", removed));
+                }
+                reply
+            }
+            Ok(false) => format!("{}/{} is not tracked.", owner, repo),
+            Err(e) => {
+                error!("Failed to untrack {}/{}: {}", owner, repo, e);
+                format!("Failed to untrack {}/{}.", owner, repo)
+            }
+        };
+        bot.send_message(msg.chat.id, reply).retried().await?;
         return Ok(());
     }
 
-    // Interactive commands (reply based)
-    if let Some(reply_to) = msg.reply_to_message() {
-        let parent_id = reply_to.id;
-
-        // Check if it's a tracked message
-        if let Ok(Some(mut data)) = state
-            .get_pr_data(parent_id.0.to_string(), msg.chat.id.0)
-            .await
-        {
-            let mut changed = false;
-            let username = msg
-                .from
-                .as_ref()
-                .map(|u| u.username.clone().unwrap_or(u.first_name.clone()))
-                .unwrap_or("unknown".to_string());
+    // Bulk housekeeping, restricted to chat admins
+    if text.starts_with("/snoozeall")
+        || text.starts_with("/refreshall")
+        || text.starts_with("/archiveall")
+        || text.starts_with("/backfill")
+    {
+        if !is_admin(&bot, &msg).await {
+            bot.send_message(msg.chat.id, "Only chat admins can run bulk operations.")
+                .retried()
+                .await?;
+            return Ok(());
+        }
+        handle_bulk_command(&bot, &msg, &text, monitor).await?;
+        return Ok(());
+    }
 
-            if text.starts_with("/addressed") || text.starts_with("/rereview") {
-                data.re_review_requested = true;
-                // remove comments when re-review is requested
-                data.comments.clear();
-                changed = true;
-            } else if text.starts_with("/review") {
-                if !data.reviewers.contains(&username) {
-                    data.reviewers.push(username);
-                    changed = true;
-                }
-            } else if text.starts_with("/approve") {
-                if !data.approvals.contains(&username) {
-                    data.approvals.push(username);
-                    changed = true;
-                }
-            } else if text.starts_with("/comment") {
-                if !data.comments.contains(&username) {
-                    data.comments.push(username);
-                    changed = true;
-                }
-            } else if text.starts_with("/giveup") {
-                data.reviewers.retain(|u| u != &username);
-                changed = true;
-            } else if text.starts_with("/merge") {
-                data.is_merged = true;
-                changed = true;
-            } else if text.starts_with("/draft") {
-                data.is_draft = !data.is_draft; // Toggle draft
-                changed = true;
+    if text.starts_with("/rules") {
+        let args = text.split_whitespace().skip(1).collect::<Vec<_>>();
+        let chat_id = msg.chat.id.0;
+        match args.first().copied() {
+            None => {
+                let rules = state.get_rules(chat_id).await.unwrap_or_default();
+                let reply = if rules.is_empty() {
+                    "No rules in this chat. Add one with /rules add if approvals<2 and age>5d then escalate".to_string()
+                } else {
+                    let lines: Vec<String> = rules
+                        .iter()
+                        .map(|r| format!("#{} {} (by {})", r.id, r.rule, r.created_by))
+                        .collect();
+                    format!("📏 Rules:\n{}", lines.join("\n"))
+                };
+                bot.send_message(msg.chat.id, reply).retried().await?;
             }
-
-            if changed {
-                if let Err(e) = state
-                    .update_pr_data(parent_id.0.to_string(), data.clone())
-                    .await
-                {
-                    error!("Failed to save state: {}", e);
+            Some("add") | Some("remove") if !is_admin(&bot, &msg).await => {
+                return reply_error(&bot, &msg, ttl, "Only chat admins can change rules.").await;
+            }
+            Some("add") => {
+                let rule_text = args[1..].join(" ");
+                if let Err(e) = Rule::parse(&rule_text) {
+                    return reply_error(&bot, &msg, ttl, format!("Invalid rule: {}.", e)).await;
                 }
-
-                let new_text = generate_message_text(&data);
-                bot.edit_message_text(msg.chat.id, parent_id, new_text)
-                    .parse_mode(ParseMode::Html)
-                    .link_preview_options(LinkPreviewOptions {
-                        is_disabled: true,
-                        url: None,
-                        prefer_small_media: false,
-                        prefer_large_media: false,
-                        show_above_text: false,
-                    })
+                let username = msg
+                    .from
+                    .as_ref()
+                    .map(|u| u.username.clone().unwrap_or(u.first_name.clone()))
+                    .unwrap_or("unknown".to_string());
+                if let Err(e) = state.add_rule(chat_id, &rule_text, &username).await {
+                    error!("Failed to save rule: {}", e);
+                    return reply_error(&bot, &msg, ttl, "Couldn't save the rule, try again.")
+                        .await;
+                }
+                bot.send_message(msg.chat.id, format!("📏 Added rule: {}", rule_text))
+                    .retried()
                     .await?;
-
-                // Delete the command message
-                bot.delete_message(msg.chat.id, msg.id).await.ok();
-                return Ok(());
+            }
+            Some("remove") => {
+                let Some(id) = args
+                    .get(1)
+                    .and_then(|a| a.trim_start_matches('#').parse::<i64>().ok())
+                else {
+                    return reply_error(&bot, &msg, ttl, "Usage: /rules remove <id>").await;
+                };
+                match state.remove_rule(chat_id, id).await {
+                    Ok(true) => {
+                        bot.send_message(msg.chat.id, format!("📏 Removed rule #{}.", id))
+                            .retried()
+                            .await?;
+                    }
+                    Ok(false) => {
+                        return reply_error(&bot, &msg, ttl, format!("No rule #{} here.", id))
+                            .await;
+                    }
+                    Err(e) => {
+                        error!("Failed to remove rule: {}", e);
+                        return reply_error(
+                            &bot,
+                            &msg,
+                            ttl,
+                            "Couldn't remove the rule, try again.",
+                        )
+                        .await;
+                    }
+                }
+            }
+            Some(_) => {
+                return reply_error(
+                    &bot,
+                    &msg,
+                    ttl,
+                    "Usage: /rules, /rules add if <condition> then <action> or /rules remove <id>",
+                )
+                .await;
             }
         }
+        return Ok(());
     }
 
-    // Check for /addressed command (Legacy specific block removed as merged above)
-
-    // Check if reply to a tracked message (Re-review logic)
-    if let Some(reply_to) = msg.reply_to_message() {
-        let parent_id = reply_to.id;
-        if let Ok(Some(mut data)) = state
-            .get_pr_data(parent_id.0.to_string(), msg.chat.id.0)
-            .await
-        {
-            if text.contains("http") || text.contains("github.com") {
-                data.re_review_requested = true;
-                // remove comments when re-review is requested
-                data.comments.clear();
+    if text.starts_with("/tickets") {
+        let args = text.split_whitespace().skip(1).collect::<Vec<_>>();
+        let chat_id = msg.chat.id.0;
+        let usage = "Usage: /tickets, /tickets add <regex> <url with {id}> or /tickets remove <id>";
+        match args.first().copied() {
+            None => {
+                let trackers = state.get_ticket_trackers(chat_id).await.unwrap_or_default();
+                let reply = if trackers.is_empty() {
+                    "No ticket trackers in this chat. Add one with /tickets add JIRA-\\d+ https://jira.example.com/browse/{id}".to_string()
+                } else {
+                    let lines: Vec<String> = trackers
+                        .iter()
+                        .map(|t| format!("#{} {} → {}", t.id, t.pattern, t.url_template))
+                        .collect();
+                    format!("🎫 Ticket trackers:\n{}", lines.join("\n"))
+                };
+                bot.send_message(msg.chat.id, reply)
+                    .link_preview_options(no_link_preview())
+                    .retried()
+                    .await?;
+            }
+            Some("add") | Some("remove") if !is_admin(&bot, &msg).await => {
+                return reply_error(
+                    &bot,
+                    &msg,
+                    ttl,
+                    "Only chat admins can change ticket trackers.",
+                )
+                .await;
+            }
+            Some("add") => {
+                let [_, pattern, url_template] = args[..] else {
+                    return reply_error(&bot, &msg, ttl, usage).await;
+                };
+                if let Err(reason) = tickets::validate(pattern, url_template) {
+                    return reply_error(&bot, &msg, ttl, format!("Invalid tracker: {}.", reason))
+                        .await;
+                }
                 if let Err(e) = state
-                    .update_pr_data(parent_id.0.to_string(), data.clone())
+                    .add_ticket_tracker(chat_id, pattern, url_template)
                     .await
                 {
-                    error!("Failed to save state: {}", e);
+                    error!("Failed to save ticket tracker: {}", e);
+                    return reply_error(&bot, &msg, ttl, "Couldn't save the tracker, try again.")
+                        .await;
+                }
+                bot.send_message(
+                    msg.chat.id,
+                    format!(
+                        "🎫 Linking {} to {}. Cards pick it up on their next sync.",
+                        pattern, url_template
+                    ),
+                )
+                .link_preview_options(no_link_preview())
+                .retried()
+                .await?;
+            }
+            Some("remove") => {
+                let Some(id) = args
+                    .get(1)
+                    .and_then(|a| a.trim_start_matches('#').parse::<i64>().ok())
+                else {
+                    return reply_error(&bot, &msg, ttl, usage).await;
+                };
+                match state.remove_ticket_tracker(chat_id, id).await {
+                    Ok(true) => {
+                        bot.send_message(msg.chat.id, format!("🎫 Removed tracker #{}.", id))
+                            .retried()
+                            .await?;
+                    }
+                    Ok(false) => {
+                        return reply_error(&bot, &msg, ttl, format!("No tracker #{} here.", id))
+                            .await;
+                    }
+                    Err(e) => {
+                        error!("Failed to remove ticket tracker: {}", e);
+                        return reply_error(
+                            &bot,
+                            &msg,
+                            ttl,
+                            "Couldn't remove the tracker, try again.",
+                        )
+                        .await;
+                    }
                 }
-                let new_text = generate_message_text(&data);
-                bot.edit_message_text(msg.chat.id, parent_id, new_text)
-                    .parse_mode(ParseMode::Html)
-                    .link_preview_options(LinkPreviewOptions {
-                        is_disabled: true,
-                        url: None,
-                        prefer_small_media: false,
-                        prefer_large_media: false,
-                        show_above_text: false,
-                    })
-                    .await?;
             }
+            Some(_) => return reply_error(&bot, &msg, ttl, usage).await,
         }
+        return Ok(());
     }
 
-    // "parse messages from other parties and if it is a link replace with your message"
-    // Check if message contains a PR link
-    if let Some((owner, repo, pr_number)) = extract_pr_info(&text) {
-        // If message is from bot, ignore (should allow loop prevention)
-        if let Some(user) = msg.from {
-            if user.is_bot {
-                // assume it's us or another bot, maybe we shouldn't replace it if it's us?
-                // But `handle_message` usually doesn't trigger for own messages unless configured.
-            } else {
-                match github.get_pr_details(&owner, &repo, pr_number).await {
-                    Ok(pr) => {
-                        // Delete user message
-                        bot.delete_message(msg.chat.id, msg.id).await?;
-
-                        let pr_data = PrData {
-                            pr_url: pr.html_url.map(|u| u.to_string()).unwrap_or_default(),
-                            title: pr.title.unwrap_or_default(),
-                            author: pr.user.map(|u| u.login).unwrap_or("unknown".to_string()),
-                            repo: format!("{}/{}", owner, repo),
-                            pr_number,
-                            reviewers: vec![],
-                            approvals: vec![],
-                            changes_requested: vec![],
-                            comments: vec![],
-                            is_merged: pr.merged_at.is_some(),
-                            is_draft: pr.draft.unwrap_or(false),
-                            re_review_requested: false,
-                            chat_id: msg.chat.id.0,
+    if text.starts_with("/templates") {
+        let chat_id = msg.chat.id.0;
+        let usage = "Usage: /templates, /templates set <name> <text>, /templates off <name> or /templates reset <name>";
+        // The body keeps its line breaks
+        let mut parts = text.splitn(4, char::is_whitespace);
+        let action = parts.nth(1);
+        let name = parts.next().unwrap_or_default();
+        let body = parts.next().unwrap_or_default().trim();
+        match action {
+            None => {
+                let own = state.get_reply_templates(chat_id).await.unwrap_or_default();
+                let lines: Vec<String> = templates::bodies(&state, chat_id)
+                    .await
+                    .into_iter()
+                    .map(|(name, body)| {
+                        let origin = if own.iter().any(|(n, _)| n == name) {
+                            "this chat's"
+                        } else {
+                            "default"
                         };
+                        if body.is_empty() {
+                            format!("/{} ({}): marks the card only", name, origin)
+                        } else {
+                            format!("/{} ({}): {}", name, origin, body)
+                        }
+                    })
+                    .collect();
+                bot.send_message(
+                    msg.chat.id,
+                    format!(
+                        "📝 Reply templates. Replied to a card, each marks you as commenting and, once you've linked your GitHub account with /link, posts its text on the PR:\n\n{}",
+                        lines.join("\n")
+                    ),
+                )
+                .link_preview_options(no_link_preview())
+                .retried()
+                .await?;
+                return Ok(());
+            }
+            Some("set") | Some("off") | Some("reset") if !is_admin(&bot, &msg).await => {
+                return reply_error(&bot, &msg, ttl, "Only chat admins can change templates.")
+                    .await;
+            }
+            Some("set") | Some("off") | Some("reset") if templates::find(name).is_none() => {
+                let names: Vec<&str> = templates::TEMPLATES.iter().map(|(n, _)| *n).collect();
+                let reason = format!("There's no template {:?}; pick {}.", name, names.join(", "));
+                return reply_error(&bot, &msg, ttl, reason).await;
+            }
+            Some("set") if body.is_empty() => return reply_error(&bot, &msg, ttl, usage).await,
+            Some(action @ ("set" | "off")) => {
+                let body = if action == "set" { body } else { "" };
+                if let Err(e) = state.set_reply_template(chat_id, name, body).await {
+                    error!("Failed to save template: {}", e);
+                    return reply_error(&bot, &msg, ttl, "Couldn't save the template, try again.")
+                        .await;
+                }
+            }
+            Some("reset") => {
+                if let Err(e) = state.remove_reply_template(chat_id, name).await {
+                    error!("Failed to reset template: {}", e);
+                    return reply_error(&bot, &msg, ttl, "Couldn't reset the template, try again.")
+                        .await;
+                }
+            }
+            Some(_) => return reply_error(&bot, &msg, ttl, usage).await,
+        }
+        let reply = match action {
+            Some("set") => format!("📝 /{} now posts:\n{}", name, body),
+            Some("off") => format!("📝 /{} now only marks the card.", name),
+            _ => format!("📝 /{} posts the default text again.", name),
+        };
+        bot.send_message(msg.chat.id, reply)
+            .link_preview_options(no_link_preview())
+            .retried()
+            .await?;
+        return Ok(());
+    }
+
+    if text.starts_with("/search") {
+        let args = text.split_whitespace().skip(1).collect::<Vec<_>>();
+        let chat_id = msg.chat.id.0;
+        match args.first().copied() {
+            None => {
+                let searches = state.get_searches(chat_id).await.unwrap_or_default();
+                let reply = if searches.is_empty() {
+                    "No searches in this chat. Add one with /search add org:acme label:\"help wanted\"".to_string()
+                } else {
+                    let lines: Vec<String> = searches
+                        .iter()
+                        .map(|s| format!("#{} {} (by {})", s.id, s.query, s.created_by))
+                        .collect();
+                    format!("🔎 Searches:\n{}", lines.join("\n"))
+                };
+                bot.send_message(msg.chat.id, reply).retried().await?;
+            }
+            Some("add") | Some("remove") if !is_admin(&bot, &msg).await => {
+                return reply_error(&bot, &msg, ttl, "Only chat admins can change searches.").await;
+            }
+            Some("add") => {
+                let query = args[1..].join(" ");
+                if query.is_empty() {
+                    return reply_error(
+                        &bot,
+                        &msg,
+                        ttl,
+                        "Usage: /search add <GitHub search query>",
+                    )
+                    .await;
+                }
+                // Running it once checks the query and marks what it finds today as seen, so
+                // only PRs that start matching later are announced
+                let current = match github.search_prs(&query).await {
+                    Ok(current) => current,
+                    Err(e) => {
+                        let reason = format!(
+                            "GitHub rejected the search: {}.",
+                            github::describe_error(&e)
+                        );
+                        return reply_error(&bot, &msg, ttl, reason).await;
+                    }
+                };
+                for (owner, repo, number) in &current {
+                    state.mark_pr_seen(chat_id, owner, repo, *number).await.ok();
+                }
+                let username = msg
+                    .from
+                    .as_ref()
+                    .map(|u| u.username.clone().unwrap_or(u.first_name.clone()))
+                    .unwrap_or("unknown".to_string());
+                if let Err(e) = state.add_search(chat_id, &query, &username).await {
+                    error!("Failed to save search: {}", e);
+                    return reply_error(&bot, &msg, ttl, "Couldn't save the search, try again.")
+                        .await;
+                }
+                bot.send_message(
+                    msg.chat.id,
+                    format!(
+                        "🔎 Added search: {}\nIt matches {} open PR(s) now; I'll announce the ones that match from here on.",
+                        query,
+                        current.len()
+                    ),
+                )
+                .retried()
+                .await?;
+            }
+            Some("remove") => {
+                let Some(id) = args
+                    .get(1)
+                    .and_then(|a| a.trim_start_matches('#').parse::<i64>().ok())
+                else {
+                    return reply_error(&bot, &msg, ttl, "Usage: /search remove <id>").await;
+                };
+                match state.remove_search(chat_id, id).await {
+                    Ok(true) => {
+                        bot.send_message(msg.chat.id, format!("🔎 Removed search #{}.", id))
+                            .retried()
+                            .await?;
+                    }
+                    Ok(false) => {
+                        return reply_error(&bot, &msg, ttl, format!("No search #{} here.", id))
+                            .await;
+                    }
+                    Err(e) => {
+                        error!("Failed to remove search: {}", e);
+                        return reply_error(
+                            &bot,
+                            &msg,
+                            ttl,
+                            "Couldn't remove the search, try again.",
+                        )
+                        .await;
+                    }
+                }
+            }
+            Some(_) => {
+                return reply_error(
+                    &bot,
+                    &msg,
+                    ttl,
+                    "Usage: /search, /search add <query> or /search remove <id>",
+                )
+                .await;
+            }
+        }
+        return Ok(());
+    }
+
+    if text.starts_with("/gates") {
+        let Some((owner, repo)) = parse_repo_arg(&text, &monitor.config().github_web_url) else {
+            bot.send_message(
+                msg.chat.id,
+                "Usage: /gates owner/repo [check check! ...|clear] (! marks a critical check)",
+            )
+            .retried()
+            .await?;
+            return Ok(());
+        };
+        let args: Vec<&str> = text.split_whitespace().skip(2).collect();
+
+        if !args.is_empty() {
+            if !is_admin(&bot, &msg).await {
+                return reply_error(&bot, &msg, ttl, "Only chat admins can change check gates.")
+                    .await;
+            }
+            let gates = if args == ["clear"] {
+                Vec::new()
+            } else {
+                checks::parse_gates(&args)
+            };
+            if let Err(e) = state.set_check_gates(&owner, &repo, &gates).await {
+                error!("Failed to save check gates for {}/{}: {}", owner, repo, e);
+                return reply_error(&bot, &msg, ttl, "Couldn't save the check gates, try again.")
+                    .await;
+            }
+        }
+
+        let gates = state
+            .get_check_gates(&owner, &repo)
+            .await
+            .unwrap_or_default();
+        let reply = if gates.is_empty() {
+            format!("No check gates configured for {}/{}.", owner, repo)
+        } else {
+            let names: Vec<String> = gates
+                .iter()
+                .map(|g| {
+                    if g.critical != 0 {
+                        format!("{} (critical)", g.check_name)
+                    } else {
+                        g.check_name.clone()
+                    }
+                })
+                .collect();
+            format!(
+                "🚦 Check gates for {}/{}: {}",
+                owner,
+                repo,
+                names.join(", ")
+            )
+        };
+        bot.send_message(msg.chat.id, reply).retried().await?;
+        return Ok(());
+    }
+
+    if text.starts_with("/pool") {
+        let usage = "Usage: /pool owner/repo [add|remove @user ...]";
+        let Some((owner, repo)) = parse_repo_arg(&text, &monitor.config().github_web_url) else {
+            bot.send_message(msg.chat.id, usage).retried().await?;
+            return Ok(());
+        };
+        let chat_id = msg.chat.id.0;
+        let args: Vec<&str> = text.split_whitespace().skip(2).collect();
+
+        if let Some(action) = args.first() {
+            let names: Vec<&str> = args[1..]
+                .iter()
+                .map(|a| a.trim_start_matches('@'))
+                .filter(|a| !a.is_empty())
+                .collect();
+            if !matches!(*action, "add" | "remove") || names.is_empty() {
+                return reply_error(&bot, &msg, ttl, usage).await;
+            }
+            if !is_admin(&bot, &msg).await {
+                return reply_error(
+                    &bot,
+                    &msg,
+                    ttl,
+                    "Only chat admins can change reviewer pools.",
+                )
+                .await;
+            }
+            for name in names {
+                let result = if *action == "add" {
+                    state.add_pool_member(chat_id, &owner, &repo, name).await
+                } else {
+                    state
+                        .remove_pool_member(chat_id, &owner, &repo, name)
+                        .await
+                        .map(|_| ())
+                };
+                if let Err(e) = result {
+                    error!(
+                        "Failed to update reviewer pool of {}/{}: {}",
+                        owner, repo, e
+                    );
+                    return reply_error(
+                        &bot,
+                        &msg,
+                        ttl,
+                        "Couldn't update the reviewer pool, try again.",
+                    )
+                    .await;
+                }
+            }
+        }
+
+        let mut pool = state
+            .get_reviewer_pool(chat_id, &owner, &repo)
+            .await
+            .unwrap_or_default();
+        let reply = if pool.is_empty() {
+            format!(
+                "No reviewer pool for {}/{}. Admins can add one: /pool {}/{} add @alice @bob",
+                owner, repo, owner, repo
+            )
+        } else {
+            pool.sort_by_key(|m| m.rank());
+            let lines: Vec<String> = pool
+                .iter()
+                .map(|m| {
+                    let last = m
+                        .last_assigned
+                        .and_then(|t| chrono::DateTime::from_timestamp(t, 0))
+                        .map(|t| format!("last assigned {}", t.format("%Y-%m-%d")))
+                        .unwrap_or("not assigned yet".to_string());
+                    format!(
+                        "• @{} — {} this week, reviewing {}, {}",
+                        m.username, m.recent_assignments, m.open_reviews, last
+                    )
+                })
+                .collect();
+            format!(
+                "🎯 Reviewer pool for {}/{}, next up first:\n{}",
+                owner,
+                repo,
+                lines.join("\n")
+            )
+        };
+        bot.send_message(msg.chat.id, reply).retried().await?;
+        return Ok(());
+    }
+
+    if text.starts_with("/lead") {
+        let chat_id = msg.chat.id.0;
+        let action = text.split_whitespace().nth(1);
+        if let Some(action) = action {
+            if !matches!(action, "add" | "remove") {
+                return reply_error(&bot, &msg, ttl, "Usage: /lead [add|remove]").await;
+            }
+            if !is_admin(&bot, &msg).await {
+                return reply_error(&bot, &msg, ttl, "Only chat admins can change the leads.")
+                    .await;
+            }
+            // Replying to someone's message names them; otherwise it's the sender
+            let Some(user) = msg
+                .reply_to_message()
+                .and_then(|m| m.from.as_ref())
+                .or(msg.from.as_ref())
+            else {
+                return Ok(());
+            };
+            let name = user.username.clone().unwrap_or(user.first_name.clone());
+            let result = if action == "add" {
+                state
+                    .add_lead(&Lead {
+                        chat_id,
+                        user_id: user.id.0 as i64,
+                        name: name.clone(),
+                    })
+                    .await
+            } else {
+                state
+                    .remove_lead(chat_id, user.id.0 as i64)
+                    .await
+                    .map(|_| ())
+            };
+            if let Err(e) = result {
+                error!("Failed to update the leads of {}: {}", chat_id, e);
+                return reply_error(&bot, &msg, ttl, "Couldn't update the leads, try again.").await;
+            }
+            let reply = if action == "add" {
+                format!(
+                    "📥 {} is now a lead here. Leads get a morning DM triaging this chat's PRs; start a private chat with me so I can send it.",
+                    name
+                )
+            } else {
+                format!("{} is no longer a lead here.", name)
+            };
+            bot.send_message(msg.chat.id, reply).retried().await?;
+            return Ok(());
+        }
+
+        let leads = state.get_leads(chat_id).await.unwrap_or_default();
+        let reply = if leads.is_empty() {
+            "No leads in this chat. Admins can reply /lead add to a member's message to make them one.".to_string()
+        } else {
+            format!(
+                "📥 Leads: {}",
+                leads
+                    .iter()
+                    .map(|l| l.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        };
+        bot.send_message(msg.chat.id, reply).retried().await?;
+        return Ok(());
+    }
+
+    if text.starts_with("/unlink") || text.starts_with("/link") {
+        let Some(user) = msg.from.as_ref() else {
+            return Ok(());
+        };
+        let telegram_id = user.id.0 as i64;
+        let name = user.username.clone().unwrap_or(user.first_name.clone());
+
+        if text.starts_with("/unlink") {
+            let reply = match state.unlink_user(telegram_id).await {
+                Ok(true) => {
+                    tenants.forget_unlinked_token(telegram_id).await;
+                    "🔗 Unlinked your GitHub account."
+                }
+                Ok(false) => "Your Telegram account isn't linked to GitHub.",
+                Err(e) => {
+                    error!("Failed to unlink user: {}", e);
+                    return reply_error(&bot, &msg, ttl, "Couldn't unlink, try again.").await;
+                }
+            };
+            bot.send_message(msg.chat.id, reply).retried().await?;
+            return Ok(());
+        }
+
+        let Some(login) = text
+            .split_whitespace()
+            .nth(1)
+            .map(|l| l.trim_start_matches('@'))
+        else {
+            let reply = match state.get_user_link(telegram_id).await {
+                Ok(Some(link)) => format!("🔗 You're linked to GitHub user {}.", link.github_login),
+                Ok(None) => "You aren't linked to GitHub yet: /link github-login".to_string(),
+                Err(e) => {
+                    error!("Failed to load user link: {}", e);
+                    return reply_error(&bot, &msg, ttl, "Couldn't look that up, try again.").await;
+                }
+            };
+            bot.send_message(msg.chat.id, reply).retried().await?;
+            return Ok(());
+        };
+
+        let login = match github.get_login(login).await {
+            Ok(login) => login,
+            Err(e) => {
+                let reason = format!(
+                    "Couldn't find GitHub user {}: {}.",
+                    login,
+                    github::describe_error(&e)
+                );
+                return reply_error(&bot, &msg, ttl, reason).await;
+            }
+        };
+        if let Ok(Some(other)) = state.get_user_by_github(&login).await {
+            if other.telegram_id != telegram_id {
+                let reason = format!("{} is already linked to another Telegram user.", login);
+                return reply_error(&bot, &msg, ttl, reason).await;
+            }
+        }
+
+        let link = UserLink {
+            telegram_id,
+            telegram_name: name.clone(),
+            telegram_username: user.username.clone(),
+            github_login: login.clone(),
+            linked_at: chrono::Utc::now().timestamp(),
+        };
+        if let Err(e) = state.link_user(&link).await {
+            error!("Failed to link user: {}", e);
+            return reply_error(&bot, &msg, ttl, "Couldn't link, try again.").await;
+        }
+        state.audit(format!("linked {} to GitHub user {}", name, login));
+        tenants.forget_unlinked_token(telegram_id).await;
+        bot.send_message(
+            msg.chat.id,
+            format!(
+                "🔗 Linked you to GitHub user {}. Their reviews now show under your name.",
+                login
+            ),
+        )
+        .retried()
+        .await?;
+        return Ok(());
+    }
+
+    if text.starts_with("/token") {
+        let Some(user) = msg.from.as_ref() else {
+            return Ok(());
+        };
+        let telegram_id = user.id.0 as i64;
+        let argument = text.split_whitespace().nth(1);
+        // Tokens belong to the user, not to a chat, so they're kept in the operator's database
+        let operator = tenants.operator();
+        let Some(key) = operator.config().user_token_key else {
+            return reply_error(
+                &bot,
+                &msg,
+                ttl,
+                "Reviews from Telegram are off: the bot's operator hasn't set GITHUB_USER_TOKEN_KEY.",
+            )
+            .await;
+        };
+
+        let reply = match argument {
+            None => match operator.state.get_github_token(telegram_id).await {
+                Ok(Some((login, _))) => format!("🔑 /approve and /changes submit your review on GitHub with your token for {}. /token off forgets it.", login),
+                Ok(None) => "I have no GitHub token of yours: /approve and /changes only mark the card. Send me /token followed by a personal access token in a private message to submit your reviews on GitHub too.".to_string(),
+                Err(e) => {
+                    error!("Failed to load GitHub token: {}", e);
+                    return reply_error(&bot, &msg, ttl, "Couldn't look that up, try again.").await;
+                }
+            },
+            Some("off") => match operator.state.remove_github_token(telegram_id).await {
+                Ok(_) => {
+                    operator
+                        .state
+                        .audit(format!("forgot the GitHub token of {}", telegram_id));
+                    "🔑 Forgot your GitHub token. /approve and /changes only mark the card now."
+                        .to_string()
+                }
+                Err(e) => {
+                    error!("Failed to remove GitHub token: {}", e);
+                    return reply_error(&bot, &msg, ttl, "Couldn't forget it, try again.").await;
+                }
+            },
+            Some(token) => {
+                if !msg.chat.is_private() {
+                    // Everybody in the chat has seen it; take it out of their sight at least
+                    bot.delete_message(msg.chat.id, msg.id).retried().await.ok();
+                    bot.send_message(
+                        msg.chat.id,
+                        "🔑 Never post a token in a group: revoke it on GitHub now, then send the new one to me in a private message.",
+                    )
+                    .retried()
+                    .await?;
+                    return Ok(());
+                }
+                // A private chat is the operator's, but the user may have linked in a tenant's
+                let links = match tenants.user_links(telegram_id).await {
+                    Ok(links) if links.is_empty() => {
+                        return reply_error(
+                            &bot,
+                            &msg,
+                            ttl,
+                            "Link your GitHub account with /link first.",
+                        )
+                        .await
+                    }
+                    Ok(links) => links,
+                    Err(e) => {
+                        error!("Failed to load user links: {}", e);
+                        return reply_error(&bot, &msg, ttl, "Couldn't look that up, try again.")
+                            .await;
+                    }
+                };
+                let login = match github.get_token_login(token).await {
+                    Ok(login) => login,
+                    Err(e) => {
+                        warn!("Failed to check a GitHub token: {}", e);
+                        return reply_error(&bot, &msg, ttl, "GitHub didn't accept that token.")
+                            .await;
+                    }
+                };
+                if !links
+                    .iter()
+                    .any(|l| l.github_login.eq_ignore_ascii_case(&login))
+                {
+                    let linked: Vec<&str> =
+                        links.iter().map(|l| l.github_login.as_str()).collect();
+                    let reason = format!(
+                        "That token belongs to {}, but you're linked to {}.",
+                        login,
+                        linked.join(" and ")
+                    );
+                    return reply_error(&bot, &msg, ttl, reason).await;
+                }
+                let sealed = match tokens::seal(&key, telegram_id, token) {
+                    Ok(sealed) => sealed,
+                    Err(e) => {
+                        error!("Failed to encrypt GitHub token: {}", e);
+                        return reply_error(&bot, &msg, ttl, "Couldn't save it, try again.").await;
+                    }
+                };
+                if let Err(e) = operator
+                    .state
+                    .set_github_token(telegram_id, &login, &sealed)
+                    .await
+                {
+                    error!("Failed to save GitHub token: {}", e);
+                    return reply_error(&bot, &msg, ttl, "Couldn't save it, try again.").await;
+                }
+                // The token stays out of the chat history
+                bot.delete_message(msg.chat.id, msg.id).retried().await.ok();
+                operator
+                    .state
+                    .audit(format!("stored the GitHub token of {}", login));
+                format!(
+                    "🔑 Saved your token for {}, encrypted. /approve and /changes now submit your review on GitHub too.\n\nWhoever holds both the bot's database and its key could act with it, so give it no more than pull request write access to the repositories you review. /token off forgets it.",
+                    login
+                )
+            }
+        };
+        bot.send_message(msg.chat.id, reply).retried().await?;
+        return Ok(());
+    }
+
+    if text.starts_with("/verify") {
+        if !msg.chat.is_private() {
+            // Posting the code where it was posted proves nothing
+            return reply_error(
+                &bot,
+                &msg,
+                ttl,
+                "Send /verify CODE to me in a private message.",
+            )
+            .await;
+        }
+        let Some(code) = text.split_whitespace().nth(1) else {
+            return reply_error(&bot, &msg, ttl, "Usage: /verify CODE").await;
+        };
+        let Some(user) = msg.from.as_ref() else {
+            return Ok(());
+        };
+        let waiting = match tenants.find_verification_code(code).await {
+            Ok(waiting) if waiting.is_empty() => {
+                return reply_error(&bot, &msg, ttl, "No chat is waiting for that code.").await;
+            }
+            Ok(waiting) => waiting,
+            Err(e) => {
+                error!("Failed to look up verification code: {}", e);
+                return reply_error(&bot, &msg, ttl, "Couldn't check the code, try again.").await;
+            }
+        };
+        // Two tenants may have posted the same code; it's for the chat the sender runs
+        let mut admin_of = None;
+        for (monitor, chat_id) in waiting {
+            match bot.get_chat_member(ChatId(chat_id), user.id).timed().await {
+                Ok(member) if member.is_privileged() => {
+                    admin_of = Some((monitor, chat_id));
+                    break;
+                }
+                Ok(_) => {}
+                Err(e) => error!("Failed to check admin status in {}: {}", chat_id, e),
+            }
+        }
+        let Some((monitor, chat_id)) = admin_of else {
+            return reply_error(&bot, &msg, ttl, "Only an admin of that chat can verify it.").await;
+        };
+        let name = user
+            .username
+            .as_ref()
+            .map(|u| format!("@{}", u))
+            .unwrap_or(user.first_name.clone());
+        verification::confirm(monitor, chat_id, &name).await;
+        bot.send_message(
+            msg.chat.id,
+            "✅ Verified, new pull requests will be announced in that chat.",
+        )
+        .retried()
+        .await?;
+        return Ok(());
+    }
+
+    if text.starts_with("/forgetme") {
+        let username = msg
+            .from
+            .as_ref()
+            .map(|u| u.username.clone().unwrap_or(u.first_name.clone()))
+            .unwrap_or("unknown".to_string());
+
+        let cards = match state.forget_user(&username).await {
+            Ok(cards) => cards,
+            Err(e) => {
+                error!("Failed to erase user data: {}", e);
+                return reply_error(&bot, &msg, ttl, "Couldn't erase your data, try again.").await;
+            }
+        };
+        if let Some(user) = msg.from.as_ref() {
+            tenants.forget_unlinked_token(user.id.0 as i64).await;
+        }
+
+        // Re-render the cards that still showed their marks
+        for (message_id, chat_id) in &cards {
+            if let Ok(Some(data)) = state.get_pr_data(message_id.clone(), *chat_id).await {
+                edit_card(
+                    &bot,
+                    &state,
+                    ChatId(*chat_id),
+                    MessageId(message_id.parse().unwrap_or(0)),
+                    &data,
+                )
+                .await
+                .ok();
+                state.audit(format!(
+                    "edited {}: /forgetme",
+                    audit::card(&data.repo, data.pr_number, *chat_id)
+                ));
+            }
+        }
+
+        bot.send_message(
+            msg.chat.id,
+            format!(
+                "🧹 Done: removed your marks from {} card(s). Your past activity is now attributed to a former member.",
+                cards.len()
+            ),
+        )
+        .retried().await?;
+        return Ok(());
+    }
+
+    if text.starts_with("/wipechat") {
+        if !is_admin(&bot, &msg).await {
+            return reply_error(
+                &bot,
+                &msg,
+                ttl,
+                "Only chat admins can wipe the chat's data.",
+            )
+            .await;
+        }
+        if text.split_whitespace().nth(1) != Some("confirm") {
+            bot.send_message(
+                msg.chat.id,
+                "⚠️ This permanently deletes everything I store for this chat: tracked cards, review marks, history, groups and repository subscriptions. Cards already posted stay in the chat but stop updating.\n\nSend /wipechat confirm to proceed.",
+            )
+            .retried().await?;
+            return Ok(());
+        }
+
+        let reply = match state.wipe_chat(msg.chat.id.0).await {
+            Ok(cards) => format!("🗑 Wiped this chat's data ({} card(s)).", cards),
+            Err(e) => {
+                error!("Failed to wipe chat {}: {}", msg.chat.id, e);
+                return reply_error(&bot, &msg, ttl, "Couldn't wipe the chat's data, try again.")
+                    .await;
+            }
+        };
+        bot.send_message(msg.chat.id, reply).retried().await?;
+        return Ok(());
+    }
+
+    if text.starts_with("/reload") {
+        // The bot's config isn't per chat, so only the default chat's admins may reload it,
+        // unless ADMIN_USER_IDS names who may
+        if !monitor.config().admin_user_ids.is_empty() {
+            if !may_manage_repos(&monitor, msg.from.as_ref()) {
+                return reply_error(
+                    &bot,
+                    &msg,
+                    ttl,
+                    "Sorry, only the bot's administrators can reload the configuration.",
+                )
+                .await;
+            }
+        } else if msg.chat.id.0 != monitor.config().chat_id || !is_admin(&bot, &msg).await {
+            return reply_error(
+                &bot,
+                &msg,
+                ttl,
+                "Only admins of the main chat can reload the configuration.",
+            )
+            .await;
+        }
+        let reply = match monitor.reload_config().await {
+            Ok(restart_needed) if restart_needed.is_empty() => {
+                "🔄 Configuration reloaded.".to_string()
+            }
+            Ok(restart_needed) => format!(
+                "🔄 Configuration reloaded. Restart the bot to apply: {}",
+                restart_needed.join(", ")
+            ),
+            Err(e) => {
+                error!("Failed to reload configuration: {}", e);
+                return reply_error(
+                    &bot,
+                    &msg,
+                    ttl,
+                    format!("Couldn't reload the configuration: {}", e),
+                )
+                .await;
+            }
+        };
+        bot.send_message(msg.chat.id, reply).retried().await?;
+        return Ok(());
+    }
+
+    if text.starts_with("/routes") {
+        // Other chats' repositories are only the bot's administrators' business
+        let allowed = if monitor.config().admin_user_ids.is_empty() {
+            msg.chat.id.0 == monitor.config().chat_id && is_admin(&bot, &msg).await
+        } else {
+            may_manage_repos(&monitor, msg.from.as_ref())
+        };
+        if !allowed {
+            return reply_error(
+                &bot,
+                &msg,
+                ttl,
+                "Only the bot's administrators can see where repositories are announced.",
+            )
+            .await;
+        }
+        let subscriptions = state.get_subscriptions().await.unwrap_or_default();
+        let reply = if subscriptions.is_empty() {
+            "No repository is tracked in any chat.".to_string()
+        } else {
+            let lines: Vec<String> = subscriptions
+                .iter()
+                .map(|((owner, repo), chats)| {
+                    let chats: Vec<String> = chats
+                        .iter()
+                        .map(|&chat_id| {
+                            if chat_id == msg.chat.id.0 {
+                                format!("{} (here)", chat_id)
+                            } else {
+                                chat_id.to_string()
+                            }
+                        })
+                        .collect();
+                    format!("{}/{} → {}", owner, repo, chats.join(", "))
+                })
+                .collect();
+            format!("🧭 Where new PRs are announced:\n\n{}", lines.join("\n"))
+        };
+        bot.send_message(msg.chat.id, reply).retried().await?;
+        return Ok(());
+    }
+
+    if text.starts_with("/list") || text.starts_with("/find") {
+        let find = text.starts_with("/find");
+        let chat_id = msg.chat.id.0;
+        let Some(user) = msg.from.as_ref() else {
+            return Ok(());
+        };
+        let user_id = user.id.0 as i64;
+        let args: Vec<&str> = text.split_whitespace().skip(1).collect();
+        let saved = state
+            .get_filters(chat_id, user_id)
+            .await
+            .unwrap_or_default();
+
+        let query = match args.as_slice() {
+            [] if find => {
+                return reply_error(
+                    &bot,
+                    &msg,
+                    ttl,
+                    "Usage: /find author:me status:in_review words from the title",
+                )
+                .await;
+            }
+            [] => None,
+            ["save", name, terms @ ..] if !find => {
+                if matches!(*name, "save" | "forget" | "filters")
+                    || name.contains(':')
+                    || name.starts_with('-')
+                {
+                    return reply_error(&bot, &msg, ttl, "Pick another name for the filter.").await;
+                }
+                let query = match Query::parse(&terms.join(" ")) {
+                    Ok(query) => query,
+                    Err(e) => {
+                        let reply = format!("Couldn't read the filter: {}", e);
+                        return reply_error(&bot, &msg, ttl, &reply).await;
+                    }
+                };
+                if let Err(e) = state
+                    .save_filter(chat_id, user_id, name, &query.to_string())
+                    .await
+                {
+                    error!("Failed to save a filter in {}: {}", chat_id, e);
+                    return reply_error(&bot, &msg, ttl, "Couldn't save the filter, try again.")
+                        .await;
+                }
+                let reply = format!(
+                    "💾 Saved \"{}\": {}\nRun /list {} to use it.",
+                    name, query, name
+                );
+                bot.send_message(msg.chat.id, reply).retried().await?;
+                return Ok(());
+            }
+            ["forget", name] if !find => {
+                let reply = match state.remove_filter(chat_id, user_id, name).await {
+                    Ok(true) => format!("Forgot the filter \"{}\".", name),
+                    Ok(false) => format!("You have no filter called \"{}\".", name),
+                    Err(e) => {
+                        error!("Failed to remove a filter in {}: {}", chat_id, e);
+                        return reply_error(
+                            &bot,
+                            &msg,
+                            ttl,
+                            "Couldn't remove the filter, try again.",
+                        )
+                        .await;
+                    }
+                };
+                bot.send_message(msg.chat.id, reply).retried().await?;
+                return Ok(());
+            }
+            ["filters"] if !find => {
+                let reply = if saved.is_empty() {
+                    "You have no saved filters here. Save one with /list save mine author:me"
+                        .to_string()
+                } else {
+                    let lines: Vec<String> = saved
+                        .iter()
+                        .map(|(name, query)| format!("• {} — {}", name, query))
+                        .collect();
+                    format!("🔎 Your filters:\n{}", lines.join("\n"))
+                };
+                bot.send_message(msg.chat.id, reply).retried().await?;
+                return Ok(());
+            }
+            [name] if saved.iter().any(|(n, _)| n == name) => saved
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, query)| query.clone()),
+            _ => Some(args.join(" ")),
+        };
+
+        let reply = match query {
+            None => generate_list_text(&state, chat_id).await,
+            Some(query) => match Query::parse(&query) {
+                Ok(query) => {
+                    let viewer = viewer(&state, user).await;
+                    filtered_list_text(&state, chat_id, &query, &viewer, find).await
+                }
+                Err(e) => {
+                    let reply = format!("Couldn't read the filter: {}", e);
+                    return reply_error(&bot, &msg, ttl, &reply).await;
+                }
+            },
+        };
+        bot.send_message(msg.chat.id, reply)
+            .parse_mode(ParseMode::Html)
+            .link_preview_options(no_link_preview())
+            .retried()
+            .await?;
+        return Ok(());
+    }
+
+    if text.starts_with("/dashboard") {
+        if !is_admin(&bot, &msg).await {
+            return reply_error(
+                &bot,
+                &msg,
+                ttl,
+                "Only chat admins can manage the dashboard.",
+            )
+            .await;
+        }
+        let chat_id = msg.chat.id;
+        // A new dashboard replaces the old one, so there's only ever one pinned
+        if let Ok(Some(old)) = state.get_dashboard(chat_id.0).await {
+            let old = MessageId(old.parse().unwrap_or(0));
+            bot.unpin_chat_message(chat_id)
+                .message_id(old)
+                .timed()
+                .await
+                .ok();
+            bot.delete_message(chat_id, old).retried().await.ok();
+            state.set_dashboard(chat_id.0, None).await.ok();
+        }
+        if text.split_whitespace().nth(1) == Some("off") {
+            state.audit(format!("removed dashboard in {}", chat_id));
+            bot.send_message(chat_id, "📌 Dashboard removed.")
+                .retried()
+                .await?;
+            return Ok(());
+        }
+
+        let sent = bot
+            .send_message(chat_id, dashboard::dashboard_text(&state, chat_id.0).await)
+            .parse_mode(ParseMode::Html)
+            .link_preview_options(no_link_preview())
+            .retried()
+            .await?;
+        if let Err(e) = state
+            .set_dashboard(chat_id.0, Some(&sent.id.0.to_string()))
+            .await
+        {
+            error!("Failed to save dashboard: {}", e);
+            return reply_error(&bot, &msg, ttl, "Couldn't save the dashboard, try again.").await;
+        }
+        state.audit(format!("created dashboard in {}", chat_id));
+        if let Err(e) = bot
+            .pin_chat_message(chat_id, sent.id)
+            .disable_notification(true)
+            .timed()
+            .await
+        {
+            error!("Failed to pin dashboard in {}: {}", chat_id, e);
+            return reply_error(
+                &bot,
+                &msg,
+                ttl,
+                "The dashboard is up, but I couldn't pin it. Give me the right to pin messages, or pin it yourself.",
+            )
+            .await;
+        }
+        return Ok(());
+    }
+
+    if text.starts_with("/buttons") {
+        let mode = match text.split_whitespace().nth(1) {
+            Some("on") => ButtonMode::Chosen,
+            Some("off") => ButtonMode::Reactions,
+            _ => {
+                let current = state
+                    .get_button_mode(msg.chat.id.0)
+                    .await
+                    .unwrap_or(ButtonMode::Reactions);
+                let reply = match current {
+                    ButtonMode::Reactions => "Cards take reactions. Use /buttons on to show buttons instead.",
+                    ButtonMode::Fallback => "Cards show buttons because reactions don't reach me here. Use /buttons on to keep them.",
+                    ButtonMode::Chosen => "Cards show buttons. Use /buttons off to go back to reactions.",
+                };
+                bot.send_message(msg.chat.id, reply).retried().await?;
+                return Ok(());
+            }
+        };
+        if !is_admin(&bot, &msg).await {
+            return reply_error(&bot, &msg, ttl, "Only chat admins can switch card buttons.").await;
+        }
+        if let Err(e) = state.set_button_mode(msg.chat.id.0, mode).await {
+            error!("Failed to save button mode: {}", e);
+            return reply_error(&bot, &msg, ttl, "Couldn't save the setting, try again.").await;
+        }
+        state.audit(format!("set button mode of {} to {:?}", msg.chat.id, mode));
+        monitor.refresh_card_buttons(msg.chat.id.0).await;
+        let reply = match mode {
+            ButtonMode::Chosen => "🔘 Cards now show Review / Approve / Comment / Re-review / Give up buttons.",
+            _ => "👍 Cards are back to reactions. If reactions can't reach me, buttons come back on their own.",
+        };
+        bot.send_message(msg.chat.id, reply).retried().await?;
+        return Ok(());
+    }
+
+    if text.starts_with("/nudges") {
+        let budget = match text.split_whitespace().nth(1) {
+            Some("off") => Some(0),
+            Some("default") => None,
+            Some(arg) => match arg.parse::<u32>() {
+                Ok(n) if n > 0 => Some(n),
+                _ => return reply_error(&bot, &msg, ttl, "Usage: /nudges 3|off|default").await,
+            },
+            None => {
+                let reply = match nudges::budget(&monitor, msg.chat.id.0).await {
+                    0 => "Reminders mention people as often as they come. Use /nudges 3 to cap them at 3 per person a day.".to_string(),
+                    n => format!(
+                        "Each person is mentioned in up to {} reminders a day here; the rest are listed in one summary after midnight (UTC). Use /nudges N to change it or /nudges off to lift the cap.",
+                        n
+                    ),
+                };
+                bot.send_message(msg.chat.id, reply).retried().await?;
+                return Ok(());
+            }
+        };
+        if !is_admin(&bot, &msg).await {
+            return reply_error(
+                &bot,
+                &msg,
+                ttl,
+                "Only chat admins can change the nudge budget.",
+            )
+            .await;
+        }
+        if let Err(e) = state.set_nudge_budget(msg.chat.id.0, budget).await {
+            error!("Failed to save nudge budget: {}", e);
+            return reply_error(&bot, &msg, ttl, "Couldn't save the setting, try again.").await;
+        }
+        state.audit(format!(
+            "set nudge budget of {} to {:?}",
+            msg.chat.id, budget
+        ));
+        let reply = match nudges::budget(&monitor, msg.chat.id.0).await {
+            0 => "🔔 Reminders mention people as often as they come.".to_string(),
+            n => format!(
+                "🔕 Each person is mentioned in up to {} reminders a day; the rest wait for the day's summary.",
+                n
+            ),
+        };
+        bot.send_message(msg.chat.id, reply).retried().await?;
+        return Ok(());
+    }
+
+    if text.starts_with("/onclose") {
+        let args: Vec<&str> = text.split_whitespace().skip(1).collect();
+        let policy = match args.as_slice() {
+            [] => {
+                let reply = match state.get_closed_cards(msg.chat.id.0).await {
+                    Ok(Some(policy)) => format!(
+                        "Cards here are {}. Use /onclose default to follow the configuration again.",
+                        describe_closed_cards(policy)
+                    ),
+                    _ => format!(
+                        "Cards here follow the configuration: they're {}, unless their repository sets its own. Use /onclose delete|archive|move <chat id>|keep <days> to choose for this chat.",
+                        describe_closed_cards(monitor.config().closed_cards)
+                    ),
+                };
+                bot.send_message(msg.chat.id, reply).retried().await?;
+                return Ok(());
+            }
+            ["default"] => None,
+            args => match ClosedCards::parse(&args.join(":")) {
+                Some(policy) => Some(policy),
+                None => {
+                    return reply_error(
+                        &bot,
+                        &msg,
+                        ttl,
+                        "Usage: /onclose delete|archive|move <chat id>|keep <days>|default",
+                    )
+                    .await;
+                }
+            },
+        };
+        if !is_admin(&bot, &msg).await {
+            return reply_error(
+                &bot,
+                &msg,
+                ttl,
+                "Only chat admins can change what happens to closed cards.",
+            )
+            .await;
+        }
+        // Summaries only go where the admin could post them too
+        if let (Some(ClosedCards::Move(archive_chat)), Some(user)) = (policy, &msg.from) {
+            let admin = match bot
+                .get_chat_member(ChatId(archive_chat), user.id)
+                .timed()
+                .await
+            {
+                Ok(member) => member.is_privileged(),
+                Err(e) => {
+                    error!("Failed to check admin status in {}: {}", archive_chat, e);
+                    false
+                }
+            };
+            if !admin {
+                return reply_error(
+                    &bot,
+                    &msg,
+                    ttl,
+                    "Only an admin of the archive chat can move cards there, and the bot must be in it.",
+                )
+                .await;
+            }
+        }
+        if let Err(e) = state.set_closed_cards(msg.chat.id.0, policy).await {
+            error!("Failed to save closed card policy: {}", e);
+            return reply_error(&bot, &msg, ttl, "Couldn't save the setting, try again.").await;
+        }
+        state.audit(format!(
+            "set closed card policy of {} to {}",
+            msg.chat.id,
+            policy.map_or("default".to_string(), |p| p.to_string())
+        ));
+        let reply = match policy {
+            Some(policy) => format!("🗂 Cards here are now {}.", describe_closed_cards(policy)),
+            None => "🗂 Cards here follow the configuration again.".to_string(),
+        };
+        bot.send_message(msg.chat.id, reply).retried().await?;
+        return Ok(());
+    }
+
+    if text.starts_with("/selfapproval") {
+        let allowed = match text.split_whitespace().nth(1) {
+            None => {
+                let reply = match state.get_self_approval(msg.chat.id.0).await {
+                    Ok(true) => "Authors may approve their own PRs here. Use /selfapproval off to stop counting those approvals.",
+                    _ => "Authors can't approve their own PRs here: their approvals aren't taken and don't count. Use /selfapproval on for solo-maintainer repositories.",
+                };
+                bot.send_message(msg.chat.id, reply).retried().await?;
+                return Ok(());
+            }
+            Some("on") => true,
+            Some("off") => false,
+            Some(_) => return reply_error(&bot, &msg, ttl, "Usage: /selfapproval [on|off]").await,
+        };
+        if !is_admin(&bot, &msg).await {
+            return reply_error(
+                &bot,
+                &msg,
+                ttl,
+                "Only chat admins can change whether authors may approve their own PRs.",
+            )
+            .await;
+        }
+        if let Err(e) = state.set_self_approval(msg.chat.id.0, allowed).await {
+            error!("Failed to save self-approval setting: {}", e);
+            return reply_error(&bot, &msg, ttl, "Couldn't save the setting, try again.").await;
+        }
+        state.audit(format!(
+            "set self-approval of {} to {}",
+            msg.chat.id,
+            if allowed { "on" } else { "off" }
+        ));
+        let reply = if allowed {
+            "🙋 Authors may now approve their own PRs here, and those approvals count."
+        } else {
+            "🙅 Authors can no longer approve their own PRs here, and approvals they already gave stop counting."
+        };
+        bot.send_message(msg.chat.id, reply).retried().await?;
+        return Ok(());
+    }
+
+    if text.starts_with("/stats") {
+        let window = match text.split_whitespace().nth(1) {
+            None => chrono::Duration::days(30),
+            Some(arg) => match bulk::parse_duration(arg).filter(|d| d.num_days() > 0) {
+                Some(window) => window,
+                None => {
+                    return reply_error(&bot, &msg, ttl, "Usage: /stats [30d]").await;
+                }
+            },
+        };
+        let since = (chrono::Utc::now() - window).timestamp();
+        let resolved: Vec<i64> = match state
+            .get_event_details(msg.chat.id.0, "conflict_resolved", since)
+            .await
+        {
+            Ok(details) => details.iter().filter_map(|d| d.parse().ok()).collect(),
+            Err(e) => {
+                error!("Failed to load conflict history: {}", e);
+                return reply_error(&bot, &msg, ttl, "Couldn't load the chat's history.").await;
+            }
+        };
+        let open = state
+            .get_chat_messages(msg.chat.id.0)
+            .await
+            .unwrap_or_default()
+            .iter()
+            .filter(|m| m.conflicted_since.is_some() && !PrStatus::parse(&m.status).is_terminal())
+            .count();
+
+        let activity = state
+            .get_activity_counts(msg.chat.id.0, since)
+            .await
+            .unwrap_or_default();
+        let count = |kind: &str| activity.get(kind).copied().unwrap_or(0);
+
+        let mut reply = format!(
+            "📊 <b>Last {} days</b>
+PRs opened: {}, reviewed: {}, approved: {}, merged: {}
+",
+            window.num_days(),
+            count("opened"),
+            count("reviewed"),
+            count("approved"),
+            count("merged")
+        );
+        if resolved.is_empty() {
+            reply.push_str(
+                "No merge conflicts resolved.
+",
+            );
+        } else {
+            let average = resolved.iter().sum::<i64>() / resolved.len() as i64;
+            reply.push_str(&format!(
+                "Merge conflicts resolved: {}, in {} on average (longest {})\n",
+                resolved.len(),
+                bulk::format_duration(average),
+                bulk::format_duration(resolved.iter().copied().max().unwrap_or(0))
+            ));
+        }
+        reply.push_str(&format!("PRs conflicted right now: {}", open));
+        bot.send_message(msg.chat.id, reply)
+            .parse_mode(ParseMode::Html)
+            .retried()
+            .await?;
+        return Ok(());
+    }
+
+    if text.starts_with("/drift") {
+        let window = match text.split_whitespace().nth(1) {
+            None => chrono::Duration::days(30),
+            Some(arg) => match bulk::parse_duration(arg).filter(|d| d.num_days() > 0) {
+                Some(window) => window,
+                None => {
+                    return reply_error(&bot, &msg, ttl, "Usage: /drift [30d]").await;
+                }
+            },
+        };
+        let since = (chrono::Utc::now() - window).timestamp();
+        let reply = drift::report_text(&state, msg.chat.id.0, since, window.num_days())
+            .await
+            .unwrap_or_else(|| {
+                "No PR with review marks set here has closed in that time.".to_string()
+            });
+        bot.send_message(msg.chat.id, reply).retried().await?;
+        return Ok(());
+    }
+
+    if text.starts_with("/fairness") {
+        let window = match text.split_whitespace().nth(1) {
+            None => chrono::Duration::days(30),
+            Some(arg) => match bulk::parse_duration(arg).filter(|d| d.num_days() > 0) {
+                Some(window) => window,
+                None => {
+                    return reply_error(&bot, &msg, ttl, "Usage: /fairness [30d]").await;
+                }
+            },
+        };
+        let reply = fairness::report_text(&state, msg.chat.id.0, window.num_days())
+            .await
+            .unwrap_or_else(|| "No reviews recorded here in that time.".to_string());
+        bot.send_message(msg.chat.id, reply).retried().await?;
+        return Ok(());
+    }
+
+    if text.starts_with("/orgstats") {
+        let window = match text.split_whitespace().nth(1) {
+            None => chrono::Duration::days(30),
+            Some(arg) => match bulk::parse_duration(arg).filter(|d| d.num_days() > 0) {
+                Some(window) => window,
+                None => {
+                    return reply_error(&bot, &msg, ttl, "Usage: /orgstats [30d]").await;
+                }
+            },
+        };
+        let reply = match federation::org_stats_text(&state, window).await {
+            Ok(reply) => reply,
+            Err(e) => {
+                error!("Failed to build org stats: {}", e);
+                return reply_error(&bot, &msg, ttl, "Couldn't load the merge archive.").await;
+            }
+        };
+        bot.send_message(msg.chat.id, reply)
+            .parse_mode(ParseMode::Html)
+            .retried()
+            .await?;
+        return Ok(());
+    }
+
+    // Help command
+    if text.starts_with("/help") || text.starts_with("/start") {
+        let config = monitor.config();
+        let map = &config.reactions;
+        let help_text = format!(
+            r#"
+<b>🤖 PR Monitor Bot Help</b>
+
+I monitor GitHub PRs and track review status via emojis or commands.
+
+<b>Commands or Reactions (reply to tracked message):</b>
+/review - Mark as reviewing{}
+/approve [text] - Approve PR{}
+/changes [text] - Request changes{}
+/comment - Add comment status{}
+/giveup - Unassign self{}
+/merge - Mark as merged{}
+/draft - Mark as draft{}
+/addressed or /rereview - Request re-review{}
+/block, /unblock - Mark as blocked or clear it
+/snooze [2d], /unsnooze - Park the PR (optionally for a while) or bring it back
+/takeover - Take over a PR whose author or reviewer left the chat
+/label add|remove name - Change the PR's labels on GitHub
+/actions - Show the card's actions as one-tap buttons, handy on mobile
+/status - Sync the card with GitHub now instead of on the next poll
+/ack - As the author, acknowledge approvals that came with comments
+/nitpick, /needs-tests, /needs-docs - Mark yourself as commenting and, with a linked GitHub account, post the canned feedback on the PR
+Where reactions don't reach me, or after /buttons on, cards have buttons for the same actions.
+
+<b>Note:</b> Review status (Approved, Changes Requested, etc.) is automatically synced from GitHub. Manual commands are useful for quick updates but GitHub state will override them on the next sync.
+
+<b>General Commands:</b>
+/upgrade (reply to link) - Replace link with tracked message
+/split (reply to grouped card) - Break a multi-PR card into individual cards
+/list - Show tracked repositories and active PRs
+/list author:me status:changes_requested - Only the PRs matching a filter (also reviewer:, repo:, label:, -negation and title words)
+/list save mine author:me - Save a filter for yourself, then run /list mine (/list filters shows yours, /list forget mine drops one)
+/find reviewer:me parser - Search this chat's cards, merged and closed ones included
+/track owner/repo - Start monitoring a repository (sent in a forum topic, its cards go there)
+/untrack owner/repo [clean] - Stop monitoring (clean also removes its active messages)
+/gates owner/repo - Show the checks rendered on the repository's cards
+/pool owner/repo - Show the repository's reviewer rotation
+/lead - Show the chat's leads
+/stats [30d] - This chat's PR activity and how quickly its merge conflicts get resolved
+/drift [30d] - How the review marks set here held up on GitHub once their PRs closed
+/fairness [30d] - Reviews each person gave vs received, flagging lasting imbalances
+/onclose [delete|archive|move &lt;chat id&gt;|keep &lt;days&gt;|default] - What happens to cards once their PR is merged or closed
+/selfapproval [on|off] - Whether authors may approve their own PRs (admins only to change)
+/orgstats [30d] - Merge statistics of this instance and its federation peers
+/link github-login - Link your GitHub account so your reviews and reactions count once
+/unlink - Remove the link
+/token &lt;token&gt; - In a private message: let /approve and /changes submit your review on GitHub (/token off forgets it)
+/forgetme - Erase your review marks and anonymise your history
+/verify CODE - In a private message: confirm a chat I posted the code in should get announcements (chat admins)
+/help - Show this message
+
+<b>Admin Commands:</b>
+/snoozeall owner/repo 2d - Snooze every active card of a repository
+/refreshall - Re-sync every active card in this chat
+/archiveall merged-before:30d - Compact cards merged more than 30 days ago
+/backfill owner/repo 90d - Import the repository's PR history (opened, reviews, merges) for /stats, /orgstats and the leaderboard, without posting cards
+/gates owner/repo build lint! e2e - Show these checks on cards; ! notifies when it fails
+/gates owner/repo clear - Remove the repository's check gates
+/pool owner/repo add @alice @bob - Assign new PRs to these reviewers in turn
+/pool owner/repo remove @alice - Take a reviewer out of the rotation
+/lead add|remove (reply to a member) - Make them a lead, who gets a morning DM triaging the PRs
+/buttons on|off - Show Review / Approve / Changes / Comment / Re-review / Give up buttons on cards instead of taking reactions
+/nudges 3|off|default - Cap the reminders that mention each person per day; the rest are listed in one summary after midnight (UTC)
+/dashboard - Pin an overview of the open PRs that I keep up to date (/dashboard off removes it)
+/wipechat - Delete everything stored for this chat (asks for confirmation)
+/rules - List this chat's notification rules
+/rules add if label=db-migration and approvals&lt;2 then mention @dba - Add a rule
+/rules remove 3 - Remove rule #3
+/tickets add JIRA-\d+ https://jira.example.com/browse/{{id}} - Link ticket ids in PR titles and descriptions on the cards (/tickets lists them, /tickets remove 2 drops #2)
+/templates set needs-tests text - Change the canned text of a reply template (/templates lists them, off posts nothing, reset restores the default)
+/search add org:acme label:"help wanted" draft:false - Announce new PRs matching a GitHub search (/search lists them, /search remove 2 drops #2)
+/reload - Re-read the configuration (main chat admins)
+/routes - Show which chats each repository's PRs are announced in (main chat admins)
+/tenant [id|leave] - Show or change which tenant serves this chat (tenant admins)
+"#,
+            map.hint(ReactionAction::Review),
+            map.hint(ReactionAction::Approve),
+            map.hint(ReactionAction::RequestChanges),
+            map.hint(ReactionAction::Comment),
+            map.hint(ReactionAction::GiveUp),
+            map.hint(ReactionAction::Merge),
+            map.hint(ReactionAction::Draft),
+            map.hint(ReactionAction::ReReview),
+        );
+        bot.send_message(msg.chat.id, help_text)
+            .parse_mode(ParseMode::Html)
+            .retried()
+            .await?;
+        return Ok(());
+    }
+
+    // Sync a card with GitHub right away instead of waiting for the next poll
+    if text.starts_with("/status") {
+        let card = match msg.reply_to_message() {
+            Some(reply_to) => state
+                .get_pr_message(&reply_to.id.0.to_string(), msg.chat.id.0)
+                .await
+                .ok()
+                .flatten(),
+            None => None,
+        };
+        let Some(card) = card else {
+            return reply_error(
+                &bot,
+                &msg,
+                ttl,
+                "Reply to a tracked PR card to use /status.",
+            )
+            .await;
+        };
+        let pr = format!("{}/{}#{}", card.repo_owner, card.repo_name, card.pr_number);
+        if let Err(e) = monitor.refresh_message(card).await {
+            error!("Failed to refresh {}: {}", pr, e);
+            let reason = format!("Couldn't refresh {}: {}.", pr, github::describe_error(&e));
+            return reply_error(&bot, &msg, ttl, reason).await;
+        }
+        bot.delete_message(msg.chat.id, msg.id).retried().await.ok();
+        return Ok(());
+    }
+
+    if text.starts_with("/actions") {
+        let card = match msg.reply_to_message() {
+            Some(reply_to) => state
+                .get_pr_data(reply_to.id.0.to_string(), msg.chat.id.0)
+                .await
+                .ok()
+                .flatten(),
+            None => None,
+        };
+        let Some(data) = card else {
+            return reply_error(
+                &bot,
+                &msg,
+                ttl,
+                "Reply to a tracked PR card to use /actions.",
+            )
+            .await;
+        };
+        let pr = format!("{}#{}", data.repo, data.pr_number);
+        bot.send_message(msg.chat.id, format!("Actions for {}:", pr))
+            .reply_parameters(ReplyParameters::new(msg.id))
+            .reply_markup(action_keyboard(&monitor.config().reactions, &pr))
+            .retried()
+            .await?;
+        return Ok(());
+    }
+
+    // A tap on the /actions keyboard arrives as a plain message naming its card
+    let mut tapped_card = None;
+    let text = match legend_tap(&text) {
+        Some((command, owner, repo, pr_number)) if msg.reply_to_message().is_none() => {
+            let card = state
+                .find_card(msg.chat.id.0, &owner, &repo, pr_number)
+                .await
+                .ok()
+                .flatten()
+                .and_then(|id| id.parse().ok());
+            let Some(id) = card else {
+                let reason = format!("{}/{}#{} has no card here anymore.", owner, repo, pr_number);
+                return reply_error(&bot, &msg, ttl, reason).await;
+            };
+            tapped_card = Some(MessageId(id));
+            command
+        }
+        _ => text,
+    };
+
+    // Interactive commands (reply based)
+    let command = command_name(&text);
+    let card_command = CARD_COMMANDS.iter().find(|c| **c == command);
+    if let Some(parent_id) = tapped_card.or(msg.reply_to_message().map(|m| m.id)) {
+        // Check if it's a tracked message
+        if let Ok(Some(mut data)) = state
+            .get_pr_data(parent_id.0.to_string(), msg.chat.id.0)
+            .await
+        {
+            let mut changed = false;
+            let username = msg
+                .from
+                .as_ref()
+                .map(|u| u.username.clone().unwrap_or(u.first_name.clone()))
+                .unwrap_or("unknown".to_string());
+
+            let mut re_review_for = None;
+            let before = data.clone();
+            if let Some(action) = command_action(command) {
+                if action == ReactionAction::Approve
+                    && !data.self_approval
+                    && is_pr_author(&state, &msg, &data).await
+                {
+                    let refusal = self_approval_refusal(&username);
+                    return reply_expiring(&bot, msg.chat.id, msg.id, ttl, refusal).await;
+                }
+                // GitHub first, so a refused review doesn't leave the mark claiming it
+                if let Err(reason) =
+                    submit::review(&github, tenants.operator(), &msg, &data, action, &text).await
+                {
+                    return reply_error(&bot, &msg, ttl, reason).await;
+                }
+                let timebox = monitor.config().review_timebox_minutes;
+                (changed, re_review_for) = apply_action(&mut data, action, &username, timebox);
+            } else if command == "/unblock" {
+                data.apply(PrEvent::Unblocked);
+                changed = true;
+            } else if command == "/block" {
+                data.apply(PrEvent::Blocked);
+                changed = true;
+            } else if command == "/unsnooze" {
+                data.apply(PrEvent::Unsnoozed);
+                changed = true;
+            } else if command == "/snooze" {
+                data.apply(PrEvent::Snoozed);
+                // An optional duration wakes the card up automatically on a later sync
+                data.snoozed_until = text
+                    .split_whitespace()
+                    .nth(1)
+                    .and_then(bulk::parse_duration)
+                    .map(|d| (chrono::Utc::now() + d).timestamp());
+                changed = true;
+            } else if command == "/label" {
+                match change_labels(&bot, &msg, &github, &state, &data, &text).await {
+                    Ok(labels) if labels == data.labels => {}
+                    Ok(labels) => {
+                        data.labels = labels;
+                        changed = true;
+                    }
+                    Err(reason) => return reply_error(&bot, &msg, ttl, reason).await,
+                }
+            } else if command == "/ack" {
+                if !data.suggestions.iter().any(|s| !s.acked) {
+                    return reply_error(
+                        &bot,
+                        &msg,
+                        ttl,
+                        "There's no approval with comments to acknowledge.",
+                    )
+                    .await;
+                }
+                if !is_pr_author(&state, &msg, &data).await && !is_admin(&bot, &msg).await {
+                    return reply_error(
+                        &bot,
+                        &msg,
+                        ttl,
+                        "Only the PR's author (or a chat admin) can acknowledge suggestions.",
+                    )
+                    .await;
+                }
+                for suggestion in &mut data.suggestions {
+                    suggestion.acked = true;
+                }
+                data.apply(PrEvent::ReviewsChanged);
+                changed = true;
+            } else if let Some(template) = templates::command(&text) {
+                // GitHub first, so a failed comment doesn't leave the mark claiming it
+                if let Err(reason) = templates::post(&github, &state, &msg, &data, template).await {
+                    return reply_error(&bot, &msg, ttl, reason).await;
+                }
+                let timebox = monitor.config().review_timebox_minutes;
+                (changed, _) = apply_action(&mut data, ReactionAction::Comment, &username, timebox);
+            } else if command == "/takeover" {
+                if data.departed.is_empty() {
+                    return reply_error(
+                        &bot,
+                        &msg,
+                        ttl,
+                        "Nobody on this PR has left the chat, there's nothing to take over.",
+                    )
+                    .await;
+                }
+                if data.departed.contains(&data.author) {
+                    data.owner = Some(username.clone());
+                }
+                let departed = std::mem::take(&mut data.departed);
+                let had_reviewers = data.reviewers.len();
+                data.reviewers.retain(|u| !departed.contains(u));
+                if data.reviewers.len() < had_reviewers && !data.reviewers.contains(&username) {
+                    data.reviewers.push(username.clone());
+                }
+                data.apply(PrEvent::ReviewsChanged);
+                changed = true;
+            }
+
+            if changed {
+                if let Err(e) = state
+                    .update_pr_data(parent_id.0.to_string(), data.clone())
+                    .await
+                {
+                    error!("Failed to save state: {}", e);
+                    return reply_error(&bot, &msg, ttl, "Couldn't save that change, try again.")
+                        .await;
+                }
+                drift::record_declared(&state, &parent_id.0.to_string(), &before, &data, &username)
+                    .await;
+
+                if let Err(e) = edit_card(&bot, &state, msg.chat.id, parent_id, &data).await {
+                    error!("Failed to update card: {}", e);
+                    return reply_error(
+                        &bot,
+                        &msg,
+                        ttl,
+                        "Saved, but I couldn't update the card. It will refresh on the next sync.",
+                    )
+                    .await;
+                }
+                state.audit(format!(
+                    "edited {}: {} by {}",
+                    audit::card(&data.repo, data.pr_number, msg.chat.id.0),
+                    text.split_whitespace().next().unwrap_or_default(),
+                    username
+                ));
+                if let Some(names) = re_review_for {
+                    notify_linked_users(
+                        &bot,
+                        &state,
+                        &data,
+                        &parent_id.0.to_string(),
+                        "🙏 Re-review requested:",
+                        &names,
+                        Some(&username),
+                    )
+                    .await;
+                }
+                monitor
+                    .apply_rules_after_change(&parent_id.0.to_string(), &data)
+                    .await;
+
+                // Delete the command message
+                bot.delete_message(msg.chat.id, msg.id).retried().await.ok();
+                return Ok(());
+            }
+            if card_command.is_some() {
+                // Nothing to change, e.g. approving twice
+                bot.delete_message(msg.chat.id, msg.id).retried().await.ok();
+                return Ok(());
+            }
+        }
+    }
+
+    if let Some(cmd) = card_command {
+        let reason = if msg.reply_to_message().is_some() {
+            "That message isn't a tracked PR card.".to_string()
+        } else {
+            format!("Reply to a tracked PR card to use {}.", cmd)
+        };
+        return reply_error(&bot, &msg, ttl, reason).await;
+    }
+
+    // Check for /addressed command (Legacy specific block removed as merged above)
+
+    // Check if reply to a tracked message (Re-review logic)
+    if let Some(reply_to) = msg.reply_to_message() {
+        let parent_id = reply_to.id;
+        if let Ok(Some(mut data)) = state
+            .get_pr_data(parent_id.0.to_string(), msg.chat.id.0)
+            .await
+        {
+            if text.contains("http") || text.contains("github.com") {
+                // remove comments when re-review is requested
+                data.comments.clear();
+                data.apply(PrEvent::ReReviewRequested);
+                if let Err(e) = state
+                    .update_pr_data(parent_id.0.to_string(), data.clone())
+                    .await
+                {
+                    error!("Failed to save state: {}", e);
+                }
+                edit_card(&bot, &state, msg.chat.id, parent_id, &data).await?;
+                state.audit(format!(
+                    "edited {}: re-review link",
+                    audit::card(&data.repo, data.pr_number, msg.chat.id.0)
+                ));
+            }
+        }
+    }
+
+    // "parse messages from other parties and if it is a link replace with your message"
+    // Check if message contains a PR link
+    let config = monitor.config();
+    if let Some((owner, repo, pr_number, item_type)) =
+        extract_pr_info(&text, &config.github_web_url, config.track_issues)
+    {
+        // If message is from bot, ignore (should allow loop prevention)
+        if let Some(user) = &msg.from {
+            if user.is_bot {
+                // assume it's us or another bot, maybe we shouldn't replace it if it's us?
+                // But `handle_message` usually doesn't trigger for own messages unless configured.
+            } else if let Ok(Some(_)) = state
+                .find_card(msg.chat.id.0, &owner, &repo, pr_number)
+                .await
+            {
+                // Already tracked here; leave the link alone rather than post a second card
+            } else if !may_manage_repos(&monitor, Some(user))
+                && !is_tracked(&state, msg.chat.id.0, &owner, &repo).await
+            {
+                // A link alone mustn't make the bot poll a repository no administrator added
+            } else {
+                match card_data_from_github(
+                    &github,
+                    &owner,
+                    &repo,
+                    pr_number,
+                    item_type,
+                    msg.chat.id.0,
+                )
+                .await
+                {
+                    Ok(pr_data) => {
+                        if !matches!(
+                            replace_with_card(&bot, &state, &msg, pr_data, "posted link").await?,
+                            Replacement::Replaced
+                        ) {
+                            return Ok(());
+                        }
+                        state
+                            .add_repository(msg.chat.id.0, &owner, &repo)
+                            .await
+                            .ok();
+                        onboarding::probe_new(&bot, &github, &state, msg.chat.id, &owner, &repo)
+                            .await;
+                    }
+                    Err(e) => error!("Failed to fetch PR: {}", e),
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Adds or removes a label on GitHub for `/label add|remove <name>`, returning the PR's labels
+/// afterwards. Linked users need triage access to the repository; anyone else must be a chat
+/// admin, and the change is made with the bot's own token either way.
+async fn change_labels(
+    bot: &Bot,
+    msg: &Message,
+    github: &GithubClient,
+    state: &StateManager,
+    data: &PrData,
+    text: &str,
+) -> Result<Vec<String>, String> {
+    let mut args = text.split_whitespace().skip(1);
+    let action = args.next().unwrap_or_default();
+    let name = args.collect::<Vec<_>>().join(" ");
+    if !matches!(action, "add" | "remove") || name.is_empty() {
+        return Err("Usage: /label add|remove <name>".to_string());
+    }
+    let Some((owner, repo)) = data.repo.split_once('/') else {
+        return Err("This card has no repository.".to_string());
+    };
+    let Some(user) = msg.from.as_ref() else {
+        return Err("Couldn't tell who sent this.".to_string());
+    };
+    if RepoCapabilities::denies(onboarding::of(state, owner, repo).await.write_labels) {
+        return Err(format!(
+            "The bot's GitHub access can't change labels in {}.",
+            data.repo
+        ));
+    }
+
+    match state.get_user_link(user.id.0 as i64).await {
+        Ok(Some(link)) => match github.get_permission(owner, repo, &link.github_login).await {
+            Ok(role) if matches!(role.as_str(), "admin" | "maintain" | "write" | "triage") => {}
+            Ok(_) => {
+                return Err(format!(
+                    "{} can't change labels in {}.",
+                    link.github_login, data.repo
+                ))
+            }
+            Err(e) => {
+                return Err(format!(
+                    "Couldn't check your access to {}: {}.",
+                    data.repo,
+                    github::describe_error(&e)
+                ))
+            }
+        },
+        Ok(None) if is_admin(bot, msg).await => {}
+        Ok(None) => {
+            return Err(
+                "Link your GitHub account with /link to change labels, or ask a chat admin."
+                    .to_string(),
+            )
+        }
+        Err(e) => {
+            error!("Failed to load user link: {}", e);
+            return Err("Couldn't look up your GitHub account, try again.".to_string());
+        }
+    }
+
+    // Start from GitHub's labels rather than the card's, which may be a sync behind
+    let pr = github
+        .get_pr_details(owner, repo, data.pr_number)
+        .await
+        .map_err(|e| format!("Couldn't load the PR: {}.", github::describe_error(&e)))?;
+    let mut labels = github::labels(&pr);
+    let present = labels.iter().any(|l| l.eq_ignore_ascii_case(&name));
+    match action {
+        "add" if !present => labels.push(name),
+        "remove" if present => labels.retain(|l| !l.eq_ignore_ascii_case(&name)),
+        _ => return Ok(labels),
+    }
+    github
+        .set_labels(owner, repo, data.pr_number, &labels)
+        .await
+        .map_err(|e| {
+            format!(
+                "Couldn't change the labels: {}.",
+                github::describe_error(&e)
+            )
+        })
+}
+
+/// The command `text` starts with, without the `@botname` it may be addressed with.
+fn command_name(text: &str) -> &str {
+    let word = text.split_whitespace().next().unwrap_or_default();
+    word.split('@').next().unwrap_or_default()
+}
+
+/// The reaction a card command stands for, if any.
+fn command_action(command: &str) -> Option<ReactionAction> {
+    const COMMANDS: [(&str, ReactionAction); 9] = [
+        ("/addressed", ReactionAction::ReReview),
+        ("/rereview", ReactionAction::ReReview),
+        ("/review", ReactionAction::Review),
+        ("/approve", ReactionAction::Approve),
+        ("/changes", ReactionAction::RequestChanges),
+        ("/comment", ReactionAction::Comment),
+        ("/giveup", ReactionAction::GiveUp),
+        ("/merge", ReactionAction::Merge),
+        ("/draft", ReactionAction::Draft),
+    ];
+    COMMANDS
+        .iter()
+        .find(|(name, _)| *name == command)
+        .map(|(_, action)| *action)
+}
+
+/// Applies a card command or button on behalf of `username`. Returns whether the card
+/// changed and, for a re-review request, whom to notify.
+pub fn apply_action(
+    data: &mut PrData,
+    action: ReactionAction,
+    username: &str,
+    timebox_minutes: u32,
+) -> (bool, Option<Vec<String>>) {
+    let username = username.to_string();
+    match action {
+        ReactionAction::ReReview => {
+            let audience = re_review_audience(data);
+            // remove comments and change requests when re-review is requested
+            data.comments.clear();
+            data.changes_requested.clear();
+            data.apply(PrEvent::ReReviewRequested);
+            return (true, Some(audience));
+        }
+        ReactionAction::Review
+        | ReactionAction::Approve
+        | ReactionAction::RequestChanges
+        | ReactionAction::Comment => {
+            let list = match action {
+                ReactionAction::Review => &mut data.reviewers,
+                ReactionAction::Approve => &mut data.approvals,
+                ReactionAction::RequestChanges => &mut data.changes_requested,
+                _ => &mut data.comments,
+            };
+            if list.contains(&username) {
+                return (false, None);
+            }
+            list.push(username.clone());
+            if action == ReactionAction::Review {
+                sessions::start(data, &username, timebox_minutes);
+            } else {
+                // Their entries now stand for what they said in Telegram
+                data.from_github.retain(|u| u != &username);
+            }
+            data.apply(PrEvent::ReviewsChanged);
+        }
+        ReactionAction::GiveUp => {
+            data.reviewers.retain(|u| u != &username);
+            data.apply(PrEvent::ReviewsChanged);
+        }
+        ReactionAction::Merge => data.apply(PrEvent::Merged),
+        ReactionAction::Draft => {
+            // Toggle draft
+            let is_draft = data.status == PrStatus::Draft;
+            data.apply(PrEvent::DraftSet(!is_draft));
+        }
+    }
+    (true, None)
+}
 
-                        let text = generate_message_text(&pr_data);
-                        let sent_msg = bot
-                            .send_message(msg.chat.id, text)
-                            .parse_mode(ParseMode::Html)
-                            .link_preview_options(LinkPreviewOptions {
-                                is_disabled: true,
-                                url: None,
-                                prefer_small_media: false,
-                                prefer_large_media: false,
-                                show_above_text: false,
-                            })
-                            .await?;
+/// Replies to a failed command with `reason`, deleting the reply after `ttl_secs` (0 keeps it).
+pub async fn reply_error(
+    bot: &Bot,
+    msg: &Message,
+    ttl_secs: u64,
+    reason: impl Into<String>,
+) -> ResponseResult<()> {
+    reply_expiring(
+        bot,
+        msg.chat.id,
+        msg.id,
+        ttl_secs,
+        format!("⚠️ {}", reason.into()),
+    )
+    .await
+}
 
-                        state
-                            .add_message(sent_msg.id.0.to_string(), pr_data)
-                            .await
-                            .ok();
-                        state.add_repository(&owner, &repo).await.ok();
-                    }
-                    Err(e) => error!("Failed to fetch PR: {}", e),
-                }
-            }
+/// Replies to `reply_to` with `text`, deleted again after `ttl_secs` unless that's 0.
+async fn reply_expiring(
+    bot: &Bot,
+    chat_id: ChatId,
+    reply_to: MessageId,
+    ttl_secs: u64,
+    text: String,
+) -> ResponseResult<()> {
+    let sent = bot
+        .send_message(chat_id, text)
+        .reply_parameters(ReplyParameters::new(reply_to))
+        .retried()
+        .await?;
+    if ttl_secs > 0 {
+        let bot = bot.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_secs(ttl_secs)).await;
+            bot.delete_message(sent.chat.id, sent.id)
+                .retried()
+                .await
+                .ok();
+        });
+    }
+    Ok(())
+}
+
+/// Whether the sender wrote the PR: their linked GitHub login or name matches its author,
+/// or they took it over.
+async fn is_pr_author(state: &StateManager, msg: &Message, data: &PrData) -> bool {
+    match msg.from.as_ref() {
+        Some(user) => is_own_pr(state, user, data).await,
+        None => false,
+    }
+}
+
+/// Whether `user` wrote the PR, as `is_pr_author` decides it.
+async fn is_own_pr(state: &StateManager, user: &User, data: &PrData) -> bool {
+    let name = user.username.clone().unwrap_or(user.first_name.clone());
+    let login = state
+        .get_user_link(user.id.0 as i64)
+        .await
+        .ok()
+        .flatten()
+        .map(|l| l.github_login);
+    data.owner.as_ref() == Some(&name)
+        || [Some(name), login]
+            .into_iter()
+            .flatten()
+            .any(|n| n.eq_ignore_ascii_case(&data.author))
+}
+
+/// The gentle no an author gets when approving their own PR.
+fn self_approval_refusal(name: &str) -> String {
+    format!(
+        "🙅 {}, authors can't approve their own PRs here, so that approval wasn't counted. Ask a teammate for a look instead.",
+        name
+    )
+}
+
+/// What a closed card policy does, for `/onclose`.
+fn describe_closed_cards(policy: ClosedCards) -> String {
+    match policy {
+        ClosedCards::Delete => "deleted once their PR is merged or closed, or struck out where Telegram no longer allows deleting them".to_string(),
+        ClosedCards::Archive => "edited into a short summary once their PR is merged or closed".to_string(),
+        ClosedCards::Move(chat_id) => format!(
+            "summarized in chat {} and deleted here once their PR is merged or closed",
+            chat_id
+        ),
+        ClosedCards::Keep(days) => format!(
+            "struck out once their PR is merged or closed, and deleted {} days later",
+            days
+        ),
+    }
+}
+
+/// Private chats have no admins to check; everywhere else the sender must be one.
+pub async fn is_admin(bot: &Bot, msg: &Message) -> bool {
+    is_chat_admin(bot, &msg.chat, msg.from.as_ref()).await
+}
+
+/// Whether `ADMIN_USER_IDS` lets the user change which repositories the bot polls; anyone
+/// may while it's empty.
+fn may_manage_repos(monitor: &Monitor, user: Option<&User>) -> bool {
+    let config = monitor.config();
+    config.admin_user_ids.is_empty()
+        || user.is_some_and(|u| config.admin_user_ids.contains(&(u.id.0 as i64)))
+}
+
+/// Whether the chat already tracks the repository.
+async fn is_tracked(state: &StateManager, chat_id: i64, owner: &str, repo: &str) -> bool {
+    state
+        .get_chat_repositories(chat_id)
+        .await
+        .unwrap_or_default()
+        .iter()
+        .any(|(o, r)| o.eq_ignore_ascii_case(owner) && r.eq_ignore_ascii_case(repo))
+}
+
+async fn is_chat_admin(bot: &Bot, chat: &Chat, user: Option<&User>) -> bool {
+    if chat.is_private() {
+        return true;
+    }
+    let Some(user) = user else {
+        return false;
+    };
+    match bot.get_chat_member(chat.id, user.id).timed().await {
+        Ok(member) => member.is_privileged(),
+        Err(e) => {
+            error!("Failed to check admin status: {}", e);
+            false
         }
     }
+}
+
+/// Validates a bulk command, confirms how many cards it will touch and runs it in the background.
+async fn handle_bulk_command(
+    bot: &Bot,
+    msg: &Message,
+    text: &str,
+    monitor: Monitor,
+) -> ResponseResult<()> {
+    let chat_id = msg.chat.id;
+    let args: Vec<&str> = text.split_whitespace().skip(1).collect();
+
+    if text.starts_with("/snoozeall") {
+        let parsed = parse_repo_arg(text, &monitor.config().github_web_url)
+            .zip(args.get(1).copied().and_then(bulk::parse_duration));
+        let Some(((owner, repo), duration)) = parsed else {
+            bot.send_message(chat_id, "Usage: /snoozeall owner/repo 2d")
+                .retried()
+                .await?;
+            return Ok(());
+        };
+        let until = chrono::Utc::now() + duration;
+        bot.send_message(
+            chat_id,
+            format!("💤 Snoozing active cards of {}/{}...", owner, repo),
+        )
+        .retried()
+        .await?;
+        let bot = bot.clone();
+        tokio::spawn(async move {
+            let count = bulk::snooze_all(&monitor, chat_id, &owner, &repo, until).await;
+            bot.send_message(
+                chat_id,
+                format!(
+                    "💤 Snoozed {} card(s) of {}/{} until {}",
+                    count,
+                    owner,
+                    repo,
+                    until.format("%Y-%m-%d %H:%M UTC")
+                ),
+            )
+            .retried()
+            .await
+            .ok();
+        });
+    } else if text.starts_with("/backfill") {
+        let parsed = parse_repo_arg(text, &monitor.config().github_web_url).zip(
+            args.get(1)
+                .copied()
+                .and_then(bulk::parse_duration)
+                .filter(|d| d.num_days() > 0),
+        );
+        let Some(((owner, repo), window)) = parsed else {
+            bot.send_message(chat_id, "Usage: /backfill owner/repo 90d")
+                .retried()
+                .await?;
+            return Ok(());
+        };
+        let since = chrono::Utc::now() - window;
+        bot.send_message(
+            chat_id,
+            format!(
+                "📥 Importing the last {} days of {}/{} from GitHub...",
+                window.num_days(),
+                owner,
+                repo
+            ),
+        )
+        .retried()
+        .await?;
+        let bot = bot.clone();
+        tokio::spawn(async move {
+            let reply = match bulk::backfill(&monitor, chat_id, &owner, &repo, since).await {
+                Ok((prs, events)) => format!(
+                    "📥 Imported {} event(s) of {} PR(s) from {}/{}. /stats and the leaderboard now include them.",
+                    events, prs, owner, repo
+                ),
+                Err(e) => {
+                    error!("Failed to backfill {}/{}: {}", owner, repo, e);
+                    format!("Couldn't import the history of {}/{}.", owner, repo)
+                }
+            };
+            bot.send_message(chat_id, reply).retried().await.ok();
+        });
+    } else if text.starts_with("/refreshall") {
+        bot.send_message(chat_id, "🔄 Refreshing all active cards...")
+            .retried()
+            .await?;
+        let bot = bot.clone();
+        tokio::spawn(async move {
+            let count = bulk::refresh_all(&monitor, chat_id).await;
+            bot.send_message(chat_id, format!("🔄 Refreshed {} card(s)", count))
+                .retried()
+                .await
+                .ok();
+        });
+    } else {
+        let age = args
+            .first()
+            .and_then(|a| a.strip_prefix("merged-before:"))
+            .and_then(bulk::parse_duration);
+        let Some(age) = age else {
+            bot.send_message(chat_id, "Usage: /archiveall merged-before:30d")
+                .retried()
+                .await?;
+            return Ok(());
+        };
+        let cutoff = chrono::Utc::now() - age;
+        bot.send_message(chat_id, "🗄 Archiving merged cards...")
+            .retried()
+            .await?;
+        let bot = bot.clone();
+        tokio::spawn(async move {
+            let count = bulk::archive_merged(&monitor, chat_id, cutoff).await;
+            bot.send_message(chat_id, format!("🗄 Archived {} card(s)", count))
+                .retried()
+                .await
+                .ok();
+        });
+    }
 
     Ok(())
 }
 
-fn extract_pr_info(text: &str) -> Option<(String, String, u64)> {
-    let re = Regex::new(r"github\.com/([^/]+)/([^/]+)/pull/(\d+)").unwrap();
-    if let Some(captures) = re.captures(text) {
-        let owner = captures.get(1)?.as_str().to_string();
-        let repo = captures.get(2)?.as_str().to_string();
-        let number = captures.get(3)?.as_str().parse::<u64>().ok()?;
-        return Some((owner, repo, number));
+pub const fn no_link_preview() -> LinkPreviewOptions {
+    LinkPreviewOptions {
+        is_disabled: true,
+        url: None,
+        prefer_small_media: false,
+        prefer_large_media: false,
+        show_above_text: false,
+    }
+}
+
+/// The buttons standing in for reactions on cards.
+fn card_keyboard() -> InlineKeyboardMarkup {
+    let button = |action: ReactionAction| {
+        InlineKeyboardButton::callback(
+            action.button_label(),
+            format!("{}{}", CARD_CALLBACK, action.name()),
+        )
+    };
+    InlineKeyboardMarkup::new([
+        vec![
+            button(ReactionAction::Review),
+            button(ReactionAction::Approve),
+            button(ReactionAction::RequestChanges),
+            button(ReactionAction::Comment),
+        ],
+        vec![
+            button(ReactionAction::GiveUp),
+            button(ReactionAction::ReReview),
+        ],
+    ])
+}
+
+/// One-tap reply keyboard of a card's actions for `/actions`, each button reading like
+/// "👍 /approve owner/repo#42" so the tap can be matched back to the card. Only the user who
+/// asked sees it, and it hides after one tap.
+fn action_keyboard(map: &ReactionMap, pr: &str) -> KeyboardMarkup {
+    let buttons: Vec<KeyboardButton> = ReactionAction::ALL
+        .iter()
+        .map(|action| {
+            let command = format!("/{} {}", action.name(), pr);
+            KeyboardButton::new(match map.emoji(*action) {
+                Some(emoji) => format!("{} {}", emoji, command),
+                None => command,
+            })
+        })
+        .collect();
+    KeyboardMarkup {
+        selective: true,
+        ..KeyboardMarkup::new(buttons.chunks(2).map(|row| row.to_vec()))
+            .one_time_keyboard()
+            .resize_keyboard()
+            .input_field_placeholder(format!("Action for {}", pr))
+    }
+}
+
+/// Parses a tap on the `/actions` keyboard into the card command and its PR.
+fn legend_tap(text: &str) -> Option<(String, String, String, u64)> {
+    let mut words: Vec<&str> = text.split_whitespace().collect();
+    // The emoji is left out for actions without one
+    if words
+        .first()
+        .is_some_and(|w| !w.starts_with('/') && !w.chars().any(char::is_alphanumeric))
+    {
+        words.remove(0);
+    }
+    let [command, pr] = words[..] else {
+        return None;
+    };
+    let action = ReactionAction::from_name(command.strip_prefix('/')?)?;
+    let (repo, number) = pr.split_once('#')?;
+    let (owner, repo) = repo.split_once('/')?;
+    Some((
+        format!("/{}", action.name()),
+        owner.to_string(),
+        repo.to_string(),
+        number.parse().ok()?,
+    ))
+}
+
+/// Buttons for a card in `chat_id`, when the chat uses them instead of reactions.
+pub async fn card_buttons(state: &StateManager, chat_id: i64) -> Option<InlineKeyboardMarkup> {
+    match state.get_button_mode(chat_id).await {
+        Ok(ButtonMode::Reactions) => None,
+        Ok(_) => Some(card_keyboard()),
+        Err(e) => {
+            error!("Failed to load button mode of {}: {}", chat_id, e);
+            None
+        }
+    }
+}
+
+/// Re-renders a card, keeping its buttons in chats that use them.
+pub async fn edit_card(
+    bot: &Bot,
+    state: &StateManager,
+    chat_id: ChatId,
+    message_id: MessageId,
+    data: &PrData,
+) -> ResponseResult<Message> {
+    let text = generate_message_text(data);
+    let mut request = bot
+        .edit_message_text(chat_id, message_id, text.clone())
+        .parse_mode(ParseMode::Html)
+        .link_preview_options(no_link_preview());
+    if let Some(buttons) = card_buttons(state, chat_id.0).await {
+        request = request.reply_markup(buttons);
+    }
+    let result = request.retried().await;
+    if result.is_ok() {
+        metrics::card_edited();
+        mirror::sync(bot, state, chat_id, message_id, &text).await;
+    }
+    // Unchanged text still means the card shows the current elapsed times
+    if matches!(
+        result,
+        Ok(_) | Err(RequestError::Api(ApiError::MessageNotModified))
+    ) {
+        state
+            .set_rendered_at(
+                &message_id.0.to_string(),
+                chat_id.0,
+                chrono::Utc::now().timestamp(),
+            )
+            .await
+            .ok();
+    }
+    result
+}
+
+pub fn pr_data_from_github(pr: &PullRequest, owner: &str, repo: &str, chat_id: i64) -> PrData {
+    PrData {
+        pr_url: pr
+            .html_url
+            .as_ref()
+            .map(|u| u.to_string())
+            .unwrap_or_default(),
+        title: pr.title.clone().unwrap_or_default(),
+        author: pr
+            .user
+            .as_ref()
+            .map(|u| u.login.clone())
+            .unwrap_or("unknown".to_string()),
+        repo: format!("{}/{}", owner, repo),
+        pr_number: pr.number,
+        status: PrStatus::from_github(
+            pr.merged_at.is_some(),
+            matches!(pr.state, Some(octocrab::models::IssueState::Closed)),
+            pr.draft.unwrap_or(false),
+        ),
+        status_since: chrono::Utc::now().timestamp(),
+        labels: github::labels(pr),
+        head_sha: Some(pr.head.sha.clone()),
+        requested_reviewers: Some(vec![]),
+        chat_id,
+        ..Default::default()
+    }
+}
+
+pub fn issue_data_from_github(issue: &Issue, owner: &str, repo: &str, chat_id: i64) -> PrData {
+    let mut data = PrData {
+        pr_url: issue.html_url.to_string(),
+        title: issue.title.clone(),
+        author: issue.user.login.clone(),
+        repo: format!("{}/{}", owner, repo),
+        pr_number: issue.number,
+        status: PrStatus::from_github(false, issue.state == IssueState::Closed, false),
+        status_since: chrono::Utc::now().timestamp(),
+        labels: issue.labels.iter().map(|l| l.name.clone()).collect(),
+        item_type: ItemType::Issue,
+        assignees: issue.assignees.iter().map(|a| a.login.clone()).collect(),
+        chat_id,
+        ..Default::default()
+    };
+    data.assignees.sort();
+    data
+}
+
+/// The card of a linked PR or issue, fresh from GitHub. An issue link to a PR, which GitHub
+/// redirects, gets the PR's card.
+async fn card_data_from_github(
+    github: &GithubClient,
+    owner: &str,
+    repo: &str,
+    number: u64,
+    item_type: ItemType,
+    chat_id: i64,
+) -> anyhow::Result<PrData> {
+    if item_type == ItemType::Issue {
+        let issue = github.get_issue(owner, repo, number).await?;
+        if issue.pull_request.is_none() {
+            return Ok(issue_data_from_github(&issue, owner, repo, chat_id));
+        }
+    }
+    let pr = github.get_pr_details(owner, repo, number).await?;
+    Ok(pr_data_from_github(&pr, owner, repo, chat_id))
+}
+
+/// Posts a fresh card for `pr_data` and starts tracking it. Posts nothing and returns `None`
+/// when the chat already has a card for the PR, and takes the card down again, returning
+/// `None`, when it can't be recorded: an untracked card would never follow its PR.
+pub async fn send_tracked_card(
+    bot: &Bot,
+    state: &StateManager,
+    chat_id: ChatId,
+    mut pr_data: PrData,
+    reason: &str,
+) -> ResponseResult<Option<Message>> {
+    match state
+        .claim_card(chat_id.0, &pr_data.repo, pr_data.pr_number)
+        .await
+    {
+        Ok(true) => {}
+        Ok(false) => return Ok(None),
+        Err(e) => {
+            error!("Failed to claim card: {}", e);
+            return Ok(None);
+        }
+    }
+
+    if let Some((owner, name)) = pr_data.repo.split_once('/') {
+        pr_data.thread_id = state
+            .get_repository_topic(chat_id.0, owner, name)
+            .await
+            .unwrap_or_default();
+    }
+    let text = generate_message_text(&pr_data);
+    let mut request = bot
+        .send_message(chat_id, text.clone())
+        .parse_mode(ParseMode::Html)
+        .link_preview_options(no_link_preview());
+    if let Some(thread_id) = pr_data.thread_id {
+        request = request.message_thread_id(ThreadId(MessageId(thread_id)));
+    }
+    if let Some(buttons) = card_buttons(state, chat_id.0).await {
+        request = request.reply_markup(buttons);
+    }
+    let sent_msg = match request.retried().await {
+        Ok(sent_msg) => sent_msg,
+        Err(e) => {
+            state
+                .release_card(chat_id.0, &pr_data.repo, pr_data.pr_number)
+                .await
+                .ok();
+            return Err(e);
+        }
+    };
+    let card = audit::card(&pr_data.repo, pr_data.pr_number, chat_id.0);
+    let recorded = match state
+        .set_card_message(
+            chat_id.0,
+            &pr_data.repo,
+            pr_data.pr_number,
+            &sent_msg.id.0.to_string(),
+        )
+        .await
+    {
+        Ok(()) => state.add_message(sent_msg.id.0.to_string(), pr_data).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = recorded {
+        error!("Failed to record card {}: {}", card, e);
+        take_down_card(bot, state, chat_id, sent_msg.id).await;
+        return Ok(None);
+    }
+
+    state.audit(format!("created {}: {}", card, reason));
+    mirror::sync(bot, state, chat_id, sent_msg.id, &text).await;
+
+    Ok(Some(sent_msg))
+}
+
+/// Deletes a card that was just posted and forgets it, along with its mirror copies.
+async fn take_down_card(bot: &Bot, state: &StateManager, chat_id: ChatId, message_id: MessageId) {
+    if let Err(e) = bot.delete_message(chat_id, message_id).retried().await {
+        error!("Failed to take down card {}: {}", message_id.0, e);
+    }
+    mirror::close(bot, state, chat_id, message_id, None).await;
+    state
+        .remove_message(&message_id.0.to_string(), chat_id.0)
+        .await
+        .ok();
+}
+
+/// How replacing a user's message carrying a PR link with the PR's card went.
+enum Replacement {
+    Replaced,
+    /// No card was posted, because the chat has one or it couldn't be recorded; the message stays.
+    NotPosted,
+    /// The message couldn't be deleted, so the card was taken down again.
+    Undeletable(RequestError),
+}
+
+/// Replaces `original` with a card for `pr_data`. The card is posted and recorded before the
+/// message is deleted, so the link survives a failure at any step: when the message can't be
+/// deleted the card is taken down again, and when it's unclear whether it was, its text is
+/// posted again.
+async fn replace_with_card(
+    bot: &Bot,
+    state: &StateManager,
+    original: &Message,
+    pr_data: PrData,
+    reason: &str,
+) -> ResponseResult<Replacement> {
+    let chat_id = original.chat.id;
+    let Some(card) = send_tracked_card(bot, state, chat_id, pr_data, reason).await? else {
+        return Ok(Replacement::NotPosted);
+    };
+    let e = match bot.delete_message(chat_id, original.id).retried().await {
+        // Gone already, e.g. deleted by its sender meanwhile
+        Ok(_) | Err(RequestError::Api(ApiError::MessageToDeleteNotFound)) => {
+            return Ok(Replacement::Replaced)
+        }
+        Err(e) => e,
+    };
+    error!(
+        "Failed to delete message {} with a PR link: {}",
+        original.id.0, e
+    );
+    take_down_card(bot, state, chat_id, card.id).await;
+    // Telegram refused outright, so the message is still there
+    if !matches!(e, RequestError::Api(_)) {
+        restore_message(bot, original).await;
+    }
+    Ok(Replacement::Undeletable(e))
+}
+
+/// Posts a deleted message's text again on behalf of its sender.
+async fn restore_message(bot: &Bot, original: &Message) {
+    let sender = original
+        .from
+        .as_ref()
+        .map_or_else(|| "Someone".to_string(), |u| u.full_name());
+    let mut request = bot
+        .send_message(
+            original.chat.id,
+            format!("💬 {}:\n{}", sender, original.text().unwrap_or_default()),
+        )
+        .link_preview_options(no_link_preview());
+    if let Some(thread_id) = original
+        .is_topic_message
+        .then_some(original.thread_id)
+        .flatten()
+    {
+        request = request.message_thread_id(thread_id);
+    }
+    if let Err(e) = request.retried().await {
+        error!("Failed to restore message {}: {}", original.id.0, e);
+    }
+}
+
+/// Who a re-review request is for: everyone who claimed, commented on or requested changes to the PR.
+pub fn re_review_audience(data: &PrData) -> Vec<String> {
+    let mut names = data.reviewers.clone();
+    for name in data.changes_requested.iter().chain(&data.comments) {
+        if !names.contains(name) {
+            names.push(name.clone());
+        }
+    }
+    names
+}
+
+/// HTML mention of a linked user: their @username, or a link to their account without one.
+pub fn mention(link: &UserLink) -> String {
+    match &link.telegram_username {
+        Some(username) => format!("@{}", username),
+        None => format!(
+            "<a href=\"tg://user?id={}\">{}</a>",
+            link.telegram_id,
+            teloxide::utils::html::escape(&link.telegram_name)
+        ),
+    }
+}
+
+/// Replies under a card with `text` followed by mentions of the `/link`ed users among `names`,
+/// leaving out `skip` (whoever caused it). Sends nothing when none of them is linked.
+pub async fn notify_linked_users(
+    bot: &Bot,
+    state: &StateManager,
+    data: &PrData,
+    message_id: &str,
+    text: &str,
+    names: &[String],
+    skip: Option<&str>,
+) {
+    let links = match state.find_linked_users(names).await {
+        Ok(links) => links,
+        Err(e) => {
+            error!("Failed to load linked users: {}", e);
+            return;
+        }
+    };
+    let mentions: Vec<String> = links
+        .iter()
+        .filter(|l| {
+            skip.is_none_or(|s| s != l.telegram_name && !s.eq_ignore_ascii_case(&l.github_login))
+        })
+        .map(mention)
+        .collect();
+    if mentions.is_empty() {
+        return;
+    }
+
+    if let Err(e) = bot
+        .send_message(
+            ChatId(data.chat_id),
+            format!("{} {}", text, mentions.join(" ")),
+        )
+        .reply_parameters(ReplyParameters::new(MessageId(
+            message_id.parse().unwrap_or(0),
+        )))
+        .parse_mode(ParseMode::Html)
+        .retried()
+        .await
+    {
+        error!("Failed to mention reviewers: {}", e);
+        return;
+    }
+    state.audit(format!(
+        "notified {}: mentioned {}",
+        audit::card(&data.repo, data.pr_number, data.chat_id),
+        mentions.join(", ")
+    ));
+}
+
+/// Link to a message in a supergroup (`-100…` chat ids); other chats have no public links.
+pub fn message_link(chat_id: i64, message_id: &str) -> Option<String> {
+    let internal_id = chat_id.to_string().strip_prefix("-100")?.to_string();
+    Some(format!("https://t.me/c/{}/{}", internal_id, message_id))
+}
+
+/// One-line summary of a card's review lists, e.g. "❤️1 👍2".
+pub fn review_summary(data: &PrData) -> String {
+    let mut parts = Vec::new();
+    for (emoji, list) in [
+        ("❤️", &data.reviewers),
+        ("👍", &data.approvals),
+        ("❌", &data.changes_requested),
+        ("👌", &data.comments),
+    ] {
+        if !list.is_empty() {
+            parts.push(format!("{}{}", emoji, list.len()));
+        }
+    }
+    parts.join(" ")
+}
+
+pub async fn generate_list_text(state: &StateManager, chat_id: i64) -> String {
+    let repos = state
+        .get_chat_repositories(chat_id)
+        .await
+        .unwrap_or_default();
+    let mut text = format!("<b>📚 Tracked repositories ({})</b>\n", repos.len());
+    for (owner, repo) in &repos {
+        text.push_str(&format!("• {}/{}\n", owner, repo));
+    }
+
+    let msgs: Vec<_> = state
+        .get_all_active_messages()
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|m| m.chat_id == chat_id)
+        .collect();
+
+    text.push_str(&format!("\n<b>📋 Active PRs ({})</b>\n", msgs.len()));
+    for m in msgs.iter().take(LIST_LIMIT) {
+        let Ok(Some(data)) = state.get_pr_data(m.message_id.clone(), m.chat_id).await else {
+            continue;
+        };
+        let card = match message_link(m.chat_id, &m.message_id) {
+            Some(link) => format!(" · <a href=\"{}\">card</a>", link),
+            None => String::new(),
+        };
+        text.push_str(&format!(
+            "{} <a href=\"{}\">{}#{}</a> {} {}{}\n",
+            data.status.badge(),
+            data.pr_url,
+            data.repo,
+            data.pr_number,
+            data.title,
+            review_summary(&data),
+            card
+        ));
+    }
+    if msgs.len() > LIST_LIMIT {
+        text.push_str(&format!("…and {} more\n", msgs.len() - LIST_LIMIT));
+    }
+
+    text
+}
+
+/// The chat's PRs matching a query, open ones only unless `include_closed` (for `/find`).
+async fn filtered_list_text(
+    state: &StateManager,
+    chat_id: i64,
+    query: &Query,
+    viewer: &Viewer,
+    include_closed: bool,
+) -> String {
+    let mut rows = Vec::new();
+    for m in state.get_chat_messages(chat_id).await.unwrap_or_default() {
+        let Ok(Some(data)) = state.get_pr_data(m.message_id.clone(), chat_id).await else {
+            continue;
+        };
+        if (include_closed || !data.status.is_terminal()) && query.matches(&data, viewer) {
+            rows.push((m.message_id, data));
+        }
+    }
+    rows.sort_by_key(|(_, d)| std::cmp::Reverse(d.status_since));
+
+    let mut text = format!(
+        "<b>🔎 {} ({})</b>\n",
+        teloxide::utils::html::escape(&query.to_string()),
+        rows.len()
+    );
+    if rows.is_empty() {
+        text.push_str("No PRs match.\n");
+    }
+    for (message_id, data) in rows.iter().take(LIST_LIMIT) {
+        let card = match message_link(chat_id, message_id) {
+            Some(link) => format!(" · <a href=\"{}\">card</a>", link),
+            None => String::new(),
+        };
+        text.push_str(&format!(
+            "{} <a href=\"{}\">{}#{}</a> {} {}{}\n",
+            data.status.badge(),
+            data.pr_url,
+            data.repo,
+            data.pr_number,
+            teloxide::utils::html::escape(&data.title),
+            review_summary(data),
+            card
+        ));
+    }
+    if rows.len() > LIST_LIMIT {
+        text.push_str(&format!("…and {} more\n", rows.len() - LIST_LIMIT));
+    }
+    text
+}
+
+/// Who `me` means in a user's filters: their name on cards and linked GitHub login.
+async fn viewer(state: &StateManager, user: &User) -> Viewer {
+    let mut names = vec![user.username.clone().unwrap_or(user.first_name.clone())];
+    if let Ok(Some(link)) = state.get_user_link(user.id.0 as i64).await {
+        names.push(link.github_login);
+    }
+    Viewer { names }
+}
+
+/// Parses the `owner/repo` argument of a command, accepting a GitHub URL as well.
+fn parse_repo_arg(text: &str, web_url: &str) -> Option<(String, String)> {
+    let arg = text.split_whitespace().nth(1)?;
+    let arg = arg
+        .trim_start_matches("https://")
+        .trim_start_matches("http://")
+        .trim_start_matches(web_host(web_url))
+        .trim_start_matches('/')
+        .trim_end_matches('/');
+    let mut parts = arg.split('/');
+    let owner = parts.next().filter(|s| !s.is_empty())?;
+    let repo = parts.next().filter(|s| !s.is_empty())?;
+    Some((owner.to_string(), repo.to_string()))
+}
+
+fn web_host(web_url: &str) -> &str {
+    web_url.split_once("://").map_or(web_url, |(_, host)| host)
+}
+
+/// Deletes the chat messages and tracking rows of every active PR of a repository in a chat.
+async fn remove_repo_messages(
+    bot: &Bot,
+    state: &StateManager,
+    chat_id: ChatId,
+    owner: &str,
+    repo: &str,
+) -> usize {
+    let msgs = match state.get_active_messages_for_repo(owner, repo).await {
+        Ok(msgs) => msgs,
+        Err(e) => {
+            error!("Failed to load messages for {}/{}: {}", owner, repo, e);
+            return 0;
+        }
+    };
+
+    let mut removed = 0;
+    for m in msgs.into_iter().filter(|m| m.chat_id == chat_id.0) {
+        bot.delete_message(chat_id, MessageId(m.message_id.parse().unwrap_or(0)))
+            .retried()
+            .await
+            .ok();
+        state.audit(format!(
+            "deleted {}: /untrack clean",
+            audit::card(&format!("{}/{}", owner, repo), m.pr_number, chat_id.0)
+        ));
+        match state.remove_message(&m.message_id, m.chat_id).await {
+            Ok(()) => removed += 1,
+            Err(e) => error!("Failed to remove message from DB: {}", e),
+        }
     }
-    None
+    removed
+}
+
+/// Finds a PR link to `web_url` (github.com, or the GitHub Enterprise server) in the text,
+/// or with `issues` (`TRACK_ISSUES`) an issue link too.
+fn extract_pr_info(
+    text: &str,
+    web_url: &str,
+    issues: bool,
+) -> Option<(String, String, u64, ItemType)> {
+    let re = Regex::new(&format!(
+        r"{}/([^/]+)/([^/]+)/(pull|issues)/(\d+)",
+        regex::escape(web_host(web_url))
+    ))
+    .unwrap();
+    let captures = re.captures_iter(text).find(|c| issues || &c[3] == "pull")?;
+    let owner = captures.get(1)?.as_str().to_string();
+    let repo = captures.get(2)?.as_str().to_string();
+    let number = captures.get(4)?.as_str().parse::<u64>().ok()?;
+    let item_type = match &captures[3] {
+        "issues" => ItemType::Issue,
+        _ => ItemType::Pr,
+    };
+    Some((owner, repo, number, item_type))
+}
+
+/// Whether the card's text shows time passing, so it goes out of date without any change.
+pub fn shows_elapsed_time(data: &PrData) -> bool {
+    data.stale_since.is_some()
+        || data
+            .review_sessions
+            .iter()
+            .any(|s| sessions::is_running(data, s))
 }
 
+/// Heads the card of a security-sensitive PR, and a group card with one.
+pub const SECURITY_NOTICE: &str =
+    "🔒 <b>Security-sensitive:</b> needs a security reviewer's approval\n\n";
+
 pub fn generate_message_text(data: &PrData) -> String {
+    if compact::is_compact(data) {
+        return compact::render(data);
+    }
+    let kind = match data.item_type {
+        ItemType::Pr => "PR",
+        ItemType::Issue => "Issue",
+    };
     let mut text = format!(
-        "<b>PR:</b> <a href=\"{}\">{}</a>\n",
-        data.pr_url, data.title
+        "<b>{}:</b> <a href=\"{}\">{}</a>\n",
+        kind, data.pr_url, data.title
     );
-    text.push_str(&format!("<b>Author:</b> {}\n", data.author));
-    text.push_str(&format!("<b>Repo:</b> {}\n\n", data.repo));
+    match &data.owner {
+        Some(owner) => text.push_str(&format!(
+            "<b>Author:</b> {} (taken over by {})\n",
+            data.author, owner
+        )),
+        None => text.push_str(&format!("<b>Author:</b> {}\n", data.author)),
+    }
+    text.push_str(&format!("<b>Repo:</b> {}\n", data.repo));
+    if !data.labels.is_empty() {
+        text.push_str(&format!("<b>Labels:</b> {}\n", data.labels.join(", ")));
+    }
+    if data.item_type == ItemType::Issue {
+        let assignees = if data.assignees.is_empty() {
+            "nobody yet".to_string()
+        } else {
+            data.assignees.join(", ")
+        };
+        text.push_str(&format!("<b>Assignees:</b> {}\n", assignees));
+    }
+    if !data.tickets.is_empty() {
+        let links: Vec<String> = data
+            .tickets
+            .iter()
+            .map(|t| {
+                format!(
+                    "<a href=\"{}\">{}</a>",
+                    teloxide::utils::html::escape(&t.url),
+                    teloxide::utils::html::escape(&t.id)
+                )
+            })
+            .collect();
+        text.push_str(&format!("🎫 {}\n", links.join(", ")));
+    }
+    text.push('\n');
+
+    text.push_str(&format!(
+        "<b>Status:</b> {} {}\n\n",
+        data.status.badge(),
+        data.status.label()
+    ));
+
+    if let Some(until) = data
+        .snoozed_until
+        .and_then(|t| chrono::DateTime::from_timestamp(t, 0))
+    {
+        text.push_str(&format!(
+            "💤 Snoozed until {}\n\n",
+            until.format("%Y-%m-%d %H:%M UTC")
+        ));
+    }
+
+    if data.security {
+        text.push_str(SECURITY_NOTICE);
+    }
+
+    if data.conflicts {
+        text.push_str("⚠️ <b>Has conflicts</b> with the base branch\n\n");
+    }
+
+    if let Some(since) = data.stale_since {
+        text.push_str(&format!(
+            "🕸 <b>Stale:</b> no activity for {} days\n\n",
+            (chrono::Utc::now().timestamp() - since) / 86400
+        ));
+    }
+
+    if !data.departed.is_empty() {
+        text.push_str(&format!(
+            "🚪 <b>Left the chat:</b> {} — reply /takeover to take over\n\n",
+            data.departed.join(", ")
+        ));
+    }
+
+    match data.unresolved_threads {
+        Some(1) => text.push_str("💬 <b>1 unresolved conversation</b> blocks the merge\n\n"),
+        Some(n) if n > 1 => text.push_str(&format!(
+            "💬 <b>{} unresolved conversations</b> block the merge\n\n",
+            n
+        )),
+        _ => {}
+    }
 
-    if data.is_merged {
-        text.push_str("<b>Status:</b> 💯 MERGED\n\n");
-    } else if data.is_draft {
-        text.push_str("<b>Status:</b> 🍳 Draft/WIP\n\n");
+    if let Some(ci) = &data.ci {
+        text.push_str(&format!("<b>Checks:</b> {}\n", ci.render()));
+    }
+    if !data.gates.is_empty() {
+        text.push_str(&format!("<b>Gates:</b> {}\n", checks::render(&data.gates)));
+    }
+    if let Some(stages) = data.approval_stages() {
+        text.push_str(&format!("<b>Approvals:</b> {}\n", stages.render()));
+    }
+    if data.ci.is_some() || !data.gates.is_empty() || data.maintainers.is_some() {
+        text.push('\n');
     }
 
-    if data.re_review_requested {
+    if data.is_re_review() {
         text.push_str("🙏 <b>Re-review Requested!</b>\n\n");
     }
 
     if !data.reviewers.is_empty() {
+        let reviewers: Vec<String> = data
+            .reviewers
+            .iter()
+            .map(|r| {
+                match data
+                    .review_sessions
+                    .iter()
+                    .find(|s| &s.reviewer == r && sessions::is_running(data, s))
+                {
+                    Some(session) => {
+                        format!("{} (reviewing for {})", r, sessions::elapsed(session))
+                    }
+                    None => r.clone(),
+                }
+            })
+            .collect();
+        text.push_str(&format!("❤️ <b>Reviewers:</b> {}\n", reviewers.join(", ")));
+    }
+    // Asked on GitHub, until they review there or claim the review here
+    let requested: Vec<String> = data
+        .requested_reviewers
+        .iter()
+        .flatten()
+        .filter(|r| !data.reviewers.iter().any(|c| c.eq_ignore_ascii_case(r)))
+        .map(|r| teloxide::utils::html::escape(r))
+        .collect();
+    if !requested.is_empty() {
         text.push_str(&format!(
-            "❤️ <b>Reviewers:</b> {}\n",
-            data.reviewers.join(", ")
+            "⏳ <b>Review requested:</b> {}\n",
+            requested.join(", ")
         ));
     }
-    if !data.approvals.is_empty() {
+    // Approvals with comments stand apart until the author acknowledges them
+    let suggested = |name: &String| {
+        data.suggestions
+            .iter()
+            .any(|s| &s.reviewer == name && !s.acked)
+    };
+    let plain: Vec<&String> = data.approvals.iter().filter(|a| !suggested(a)).collect();
+    if !plain.is_empty() {
         text.push_str(&format!(
             "👍 <b>Approved:</b> {}\n",
-            data.approvals.join(", ")
+            by_source(data, &plain)
         ));
     }
+    for name in data.approvals.iter().filter(|a| suggested(a)) {
+        text.push_str(&format!("👍💬 {} (approved w/ comments)\n", name));
+    }
+    if data.awaiting_ack() {
+        text.push_str("💬 Author, reply /ack once the suggestions are addressed\n");
+    }
     if !data.changes_requested.is_empty() {
         text.push_str(&format!(
             "❌ <b>Changes Requested:</b> {}\n",
-            data.changes_requested.join(", ")
+            by_source(data, &data.changes_requested.iter().collect::<Vec<_>>())
         ));
     }
     if !data.comments.is_empty() {
         text.push_str(&format!(
             "👌 <b>Comments:</b> {}\n",
-            data.comments.join(", ")
+            by_source(data, &data.comments.iter().collect::<Vec<_>>())
         ));
     }
 
     text
 }
+
+/// "bob · alice, carol on GitHub": names marked in Telegram first, then those whose entry is
+/// their GitHub review.
+fn by_source(data: &PrData, names: &[&String]) -> String {
+    let (github, telegram): (Vec<&str>, Vec<&str>) = names
+        .iter()
+        .map(|n| n.as_str())
+        .partition(|n| data.from_github.iter().any(|g| g == n));
+    let mut parts = vec![];
+    if !telegram.is_empty() {
+        parts.push(telegram.join(", "));
+    }
+    if !github.is_empty() {
+        parts.push(format!("{} on GitHub", github.join(", ")));
+    }
+    parts.join(" · ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn card_commands_match_whole_words() {
+        assert_eq!(command_name("/review"), "/review");
+        assert_eq!(command_name("/review@pr_bot looks good"), "/review");
+        assert_eq!(command_name("  /label bug"), "/label");
+        assert_eq!(command_name(""), "");
+        assert_eq!(command_action("/review"), Some(ReactionAction::Review));
+        assert_eq!(command_action(command_name("/reviewers")), None);
+        assert_eq!(
+            command_action(command_name("/rereview@pr_bot")),
+            Some(ReactionAction::ReReview)
+        );
+        assert!(!CARD_COMMANDS.contains(&command_name("/blocked")));
+    }
+}
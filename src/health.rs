@@ -0,0 +1,115 @@
+use crate::state::StateManager;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::{Json, Router};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, LazyLock};
+use tokio::time::Duration;
+use tracing::warn;
+
+pub const HEALTH_PATH: &str = "/healthz";
+/// Polling cycles that may pass without a successful one before the bot reports unhealthy,
+/// but never less than `MIN_DEADLINE`: a cycle over many repositories takes a while itself.
+const MISSED_POLLS: u32 = 3;
+const MIN_DEADLINE: Duration = Duration::from_secs(10 * 60);
+
+/// Unix times of the last successful GitHub poll and Telegram call, and of when a sync paused
+/// for the rate limit resumes; 0 until there is one.
+static LAST_POLL: AtomicI64 = AtomicI64::new(0);
+static LAST_TELEGRAM_CALL: AtomicI64 = AtomicI64::new(0);
+static PAUSED_UNTIL: AtomicI64 = AtomicI64::new(0);
+/// Until the first poll, its deadline counts from the start.
+static STARTED: LazyLock<i64> = LazyLock::new(|| Utc::now().timestamp());
+
+pub fn poll_succeeded() {
+    LAST_POLL.store(Utc::now().timestamp(), Ordering::Relaxed);
+    PAUSED_UNTIL.store(0, Ordering::Relaxed);
+}
+
+pub fn sync_paused(until: DateTime<Utc>) {
+    PAUSED_UNTIL.store(until.timestamp(), Ordering::Relaxed);
+}
+
+pub fn telegram_call_succeeded() {
+    LAST_TELEGRAM_CALL.store(Utc::now().timestamp(), Ordering::Relaxed);
+}
+
+fn last(at: &AtomicI64) -> Option<DateTime<Utc>> {
+    match at.load(Ordering::Relaxed) {
+        0 => None,
+        at => DateTime::from_timestamp(at, 0),
+    }
+}
+
+struct HealthState {
+    state: Arc<StateManager>,
+    poll_interval: Duration,
+}
+
+#[derive(Serialize)]
+struct Health {
+    healthy: bool,
+    database: String,
+    last_poll: Option<DateTime<Utc>>,
+    sync_paused_until: Option<DateTime<Utc>>,
+    last_telegram_call: Option<DateTime<Utc>>,
+    safe_mode: bool,
+}
+
+/// Router answering `GET /healthz` with 200, or 503 when the database can't be reached or the
+/// GitHub sync hasn't completed a poll for several intervals, so an orchestrator can restart
+/// the bot. Telegram calls only happen when there's something to send, so their time is
+/// reported but doesn't fail the check.
+pub fn router(state: Arc<StateManager>, poll_interval: Duration) -> Router {
+    LazyLock::force(&STARTED);
+    Router::new()
+        .route(HEALTH_PATH, get(serve))
+        .with_state(Arc::new(HealthState {
+            state,
+            poll_interval,
+        }))
+}
+
+async fn serve(State(ctx): State<Arc<HealthState>>) -> impl IntoResponse {
+    let database = match ctx.state.ping().await {
+        Ok(()) => "ok".to_string(),
+        Err(e) => e.to_string(),
+    };
+    let now = Utc::now();
+    let last_poll = last(&LAST_POLL);
+    let deadline = (ctx.poll_interval * MISSED_POLLS)
+        .max(MIN_DEADLINE)
+        .as_secs() as i64;
+    // A sync paused for the rate limit is waiting on purpose, not dead
+    let sync_paused_until = last(&PAUSED_UNTIL).filter(|until| *until > now);
+    // Safe mode doesn't sync at all, and a restart wouldn't get it out of it
+    let safe_mode = ctx.state.safe_mode().is_some();
+    let polling = safe_mode
+        || sync_paused_until.is_some()
+        || now.timestamp() - last_poll.map_or(*STARTED, |t| t.timestamp()) <= deadline;
+
+    let health = Health {
+        healthy: database == "ok" && polling,
+        database,
+        last_poll,
+        sync_paused_until,
+        last_telegram_call: last(&LAST_TELEGRAM_CALL),
+        safe_mode,
+    };
+    if !health.healthy {
+        warn!(
+            "Health check failed: database={} last_poll={:?}",
+            health.database, health.last_poll
+        );
+    }
+    let status = if health.healthy {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(health))
+}
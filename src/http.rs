@@ -0,0 +1,68 @@
+use crate::metrics::Timed;
+use anyhow::Result;
+use axum::Router;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use teloxide::prelude::*;
+use teloxide::types::AllowedUpdate;
+use teloxide::update_listeners::{webhooks, UpdateListener};
+use tracing::{error, info};
+use url::Url;
+
+/// HTTP routers grouped by listen address, so endpoints configured on the same
+/// address are served by a single listener.
+#[derive(Default)]
+pub struct HttpServers {
+    servers: Vec<(String, Router)>,
+}
+
+impl HttpServers {
+    pub fn mount(&mut self, addr: &str, router: Router) {
+        match self.servers.iter_mut().find(|(a, _)| a == addr) {
+            Some((_, existing)) => *existing = std::mem::take(existing).merge(router),
+            None => self.servers.push((addr.to_string(), router)),
+        }
+    }
+
+    pub fn spawn(self) {
+        for (addr, router) in self.servers {
+            tokio::spawn(async move {
+                let listener = match tokio::net::TcpListener::bind(&addr).await {
+                    Ok(listener) => listener,
+                    Err(e) => {
+                        error!("Failed to bind HTTP listener on {}: {}", addr, e);
+                        return;
+                    }
+                };
+                info!("HTTP listener started on {}", addr);
+                if let Err(e) = axum::serve(listener, router).await {
+                    error!("HTTP server on {} stopped: {}", addr, e);
+                }
+            });
+        }
+    }
+}
+
+/// Registers `url` as the bot's webhook and returns the update listener for the
+/// dispatcher together with the router that receives Telegram's deliveries.
+///
+/// teloxide's own setup doesn't forward `allowed_updates`, and Telegram only sends
+/// reaction updates when they are asked for explicitly, so registration happens here.
+pub async fn telegram_webhook(
+    bot: &Bot,
+    url: Url,
+    addr: SocketAddr,
+    secret: String,
+    allowed_updates: Vec<AllowedUpdate>,
+) -> Result<(impl UpdateListener<Err = Infallible>, Router)> {
+    bot.set_webhook(url.clone())
+        .secret_token(secret.clone())
+        .allowed_updates(allowed_updates)
+        .timed()
+        .await?;
+    info!("Telegram webhook registered at {}", url);
+
+    let options = webhooks::Options::new(addr, url).secret_token(secret);
+    let (listener, _stop_flag, router) = webhooks::axum_no_setup(options);
+    Ok((listener, router))
+}
@@ -0,0 +1,217 @@
+use crate::bulk;
+use crate::checks::GateState;
+use crate::handlers;
+use crate::lifecycle::PrStatus;
+use crate::metrics::Timed;
+use crate::monitor::Monitor;
+use crate::sender::Retried;
+use crate::state::PrData;
+use chrono::Utc;
+use std::collections::BTreeMap;
+use teloxide::prelude::*;
+use teloxide::types::ParseMode;
+use tracing::{error, info};
+
+const JOB: &str = "lead_inbox";
+const CHECK_INTERVAL: tokio::time::Duration = tokio::time::Duration::from_secs(5 * 60);
+/// PRs listed per section, keeping the DM under Telegram's message size limit.
+const SECTION_LIMIT: usize = 10;
+
+/// A PR listed in the inbox: its card's message id, its data and a note on why it's there.
+type Entry<'a> = (&'a String, &'a PrData, String);
+
+/// Sends every chat lead a private triage of their chats' PRs once a day at `LEAD_INBOX_TIME` (UTC).
+pub async fn run(monitor: Monitor) {
+    loop {
+        send_due(&monitor).await;
+        tokio::time::sleep(CHECK_INTERVAL).await;
+    }
+}
+
+async fn send_due(monitor: &Monitor) {
+    let config = monitor.config();
+    let Some(time) = config.lead_inbox_time else {
+        return;
+    };
+    let now = Utc::now();
+    let scheduled = now.date_naive().and_time(time).and_utc();
+    if now < scheduled {
+        return;
+    }
+
+    // A lead of several chats gets a single DM
+    let mut leads: BTreeMap<i64, Vec<i64>> = BTreeMap::new();
+    for lead in monitor.state.get_all_leads().await.unwrap_or_default() {
+        leads.entry(lead.user_id).or_default().push(lead.chat_id);
+    }
+
+    for (user_id, chats) in leads {
+        // Private chats share their id with the user, so the job is tracked per lead
+        let last_run = monitor.state.get_job_run(user_id, JOB).await.ok().flatten();
+        if last_run.is_some_and(|t| t >= scheduled.timestamp()) {
+            continue;
+        }
+        monitor
+            .state
+            .set_job_run(user_id, JOB, now.timestamp())
+            .await
+            .ok();
+
+        let mut text = String::new();
+        for chat_id in chats {
+            if let Some(section) = chat_inbox(monitor, chat_id, config.review_sla_hours).await {
+                text.push_str(&section);
+            }
+        }
+        if text.is_empty() {
+            continue;
+        }
+
+        info!("Sending the lead inbox to {}", user_id);
+        match monitor
+            .bot
+            .send_message(
+                ChatId(user_id),
+                format!("📥 <b>Your PR inbox</b>\n{}", text),
+            )
+            .parse_mode(ParseMode::Html)
+            .link_preview_options(handlers::no_link_preview())
+            .retried()
+            .await
+        {
+            Ok(_) => monitor
+                .state
+                .audit(format!("notified {}: lead inbox", user_id)),
+            // Bots can't start a private chat; the lead has to message the bot first
+            Err(e) => error!("Failed to send the lead inbox to {}: {}", user_id, e),
+        }
+    }
+}
+
+/// The triage of one chat, or `None` when nothing in it needs the lead.
+async fn chat_inbox(monitor: &Monitor, chat_id: i64, sla_hours: u32) -> Option<String> {
+    let now = Utc::now().timestamp();
+    let mut cards = Vec::new();
+    for msg in monitor
+        .state
+        .get_chat_messages(chat_id)
+        .await
+        .unwrap_or_default()
+    {
+        if let Ok(Some(data)) = monitor
+            .state
+            .get_pr_data(msg.message_id.clone(), chat_id)
+            .await
+        {
+            if !data.status.is_terminal() {
+                cards.push((msg.message_id, data));
+            }
+        }
+    }
+
+    let waiting = |d: &PrData| matches!(d.status, PrStatus::AwaitingReview | PrStatus::InReview);
+    let sections: [(&str, Vec<Entry>); 4] = [
+        (
+            "⏰ Past the review SLA",
+            cards
+                .iter()
+                .filter(|(_, d)| {
+                    sla_hours > 0 && waiting(d) && now - d.status_since > sla_hours as i64 * 3600
+                })
+                .map(|(m, d)| {
+                    (
+                        m,
+                        d,
+                        format!("waiting {}", bulk::format_duration(now - d.status_since)),
+                    )
+                })
+                .collect(),
+        ),
+        (
+            "🔒 Security-sensitive, no reviewer",
+            cards
+                .iter()
+                .filter(|(_, d)| {
+                    d.security
+                        && d.status != PrStatus::Draft
+                        && d.reviewers.is_empty()
+                        && d.approvals.is_empty()
+                        && d.comments.is_empty()
+                })
+                .map(|(m, d)| (m, d, String::new()))
+                .collect(),
+        ),
+        (
+            "✅ Approved, not merged",
+            cards
+                .iter()
+                .filter(|(_, d)| matches!(d.status, PrStatus::Approved | PrStatus::ReadyToMerge))
+                .map(|(m, d)| {
+                    (
+                        m,
+                        d,
+                        format!("for {}", bulk::format_duration(now - d.status_since)),
+                    )
+                })
+                .collect(),
+        ),
+        (
+            "⛔ Blocked",
+            cards
+                .iter()
+                .filter_map(|(m, d)| {
+                    let reasons = block_reasons(d);
+                    (!reasons.is_empty()).then(|| (m, d, reasons.join(", ")))
+                })
+                .collect(),
+        ),
+    ];
+    if sections.iter().all(|(_, s)| s.is_empty()) {
+        return None;
+    }
+
+    let title = match monitor.bot.get_chat(ChatId(chat_id)).timed().await {
+        Ok(chat) => chat.title().unwrap_or("this chat").to_string(),
+        Err(_) => chat_id.to_string(),
+    };
+    let mut text = format!("\n<b>{}</b>\n", teloxide::utils::html::escape(&title));
+    for (heading, section) in sections.iter().filter(|(_, s)| !s.is_empty()) {
+        text.push_str(&format!("{} ({})\n", heading, section.len()));
+        for (message_id, data, note) in section.iter().take(SECTION_LIMIT) {
+            let link =
+                handlers::message_link(chat_id, message_id).unwrap_or_else(|| data.pr_url.clone());
+            text.push_str(&format!(
+                "• <a href=\"{}\">{}#{}</a> {}",
+                link,
+                data.repo,
+                data.pr_number,
+                teloxide::utils::html::escape(&data.title)
+            ));
+            if !note.is_empty() {
+                text.push_str(&format!(" — {}", teloxide::utils::html::escape(note)));
+            }
+            text.push('\n');
+        }
+        if section.len() > SECTION_LIMIT {
+            text.push_str(&format!("…and {} more\n", section.len() - SECTION_LIMIT));
+        }
+    }
+    Some(text)
+}
+
+/// Why an open PR can't move forward: marked blocked, conflicts or failed check gates.
+fn block_reasons(data: &PrData) -> Vec<String> {
+    let mut reasons = Vec::new();
+    if data.status == PrStatus::Blocked {
+        reasons.push("marked blocked".to_string());
+    }
+    if data.conflicts {
+        reasons.push("merge conflicts".to_string());
+    }
+    for (gate, state) in &data.gates {
+        if *state == GateState::Failed {
+            reasons.push(format!("{} failed", gate));
+        }
+    }
+    reasons
+}
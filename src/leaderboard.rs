@@ -0,0 +1,98 @@
+use crate::monitor::Monitor;
+use crate::sender::Retried;
+use chrono::{Datelike, Duration, Utc};
+use std::collections::BTreeSet;
+use teloxide::prelude::*;
+use tracing::{error, info};
+
+const JOB: &str = "weekly_leaderboard";
+const CHECK_INTERVAL: tokio::time::Duration = tokio::time::Duration::from_secs(60 * 60);
+const PLACES: usize = 10;
+
+/// Posts who reviewed and approved the most PRs over the past week, every `LEADERBOARD_DAY` (UTC).
+pub async fn run(monitor: Monitor) {
+    loop {
+        post_due(&monitor).await;
+        tokio::time::sleep(CHECK_INTERVAL).await;
+    }
+}
+
+async fn post_due(monitor: &Monitor) {
+    let Some(day) = monitor.config().leaderboard_day else {
+        return;
+    };
+    let now = Utc::now();
+    if now.weekday() != day {
+        return;
+    }
+
+    let mut chats: BTreeSet<i64> = BTreeSet::new();
+    for (_, subscribers) in monitor.state.get_subscriptions().await.unwrap_or_default() {
+        chats.extend(subscribers);
+    }
+    let since = (now - Duration::days(7)).timestamp();
+    for chat_id in chats {
+        // Already posted today
+        let last_run = monitor.state.get_job_run(chat_id, JOB).await.ok().flatten();
+        if last_run.is_some_and(|t| t > (now - Duration::days(6)).timestamp()) {
+            continue;
+        }
+        monitor
+            .state
+            .set_job_run(chat_id, JOB, now.timestamp())
+            .await
+            .ok();
+
+        let mut counts = match monitor.state.get_review_counts(chat_id, since).await {
+            Ok(counts) => counts,
+            Err(e) => {
+                error!("Failed to load review counts of {}: {}", chat_id, e);
+                continue;
+            }
+        };
+        if counts.is_empty() {
+            continue;
+        }
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then(b.2.cmp(&a.2)).then(a.0.cmp(&b.0)));
+
+        let lines: Vec<String> = counts
+            .iter()
+            .take(PLACES)
+            .enumerate()
+            .map(|(i, (name, reviewed, approved))| {
+                let place = match i {
+                    0 => "🥇".to_string(),
+                    1 => "🥈".to_string(),
+                    2 => "🥉".to_string(),
+                    _ => format!("{}.", i + 1),
+                };
+                format!(
+                    "{} {} — {} reviewed, {} approved",
+                    place, name, reviewed, approved
+                )
+            })
+            .collect();
+        let text = format!(
+            "🏆 Review leaderboard, last 7 days\n\n{}\n\nThanks for keeping PRs moving!",
+            lines.join("\n")
+        );
+        info!("Posting the weekly review leaderboard to {}", chat_id);
+        match monitor
+            .bot
+            .send_message(ChatId(chat_id), text)
+            .disable_notification(true)
+            .retried()
+            .await
+        {
+            Ok(_) => monitor.state.audit(format!(
+                "notified {}: weekly review leaderboard of {} reviewers",
+                chat_id,
+                counts.len()
+            )),
+            Err(e) => error!(
+                "Failed to send the review leaderboard to {}: {}",
+                chat_id, e
+            ),
+        }
+    }
+}
@@ -0,0 +1,255 @@
+use crate::state::PrData;
+use serde::{Deserialize, Serialize};
+
+/// Where a tracked PR is in its review lifecycle. Persisted as `messages.status`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PrStatus {
+    Draft,
+    #[default]
+    AwaitingReview,
+    InReview,
+    ChangesRequested,
+    Approved,
+    ReadyToMerge,
+    Merged,
+    Closed,
+    Blocked,
+    Snoozed,
+}
+
+/// Where a PR of a repository with maintainers stands on its two approvals: one from a
+/// peer and one from a maintainer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ApprovalStages {
+    pub peer: bool,
+    pub maintainer: bool,
+}
+
+impl ApprovalStages {
+    pub const fn complete(self) -> bool {
+        self.peer && self.maintainer
+    }
+
+    /// E.g. "Peer ✅ · Maintainer ⬜".
+    pub fn render(self) -> String {
+        let mark = |done| if done { "✅" } else { "⬜" };
+        format!(
+            "Peer {} · Maintainer {}",
+            mark(self.peer),
+            mark(self.maintainer)
+        )
+    }
+}
+
+/// Something that happened to a PR, from a reaction, a command or the GitHub sync.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PrEvent {
+    /// Reviewer/approval/comment lists changed
+    ReviewsChanged,
+    DraftSet(bool),
+    ReReviewRequested,
+    ReReviewWithdrawn,
+    /// GitHub reports the PR as mergeable (clean) or not
+    MergeReady(bool),
+    Merged,
+    Closed,
+    Reopened,
+    Blocked,
+    Unblocked,
+    Snoozed,
+    Unsnoozed,
+}
+
+impl PrStatus {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Draft => "draft",
+            Self::AwaitingReview => "awaiting_review",
+            Self::InReview => "in_review",
+            Self::ChangesRequested => "changes_requested",
+            Self::Approved => "approved",
+            Self::ReadyToMerge => "ready_to_merge",
+            Self::Merged => "merged",
+            Self::Closed => "closed",
+            Self::Blocked => "blocked",
+            Self::Snoozed => "snoozed",
+        }
+    }
+
+    /// Parses a persisted status; unknown values fall back to `AwaitingReview`.
+    pub fn parse(s: &str) -> Self {
+        match s {
+            "draft" => Self::Draft,
+            "in_review" => Self::InReview,
+            "changes_requested" => Self::ChangesRequested,
+            "approved" => Self::Approved,
+            "ready_to_merge" => Self::ReadyToMerge,
+            "merged" => Self::Merged,
+            "closed" => Self::Closed,
+            "blocked" => Self::Blocked,
+            "snoozed" => Self::Snoozed,
+            _ => Self::AwaitingReview,
+        }
+    }
+
+    /// Emoji badge used on cards and group lines.
+    pub const fn badge(self) -> &'static str {
+        match self {
+            Self::Draft => "🍳",
+            Self::AwaitingReview => "⏳",
+            Self::InReview => "👀",
+            Self::ChangesRequested => "❌",
+            Self::Approved => "👍",
+            Self::ReadyToMerge => "🚀",
+            Self::Merged => "💯",
+            Self::Closed => "🚫",
+            Self::Blocked => "⛔",
+            Self::Snoozed => "💤",
+        }
+    }
+
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::Draft => "Draft/WIP",
+            Self::AwaitingReview => "Awaiting review",
+            Self::InReview => "In review",
+            Self::ChangesRequested => "Changes requested",
+            Self::Approved => "Approved",
+            Self::ReadyToMerge => "Ready to merge",
+            Self::Merged => "MERGED",
+            Self::Closed => "CLOSED",
+            Self::Blocked => "Blocked",
+            Self::Snoozed => "Snoozed",
+        }
+    }
+
+    pub const fn is_terminal(self) -> bool {
+        matches!(self, Self::Merged | Self::Closed)
+    }
+
+    /// Initial status of a PR freshly fetched from GitHub.
+    pub const fn from_github(merged: bool, closed: bool, draft: bool) -> Self {
+        if merged {
+            Self::Merged
+        } else if closed {
+            Self::Closed
+        } else if draft {
+            Self::Draft
+        } else {
+            Self::AwaitingReview
+        }
+    }
+
+    /// Status implied by the review lists alone, for states that aren't sticky.
+    fn settle(self, data: &PrData) -> Self {
+        if !data.changes_requested.is_empty() {
+            Self::ChangesRequested
+        } else if data.counted_approvals().next().is_some()
+            && data.approval_stages().is_none_or(ApprovalStages::complete)
+            && !data.awaiting_ack()
+        {
+            if self == Self::ReadyToMerge {
+                Self::ReadyToMerge
+            } else {
+                Self::Approved
+            }
+        } else if !data.reviewers.is_empty()
+            || !data.comments.is_empty()
+            || !data.approvals.is_empty()
+        {
+            Self::InReview
+        } else {
+            Self::AwaitingReview
+        }
+    }
+
+    /// The lifecycle transition table.
+    ///
+    /// Merged/Closed, Draft, Blocked and Snoozed are sticky: review activity doesn't move
+    /// a PR out of them, only their dedicated counter-event does. A re-review request parks
+    /// the PR in `AwaitingReview` until reviewers act again.
+    pub fn transition(self, event: PrEvent, data: &PrData) -> Self {
+        use PrEvent as E;
+
+        match (self, event) {
+            (_, E::Merged) => Self::Merged,
+            (_, E::Closed) => Self::Closed,
+            (Self::Merged | Self::Closed, E::Reopened) => Self::AwaitingReview.settle(data),
+            (Self::Merged | Self::Closed, _) => self,
+
+            (_, E::DraftSet(true)) => Self::Draft,
+            (Self::Draft, E::DraftSet(false)) => Self::AwaitingReview.settle(data),
+            (Self::Draft, _) => self,
+
+            (_, E::Snoozed) => Self::Snoozed,
+            (Self::Snoozed, E::Unsnoozed) => Self::AwaitingReview.settle(data),
+            (Self::Snoozed, _) => self,
+
+            (_, E::Blocked) => Self::Blocked,
+            (Self::Blocked, E::Unblocked) => Self::AwaitingReview.settle(data),
+            (Self::Blocked, _) => self,
+
+            (_, E::ReReviewRequested) => Self::AwaitingReview,
+            (Self::Approved, E::MergeReady(true)) => Self::ReadyToMerge,
+            (Self::ReadyToMerge, E::MergeReady(false)) => Self::Approved,
+            (_, E::MergeReady(_)) => self,
+
+            (_, E::ReviewsChanged | E::ReReviewWithdrawn) => self.settle(data),
+            (_, E::DraftSet(false) | E::Reopened | E::Unsnoozed | E::Unblocked) => self,
+        }
+    }
+}
+
+impl PrData {
+    /// Applies `event` to the card's lifecycle status.
+    pub fn apply(&mut self, event: PrEvent) {
+        match event {
+            PrEvent::ReReviewRequested => self.review_round += 1,
+            PrEvent::ReReviewWithdrawn => self.review_round = self.review_round.saturating_sub(1),
+            _ => {}
+        }
+        let status = self.status.transition(event, self);
+        if status != self.status {
+            self.status = status;
+            self.status_since = chrono::Utc::now().timestamp();
+        }
+        if status != PrStatus::Snoozed {
+            self.snoozed_until = None;
+        }
+    }
+
+    /// The two approval stages, when the repository has maintainers. Maintainers' approvals
+    /// only count for their stage, everyone else's only for the peer one.
+    pub fn approval_stages(&self) -> Option<ApprovalStages> {
+        let maintainers = self.maintainers.as_ref()?;
+        let is_maintainer =
+            |name: &String| maintainers.iter().any(|m| m.eq_ignore_ascii_case(name));
+        Some(ApprovalStages {
+            peer: self.counted_approvals().any(|a| !is_maintainer(a)),
+            maintainer: self.counted_approvals().any(is_maintainer),
+        })
+    }
+
+    /// Whether `name` is the card's author, by GitHub login or by who posted the link.
+    pub fn is_author(&self, name: &str) -> bool {
+        name.eq_ignore_ascii_case(&self.author) || self.owner.as_deref() == Some(name)
+    }
+
+    /// The approvals that count towards the PR being approved: the author's own only where the
+    /// chat allows them (/selfapproval).
+    pub fn counted_approvals(&self) -> impl Iterator<Item = &String> {
+        self.approvals
+            .iter()
+            .filter(|a| self.self_approval || !self.is_author(a))
+    }
+
+    /// Whether an approval with comments holds the PR back until the author sends /ack.
+    pub fn awaiting_ack(&self) -> bool {
+        self.suggestions.iter().any(|s| s.holds && !s.acked)
+    }
+
+    /// Whether the card is waiting on reviewers after the author addressed feedback.
+    pub fn is_re_review(&self) -> bool {
+        self.status == PrStatus::AwaitingReview && self.review_round > 0
+    }
+}
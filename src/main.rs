@@ -1,450 +1,361 @@
-use chrono::Utc;
-use log::{error, info};
+use http::HttpServers;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
 use std::sync::Arc;
 use teloxide::prelude::*;
-use teloxide::types::{LinkPreviewOptions, MessageId, ParseMode, Recipient};
-use tokio::time::{sleep, Duration};
-
+use teloxide::types::AllowedUpdate;
+use tenants::Tenants;
+use tokio::task::JoinHandle;
+use tokio::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info};
+use tracing_subscriber::EnvFilter;
+
+mod audit;
+mod branch_health;
+mod bulk;
+mod checks;
+mod cleanup;
+mod codeowners;
+mod compact;
 mod config;
+mod dashboard;
 mod db;
+mod demo;
+mod digest;
+mod discovery;
+mod drift;
+mod fairness;
+mod federation;
+mod filter;
 mod github;
+mod groups;
 mod handlers;
+mod health;
+mod http;
+mod inbox;
+mod leaderboard;
+mod lifecycle;
+mod metrics;
+mod mirror;
+mod monitor;
+mod nudges;
+mod onboarding;
+mod reactions;
+mod reconcile;
+mod releases;
+mod reminders;
+mod report;
+mod rerender;
+mod rules;
+mod safe_mode;
+mod security;
+mod sender;
+mod sessions;
+mod stale;
 mod state;
-
+mod storage;
+mod submit;
+mod templates;
+mod tenants;
+mod tickets;
+mod tokens;
+mod updates;
+mod verification;
+mod webhook;
+
+use audit::Audit;
 use config::Config;
 use db::Db;
 use github::GithubClient;
+use monitor::Monitor;
 use state::StateManager;
 
+/// Logs to stderr, filtered by `RUST_LOG`. `LOG_FORMAT=json` writes one JSON object per line
+/// instead, carrying the fields of the spans it was logged in (repo, pr_number, chat_id, ...).
+fn init_logging() {
+    let logs = tracing_subscriber::fmt().with_env_filter(EnvFilter::from_default_env());
+    if std::env::var("LOG_FORMAT").is_ok_and(|format| format == "json") {
+        logs.json()
+            .with_current_span(false)
+            .with_span_list(true)
+            .init();
+    } else {
+        logs.init();
+    }
+}
+
 #[tokio::main]
 async fn main() {
-    env_logger::init();
+    init_logging();
     info!("Starting bot...");
 
-    let config = Config::from_env().expect("Failed to load configuration");
+    let config = Config::load().expect("Failed to load configuration");
     let bot = Bot::new(config.telegram_bot_token.clone());
-    let github =
-        GithubClient::new(config.github_token.clone()).expect("Failed to create Github client");
-
-    // Initialize DB
-    let database_url =
-        std::env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite:bot.db".to_string());
-    let db = Db::new(&database_url)
-        .await
-        .expect("Failed to connect to database");
-    let state = Arc::new(StateManager::new(db));
-
-    // Seed repositories from config
-    for (owner, repo) in &config.repositories {
-        state.add_repository(owner, repo).await.ok();
+    let monitor = start_instance(&bot, config.clone()).await;
+    let state = monitor.state.clone();
+    let github = monitor.github.clone();
+
+    // Tenants run isolated next to the operator's configuration, each with its own database
+    let mut tenant_monitors = BTreeMap::new();
+    let mut databases = vec![config.database_url.clone()];
+    for (id, path) in &config.tenants {
+        let tenant = Config::load_tenant(id, path, &config.telegram_bot_token)
+            .unwrap_or_else(|e| panic!("Failed to load the configuration of tenant {}: {}", id, e));
+        if databases.contains(&tenant.database_url) {
+            panic!("Tenant {} must have a DATABASE_URL of its own", id);
+        }
+        databases.push(tenant.database_url.clone());
+        info!("Serving tenant {}", id);
+        tenant_monitors.insert(id.clone(), start_instance(&bot, tenant).await);
+    }
+    let tenants = Arc::new(Tenants::new(monitor.clone(), tenant_monitors));
+    if state.safe_mode().is_none() {
+        tenants.bind_configured_chats().await;
     }
 
-    let bot_clone = bot.clone();
-    let config_clone = config.clone();
-    let github_clone = github.clone();
-    let state_clone = state.clone();
-
-    // Spawn GitHub monitoring task
-    tokio::spawn(async move {
-        let mut last_check = Utc::now() - chrono::Duration::minutes(1);
-
-        loop {
-            info!("Checking for new PRs...");
-            // Fetch latest list of repos from DB
-            let repos = state_clone.get_repositories().await.unwrap_or_default();
-            let ignored_repos = config_clone.ignored_repositories.clone();
-
-            for (owner, repo) in repos {
-                // Skip if this repo is in the ignored list
-                if ignored_repos.iter().any(|(o, r)| o == &owner && r == &repo) {
-                    continue;
-                }
-
-                match github_clone.get_new_prs(&owner, &repo, last_check).await {
-                    Ok(prs) => {
-                        for pr in prs {
-                            // Check if already seen using DB
-                            if state_clone
-                                .is_pr_seen(&repo, pr.id.0)
-                                .await
-                                .unwrap_or(false)
-                            {
-                                continue;
-                            }
-
-                            let title = pr.title.clone().unwrap_or_default();
-                            let author = pr
-                                .user
-                                .clone()
-                                .map(|u| u.login)
-                                .unwrap_or("unknown".to_string());
-                            let pr_url = pr
-                                .html_url
-                                .clone()
-                                .map(|u| u.to_string())
-                                .unwrap_or_default();
-
-                            let msg = format!(
-                                "New PR included:\n\nTitle: {}\nAuthor: {}\nRepo: {}/{}\nLink: {}",
-                                title, author, owner, repo, pr_url
-                            );
-
-                            // Send to configured chat ID (for monitored PRs)
-                            match bot_clone
-                                .send_message(Recipient::Id(ChatId(config_clone.chat_id)), msg)
-                                .await
-                            {
-                                Ok(sent_msg) => {
-                                    // Fetch initial reviews (if any, though usually none on creation)
-                                    let mut approvals = vec![];
-                                    let mut changes_requested = vec![];
-                                    let mut comments = vec![];
-
-                                    if let Ok(reviews) =
-                                        github_clone.get_pr_reviews(&owner, &repo, pr.number).await
-                                    {
-                                        for review in reviews {
-                                            if let Some(user) = review.user {
-                                                let username = user.login;
-                                                match review.state {
-                                                    Some(octocrab::models::pulls::ReviewState::Approved) => {
-                                                        if !approvals.contains(&username) { approvals.push(username); }
-                                                    },
-                                                    Some(octocrab::models::pulls::ReviewState::ChangesRequested) => {
-                                                        if !changes_requested.contains(&username) { changes_requested.push(username); }
-                                                    },
-                                                    Some(octocrab::models::pulls::ReviewState::Commented) => {
-                                                        if !comments.contains(&username) { comments.push(username); }
-                                                    },
-                                                    _ => {}
-                                                }
-                                            }
-                                        }
-                                    }
-
-                                    // We don't automatically track *messages* sent by this loop as "interactive" unless we want to.
-                                    // But the user requirements say "If it sees a new PR included, it will send a message... The review statuses are tracked using reactions"
-                                    // So YES, we must track this message in DB so reactions work.
-
-                                    let pr_data = state::PrData {
-                                        pr_url,
-                                        title,
-                                        author,
-                                        repo: format!("{}/{}", owner, repo),
-                                        pr_number: pr.number,
-                                        reviewers: vec![],
-                                        approvals,
-                                        changes_requested,
-                                        comments,
-                                        is_merged: pr.merged_at.is_some(),
-                                        is_draft: pr.draft.unwrap_or(false),
-                                        re_review_requested: false,
-                                        chat_id: config_clone.chat_id,
-                                    };
-                                    state_clone
-                                        .add_message(sent_msg.id.0.to_string(), pr_data)
-                                        .await
-                                        .ok();
-                                }
-                                Err(e) => error!("Failed to send message: {}", e),
-                            }
+    // SIGHUP reloads the configuration, like /reload, and every tenant's
+    #[cfg(unix)]
+    {
+        let tenants = tenants.clone();
+        tokio::spawn(async move {
+            let mut hangups =
+                tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())
+                    .expect("Failed to install SIGHUP handler");
+            while hangups.recv().await.is_some() {
+                for monitor in tenants.all().filter(|m| m.state.safe_mode().is_none()) {
+                    match monitor.reload_config().await {
+                        Ok(restart_needed) if !restart_needed.is_empty() => {
+                            info!("Reloaded; restart to apply: {}", restart_needed.join(", "))
                         }
+                        Ok(_) => {}
+                        Err(e) => error!("Failed to reload configuration: {}", e),
                     }
-                    Err(e) => error!("Failed to fetch PRs for {}/{}: {}", owner, repo, e),
                 }
             }
+        });
+    }
 
-            // Cleanup closed/merged PRs
-            if let Ok(active_msgs) = state_clone.get_all_active_messages().await {
-                for msg in active_msgs {
-                    match github_clone
-                        .get_pr_details(&msg.repo_owner, &msg.repo_name, msg.pr_number as u64)
-                        .await
-                    {
-                        Ok(pr) => {
-                            let is_closed =
-                                matches!(pr.state, Some(octocrab::models::IssueState::Closed));
-                            let is_merged = pr.merged_at.is_some();
-
-                            // Update Draft status if changed
-                            let current_draft = pr.draft.unwrap_or(false);
-                            let mut data_changed = false;
-                            let current_data_opt = state_clone
-                                .get_pr_data(msg.message_id.clone(), msg.chat_id)
-                                .await
-                                .unwrap_or(None);
-
-                            if let Some(mut data) = current_data_opt.clone() {
-                                // Check title changes
-                                let current_title = pr.title.clone().unwrap_or_default();
-                                if data.title != current_title {
-                                    info!(
-                                        "PR {}/{}#{} title changed from '{}' to '{}'. Updating...",
-                                        msg.repo_owner, msg.repo_name, msg.pr_number, data.title, current_title
-                                    );
-                                    data.title = current_title;
-                                    data_changed = true;
-                                }
-
-                                // Check draft status
-                                if msg.is_draft != current_draft {
-                                    info!(
-                                        "PR {}/{}#{} draft status changed to {}. Updating...",
-                                        msg.repo_owner, msg.repo_name, msg.pr_number, current_draft
-                                    );
-                                    data.is_draft = current_draft;
-                                    data_changed = true;
-                                }
-
-                                // Sync reviews from GitHub
-                                // Fetch reviews and requested reviewers
-                                let mut new_approvals = vec![];
-                                let mut new_changes_requested = vec![];
-                                let mut new_comments = vec![];
-                                let mut new_reviewers = vec![]; // Requested reviewers
-
-                                // 1. Get actual reviews
-                                if let Ok(reviews) = github_clone
-                                    .get_pr_reviews(
-                                        &msg.repo_owner,
-                                        &msg.repo_name,
-                                        msg.pr_number as u64,
-                                    )
-                                    .await
-                                {
-                                    // We need to deduplicate by user, taking the LATEST review state
-                                    // Reviews are returned chronologically? API docs say "The list of reviews returns in chronological order."
-                                    // So we can iterate and overwrite.
-
-                                    // Map username -> state
-                                    use std::collections::HashMap;
-                                    let mut user_state: HashMap<
-                                        String,
-                                        octocrab::models::pulls::ReviewState,
-                                    > = HashMap::new();
-
-                                    for review in reviews {
-                                        if let Some(user) = review.user {
-                                            // Ignore bots
-                                            if user.r#type == "Bot" || user.login.ends_with("[bot]")
-                                            {
-                                                continue;
-                                            }
-
-                                            if let Some(state) = review.state {
-                                                user_state.insert(user.login, state);
-                                            }
-                                        }
-                                    }
-
-                                    for (user, state) in user_state {
-                                        match state {
-                                             octocrab::models::pulls::ReviewState::Approved => new_approvals.push(user),
-                                             octocrab::models::pulls::ReviewState::ChangesRequested => new_changes_requested.push(user),
-                                             octocrab::models::pulls::ReviewState::Commented => new_comments.push(user),
-                                             _ => {} // Dismissed, Pending, etc.
-                                         }
-                                    }
-                                }
-
-                                // Sort for consistent comparison
-                                new_approvals.sort();
-                                new_changes_requested.sort();
-                                new_comments.sort();
-                                new_reviewers.sort();
-
-                                // Compare with existing data (which should also be sorted if we want strict equality, but vector equality checks elements)
-                                // Actually, PrData vectors might not be sorted. Let's sort them for comparison.
-                                data.approvals.sort();
-                                data.changes_requested.sort();
-                                data.comments.sort();
-                                data.reviewers.sort();
-
-                                if data.approvals != new_approvals
-                                    || data.changes_requested != new_changes_requested
-                                    || data.comments != new_comments
-                                    || data.reviewers != new_reviewers
-                                {
-                                    info!(
-                                        "PR {}/{}#{} review status changed. Syncing...",
-                                        msg.repo_owner, msg.repo_name, msg.pr_number
-                                    );
-                                    data.approvals = new_approvals;
-                                    data.changes_requested = new_changes_requested;
-                                    data.comments = new_comments;
-                                    data.reviewers = new_reviewers;
-                                    data_changed = true;
-                                }
-
-                                if data_changed {
-                                    if let Err(e) = state_clone
-                                        .update_pr_data(msg.message_id.clone(), data.clone())
-                                        .await
-                                    {
-                                        error!("Failed to update PR data in DB: {}", e);
-                                    } else {
-                                        // Update chat message
-                                        let new_text = handlers::generate_message_text(&data);
-                                        if let Err(e) = bot_clone
-                                            .edit_message_text(
-                                                ChatId(msg.chat_id),
-                                                MessageId(msg.message_id.parse().unwrap_or(0)),
-                                                new_text,
-                                            )
-                                            .parse_mode(ParseMode::Html)
-                                            .link_preview_options(LinkPreviewOptions {
-                                                is_disabled: true,
-                                                url: None,
-                                                prefer_small_media: false,
-                                                prefer_large_media: false,
-                                                show_above_text: false,
-                                            })
-                                            .await
-                                        {
-                                            error!("Failed to update PR message in chat: {}", e);
-                                        }
-                                    }
-                                }
-                            }
-
-                            if is_closed || is_merged {
-                                info!(
-                                    "PR {}/{}#{} is closed/merged. Cleaning up...",
-                                    msg.repo_owner, msg.repo_name, msg.pr_number
-                                );
-
-                                let message_id = MessageId(msg.message_id.parse().unwrap_or(0));
-                                let chat_id = ChatId(msg.chat_id);
-                                let status_text = if is_merged { "MERGED" } else { "CLOSED" };
-
-                                // 1. Try to delete first (works only if <48h old)
-                                let delete_result =
-                                    bot_clone.delete_message(chat_id, message_id).await;
-
-                                match &delete_result {
-                                    Ok(_) => {
-                                        info!(
-                                            "PR {}/{}#{}: Message deleted successfully",
-                                            msg.repo_owner, msg.repo_name, msg.pr_number
-                                        );
-                                    }
-                                    Err(e) => {
-                                        info!(
-                                            "PR {}/{}#{}: Could not delete message (>48h?): {}. Trying to edit...",
-                                            msg.repo_owner, msg.repo_name, msg.pr_number, e
-                                        );
+    let mut http = HttpServers::default();
+
+    // With webhooks delivering changes within seconds, polling only needs to catch missed deliveries
+    let default_poll_secs = if let (Some(addr), Some(secret)) = (
+        config
+            .webhook_listen_addr
+            .as_ref()
+            .filter(|_| state.safe_mode().is_none()),
+        &config.webhook_secret,
+    ) {
+        http.mount(addr, webhook::router(secret.clone(), monitor.clone()));
+        15 * 60
+    } else {
+        90
+    };
+    if let Some(secret) = &config.federation_secret {
+        if let Some(addr) = &config.federation_listen_addr {
+            http.mount(
+                addr,
+                federation::router(
+                    state.clone(),
+                    config.federation_instance.clone(),
+                    secret.clone(),
+                ),
+            );
+        }
+        if !config.federation_peers.is_empty() && state.safe_mode().is_none() {
+            tokio::spawn(federation::pull_peers(
+                state.clone(),
+                config.federation_peers.clone(),
+                secret.clone(),
+            ));
+        }
+    }
+    if let Some(addr) = &config.metrics_listen_addr {
+        http.mount(addr, metrics::router());
+    }
+    let poll_interval = Duration::from_secs(config.poll_interval_secs.unwrap_or(default_poll_secs));
+    if let Some(addr) = &config.health_listen_addr {
+        http.mount(addr, health::router(state.clone(), poll_interval));
+    }
 
-                                        // 2. If delete failed, try to edit
-                                        if let Some(mut data) = current_data_opt {
-                                            data.is_merged = is_merged;
+    // Spawn GitHub monitoring tasks; they're joined on shutdown so their last cycles complete
+    let shutdown = CancellationToken::new();
+    let mut syncs = Vec::new();
+    if state.safe_mode().is_none() {
+        syncs.push(spawn_tasks(&monitor, poll_interval, shutdown.clone()));
+    }
+    for tenant in tenants.all().skip(1) {
+        if tenant.state.safe_mode().is_some() {
+            continue;
+        }
+        // Webhook listeners are the operator's, so tenants poll at the default pace
+        let poll_interval = Duration::from_secs(tenant.config().poll_interval_secs.unwrap_or(90));
+        syncs.push(spawn_tasks(tenant, poll_interval, shutdown.clone()));
+    }
 
-                                            let final_text = if is_merged {
-                                                format!(
-                                                    "✅ <b>MERGED</b>\n\n<s>{}</s>",
-                                                    handlers::generate_message_text(&data)
-                                                )
-                                            } else {
-                                                format!(
-                                                    "🚫 <b>CLOSED</b>\n\n<s>{}</s>",
-                                                    handlers::generate_message_text(&data)
-                                                )
-                                            };
+    // Run Teloxide dispatcher
+    // Handlers get the configuration of the tenant serving the chat
+    let routed = dptree::map_async(tenants::route)
+        .map(|monitor: Monitor| monitor.state.clone())
+        .map(|monitor: Monitor| Arc::new(monitor.github.clone()))
+        .branch(dptree::filter(safe_mode::is_on).endpoint(safe_mode::handle))
+        .branch(Update::filter_message_reaction_updated().endpoint(handlers::handle_reaction))
+        .branch(Update::filter_callback_query().endpoint(handlers::handle_callback))
+        .branch(Update::filter_chat_member().endpoint(handlers::handle_chat_member))
+        .branch(Update::filter_message().endpoint(handlers::handle_message));
+    let handler = dptree::entry()
+        .filter_async(updates::claim)
+        .branch(
+            Update::filter_message()
+                .filter(tenants::is_command)
+                .endpoint(tenants::handle_command),
+        )
+        .branch(routed);
+
+    let mut dispatcher = Dispatcher::builder(bot.clone(), handler)
+        .dependencies(dptree::deps![
+            state.clone(),
+            Arc::new(github),
+            monitor.clone(),
+            tenants.clone()
+        ])
+        .enable_ctrlc_handler()
+        .build();
+
+    // Ctrl-C stops the dispatcher by itself; SIGTERM, which service managers send, does too
+    #[cfg(unix)]
+    {
+        let dispatcher_shutdown = dispatcher.shutdown_token();
+        tokio::spawn(async move {
+            let mut terms =
+                tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+                    .expect("Failed to install SIGTERM handler");
+            if terms.recv().await.is_some() {
+                info!("SIGTERM received, shutting down");
+                if let Ok(stopped) = dispatcher_shutdown.shutdown() {
+                    stopped.await;
+                }
+            }
+        });
+    }
 
-                                            let edit_result = bot_clone
-                                                .edit_message_text(chat_id, message_id, final_text)
-                                                .parse_mode(ParseMode::Html)
-                                                .link_preview_options(LinkPreviewOptions {
-                                                    is_disabled: true,
-                                                    url: None,
-                                                    prefer_small_media: false,
-                                                    prefer_large_media: false,
-                                                    show_above_text: false,
-                                                })
-                                                .await;
+    match &config.telegram_webhook_url {
+        Some(url) => {
+            let url = url
+                .parse()
+                .expect("TELEGRAM_WEBHOOK_URL must be a valid URL");
+            let addr = config
+                .telegram_webhook_listen_addr
+                .parse()
+                .expect("TELEGRAM_WEBHOOK_LISTEN_ADDR must be a socket address");
+            // Without an explicit secret, derive a stable one from the bot token
+            let secret = config.telegram_webhook_secret.clone().unwrap_or_else(|| {
+                hex::encode(Sha256::digest(config.telegram_bot_token.as_bytes()))[..32].to_string()
+            });
+
+            let (listener, router) = http::telegram_webhook(
+                &bot,
+                url,
+                addr,
+                secret,
+                vec![
+                    AllowedUpdate::Message,
+                    AllowedUpdate::MessageReaction,
+                    AllowedUpdate::CallbackQuery,
+                    AllowedUpdate::ChatMember,
+                ],
+            )
+            .await
+            .expect("Failed to set up Telegram webhook");
+            http.mount(&config.telegram_webhook_listen_addr, router);
+            http.spawn();
+
+            dispatcher
+                .dispatch_with_listener(
+                    listener,
+                    LoggingErrorHandler::with_custom_text("An error from the update listener"),
+                )
+                .await;
+        }
+        None => {
+            http.spawn();
+            updates::resume_polling(&bot, &state).await;
+            dispatcher.dispatch().await;
+        }
+    }
 
-                                            match &edit_result {
-                                                Ok(_) => {
-                                                    info!(
-                                                        "PR {}/{}#{}: Message edited to show {} status",
-                                                        msg.repo_owner, msg.repo_name, msg.pr_number, status_text
-                                                    );
-                                                }
-                                                Err(edit_err) => {
-                                                    // 3. If edit also failed, reply with a message to remove
-                                                    info!(
-                                                        "PR {}/{}#{}: Could not edit message: {}. Sending reply...",
-                                                        msg.repo_owner, msg.repo_name, msg.pr_number, edit_err
-                                                    );
+    // Updates are handled by now; let the sync finish its cycle before closing the database
+    info!("Waiting for the GitHub sync to stop...");
+    shutdown.cancel();
+    for sync in syncs {
+        if let Err(e) = sync.await {
+            error!("GitHub sync task failed: {}", e);
+        }
+    }
+    for monitor in tenants.all() {
+        monitor.state.close().await;
+    }
+    info!("Bot stopped");
+}
 
-                                                    let reply_text = format!(
-                                                        "⚠️ PR #{} is now <b>{}</b>. Please remove the message above.",
-                                                        msg.pr_number, status_text
-                                                    );
-                                                    match bot_clone
-                                                        .send_message(chat_id, reply_text)
-                                                        .parse_mode(ParseMode::Html)
-                                                        .reply_parameters(
-                                                            teloxide::types::ReplyParameters::new(
-                                                                message_id,
-                                                            ),
-                                                        )
-                                                        .await
-                                                    {
-                                                        Ok(_) => {
-                                                            info!(
-                                                                "PR {}/{}#{}: Sent reply requesting removal",
-                                                                msg.repo_owner, msg.repo_name, msg.pr_number
-                                                            );
-                                                        }
-                                                        Err(reply_err) => {
-                                                            error!(
-                                                                "PR {}/{}#{}: Failed to send reply: {}",
-                                                                msg.repo_owner, msg.repo_name, msg.pr_number, reply_err
-                                                            );
-                                                        }
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
+/// Connects a configuration's GitHub access and database, the operator's or a tenant's, and
+/// applies it.
+async fn start_instance(bot: &Bot, config: Config) -> Monitor {
+    let github = GithubClient::new(&config.github_auth, config.github_api_url.as_deref())
+        .await
+        .expect("Failed to create Github client");
 
-                                // Remove from DB tracking
-                                if let Err(e) = state_clone
-                                    .remove_message(&msg.message_id, msg.chat_id)
-                                    .await
-                                {
-                                    error!("Failed to remove message from DB: {}", e);
-                                } else {
-                                    info!(
-                                        "PR {}/{}#{}: Removed from DB tracking",
-                                        msg.repo_owner, msg.repo_name, msg.pr_number
-                                    );
-                                }
-                            }
-                        }
-                        Err(e) => error!(
-                            "Failed to check status for {}/{}#{}: {}",
-                            msg.repo_owner, msg.repo_name, msg.pr_number, e
-                        ),
-                    }
-                }
-            }
+    // Initialize DB
+    let db = Db::new(&config.database_url, config.tenant.is_none())
+        .await
+        .expect("Failed to connect to database");
+    let audit = match config.audit_chat_id {
+        Some(chat_id) => Audit::spawn(bot.clone(), chat_id, config.audit_thread_id),
+        None => Audit::default(),
+    };
+    let github = github.with_response_cache(db.clone());
+    let state = Arc::new(StateManager::new(db, audit));
+    if state.safe_mode().is_some() {
+        let monitor = Monitor::new(bot.clone(), github, state, config);
+        safe_mode::alert(&monitor).await;
+        return monitor;
+    }
 
-            last_check = Utc::now();
-            sleep(Duration::from_secs(90)).await;
-        }
-    });
+    // Repositories from config are subscribed to the default chat
+    state
+        .claim_unassigned_repositories(config.chat_id)
+        .await
+        .expect("Failed to migrate repository subscriptions");
 
-    // Run Teloxide dispatcher
-    let handler = dptree::entry()
-        .branch(Update::filter_message_reaction_updated().endpoint(handlers::handle_reaction))
-        .branch(Update::filter_message().endpoint(handlers::handle_message));
+    let monitor = Monitor::new(bot.clone(), github, state, config);
+    monitor.apply_config(None).await;
+    monitor.repair_duplicate_cards().await;
+    monitor
+}
 
-    Dispatcher::builder(bot, handler)
-        .dependencies(dptree::deps![state, Arc::new(github)])
-        .enable_ctrlc_handler()
-        .build()
-        .dispatch()
-        .await;
+/// Starts a configuration's background work. Returns the GitHub sync, to be joined on shutdown.
+fn spawn_tasks(
+    monitor: &Monitor,
+    poll_interval: Duration,
+    shutdown: CancellationToken,
+) -> JoinHandle<()> {
+    let sync = tokio::spawn(monitor.clone().run(poll_interval, shutdown));
+    tokio::spawn(cleanup::run(monitor.clone()));
+    tokio::spawn(reminders::run(monitor.clone()));
+    tokio::spawn(sessions::run(monitor.clone()));
+    tokio::spawn(stale::run(monitor.clone()));
+    tokio::spawn(groups::run(monitor.clone()));
+    tokio::spawn(compact::run(monitor.clone()));
+    tokio::spawn(branch_health::run(monitor.clone()));
+    tokio::spawn(discovery::run(monitor.clone()));
+    tokio::spawn(digest::run(monitor.clone()));
+    tokio::spawn(nudges::run(monitor.clone()));
+    tokio::spawn(inbox::run(monitor.clone()));
+    tokio::spawn(leaderboard::run(monitor.clone()));
+    tokio::spawn(drift::run(monitor.clone()));
+    tokio::spawn(fairness::run(monitor.clone()));
+    tokio::spawn(dashboard::run(monitor.clone()));
+    tokio::spawn(demo::run(monitor.clone()));
+    tokio::spawn(monitor.clone().check_reaction_support());
+    sync
 }
@@ -0,0 +1,299 @@
+use crate::health;
+use axum::http::header::CONTENT_TYPE;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::Router;
+use futures::future::BoxFuture;
+use futures::stream::{BoxStream, StreamExt};
+use prometheus::{
+    register_histogram, register_histogram_vec, register_int_counter, register_int_counter_vec,
+    register_int_gauge_vec, Encoder, Histogram, HistogramVec, IntCounter, IntCounterVec,
+    IntGaugeVec, TextEncoder,
+};
+use sqlx::any::{Any, AnyQueryResult, AnyRow, AnyStatement, AnyTypeInfo};
+use sqlx::{AnyPool, Describe, Either, Execute, Executor, Transaction};
+use std::future::Future;
+use std::sync::LazyLock;
+use std::time::Instant;
+use teloxide::requests::{HasPayload, Output, Payload, Request};
+use teloxide::RequestError;
+
+pub const METRICS_PATH: &str = "/metrics";
+
+/// Seconds; Telegram and GitHub calls usually take 50ms–1s, slow ones several seconds.
+const API_BUCKETS: &[f64] = &[0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+const DB_BUCKETS: &[f64] = &[
+    0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.5, 1.0,
+];
+/// Seconds; a cycle takes a few seconds per repository, more with many active cards.
+const CYCLE_BUCKETS: &[f64] = &[1.0, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0, 600.0];
+
+static GITHUB_LATENCY: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec!(
+        "github_request_duration_seconds",
+        "Latency of GitHub API calls by endpoint",
+        &["endpoint"],
+        API_BUCKETS.to_vec()
+    )
+    .expect("metric registers once")
+});
+
+static TELEGRAM_LATENCY: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec!(
+        "telegram_request_duration_seconds",
+        "Latency of Telegram Bot API calls by method",
+        &["method"],
+        API_BUCKETS.to_vec()
+    )
+    .expect("metric registers once")
+});
+
+static DB_LATENCY: LazyLock<Histogram> = LazyLock::new(|| {
+    register_histogram!(
+        "db_query_duration_seconds",
+        "Latency of database queries outside transactions",
+        DB_BUCKETS.to_vec()
+    )
+    .expect("metric registers once")
+});
+
+static RATE_LIMIT_HITS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "rate_limit_hits_total",
+        "Calls rejected by a rate limit, by api (github or telegram)",
+        &["api"]
+    )
+    .expect("metric registers once")
+});
+
+static GITHUB_ERRORS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "github_request_errors_total",
+        "Failed GitHub API calls by endpoint",
+        &["endpoint"]
+    )
+    .expect("metric registers once")
+});
+
+static GITHUB_RATE_REMAINING: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register_int_gauge_vec!(
+        "github_rate_limit_remaining",
+        "Requests left in the current GitHub rate limit window, by resource",
+        &["resource"]
+    )
+    .expect("metric registers once")
+});
+
+static TELEGRAM_ERRORS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "telegram_request_errors_total",
+        "Failed Telegram Bot API calls by method and kind (api, flood, network or other)",
+        &["method", "kind"]
+    )
+    .expect("metric registers once")
+});
+
+static PRS_ANNOUNCED: LazyLock<IntCounter> = LazyLock::new(|| {
+    register_int_counter!(
+        "prs_announced_total",
+        "PRs announced in chats, on their own cards or in group cards"
+    )
+    .expect("metric registers once")
+});
+
+static CARD_EDITS: LazyLock<IntCounter> = LazyLock::new(|| {
+    register_int_counter!(
+        "card_edits_total",
+        "PR cards edited, by the GitHub sync or from the chat"
+    )
+    .expect("metric registers once")
+});
+
+static POLL_CYCLE: LazyLock<Histogram> = LazyLock::new(|| {
+    register_histogram!(
+        "poll_cycle_duration_seconds",
+        "Duration of GitHub polling cycles",
+        CYCLE_BUCKETS.to_vec()
+    )
+    .expect("metric registers once")
+});
+
+/// Router serving every metric in the Prometheus text format at `GET /metrics`.
+pub fn router() -> Router {
+    Router::new().route(METRICS_PATH, get(serve))
+}
+
+async fn serve() -> impl IntoResponse {
+    let encoder = TextEncoder::new();
+    let mut body = Vec::new();
+    if let Err(e) = encoder.encode(&prometheus::gather(), &mut body) {
+        tracing::error!("Failed to encode metrics: {}", e);
+    }
+    ([(CONTENT_TYPE, encoder.format_type().to_string())], body)
+}
+
+/// Runs a GitHub call, recording its latency under `endpoint` and whether a rate limit
+/// rejected it.
+pub async fn github<T>(
+    endpoint: &'static str,
+    call: impl Future<Output = Result<T, octocrab::Error>>,
+) -> Result<T, octocrab::Error> {
+    let started = Instant::now();
+    let result = call.await;
+    GITHUB_LATENCY
+        .with_label_values(&[endpoint])
+        .observe(started.elapsed().as_secs_f64());
+    if let Err(e) = &result {
+        GITHUB_ERRORS.with_label_values(&[endpoint]).inc();
+        if is_rate_limit(e) {
+            RATE_LIMIT_HITS.with_label_values(&["github"]).inc();
+        }
+    }
+    result
+}
+
+/// Records what is left of a GitHub rate limit (`core`, `graphql` or `search`).
+pub fn github_rate_remaining(resource: &str, remaining: i64) {
+    GITHUB_RATE_REMAINING
+        .with_label_values(&[resource])
+        .set(remaining);
+}
+
+pub fn prs_announced(count: usize) {
+    PRS_ANNOUNCED.inc_by(count as u64);
+}
+
+pub fn card_edited() {
+    CARD_EDITS.inc();
+}
+
+pub fn poll_cycle_finished(elapsed: std::time::Duration) {
+    POLL_CYCLE.observe(elapsed.as_secs_f64());
+}
+
+/// Whether GitHub rejected the call for a rate limit. Primary limits answer 403, secondary
+/// ones 403 or 429; both say "rate limit".
+pub fn is_rate_limit(e: &octocrab::Error) -> bool {
+    match e {
+        octocrab::Error::GitHub { source, .. } => {
+            matches!(source.status_code.as_u16(), 403 | 429)
+                && source.message.to_lowercase().contains("rate limit")
+        }
+        _ => false,
+    }
+}
+
+/// Sends a Telegram request, recording its latency under the Bot API method's name and
+/// whether flood control rejected it.
+pub trait Timed: Request<Err = RequestError> {
+    fn timed(self) -> impl Future<Output = Result<Output<Self>, RequestError>> + Send;
+}
+
+impl<R> Timed for R
+where
+    R: Request<Err = RequestError> + Send,
+{
+    fn timed(self) -> impl Future<Output = Result<Output<Self>, RequestError>> + Send {
+        let started = Instant::now();
+        let request = self.send();
+        async move {
+            let result = request.await;
+            telegram::<<R as HasPayload>::Payload, _>(started, &result);
+            result
+        }
+    }
+}
+
+/// Records one finished Telegram call started at `started`.
+pub fn telegram<P: Payload, T>(started: Instant, result: &Result<T, RequestError>) {
+    TELEGRAM_LATENCY
+        .with_label_values(&[P::NAME])
+        .observe(started.elapsed().as_secs_f64());
+    let kind = match result {
+        Ok(_) => {
+            health::telegram_call_succeeded();
+            return;
+        }
+        Err(RequestError::RetryAfter(_)) => {
+            RATE_LIMIT_HITS.with_label_values(&["telegram"]).inc();
+            "flood"
+        }
+        Err(RequestError::Api(_) | RequestError::MigrateToChatId(_)) => "api",
+        Err(RequestError::Network(_) | RequestError::Io(_)) => "network",
+        Err(_) => "other",
+    };
+    TELEGRAM_ERRORS.with_label_values(&[P::NAME, kind]).inc();
+}
+
+/// The database pool, timing every query run on it for `db_query_duration_seconds`.
+/// Queries inside transactions run on the transaction's connection and aren't timed.
+#[derive(Clone, Debug)]
+pub struct TimedPool(pub AnyPool);
+
+impl TimedPool {
+    pub async fn begin(&self) -> Result<Transaction<'static, Any>, sqlx::Error> {
+        self.0.begin().await
+    }
+
+    pub async fn close(&self) {
+        self.0.close().await
+    }
+}
+
+impl<'p> Executor<'p> for &'p TimedPool {
+    type Database = Any;
+
+    fn fetch_many<'e, 'q: 'e, E>(
+        self,
+        query: E,
+    ) -> BoxStream<'e, Result<Either<AnyQueryResult, AnyRow>, sqlx::Error>>
+    where
+        'p: 'e,
+        E: 'q + Execute<'q, Any>,
+    {
+        // Observed once the caller is done with the stream
+        let timer = DB_LATENCY.start_timer();
+        self.0
+            .fetch_many(query)
+            .map(move |step| {
+                let _ = &timer;
+                step
+            })
+            .boxed()
+    }
+
+    fn fetch_optional<'e, 'q: 'e, E>(
+        self,
+        query: E,
+    ) -> BoxFuture<'e, Result<Option<AnyRow>, sqlx::Error>>
+    where
+        'p: 'e,
+        E: 'q + Execute<'q, Any>,
+    {
+        let timer = DB_LATENCY.start_timer();
+        let fetch = self.0.fetch_optional(query);
+        Box::pin(async move {
+            let row = fetch.await;
+            timer.observe_duration();
+            row
+        })
+    }
+
+    fn prepare_with<'e, 'q: 'e>(
+        self,
+        sql: &'q str,
+        parameters: &'e [AnyTypeInfo],
+    ) -> BoxFuture<'e, Result<AnyStatement<'q>, sqlx::Error>>
+    where
+        'p: 'e,
+    {
+        self.0.prepare_with(sql, parameters)
+    }
+
+    fn describe<'e, 'q: 'e>(self, sql: &'q str) -> BoxFuture<'e, Result<Describe<Any>, sqlx::Error>>
+    where
+        'p: 'e,
+    {
+        self.0.describe(sql)
+    }
+}
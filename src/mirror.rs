@@ -0,0 +1,170 @@
+use crate::handlers::no_link_preview;
+use crate::sender::Retried;
+use crate::state::StateManager;
+use teloxide::prelude::*;
+use teloxide::types::{MessageId, ParseMode};
+use tracing::{error, info};
+
+/// Brings the copies of a card in the chats mirroring its chat up to `text` (HTML), posting
+/// the copy in mirrors that don't have one yet. Copies carry no buttons.
+pub async fn sync(
+    bot: &Bot,
+    state: &StateManager,
+    chat_id: ChatId,
+    message_id: MessageId,
+    text: &str,
+) {
+    let mirrors = match state.get_mirror_chats(chat_id.0).await {
+        Ok(mirrors) => mirrors,
+        Err(e) => {
+            error!("Failed to load the mirrors of {}: {}", chat_id, e);
+            return;
+        }
+    };
+    if mirrors.is_empty() {
+        return;
+    }
+    let source_id = message_id.0.to_string();
+    let copies = state
+        .get_mirror_cards(chat_id.0, &source_id)
+        .await
+        .unwrap_or_default();
+
+    for mirror in mirrors {
+        match copies.iter().find(|(chat, _)| *chat == mirror) {
+            Some((_, copy_id)) => {
+                if let Err(e) = bot
+                    .edit_message_text(
+                        ChatId(mirror),
+                        MessageId(copy_id.parse().unwrap_or(0)),
+                        text,
+                    )
+                    .parse_mode(ParseMode::Html)
+                    .link_preview_options(no_link_preview())
+                    .retried()
+                    .await
+                {
+                    error!(
+                        "Failed to update the copy of {} in {}: {}",
+                        source_id, mirror, e
+                    );
+                }
+            }
+            None => {
+                match bot
+                    .send_message(ChatId(mirror), text)
+                    .parse_mode(ParseMode::Html)
+                    .link_preview_options(no_link_preview())
+                    .disable_notification(true)
+                    .retried()
+                    .await
+                {
+                    Ok(copy) => {
+                        info!("Copied card {} of {} to {}", source_id, chat_id, mirror);
+                        if let Err(e) = state
+                            .add_mirror_card(chat_id.0, &source_id, mirror, &copy.id.0.to_string())
+                            .await
+                        {
+                            error!(
+                                "Failed to record the copy of {} in {}: {}",
+                                source_id, mirror, e
+                            );
+                        }
+                    }
+                    Err(e) => error!("Failed to copy card {} to {}: {}", source_id, mirror, e),
+                }
+            }
+        }
+    }
+}
+
+/// Leaves the copies of an archived card in place as `text` (HTML), like the card itself, and
+/// stops following them.
+pub async fn archive(
+    bot: &Bot,
+    state: &StateManager,
+    chat_id: ChatId,
+    message_id: MessageId,
+    text: &str,
+) {
+    sync(bot, state, chat_id, message_id, text).await;
+    state
+        .delete_mirror_cards(chat_id.0, &message_id.0.to_string())
+        .await
+        .ok();
+}
+
+/// Strikes out the copies of a kept card as `text` (HTML), like the card itself, and schedules
+/// them for deletion along with it.
+pub async fn keep(
+    bot: &Bot,
+    state: &StateManager,
+    chat_id: ChatId,
+    message_id: MessageId,
+    text: &str,
+    delete_at: i64,
+) {
+    sync(bot, state, chat_id, message_id, text).await;
+    let source_id = message_id.0.to_string();
+    let copies = state
+        .get_mirror_cards(chat_id.0, &source_id)
+        .await
+        .unwrap_or_default();
+    for (mirror, copy_id) in &copies {
+        if let Err(e) = state.add_kept_card(*mirror, copy_id, delete_at).await {
+            error!(
+                "Failed to schedule deleting the copy of {} in {}: {}",
+                source_id, mirror, e
+            );
+        }
+    }
+    if !copies.is_empty() {
+        state.delete_mirror_cards(chat_id.0, &source_id).await.ok();
+    }
+}
+
+/// Takes down the copies of a closed card like the card itself: deleted, or replaced with
+/// `final_text` where Telegram no longer allows deleting them.
+pub async fn close(
+    bot: &Bot,
+    state: &StateManager,
+    chat_id: ChatId,
+    message_id: MessageId,
+    final_text: Option<&str>,
+) {
+    let source_id = message_id.0.to_string();
+    let copies = match state.get_mirror_cards(chat_id.0, &source_id).await {
+        Ok(copies) => copies,
+        Err(e) => {
+            error!("Failed to load the copies of {}: {}", source_id, e);
+            return;
+        }
+    };
+    for (mirror, copy_id) in &copies {
+        let copy_id = MessageId(copy_id.parse().unwrap_or(0));
+        if bot
+            .delete_message(ChatId(*mirror), copy_id)
+            .retried()
+            .await
+            .is_ok()
+        {
+            continue;
+        }
+        if let Some(final_text) = final_text {
+            bot.edit_message_text(ChatId(*mirror), copy_id, final_text)
+                .parse_mode(ParseMode::Html)
+                .link_preview_options(no_link_preview())
+                .retried()
+                .await
+                .ok();
+        }
+    }
+    if !copies.is_empty() {
+        state.delete_mirror_cards(chat_id.0, &source_id).await.ok();
+    }
+}
+
+/// Whether the chat only mirrors another chat's cards; its reactions and commands are ignored.
+pub async fn is_mirror(state: &StateManager, chat_id: ChatId) -> bool {
+    state.is_mirror_chat(chat_id.0).await.unwrap_or(false)
+}
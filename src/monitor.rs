@@ -0,0 +1,2609 @@
+use crate::audit;
+use crate::bulk;
+use crate::checks;
+use crate::codeowners;
+use crate::config::{ClosedCards, Config, PrFilter};
+use crate::db::{ArchivedMerge, ButtonMode, PrActivity, PrMessage, RepoCapabilities};
+use crate::drift;
+use crate::github::{self, GithubClient, PrSnapshot};
+use crate::groups::{self, NewPr};
+use crate::handlers;
+use crate::health;
+use crate::lifecycle::{PrEvent, PrStatus};
+use crate::metrics::{self, Timed};
+use crate::mirror;
+use crate::nudges;
+use crate::onboarding;
+use crate::reconcile;
+use crate::releases;
+use crate::report::{CycleReport, SyncCounters};
+use crate::rerender;
+use crate::rules::{Facts, Rule, RuleAction};
+use crate::sender::{Retried, Throttle, GROUP_SEND_INTERVAL};
+use crate::state::{ItemType, PrData, StateManager, Suggestion};
+use crate::tickets;
+use crate::updates;
+use crate::verification;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use octocrab::models::issues::Issue;
+use octocrab::models::pulls::{MergeableState, PullRequest, ReviewState};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+use teloxide::prelude::*;
+use teloxide::types::{MessageId, ParseMode, ReactionType, Recipient, ReplyParameters, ThreadId};
+use tokio::time::{sleep, Duration};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, instrument, warn};
+
+/// Rebase instructions for `CONFLICT_NUDGE_TEXT`'s default.
+const DEFAULT_CONFLICT_NUDGE: &str = "⚠️ {author}, this PR has conflicted with {base} for {duration}. Please rebase:\ngit fetch origin && git rebase origin/{base}\ngit push --force-with-lease\nI'll check again after your next push.";
+
+/// PRs updated on GitHub within this long are synced every poll; quieter ones less often.
+const QUIET_AFTER_SECS: i64 = 60 * 60;
+
+/// Everything the background sync needs: shared by the poll loop and the webhook receiver.
+/// A card taken down once its PR closed, or once every PR of a group card did.
+pub struct ClosingCard {
+    pub chat_id: i64,
+    pub message_id: String,
+    pub name: String,         // how logs and the audit log call it, see audit::card
+    pub reason: &'static str, // e.g. "merged on GitHub"
+}
+
+#[derive(Clone)]
+pub struct Monitor {
+    pub bot: Bot,
+    pub github: GithubClient,
+    pub state: Arc<StateManager>,
+    pub config: Arc<RwLock<Arc<Config>>>, // swapped wholesale by `reload_config`
+    pub counters: SyncCounters,
+}
+
+impl Monitor {
+    pub fn new(bot: Bot, github: GithubClient, state: Arc<StateManager>, config: Config) -> Self {
+        Self {
+            bot,
+            github,
+            state,
+            config: Arc::new(RwLock::new(Arc::new(config))),
+            counters: SyncCounters::default(),
+        }
+    }
+
+    /// The current configuration. A reload doesn't change a snapshot that was already taken.
+    pub fn config(&self) -> Arc<Config> {
+        self.config.read().unwrap().clone()
+    }
+
+    /// Subscribes the configured repositories and applies their per-repo options and the mirror
+    /// chats. On reload, repositories dropped from the `GITHUB_REPOS` list are unsubscribed from
+    /// the default chat, and those whose route to a chat (`chats` or `REPO_CHATS`) was dropped
+    /// from that chat.
+    pub async fn apply_config(&self, previous: Option<&Config>) {
+        let config = self.config();
+        // Routes the configuration no longer has lose their subscriptions, but only the ones the
+        // configuration made: a chat that also sent /track keeps the repository
+        if let Some(previous) = previous {
+            let same = |a: &(String, String), owner: &str, name: &str| {
+                a.0.eq_ignore_ascii_case(owner) && a.1.eq_ignore_ascii_case(name)
+            };
+            for repo in &previous.repositories {
+                let kept = config
+                    .repositories
+                    .iter()
+                    .any(|r| same(r, &repo.0, &repo.1));
+                if !kept {
+                    self.state
+                        .remove_config_repository(previous.chat_id, &repo.0, &repo.1)
+                        .await
+                        .ok();
+                }
+            }
+            for options in &previous.repo_options {
+                let routed = |chat_id: &i64| {
+                    config.repo_options.iter().any(|o| {
+                        o.owner.eq_ignore_ascii_case(&options.owner)
+                            && o.name.eq_ignore_ascii_case(&options.name)
+                            && o.chats.contains(chat_id)
+                    })
+                };
+                for chat_id in options.chats.iter().filter(|c| !routed(c)) {
+                    self.state
+                        .remove_config_repository(*chat_id, &options.owner, &options.name)
+                        .await
+                        .ok();
+                }
+            }
+        }
+
+        for (owner, repo) in &config.repositories {
+            self.state
+                .add_config_repository(config.chat_id, owner, repo)
+                .await
+                .ok();
+        }
+        for options in &config.repo_options {
+            for chat_id in &options.chats {
+                self.state
+                    .add_config_repository(*chat_id, &options.owner, &options.name)
+                    .await
+                    .ok();
+            }
+            if let Some(gates) = &options.gates {
+                self.state
+                    .set_check_gates(&options.owner, &options.name, gates)
+                    .await
+                    .ok();
+            }
+        }
+        if let Err(e) = self.state.set_mirror_chats(&config.mirror_chats).await {
+            error!("Failed to save the mirror chats: {}", e);
+        }
+        verification::request_pending(self).await;
+    }
+
+    /// Re-reads the configuration and applies it. Returns the settings that changed but are
+    /// bound at startup, so only take effect after a restart.
+    pub async fn reload_config(&self) -> Result<Vec<&'static str>> {
+        let old = self.config();
+        let new = match &old.tenant {
+            Some((id, path)) => Config::load_tenant(id, path, &old.telegram_bot_token)?,
+            None => Config::load()?,
+        };
+
+        let mut restart_needed = Vec::new();
+        let mut check = |name: &'static str, changed: bool| {
+            if changed {
+                restart_needed.push(name);
+            }
+        };
+        check(
+            "TELEGRAM_BOT_TOKEN",
+            new.telegram_bot_token != old.telegram_bot_token,
+        );
+        check("GitHub credentials", new.github_auth != old.github_auth);
+        check("GITHUB_API_URL", new.github_api_url != old.github_api_url);
+        check("DATABASE_URL", new.database_url != old.database_url);
+        check("TENANTS", new.tenants != old.tenants);
+        check(
+            "POLL_INTERVAL_SECONDS",
+            new.poll_interval_secs != old.poll_interval_secs,
+        );
+        check(
+            "WEBHOOK_LISTEN_ADDR",
+            new.webhook_listen_addr != old.webhook_listen_addr,
+        );
+        check(
+            "GITHUB_WEBHOOK_SECRET",
+            new.webhook_secret != old.webhook_secret,
+        );
+        check(
+            "TELEGRAM_WEBHOOK_URL",
+            new.telegram_webhook_url != old.telegram_webhook_url,
+        );
+        check(
+            "TELEGRAM_WEBHOOK_LISTEN_ADDR",
+            new.telegram_webhook_listen_addr != old.telegram_webhook_listen_addr,
+        );
+        check(
+            "TELEGRAM_WEBHOOK_SECRET",
+            new.telegram_webhook_secret != old.telegram_webhook_secret,
+        );
+        check("AUDIT_CHAT_ID", new.audit_chat_id != old.audit_chat_id);
+        check(
+            "AUDIT_THREAD_ID",
+            new.audit_thread_id != old.audit_thread_id,
+        );
+        check(
+            "FEDERATION_INSTANCE",
+            new.federation_instance != old.federation_instance,
+        );
+        check(
+            "FEDERATION_SECRET",
+            new.federation_secret != old.federation_secret,
+        );
+        check(
+            "FEDERATION_LISTEN_ADDR",
+            new.federation_listen_addr != old.federation_listen_addr,
+        );
+        check(
+            "FEDERATION_PEERS",
+            new.federation_peers != old.federation_peers,
+        );
+        check(
+            "METRICS_LISTEN_ADDR",
+            new.metrics_listen_addr != old.metrics_listen_addr,
+        );
+        check(
+            "HEALTH_LISTEN_ADDR",
+            new.health_listen_addr != old.health_listen_addr,
+        );
+
+        *self.config.write().unwrap() = Arc::new(new);
+        self.apply_config(Some(&old)).await;
+        info!("Configuration reloaded");
+        Ok(restart_needed)
+    }
+
+    /// Polls every `interval` until `shutdown` is cancelled. A cycle that has started is
+    /// finished first, so no card is left half updated.
+    pub async fn run(self, interval: Duration, shutdown: CancellationToken) {
+        while !shutdown.is_cancelled() {
+            // A skipped cycle leaves the repositories' last polls alone, so PRs opened
+            // meanwhile are still found
+            if let Some(resets_at) = self.github.sync_paused_until().await {
+                health::sync_paused(resets_at);
+                warn!(
+                    "GitHub rate limit is nearly used up, pausing the sync until {}",
+                    resets_at
+                );
+            } else {
+                self.poll_cycle(interval).await;
+            }
+            tokio::select! {
+                _ = sleep(interval) => {}
+                _ = shutdown.cancelled() => {}
+            }
+        }
+        info!("GitHub sync stopped");
+    }
+
+    #[instrument(skip_all)]
+    async fn poll_cycle(&self, interval: Duration) {
+        info!("Checking for new PRs...");
+        let cycle_start = Utc::now();
+        let mut report = CycleReport::start(&self.github).await;
+        // Fetch latest subscriptions from DB; each repo is queried once for all its chats
+        let subscriptions = self.state.get_subscriptions().await.unwrap_or_default();
+        let mut new_prs: HashMap<i64, Vec<NewPr>> = HashMap::new();
+        let mut polled = Vec::new();
+        let mut asked = 0;
+
+        for ((owner, repo), chats) in subscriptions {
+            // Skip if this repo is in the ignored list
+            if self.is_ignored(&owner, &repo) {
+                continue;
+            }
+            report.repos += 1;
+
+            // Resume from the last poll, also across restarts; a repository polled for the
+            // first time only announces PRs from now on
+            let since = match self.state.get_last_poll(&owner, &repo).await {
+                Ok(Some(at)) => DateTime::from_timestamp(at, 0),
+                _ => None,
+            }
+            .unwrap_or(cycle_start - chrono::Duration::minutes(1));
+            if RepoCapabilities::denies(onboarding::of(&self.state, &owner, &repo).await.list_prs) {
+                debug!("Not polling {}/{}: the bot can't list its PRs", owner, repo);
+                continue;
+            }
+            let filter = self.pr_filter(&owner, &repo);
+            asked += 1;
+            // Issues are announced right away; when they can't be listed, the next cycle
+            // polls the repository from the same point again
+            let issues_polled = !self.config().track_issues
+                || self.announce_new_issues(&owner, &repo, since, &chats).await;
+            let releases_polled = !self.config().announce_releases
+                || releases::announce_new(self, &owner, &repo, since, &chats).await;
+            match self.github.get_new_prs(&owner, &repo, since, &filter).await {
+                Ok(prs) => {
+                    if issues_polled && releases_polled {
+                        polled.push((owner.clone(), repo.clone()));
+                    }
+                    for pr in prs {
+                        let author = pr.user.as_ref().map(|u| u.login.as_str());
+                        if author.is_some_and(|a| self.skips_author(&owner, &repo, a)) {
+                            info!(
+                                "Not announcing {}/{}#{} by a skipped author",
+                                owner, repo, pr.number
+                            );
+                            continue;
+                        }
+                        let hold = self.holds_draft(&pr);
+                        for &chat_id in &chats {
+                            // Check if already announced in this chat using DB
+                            if self
+                                .state
+                                .is_pr_seen(chat_id, &owner, &repo, pr.number)
+                                .await
+                                .unwrap_or(false)
+                            {
+                                continue;
+                            }
+                            if hold {
+                                self.hold_draft(chat_id, &owner, &repo, pr.number).await;
+                                continue;
+                            }
+                            new_prs.entry(chat_id).or_default().push((
+                                owner.clone(),
+                                repo.clone(),
+                                pr.clone(),
+                            ));
+                        }
+                    }
+                }
+                Err(e) => {
+                    error!("Failed to fetch PRs for {}/{}: {}", owner, repo, e);
+                    self.counters.error();
+                }
+            }
+        }
+
+        self.poll_searches(&mut new_prs).await;
+
+        for (chat_id, pr) in self.release_ready_drafts(None).await {
+            new_prs.entry(chat_id).or_default().push(pr);
+        }
+
+        for (chat_id, prs) in new_prs {
+            report.announced += prs.len();
+            self.announce_new_prs(chat_id, prs).await;
+        }
+        // A cycle where GitHub answered for none of the repositories asked isn't a poll
+        let answered = asked == 0 || !polled.is_empty();
+        // Only once announced, so a crash in between finds the PRs again
+        for (owner, repo) in polled {
+            if let Err(e) = self
+                .state
+                .set_last_poll(&owner, &repo, cycle_start.timestamp())
+                .await
+            {
+                error!("Failed to save the last poll of {}/{}: {}", owner, repo, e);
+            }
+        }
+
+        groups::sync_groups(self).await;
+
+        // Cleanup closed/merged PRs
+        if let Ok(active_msgs) = self.state.get_all_active_messages().await {
+            let activity: HashMap<(String, String, i64), PrActivity> = self
+                .state
+                .get_pr_activity()
+                .await
+                .unwrap_or_default()
+                .into_iter()
+                .map(|a| ((a.repo_owner.clone(), a.repo_name.clone(), a.pr_number), a))
+                .collect();
+            let now = Utc::now().timestamp();
+            // Half a cycle of slack so a quiet PR isn't pushed back a whole cycle by jitter
+            let quiet_interval = (interval.as_secs() as i64
+                * self.config().quiet_sync_every as i64)
+                .saturating_sub(interval.as_secs() as i64 / 2);
+
+            let mut skipped = 0;
+            let mut due = Vec::new();
+            for msg in active_msgs {
+                let quiet = activity
+                    .get(&(msg.repo_owner.clone(), msg.repo_name.clone(), msg.pr_number))
+                    .is_some_and(|a| {
+                        now - a.last_active > QUIET_AFTER_SECS
+                            && now - a.last_synced < quiet_interval
+                    });
+                // Expired snoozes are woken up by the sync, so those cards can't wait
+                if quiet && msg.snoozed_until.is_none_or(|t| t > now) {
+                    skipped += 1;
+                    continue;
+                }
+                due.push(msg);
+            }
+            if skipped > 0 {
+                info!("Skipped {} cards of quiet PRs this cycle", skipped);
+            }
+
+            // One GraphQL query per batch of PRs instead of two REST calls per card
+            let mut prs: Vec<github::PrKey> = due
+                .iter()
+                .filter(|m| ItemType::parse(&m.item_type) == ItemType::Pr)
+                .map(|m| {
+                    (
+                        m.repo_owner.clone(),
+                        m.repo_name.clone(),
+                        m.pr_number as u64,
+                    )
+                })
+                .collect();
+            prs.sort();
+            prs.dedup();
+            let snapshots = self.github.get_pr_snapshots(&prs).await;
+            for msg in due {
+                let key = (
+                    msg.repo_owner.clone(),
+                    msg.repo_name.clone(),
+                    msg.pr_number as u64,
+                );
+                // Issue cards are never in a batch and go to REST
+                match snapshots.get(&key) {
+                    Some(pr) => self.sync_snapshot(msg, pr.clone()).await,
+                    None => self.sync_message(msg).await,
+                }
+            }
+            // After the sync's own edits, which count as fresh renders
+            rerender::run_due(self).await;
+            self.delete_kept_cards().await;
+            self.state.prune_pr_activity().await.ok();
+            self.github.prune_response_cache().await;
+            self.state
+                .prune_updates(chrono::Utc::now().timestamp() - updates::RETENTION_SECS)
+                .await
+                .ok();
+        }
+
+        if answered {
+            health::poll_succeeded();
+        }
+
+        let config = self.config();
+        report
+            .finish(
+                &self.bot,
+                &self.github,
+                &self.counters,
+                config
+                    .cycle_report_chat_id
+                    .map(|chat_id| (chat_id, config.cycle_report_thread_id)),
+            )
+            .await;
+    }
+
+    /// Posts cards for the repository's new issues with `ISSUE_LABELS` in its chats. False when
+    /// GitHub couldn't list them.
+    async fn announce_new_issues(
+        &self,
+        owner: &str,
+        repo: &str,
+        since: DateTime<Utc>,
+        chats: &[i64],
+    ) -> bool {
+        let labels = self.config().issue_labels.clone();
+        let issues = match self
+            .github
+            .get_new_issues(owner, repo, since, &labels)
+            .await
+        {
+            Ok(issues) => issues,
+            Err(e) => {
+                error!("Failed to fetch issues for {}/{}: {}", owner, repo, e);
+                self.counters.error();
+                return false;
+            }
+        };
+        for issue in issues {
+            if self.skips_author(owner, repo, &issue.user.login) {
+                continue;
+            }
+            for &chat_id in chats {
+                if self
+                    .state
+                    .is_pr_seen(chat_id, owner, repo, issue.number)
+                    .await
+                    .unwrap_or(false)
+                {
+                    continue;
+                }
+                let data = handlers::issue_data_from_github(&issue, owner, repo, chat_id);
+                if let Err(e) = handlers::send_tracked_card(
+                    &self.bot,
+                    &self.state,
+                    ChatId(chat_id),
+                    data,
+                    "new issue",
+                )
+                .await
+                {
+                    error!(
+                        "Failed to announce issue {}/{}#{} in {}: {}",
+                        owner, repo, issue.number, chat_id, e
+                    );
+                    self.counters.error();
+                }
+            }
+        }
+        true
+    }
+
+    /// Adds the new matches of every chat's `/search` subscriptions to `new_prs`, skipping PRs
+    /// the repository polling already found for the chat. The query decides about drafts.
+    async fn poll_searches(&self, new_prs: &mut HashMap<i64, Vec<NewPr>>) {
+        let searches = match self.state.get_all_searches().await {
+            Ok(searches) => searches,
+            Err(e) => {
+                error!("Failed to load search subscriptions: {}", e);
+                return;
+            }
+        };
+        for search in searches {
+            let matches = match self.github.search_prs(&search.query).await {
+                Ok(matches) => matches,
+                Err(e) => {
+                    error!(
+                        "Search #{} ({}) of {} failed: {}",
+                        search.id, search.query, search.chat_id, e
+                    );
+                    self.counters.error();
+                    continue;
+                }
+            };
+            for (owner, repo, number) in matches {
+                if self
+                    .state
+                    .is_pr_seen(search.chat_id, &owner, &repo, number)
+                    .await
+                    .unwrap_or(true)
+                {
+                    continue;
+                }
+                let queued = new_prs.get(&search.chat_id).is_some_and(|prs| {
+                    prs.iter()
+                        .any(|(o, r, pr)| *o == owner && *r == repo && pr.number == number)
+                });
+                if queued {
+                    continue;
+                }
+                let pr = match self.github.get_pr_details(&owner, &repo, number).await {
+                    Ok(pr) => pr,
+                    Err(e) => {
+                        error!(
+                            "Failed to fetch {}/{}#{} found by search #{}: {}",
+                            owner, repo, number, search.id, e
+                        );
+                        continue;
+                    }
+                };
+                let author = pr.user.as_ref().map(|u| u.login.as_str());
+                if author.is_some_and(|a| self.skips_author(&owner, &repo, a)) {
+                    continue;
+                }
+                info!(
+                    "Search #{} found {}/{}#{} for {}",
+                    search.id, owner, repo, number, search.chat_id
+                );
+                new_prs
+                    .entry(search.chat_id)
+                    .or_default()
+                    .push((owner, repo, pr));
+            }
+        }
+    }
+
+    pub fn is_ignored(&self, owner: &str, repo: &str) -> bool {
+        self.config()
+            .ignored_repositories
+            .iter()
+            .any(|(o, r)| o == owner && r == repo)
+    }
+
+    /// The `[repos]` filter of a repository; lets every PR through when it has none.
+    pub fn pr_filter(&self, owner: &str, repo: &str) -> PrFilter {
+        self.config()
+            .repo_options
+            .iter()
+            .find(|o| o.owner == owner && o.name == repo)
+            .map(|o| o.filter.clone())
+            .unwrap_or_default()
+    }
+
+    /// The `[repos]` maintainers of a repository with teams expanded, plus the card names of
+    /// linked maintainers; `None` when the repository doesn't use two-stage approval.
+    pub async fn maintainer_names(
+        &self,
+        owner: &str,
+        repo: &str,
+        identities: &HashMap<String, String>,
+    ) -> Option<Vec<String>> {
+        let configured = self
+            .config()
+            .repo_options
+            .iter()
+            .find(|o| o.owner == owner && o.name == repo)
+            .map(|o| o.maintainers.clone())
+            .filter(|m| !m.is_empty())?;
+        let mut logins = Vec::new();
+        for maintainer in configured {
+            match maintainer.split_once('/') {
+                Some((org, team)) => logins.extend(self.github.get_team_members(org, team).await),
+                None => logins.push(maintainer.trim_start_matches('@').to_string()),
+            }
+        }
+        let mut names = Vec::new();
+        for login in logins {
+            if let Some(name) = identities.get(&login.to_lowercase()) {
+                names.push(name.clone());
+            }
+            names.push(login);
+        }
+        names.sort();
+        names.dedup();
+        Some(names)
+    }
+
+    /// Whether a new PR is a draft to be held back until it's ready, see `HOLD_DRAFTS`.
+    pub fn holds_draft(&self, pr: &PullRequest) -> bool {
+        self.config().hold_drafts && pr.draft == Some(true)
+    }
+
+    /// Remembers a draft in `seen_prs` without announcing it.
+    pub async fn hold_draft(&self, chat_id: i64, owner: &str, repo: &str, pr_number: u64) {
+        info!(
+            "Holding draft {}/{}#{} in {} until it's ready",
+            owner, repo, pr_number, chat_id
+        );
+        if let Err(e) = self.state.hold_pr(chat_id, owner, repo, pr_number).await {
+            error!(
+                "Failed to hold draft {}/{}#{}: {}",
+                owner, repo, pr_number, e
+            );
+        }
+    }
+
+    /// Releases held drafts that GitHub now reports ready for review (or all held ones of
+    /// `only`), returning them by chat for the normal announcement. Drafts closed while
+    /// held, or of repositories the chat no longer tracks, are released silently.
+    pub async fn release_ready_drafts(&self, only: Option<(&str, &str, u64)>) -> Vec<(i64, NewPr)> {
+        let mut held: HashMap<(String, String, u64), Vec<i64>> = HashMap::new();
+        for (chat_id, owner, repo, number) in self.state.get_held_prs().await.unwrap_or_default() {
+            if only.is_none_or(|(o, r, n)| o == owner && r == repo && n == number) {
+                held.entry((owner, repo, number)).or_default().push(chat_id);
+            }
+        }
+
+        let mut ready = Vec::new();
+        for ((owner, repo, number), chats) in held {
+            let pr = match self.github.get_pr_details(&owner, &repo, number).await {
+                Ok(pr) => pr,
+                Err(e) => {
+                    error!(
+                        "Failed to check held draft {}/{}#{}: {}",
+                        owner, repo, number, e
+                    );
+                    continue;
+                }
+            };
+            let open = pr.closed_at.is_none() && pr.merged_at.is_none();
+            if open && pr.draft == Some(true) {
+                continue;
+            }
+            let subscribers = self
+                .state
+                .get_subscribers(&owner, &repo)
+                .await
+                .unwrap_or_default();
+            for chat_id in chats {
+                // Only the first release announces, should the webhook and the poll race
+                if !self
+                    .state
+                    .release_pr(chat_id, &owner, &repo, number)
+                    .await
+                    .unwrap_or(false)
+                {
+                    continue;
+                }
+                if open && subscribers.contains(&chat_id) {
+                    info!(
+                        "Draft {}/{}#{} is ready, announcing it in {}",
+                        owner, repo, number, chat_id
+                    );
+                    ready.push((chat_id, (owner.clone(), repo.clone(), pr.clone())));
+                }
+            }
+        }
+        ready
+    }
+
+    /// Whether new PRs by `author` aren't announced for the repository, by `SKIP_AUTHORS`
+    /// or the repository's `skip_authors`.
+    pub fn skips_author(&self, owner: &str, repo: &str, author: &str) -> bool {
+        let config = self.config();
+        let repo_list = config
+            .repo_options
+            .iter()
+            .find(|o| o.owner == owner && o.name == repo)
+            .map(|o| o.skip_authors.as_slice())
+            .unwrap_or_default();
+        config
+            .skip_authors
+            .iter()
+            .chain(repo_list)
+            .any(|a| a.eq_ignore_ascii_case(author))
+    }
+
+    /// Announces new PRs in `chat_id`, folding bursts from a single author into one group card.
+    pub async fn announce_new_prs(&self, chat_id: i64, new_prs: Vec<NewPr>) {
+        if !self.state.is_chat_verified(chat_id).await.unwrap_or(false) {
+            debug!(
+                "Not announcing {} PRs in {}, the chat isn't verified yet",
+                new_prs.len(),
+                chat_id
+            );
+            return;
+        }
+        let mut sensitive = HashSet::new();
+        for (owner, repo, pr) in &new_prs {
+            if self
+                .config()
+                .security
+                .is_sensitive(&self.github, owner, repo, pr)
+                .await
+            {
+                self.announce_to_security_chat(owner, repo, pr).await;
+                sensitive.insert((owner.clone(), repo.clone(), pr.number));
+            }
+        }
+
+        let group_since = (Utc::now()
+            - chrono::Duration::minutes(self.config().group_window_minutes))
+        .timestamp();
+
+        let holds = self.config().group_threshold > 0 && self.config().group_hold_minutes > 0;
+        for (author, prs) in groups::bucket_by_author(new_prs) {
+            let existing_group = self
+                .state
+                .find_recent_group(chat_id, &author, group_since)
+                .await
+                .unwrap_or(None);
+            let is_burst =
+                self.config().group_threshold > 0 && prs.len() > self.config().group_threshold;
+
+            if existing_group.is_none() && holds {
+                groups::hold(self, chat_id, &author, &prs).await;
+            } else if existing_group.is_some() || is_burst {
+                groups::announce_group(self, chat_id, &author, prs, existing_group).await;
+            } else {
+                for (owner, repo, pr) in prs {
+                    let is_sensitive =
+                        sensitive.contains(&(owner.clone(), repo.clone(), pr.number));
+                    self.announce_pr(chat_id, &owner, &repo, pr, is_sensitive)
+                        .await;
+                }
+            }
+        }
+    }
+
+    /// Posts a copy of a security-sensitive PR's announcement to the security chat, once.
+    async fn announce_to_security_chat(&self, owner: &str, repo: &str, pr: &PullRequest) {
+        let Some(security_chat) = self.config().security.chat_id else {
+            return;
+        };
+        if self
+            .state
+            .is_pr_seen(security_chat, owner, repo, pr.number)
+            .await
+            .unwrap_or(false)
+        {
+            return;
+        }
+
+        let text = format!(
+            "🔒 Security review needed:\n\nTitle: {}\nAuthor: {}\nRepo: {}/{}\nLink: {}",
+            pr.title.clone().unwrap_or_default(),
+            pr.user
+                .as_ref()
+                .map(|u| u.login.as_str())
+                .unwrap_or("unknown"),
+            owner,
+            repo,
+            pr.html_url
+                .as_ref()
+                .map(|u| u.to_string())
+                .unwrap_or_default()
+        );
+        match self
+            .bot
+            .send_message(ChatId(security_chat), text)
+            .retried()
+            .await
+        {
+            Ok(_) => {
+                self.state.audit(format!(
+                    "announced {}: security review needed",
+                    audit::card(&format!("{}/{}", owner, repo), pr.number, security_chat)
+                ));
+                self.state
+                    .mark_pr_seen(security_chat, owner, repo, pr.number)
+                    .await
+                    .ok();
+            }
+            Err(e) => error!("Failed to send security announcement: {}", e),
+        }
+    }
+
+    /// Re-syncs every tracked message for a single PR, e.g. after a webhook delivery.
+    pub async fn sync_pr(&self, owner: &str, repo: &str, pr_number: u64) {
+        match self.state.get_messages_for_pr(owner, repo, pr_number).await {
+            Ok(msgs) => {
+                for msg in msgs {
+                    self.sync_message(msg).await;
+                }
+            }
+            Err(e) => error!(
+                "Failed to load tracked messages for {}/{}#{}: {}",
+                owner, repo, pr_number, e
+            ),
+        }
+    }
+
+    /// Pulls the latest state of a tracked PR from GitHub, re-renders its message and
+    /// cleans it up once the PR is closed or merged.
+    pub async fn sync_message(&self, msg: PrMessage) {
+        let card = format!("{}/{}#{}", msg.repo_owner, msg.repo_name, msg.pr_number);
+        if let Err(e) = self.refresh_message(msg).await {
+            error!("Failed to check status for {}: {}", card, e);
+            self.counters.error();
+        }
+    }
+
+    /// `sync_message` on demand, e.g. for `/status`, handing GitHub's error back instead of
+    /// logging it.
+    pub async fn refresh_message(&self, msg: PrMessage) -> Result<()> {
+        if ItemType::parse(&msg.item_type) == ItemType::Issue {
+            let issue = self
+                .github
+                .get_issue(&msg.repo_owner, &msg.repo_name, msg.pr_number as u64)
+                .await?;
+            self.sync_issue(msg, issue).await;
+            return Ok(());
+        }
+        let pr = self
+            .github
+            .get_pr_snapshot(&msg.repo_owner, &msg.repo_name, msg.pr_number as u64)
+            .await?;
+        self.sync_snapshot(msg, pr).await;
+        Ok(())
+    }
+
+    /// `sync_message` with the PR already fetched, e.g. by a GraphQL batch.
+    #[instrument(
+        name = "sync",
+        skip_all,
+        fields(
+            chat_id = msg.chat_id,
+            repo = %format_args!("{}/{}", msg.repo_owner, msg.repo_name),
+            pr_number = msg.pr_number,
+            message_id = %msg.message_id,
+        )
+    )]
+    pub async fn sync_snapshot(&self, msg: PrMessage, pr: PrSnapshot) {
+        let is_closed = pr.closed;
+        let is_merged = pr.merged_at.is_some();
+
+        let now = Utc::now().timestamp();
+        self.state
+            .set_pr_activity(&PrActivity {
+                repo_owner: msg.repo_owner.clone(),
+                repo_name: msg.repo_name.clone(),
+                pr_number: msg.pr_number,
+                last_active: pr.updated_at.map_or(now, |t| t.timestamp()),
+                last_synced: now,
+            })
+            .await
+            .ok();
+
+        // Update Draft status if changed
+        let current_draft = pr.draft;
+        let mut data_changed = false;
+        let current_data_opt = self
+            .state
+            .get_pr_data(msg.message_id.clone(), msg.chat_id)
+            .await
+            .unwrap_or(None);
+
+        if let Some(mut data) = current_data_opt.clone() {
+            // Wake up cards whose snooze has run out
+            if data
+                .snoozed_until
+                .is_some_and(|t| t <= Utc::now().timestamp())
+            {
+                data.apply(PrEvent::Unsnoozed);
+                data_changed = true;
+            }
+
+            // Check title changes
+            let current_title = pr.title.clone();
+            if data.title != current_title {
+                info!(
+                    "PR {}/{}#{} title changed from '{}' to '{}'. Updating...",
+                    msg.repo_owner, msg.repo_name, msg.pr_number, data.title, current_title
+                );
+                data.title = current_title;
+                data_changed = true;
+            }
+
+            if data.labels != pr.labels {
+                data.labels = pr.labels.clone();
+                data_changed = true;
+            }
+
+            let trackers = self
+                .state
+                .get_ticket_trackers(msg.chat_id)
+                .await
+                .unwrap_or_default();
+            let tickets = tickets::find(&trackers, &[&pr.title, &pr.body]);
+            if data.tickets != tickets {
+                data.tickets = tickets;
+                data_changed = true;
+            }
+
+            // Check draft status
+            let became_ready = data.status == PrStatus::Draft && !current_draft;
+            if (data.status == PrStatus::Draft) != current_draft {
+                info!(
+                    "PR {}/{}#{} draft status changed to {}. Updating...",
+                    msg.repo_owner, msg.repo_name, msg.pr_number, current_draft
+                );
+                data.apply(PrEvent::DraftSet(current_draft));
+                data_changed = true;
+            }
+
+            // Bookkeeping that isn't shown on the card is saved without re-rendering it
+            let mut needs_save = false;
+
+            // New commits after a change request mean the author is likely done addressing it
+            if data.head_sha.as_deref() != Some(pr.head_sha.as_str()) {
+                needs_save = true;
+                let pushed = data.head_sha.is_some();
+                data.head_sha = Some(pr.head_sha.clone());
+                if pushed && data.status == PrStatus::ChangesRequested {
+                    info!(
+                        "PR {}/{}#{}: new commits after changes were requested. Requesting re-review...",
+                        msg.repo_owner, msg.repo_name, msg.pr_number
+                    );
+                    handlers::notify_linked_users(
+                        &self.bot,
+                        &self.state,
+                        &data,
+                        &msg.message_id,
+                        "🙏 New commits, please re-review:",
+                        &handlers::re_review_audience(&data),
+                        Some(&data.author),
+                    )
+                    .await;
+                    data.comments.clear();
+                    data.changes_requested.clear();
+                    data.apply(PrEvent::ReReviewRequested);
+                    self.state
+                        .record_event(
+                            &data,
+                            &msg.message_id,
+                            "github",
+                            "re_review_requested",
+                            &pr.head_sha,
+                        )
+                        .await
+                        .ok();
+                    data_changed = true;
+                }
+            }
+
+            // Linked users show up under their Telegram name only
+            let identities = self.state.get_github_identities().await.unwrap_or_default();
+
+            // Newly requested GitHub reviewers get pinged in the chat when they're linked, and
+            // so do the members of newly requested teams
+            let requested: Vec<String> = pr
+                .requested_reviewers
+                .iter()
+                .map(|l| identities.get(&l.to_lowercase()).unwrap_or(l).clone())
+                .collect();
+            if data.requested_reviewers.as_ref() != Some(&requested) {
+                if let Some(before) = &data.requested_reviewers {
+                    let mut added: Vec<String> = Vec::new();
+                    // Pings go by GitHub login, the card by name
+                    for (login, name) in pr.requested_reviewers.iter().zip(&requested) {
+                        if before.contains(name) {
+                            continue;
+                        }
+                        match login.split_once('/') {
+                            Some((org, team)) => {
+                                added.extend(self.github.get_team_members(org, team).await)
+                            }
+                            None => added.push(login.clone()),
+                        }
+                    }
+                    if !added.is_empty() {
+                        handlers::notify_linked_users(
+                            &self.bot,
+                            &self.state,
+                            &data,
+                            &msg.message_id,
+                            "👀 Review requested:",
+                            &added,
+                            Some(&data.author),
+                        )
+                        .await;
+                    }
+                }
+                // The card lists them; a first sync finding nobody requested changes nothing
+                let shown =
+                    data.requested_reviewers.as_deref().unwrap_or_default() != requested.as_slice();
+                data.requested_reviewers = Some(requested);
+                if shown {
+                    data_changed = true;
+                } else {
+                    needs_save = true;
+                }
+            }
+
+            if reconcile::merge_identities(&mut data, &identities) {
+                data_changed = true;
+            }
+
+            // Who counts as a maintainer follows the config and the teams named in it
+            let maintainers = self
+                .maintainer_names(&msg.repo_owner, &msg.repo_name, &identities)
+                .await;
+            if maintainers != data.maintainers {
+                data.maintainers = maintainers;
+                data.apply(PrEvent::ReviewsChanged);
+                data_changed = true;
+            }
+
+            // Sync reviews from GitHub, reconciling them with Telegram-declared states
+            if let Some(reviews) = &pr.reviews {
+                // Reviews come in the order they were submitted, so the latest one per user wins
+                let mut user_state: HashMap<String, ReviewState> = HashMap::new();
+                // Name -> (review id, with comments) of each standing approval
+                let mut approved: HashMap<String, (u64, bool)> = HashMap::new();
+
+                for review in reviews {
+                    // During a re-review, comments and change requests on older commits are stale
+                    if data.is_re_review()
+                        && matches!(
+                            review.state,
+                            ReviewState::Commented | ReviewState::ChangesRequested
+                        )
+                        && review.commit_id.as_deref() != Some(pr.head_sha.as_str())
+                    {
+                        continue;
+                    }
+                    let name = identities
+                        .get(&review.login.to_lowercase())
+                        .cloned()
+                        .unwrap_or(review.login.clone());
+                    // A plain comment doesn't withdraw an earlier approval or change request
+                    if review.state == ReviewState::Commented && user_state.contains_key(&name) {
+                        continue;
+                    }
+                    if review.state == ReviewState::Approved {
+                        approved.insert(name.clone(), (review.id, review.with_comments));
+                    } else {
+                        approved.remove(&name);
+                    }
+                    user_state.insert(name, review.state);
+                }
+
+                let lists_before = (
+                    data.approvals.clone(),
+                    data.changes_requested.clone(),
+                    data.comments.clone(),
+                    data.from_github.clone(),
+                );
+
+                for demotion in reconcile::reconcile(&mut data, &user_state) {
+                    let detail = format!(
+                        "Telegram {} replaced by GitHub review ({})",
+                        demotion.from, demotion.to
+                    );
+                    info!(
+                        "PR {}/{}#{}: {}: {}",
+                        msg.repo_owner, msg.repo_name, msg.pr_number, demotion.user, detail
+                    );
+                    self.state
+                        .record_event(
+                            &data,
+                            &msg.message_id,
+                            &demotion.user,
+                            "status_reconciled",
+                            &detail,
+                        )
+                        .await
+                        .ok();
+                }
+
+                if (
+                    &data.approvals,
+                    &data.changes_requested,
+                    &data.comments,
+                    &data.from_github,
+                ) != (
+                    &lists_before.0,
+                    &lists_before.1,
+                    &lists_before.2,
+                    &lists_before.3,
+                ) {
+                    info!(
+                        "PR {}/{}#{} review status changed. Syncing...",
+                        msg.repo_owner, msg.repo_name, msg.pr_number
+                    );
+                    data.apply(PrEvent::ReviewsChanged);
+                    data_changed = true;
+                }
+
+                // Approvals with comments keep their acknowledgement until a new review replaces them
+                let hold = self.config().hold_suggested_approvals;
+                let mut suggestions: Vec<Suggestion> = approved
+                    .into_iter()
+                    .filter(|(name, (_, with_comments))| {
+                        *with_comments && data.approvals.contains(name)
+                    })
+                    .map(|(reviewer, (review_id, _))| {
+                        data.suggestions
+                            .iter()
+                            .find(|s| s.review_id == review_id)
+                            .cloned()
+                            .unwrap_or(Suggestion {
+                                reviewer,
+                                review_id,
+                                acked: false,
+                                holds: hold,
+                            })
+                    })
+                    .collect();
+                suggestions.sort_by(|a, b| a.reviewer.cmp(&b.reviewer));
+                if suggestions != data.suggestions {
+                    data.suggestions = suggestions;
+                    data.apply(PrEvent::ReviewsChanged);
+                    data_changed = true;
+                }
+            }
+
+            // Labels can mark a PR security-sensitive later; the flag is sticky once set
+            if !data.security && self.config().security.has_label(&pr.labels) {
+                data.security = true;
+                data_changed = true;
+            }
+
+            // `mergeable` is unknown while GitHub is still computing it; keep the last answer then
+            let conflicts = match (pr.mergeable, &pr.mergeable_state) {
+                (_, Some(MergeableState::Dirty)) | (Some(false), _) => Some(true),
+                (Some(true), _) => Some(false),
+                _ => None,
+            };
+            if let Some(conflicts) = conflicts.filter(|c| *c != data.conflicts) {
+                data.conflicts = conflicts;
+                data_changed = true;
+            }
+
+            // Conflicts are timed for /stats, and long-standing ones get the author a
+            // rebase nudge, repeated after each push that doesn't resolve them
+            match (data.conflicts, data.conflicted_since) {
+                (true, None) => {
+                    data.conflicted_since = Some(now);
+                    needs_save = true;
+                    self.state
+                        .record_event(&data, &msg.message_id, "github", "conflict_started", "")
+                        .await
+                        .ok();
+                }
+                (false, Some(since)) => {
+                    data.conflicted_since = None;
+                    data.conflict_nudged_sha = None;
+                    needs_save = true;
+                    self.state
+                        .record_event(
+                            &data,
+                            &msg.message_id,
+                            "github",
+                            "conflict_resolved",
+                            &(now - since).to_string(),
+                        )
+                        .await
+                        .ok();
+                }
+                _ => {}
+            }
+            let nudge_after = self.config().conflict_nudge_hours as i64 * 3600;
+            if nudge_after > 0
+                && conflicts == Some(true)
+                && !(is_closed || is_merged)
+                && !matches!(data.status, PrStatus::Draft | PrStatus::Snoozed)
+                && data
+                    .conflicted_since
+                    .is_some_and(|since| now - since >= nudge_after)
+                && data.conflict_nudged_sha.as_deref() != Some(pr.head_sha.as_str())
+                && self
+                    .nudge_rebase(&msg.message_id, &data, &pr.base_ref)
+                    .await
+            {
+                data.conflict_nudged_sha = Some(pr.head_sha.clone());
+                needs_save = true;
+            }
+
+            // Where the base branch requires resolved conversations, open ones block the merge
+            if !(is_closed || is_merged) {
+                let unresolved = if self
+                    .github
+                    .requires_conversation_resolution(&msg.repo_owner, &msg.repo_name, &pr.base_ref)
+                    .await
+                {
+                    match self
+                        .github
+                        .get_unresolved_threads(
+                            &msg.repo_owner,
+                            &msg.repo_name,
+                            msg.pr_number as u64,
+                        )
+                        .await
+                    {
+                        Ok(count) => Some(count),
+                        Err(e) => {
+                            error!(
+                                "Failed to count unresolved conversations of {}/{}#{}: {}",
+                                msg.repo_owner, msg.repo_name, msg.pr_number, e
+                            );
+                            data.unresolved_threads
+                        }
+                    }
+                } else {
+                    None
+                };
+                if unresolved != data.unresolved_threads {
+                    data.unresolved_threads = unresolved;
+                    data_changed = true;
+                }
+            }
+
+            // GitHub has the final word on whether an approved PR can actually be merged,
+            // sensitive PRs additionally wait for a security reviewer, and required
+            // conversation resolution for their open threads
+            let status_before = data.status;
+            data.apply(PrEvent::MergeReady(
+                matches!(pr.mergeable_state, Some(MergeableState::Clean))
+                    && self.config().security.is_satisfied(&data)
+                    && data.unresolved_threads.unwrap_or(0) == 0,
+            ));
+            if data.status != status_before {
+                data_changed = true;
+            }
+
+            // Per-gate results of the configured checks on the head commit
+            let gates = self
+                .state
+                .get_check_gates(&msg.repo_owner, &msg.repo_name)
+                .await
+                .unwrap_or_default();
+            let mut failed_gates = Vec::new();
+            let reads_checks = !RepoCapabilities::denies(
+                onboarding::of(&self.state, &msg.repo_owner, &msg.repo_name)
+                    .await
+                    .read_checks,
+            );
+            let evaluated = if gates.is_empty() || !reads_checks {
+                Ok(Vec::new())
+            } else {
+                checks::evaluate(
+                    &self.github,
+                    &msg.repo_owner,
+                    &msg.repo_name,
+                    &pr.head_sha,
+                    &gates,
+                )
+                .await
+            };
+            match evaluated {
+                Ok(evaluated) if evaluated != data.gates => {
+                    failed_gates = checks::newly_failed(&gates, &data.gates, &evaluated);
+                    data.gates = evaluated;
+                    data_changed = true;
+                }
+                Ok(_) => {}
+                Err(e) => error!(
+                    "Failed to fetch check runs for {}/{}#{}: {}",
+                    msg.repo_owner, msg.repo_name, msg.pr_number, e
+                ),
+            }
+
+            // Overall CI result of the head commit, so reviewers see whether it's green
+            let ci = if reads_checks {
+                checks::summarize(&self.github, &msg.repo_owner, &msg.repo_name, &pr.head_sha).await
+            } else {
+                Ok(None)
+            };
+            match ci {
+                Ok(ci) if ci != data.ci => {
+                    data.ci = ci;
+                    data_changed = true;
+                }
+                Ok(_) => {}
+                Err(e) => error!(
+                    "Failed to fetch CI status for {}/{}#{}: {}",
+                    msg.repo_owner, msg.repo_name, msg.pr_number, e
+                ),
+            }
+
+            if became_ready {
+                self.notify_ready(&msg.message_id, &data).await;
+            }
+
+            for gate in &failed_gates {
+                info!(
+                    "PR {}/{}#{}: critical check {} failed",
+                    msg.repo_owner, msg.repo_name, msg.pr_number, gate
+                );
+                self.state
+                    .record_event(&data, &msg.message_id, "github", "gate_failed", gate)
+                    .await
+                    .ok();
+                self.bot
+                    .send_message(
+                        ChatId(msg.chat_id),
+                        format!(
+                            "❌ Critical check <b>{}</b> failed on <a href=\"{}\">{}#{}</a>",
+                            gate, data.pr_url, data.repo, data.pr_number
+                        ),
+                    )
+                    .reply_parameters(ReplyParameters::new(MessageId(
+                        msg.message_id.parse().unwrap_or(0),
+                    )))
+                    .parse_mode(ParseMode::Html)
+                    .link_preview_options(handlers::no_link_preview())
+                    .retried()
+                    .await
+                    .ok();
+                self.state.audit(format!(
+                    "notified {}: critical check {} failed",
+                    audit::card(&data.repo, data.pr_number, msg.chat_id),
+                    gate
+                ));
+            }
+
+            // Marks set in Telegram while GitHub was being asked are kept
+            if (data_changed || needs_save)
+                && self
+                    .merge_concurrent(&msg, current_data_opt.as_ref(), &mut data)
+                    .await
+            {
+                data_changed = true;
+            }
+
+            if data_changed {
+                if let Err(e) = self
+                    .state
+                    .update_pr_data(msg.message_id.clone(), data.clone())
+                    .await
+                {
+                    error!("Failed to update PR data in DB: {}", e);
+                    self.counters.error();
+                } else {
+                    // Update chat message
+                    match handlers::edit_card(
+                        &self.bot,
+                        &self.state,
+                        ChatId(msg.chat_id),
+                        MessageId(msg.message_id.parse().unwrap_or(0)),
+                        &data,
+                    )
+                    .await
+                    {
+                        Ok(_) => self.counters.card_updated(),
+                        Err(e) => {
+                            error!("Failed to update PR message in chat: {}", e);
+                            self.counters.error();
+                        }
+                    }
+                    self.state.audit(format!(
+                        "edited {}: GitHub sync, {}",
+                        audit::card(&data.repo, data.pr_number, msg.chat_id),
+                        data.status.label()
+                    ));
+                }
+            } else if needs_save {
+                self.state
+                    .update_pr_data(msg.message_id.clone(), data.clone())
+                    .await
+                    .ok();
+            }
+
+            if !(is_closed || is_merged) {
+                self.apply_rules(&msg.message_id, &data, &pr).await;
+            }
+        }
+
+        if is_closed || is_merged {
+            self.retire_card(&msg, current_data_opt, &pr).await;
+        }
+    }
+
+    /// Takes over what changed on the card since `base` was loaded (`reconcile::merge_concurrent`),
+    /// right before a sync saves `data`. Returns whether that changed `data`.
+    async fn merge_concurrent(
+        &self,
+        msg: &PrMessage,
+        base: Option<&PrData>,
+        data: &mut PrData,
+    ) -> bool {
+        let Some(base) = base else {
+            return false;
+        };
+        let Ok(Some(stored)) = self
+            .state
+            .get_pr_data(msg.message_id.clone(), msg.chat_id)
+            .await
+        else {
+            return false;
+        };
+        if !reconcile::merge_concurrent(data, base, &stored) {
+            return false;
+        }
+        data.apply(PrEvent::ReviewsChanged);
+        true
+    }
+
+    /// `sync_snapshot` for the card of an issue (`TRACK_ISSUES`): follows its title, labels
+    /// and assignees, pinging linked users newly assigned, and retires the card once the
+    /// issue is closed.
+    pub async fn sync_issue(&self, msg: PrMessage, issue: Issue) {
+        let pr = PrSnapshot::from(&issue);
+        let now = Utc::now().timestamp();
+        self.state
+            .set_pr_activity(&PrActivity {
+                repo_owner: msg.repo_owner.clone(),
+                repo_name: msg.repo_name.clone(),
+                pr_number: msg.pr_number,
+                last_active: pr.updated_at.map_or(now, |t| t.timestamp()),
+                last_synced: now,
+            })
+            .await
+            .ok();
+
+        let current_data_opt = self
+            .state
+            .get_pr_data(msg.message_id.clone(), msg.chat_id)
+            .await
+            .unwrap_or(None);
+        if let Some(mut data) = current_data_opt.clone() {
+            let mut data_changed = false;
+            if data.snoozed_until.is_some_and(|t| t <= now) {
+                data.apply(PrEvent::Unsnoozed);
+                data_changed = true;
+            }
+            if data.title != pr.title || data.labels != pr.labels {
+                data.title = pr.title.clone();
+                data.labels = pr.labels.clone();
+                data_changed = true;
+            }
+
+            let mut assignees: Vec<String> =
+                issue.assignees.iter().map(|a| a.login.clone()).collect();
+            assignees.sort();
+            if data.assignees != assignees {
+                info!(
+                    "Issue {}/{}#{} assignees changed to {:?}",
+                    msg.repo_owner, msg.repo_name, msg.pr_number, assignees
+                );
+                let added: Vec<String> = assignees
+                    .iter()
+                    .filter(|l| !data.assignees.contains(l))
+                    .cloned()
+                    .collect();
+                if !added.is_empty() && !pr.closed {
+                    handlers::notify_linked_users(
+                        &self.bot,
+                        &self.state,
+                        &data,
+                        &msg.message_id,
+                        "📌 Assigned:",
+                        &added,
+                        None,
+                    )
+                    .await;
+                }
+                data.assignees = assignees;
+                data_changed = true;
+            }
+
+            if data_changed && !pr.closed {
+                self.merge_concurrent(&msg, current_data_opt.as_ref(), &mut data)
+                    .await;
+                if let Err(e) = self
+                    .state
+                    .update_pr_data(msg.message_id.clone(), data.clone())
+                    .await
+                {
+                    error!("Failed to update issue data in DB: {}", e);
+                    self.counters.error();
+                } else {
+                    match handlers::edit_card(
+                        &self.bot,
+                        &self.state,
+                        ChatId(msg.chat_id),
+                        MessageId(msg.message_id.parse().unwrap_or(0)),
+                        &data,
+                    )
+                    .await
+                    {
+                        Ok(_) => self.counters.card_updated(),
+                        Err(e) => {
+                            error!("Failed to update issue message in chat: {}", e);
+                            self.counters.error();
+                        }
+                    }
+                    self.state.audit(format!(
+                        "edited {}: GitHub sync, assigned to {}",
+                        audit::card(&data.repo, data.pr_number, msg.chat_id),
+                        if data.assignees.is_empty() {
+                            "nobody".to_string()
+                        } else {
+                            data.assignees.join(", ")
+                        }
+                    ));
+                }
+            }
+        }
+
+        if pr.closed {
+            self.retire_card(&msg, current_data_opt, &pr).await;
+        }
+    }
+
+    /// Takes down the card of a closed PR or issue, or archives, moves or keeps it as
+    /// `closed_cards` says, and stops tracking it.
+    async fn retire_card(
+        &self,
+        msg: &PrMessage,
+        current_data_opt: Option<PrData>,
+        pr: &PrSnapshot,
+    ) {
+        let is_merged = pr.merged_at.is_some();
+        info!(
+            "PR {}/{}#{} is closed/merged. Cleaning up...",
+            msg.repo_owner, msg.repo_name, msg.pr_number
+        );
+
+        let message_id = MessageId(msg.message_id.parse().unwrap_or(0));
+        let chat_id = ChatId(msg.chat_id);
+        let status_text = if is_merged { "MERGED" } else { "CLOSED" };
+
+        // Settle how the card's Telegram marks held up before it's gone
+        if let (Some(data), Some(reviews)) = (&current_data_opt, &pr.reviews) {
+            let identities = self.state.get_github_identities().await.unwrap_or_default();
+            drift::record_outcomes(&self.state, data, &msg.message_id, reviews, &identities).await;
+        }
+
+        // Keep merge statistics once the card is gone
+        if let (Some(merged_at), Some(data)) = (pr.merged_at, &current_data_opt) {
+            let entry = ArchivedMerge {
+                source: String::new(),
+                chat_id: msg.chat_id,
+                repo_owner: msg.repo_owner.clone(),
+                repo_name: msg.repo_name.clone(),
+                pr_number: msg.pr_number,
+                title: data.title.clone(),
+                author: data.author.clone(),
+                opened_at: pr.created_at.map_or(0, |t| t.timestamp()),
+                merged_at: merged_at.timestamp(),
+                review_rounds: data.review_round as i64 + 1,
+                approvals: data.approvals.len() as i64,
+            };
+            if let Err(e) = self.state.archive_merge(&entry).await {
+                error!("Failed to archive merge: {}", e);
+            }
+            self.state
+                .record_event(data, &msg.message_id, &data.author, "merged", "")
+                .await
+                .ok();
+        }
+
+        // What the card shows instead where it can't be deleted anymore
+        let final_text = current_data_opt.clone().map(|mut data| {
+            data.apply(if is_merged {
+                PrEvent::Merged
+            } else {
+                PrEvent::Closed
+            });
+            if is_merged {
+                format!(
+                    "✅ <b>MERGED</b>\n\n<s>{}</s>",
+                    handlers::generate_message_text(&data)
+                )
+            } else {
+                format!(
+                    "🚫 <b>CLOSED</b>\n\n<s>{}</s>",
+                    handlers::generate_message_text(&data)
+                )
+            }
+        });
+        // Archived or kept cards stay in place for a while, so replies under them don't dangle
+        let policy = self
+            .closed_cards(msg.chat_id, &msg.repo_owner, &msg.repo_name)
+            .await;
+        let card = ClosingCard {
+            chat_id: msg.chat_id,
+            message_id: msg.message_id.clone(),
+            name: audit::card(
+                &format!("{}/{}", msg.repo_owner, msg.repo_name),
+                msg.pr_number,
+                msg.chat_id,
+            ),
+            reason: if is_merged {
+                "merged on GitHub"
+            } else {
+                "closed on GitHub"
+            },
+        };
+        let stays = match (policy, &current_data_opt, &final_text) {
+            (ClosedCards::Archive, Some(data), _) => {
+                let summary = closed_summary(data, pr, is_merged);
+                self.archive_card(&card, &summary).await
+            }
+            (ClosedCards::Move(archive_chat), Some(data), _) => {
+                let summary = closed_summary(data, pr, is_merged);
+                self.move_card(&card, &summary, archive_chat).await;
+                false
+            }
+            (ClosedCards::Keep(days), _, Some(final_text)) => {
+                self.keep_card(&card, final_text, days).await
+            }
+            _ => false,
+        };
+        if !stays {
+            mirror::close(
+                &self.bot,
+                &self.state,
+                chat_id,
+                message_id,
+                final_text.as_deref(),
+            )
+            .await;
+
+            // 1. Try to delete first (works only if <48h old)
+            let delete_result = self.bot.delete_message(chat_id, message_id).retried().await;
+            self.state.audit(format!(
+                "{} {}: {} on GitHub",
+                if delete_result.is_ok() {
+                    "deleted"
+                } else {
+                    "struck out"
+                },
+                audit::card(
+                    &format!("{}/{}", msg.repo_owner, msg.repo_name),
+                    msg.pr_number,
+                    msg.chat_id
+                ),
+                status_text.to_lowercase()
+            ));
+
+            match &delete_result {
+                Ok(_) => {
+                    info!(
+                        "PR {}/{}#{}: Message deleted successfully",
+                        msg.repo_owner, msg.repo_name, msg.pr_number
+                    );
+                }
+                Err(e) => {
+                    info!(
+                        "PR {}/{}#{}: Could not delete message (>48h?): {}. Trying to edit...",
+                        msg.repo_owner, msg.repo_name, msg.pr_number, e
+                    );
+
+                    // 2. If delete failed, try to edit
+                    if let Some(final_text) = final_text {
+                        let edit_result = self
+                            .bot
+                            .edit_message_text(chat_id, message_id, final_text)
+                            .parse_mode(ParseMode::Html)
+                            .link_preview_options(handlers::no_link_preview())
+                            .retried()
+                            .await;
+
+                        match &edit_result {
+                            Ok(_) => {
+                                info!(
+                                    "PR {}/{}#{}: Message edited to show {} status",
+                                    msg.repo_owner, msg.repo_name, msg.pr_number, status_text
+                                );
+                            }
+                            Err(edit_err) => {
+                                // 3. If edit also failed, reply with a message to remove
+                                info!(
+                                    "PR {}/{}#{}: Could not edit message: {}. Sending reply...",
+                                    msg.repo_owner, msg.repo_name, msg.pr_number, edit_err
+                                );
+
+                                let reply_text = format!(
+                                    "⚠️ PR #{} is now <b>{}</b>. Please remove the message above.",
+                                    msg.pr_number, status_text
+                                );
+                                match self
+                                    .bot
+                                    .send_message(chat_id, reply_text)
+                                    .parse_mode(ParseMode::Html)
+                                    .reply_parameters(teloxide::types::ReplyParameters::new(
+                                        message_id,
+                                    ))
+                                    .retried()
+                                    .await
+                                {
+                                    Ok(_) => {
+                                        info!(
+                                            "PR {}/{}#{}: Sent reply requesting removal",
+                                            msg.repo_owner, msg.repo_name, msg.pr_number
+                                        );
+                                    }
+                                    Err(reply_err) => {
+                                        error!(
+                                            "PR {}/{}#{}: Failed to send reply: {}",
+                                            msg.repo_owner, msg.repo_name, msg.pr_number, reply_err
+                                        );
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        // Remove from DB tracking
+        if let Err(e) = self
+            .state
+            .remove_message(&msg.message_id, msg.chat_id)
+            .await
+        {
+            error!("Failed to remove message from DB: {}", e);
+        } else {
+            info!(
+                "PR {}/{}#{}: Removed from DB tracking",
+                msg.repo_owner, msg.repo_name, msg.pr_number
+            );
+        }
+    }
+
+    /// What happens to the chat's cards of the repository once their PR closes: the chat's
+    /// `/onclose`, else the repository's `closed_cards`, else `CLOSED_CARDS`.
+    pub async fn closed_cards(&self, chat_id: i64, owner: &str, repo: &str) -> ClosedCards {
+        if let Ok(Some(policy)) = self.state.get_closed_cards(chat_id).await {
+            return policy;
+        }
+        let config = self.config();
+        config
+            .repo_options
+            .iter()
+            .find(|o| o.owner == owner && o.name == repo)
+            .and_then(|o| o.closed_cards)
+            .unwrap_or(config.closed_cards)
+    }
+
+    /// Edits the card of a closed PR, and its copies in mirrors, into `summary`. False when
+    /// the card couldn't be edited and has to be taken down instead.
+    pub async fn archive_card(&self, card: &ClosingCard, summary: &str) -> bool {
+        let chat_id = ChatId(card.chat_id);
+        let message_id = MessageId(card.message_id.parse().unwrap_or(0));
+        if let Err(e) = self
+            .bot
+            .edit_message_text(chat_id, message_id, summary)
+            .parse_mode(ParseMode::Html)
+            .link_preview_options(handlers::no_link_preview())
+            .retried()
+            .await
+        {
+            info!(
+                "{}: Could not archive message: {}. Taking it down...",
+                card.name, e
+            );
+            return false;
+        }
+        mirror::archive(&self.bot, &self.state, chat_id, message_id, summary).await;
+        self.state
+            .audit(format!("archived {}: {}", card.name, card.reason));
+        true
+    }
+
+    /// Posts the summary of a closed PR's card to the archive chat before the card is taken
+    /// down. The card goes either way: the archive chat missing it beats the card lingering.
+    pub async fn move_card(&self, card: &ClosingCard, summary: &str, archive_chat: i64) {
+        match self
+            .bot
+            .send_message(ChatId(archive_chat), summary)
+            .parse_mode(ParseMode::Html)
+            .link_preview_options(handlers::no_link_preview())
+            .disable_notification(true)
+            .retried()
+            .await
+        {
+            Ok(_) => self.state.audit(format!(
+                "moved {} to {}: {}",
+                card.name, archive_chat, card.reason
+            )),
+            Err(e) => error!(
+                "{}: Failed to post to archive chat {}: {}",
+                card.name, archive_chat, e
+            ),
+        }
+    }
+
+    /// Strikes out the card of a closed PR, and its copies in mirrors, and schedules them for
+    /// deletion in `days`. False when the card couldn't be edited and has to be taken down now.
+    pub async fn keep_card(&self, card: &ClosingCard, final_text: &str, days: u32) -> bool {
+        let chat_id = ChatId(card.chat_id);
+        let message_id = MessageId(card.message_id.parse().unwrap_or(0));
+        if let Err(e) = self
+            .bot
+            .edit_message_text(chat_id, message_id, final_text)
+            .parse_mode(ParseMode::Html)
+            .link_preview_options(handlers::no_link_preview())
+            .retried()
+            .await
+        {
+            info!(
+                "{}: Could not strike out message: {}. Taking it down...",
+                card.name, e
+            );
+            return false;
+        }
+        let delete_at = (Utc::now() + chrono::Duration::days(days as i64)).timestamp();
+        if let Err(e) = self
+            .state
+            .add_kept_card(card.chat_id, &card.message_id, delete_at)
+            .await
+        {
+            error!(
+                "Failed to schedule deleting card {}: {}",
+                card.message_id, e
+            );
+        }
+        mirror::keep(
+            &self.bot,
+            &self.state,
+            chat_id,
+            message_id,
+            final_text,
+            delete_at,
+        )
+        .await;
+        self.state.audit(format!(
+            "kept {} for {} days: {}",
+            card.name, days, card.reason
+        ));
+        true
+    }
+
+    /// Deletes the kept cards of closed PRs, and their copies, once their days are up. Those
+    /// Telegram no longer allows deleting stay struck out.
+    async fn delete_kept_cards(&self) {
+        let due = match self.state.get_due_kept_cards(Utc::now().timestamp()).await {
+            Ok(due) => due,
+            Err(e) => {
+                error!("Failed to load kept cards: {}", e);
+                return;
+            }
+        };
+        for (chat_id, message_id) in due {
+            match self
+                .bot
+                .delete_message(ChatId(chat_id), MessageId(message_id.parse().unwrap_or(0)))
+                .retried()
+                .await
+            {
+                Ok(_) => info!("Deleted kept card {} in {}", message_id, chat_id),
+                Err(e) => info!(
+                    "Could not delete kept card {} in {}: {}",
+                    message_id, chat_id, e
+                ),
+            }
+            self.state.remove_kept_card(chat_id, &message_id).await.ok();
+        }
+    }
+
+    /// Replies under a card whose PR left draft, mentioning its reviewers when configured.
+    async fn notify_ready(&self, message_id: &str, data: &PrData) {
+        let mut text = format!(
+            "📣 <a href=\"{}\">{}#{}</a> is now ready for review",
+            data.pr_url, data.repo, data.pr_number
+        );
+        if self.config().ready_ping_reviewers && !data.reviewers.is_empty() {
+            let mentions: Vec<String> = data.reviewers.iter().map(|r| format!("@{}", r)).collect();
+            text.push_str(&format!("\n{}", mentions.join(" ")));
+        }
+        if let Err(e) = self
+            .bot
+            .send_message(ChatId(data.chat_id), text)
+            .reply_parameters(ReplyParameters::new(MessageId(
+                message_id.parse().unwrap_or(0),
+            )))
+            .parse_mode(ParseMode::Html)
+            .link_preview_options(handlers::no_link_preview())
+            .retried()
+            .await
+        {
+            error!("Failed to send ready-for-review notification: {}", e);
+            return;
+        }
+        self.state.audit(format!(
+            "notified {}: ready for review",
+            audit::card(&data.repo, data.pr_number, data.chat_id)
+        ));
+    }
+
+    /// Asks the author of a conflicted PR to rebase onto `base`. Returns whether it was sent,
+    /// or held for the day's summary because the author is past their nudge budget.
+    async fn nudge_rebase(&self, message_id: &str, data: &PrData, base: &str) -> bool {
+        let config = self.config();
+        let since = data.conflicted_since.unwrap_or(data.status_since);
+        let author = match self.state.get_user_by_github(&data.author).await {
+            Ok(Some(link)) => {
+                let line = format!(
+                    "{} conflicts with {} for {}",
+                    nudges::card_link(data, message_id),
+                    teloxide::utils::html::escape(base),
+                    bulk::format_duration(Utc::now().timestamp() - since)
+                );
+                if nudges::allowed(self, data.chat_id, &[nudges::username(&link)], &line)
+                    .await
+                    .is_empty()
+                {
+                    return true;
+                }
+                handlers::mention(&link)
+            }
+            _ => teloxide::utils::html::escape(&data.author),
+        };
+        let text = teloxide::utils::html::escape(
+            config
+                .conflict_nudge_text
+                .as_deref()
+                .unwrap_or(DEFAULT_CONFLICT_NUDGE),
+        )
+        .replace("{author}", &author)
+        .replace("{base}", &teloxide::utils::html::escape(base))
+        .replace(
+            "{duration}",
+            &bulk::format_duration(Utc::now().timestamp() - since),
+        );
+        if let Err(e) = self
+            .bot
+            .send_message(ChatId(data.chat_id), text)
+            .reply_parameters(ReplyParameters::new(MessageId(
+                message_id.parse().unwrap_or(0),
+            )))
+            .parse_mode(ParseMode::Html)
+            .retried()
+            .await
+        {
+            error!("Failed to send rebase nudge: {}", e);
+            return false;
+        }
+        self.state.audit(format!(
+            "notified {}: conflicted for {}",
+            audit::card(&data.repo, data.pr_number, data.chat_id),
+            bulk::format_duration(Utc::now().timestamp() - since)
+        ));
+        true
+    }
+
+    /// Runs the chat's `/rules` against a card. A rule fires once when its condition starts
+    /// holding and can fire again after the condition stopped holding.
+    pub async fn apply_rules(&self, message_id: &str, data: &PrData, pr: &PrSnapshot) {
+        let rules = self.state.get_rules(data.chat_id).await.unwrap_or_default();
+        if rules.is_empty() {
+            return;
+        }
+
+        let now = Utc::now().timestamp();
+        let facts = Facts {
+            data,
+            labels: &pr.labels,
+            opened_at: pr.created_at.map_or(now, |t| t.timestamp()),
+            now,
+        };
+
+        for stored in rules {
+            let rule = match Rule::parse(&stored.rule) {
+                Ok(rule) => rule,
+                Err(e) => {
+                    error!("Skipping unreadable rule #{}: {}", stored.id, e);
+                    continue;
+                }
+            };
+            let holds = rule.matches(&facts);
+            let Ok(hit) = self
+                .state
+                .is_rule_hit(stored.id, data.chat_id, message_id)
+                .await
+            else {
+                continue;
+            };
+            if holds == hit {
+                continue;
+            }
+            if holds {
+                self.fire_rule(message_id, data, stored.id, &stored.rule, &rule.action)
+                    .await;
+            }
+            self.state
+                .set_rule_hit(stored.id, data.chat_id, message_id, holds)
+                .await
+                .ok();
+        }
+    }
+
+    /// Like `apply_rules`, for changes made in the chat: fetches the PR only if the chat has rules.
+    pub async fn apply_rules_after_change(&self, message_id: &str, data: &PrData) {
+        if self
+            .state
+            .get_rules(data.chat_id)
+            .await
+            .map_or(true, |r| r.is_empty())
+        {
+            return;
+        }
+        let Some((owner, repo)) = data.repo.split_once('/') else {
+            return;
+        };
+        match self
+            .github
+            .get_pr_details(owner, repo, data.pr_number)
+            .await
+        {
+            Ok(pr) => {
+                self.apply_rules(message_id, data, &PrSnapshot::from(&pr))
+                    .await
+            }
+            Err(e) => error!(
+                "Failed to fetch {}#{} for rules: {}",
+                data.repo, data.pr_number, e
+            ),
+        }
+    }
+
+    /// Usernames of the chat's human admins, for mentioning them.
+    pub async fn admin_usernames(&self, chat_id: ChatId) -> Vec<String> {
+        match self.bot.get_chat_administrators(chat_id).timed().await {
+            Ok(admins) => admins
+                .into_iter()
+                .filter(|m| !m.user.is_bot)
+                .filter_map(|m| m.user.username)
+                .collect(),
+            Err(e) => {
+                error!("Failed to list admins of {}: {}", chat_id, e);
+                Vec::new()
+            }
+        }
+    }
+
+    async fn fire_rule(
+        &self,
+        message_id: &str,
+        data: &PrData,
+        rule_id: i64,
+        rule_text: &str,
+        action: &RuleAction,
+    ) {
+        let chat_id = ChatId(data.chat_id);
+        let (prefix, names) = match action {
+            RuleAction::Mention(names) => ("🔔", names.clone()),
+            RuleAction::Escalate => ("⏫ Escalated:", self.admin_usernames(chat_id).await),
+        };
+        let line = format!(
+            "{} rule #{}: {}",
+            nudges::card_link(data, message_id),
+            rule_id,
+            teloxide::utils::html::escape(rule_text)
+        );
+        let allowed = nudges::allowed(self, data.chat_id, &names, &line).await;
+        // Everyone it would mention is past their nudge budget: the day's summary brings it
+        let held = !names.is_empty() && allowed.is_empty();
+        let mentions: Vec<String> = allowed.iter().map(|n| format!("@{}", n)).collect();
+        let text = format!(
+            "{} {} — rule #{}: {}",
+            prefix,
+            mentions.join(" "),
+            rule_id,
+            rule_text
+        );
+
+        info!(
+            "Rule #{} fired for {}#{}",
+            rule_id, data.repo, data.pr_number
+        );
+        self.state
+            .record_event(
+                data,
+                message_id,
+                "rules",
+                "rule_fired",
+                &rule_id.to_string(),
+            )
+            .await
+            .ok();
+        if held {
+            return;
+        }
+        if let Err(e) = self
+            .bot
+            .send_message(chat_id, text)
+            .reply_parameters(ReplyParameters::new(MessageId(
+                message_id.parse().unwrap_or(0),
+            )))
+            .retried()
+            .await
+        {
+            error!("Failed to send rule notification: {}", e);
+            return;
+        }
+        self.state.audit(format!(
+            "notified {}: rule #{}",
+            audit::card(&data.repo, data.pr_number, data.chat_id),
+            rule_id
+        ));
+    }
+
+    /// Sends the announcement for a single new PR and starts tracking it.
+    #[instrument(
+        name = "announce",
+        skip_all,
+        fields(
+            chat_id = chat_id,
+            repo = %format_args!("{}/{}", owner, repo),
+            pr_number = pr.number,
+        )
+    )]
+    pub async fn announce_pr(
+        &self,
+        chat_id: i64,
+        owner: &str,
+        repo: &str,
+        pr: PullRequest,
+        sensitive: bool,
+    ) {
+        let title = pr.title.clone().unwrap_or_default();
+        let author = pr
+            .user
+            .clone()
+            .map(|u| u.login)
+            .unwrap_or("unknown".to_string());
+        let pr_url = pr
+            .html_url
+            .clone()
+            .map(|u| u.to_string())
+            .unwrap_or_default();
+
+        let msg = format!(
+            "{}New PR included:\n\nTitle: {}\nAuthor: {}\nRepo: {}/{}\nLink: {}",
+            if sensitive { "🔒 " } else { "" },
+            title,
+            author,
+            owner,
+            repo,
+            pr_url
+        );
+
+        // A retry after a partial failure finds the earlier claim instead of posting again
+        let full_name = format!("{}/{}", owner, repo);
+        match self.state.claim_card(chat_id, &full_name, pr.number).await {
+            Ok(true) => {}
+            Ok(false) => {
+                info!(
+                    "{}#{} already has a card in {}",
+                    full_name, pr.number, chat_id
+                );
+                self.state
+                    .mark_pr_seen(chat_id, owner, repo, pr.number)
+                    .await
+                    .ok();
+                return;
+            }
+            Err(e) => {
+                error!(
+                    "Failed to claim card for {}#{}: {}",
+                    full_name, pr.number, e
+                );
+                return;
+            }
+        }
+
+        // Send to the subscribing chat, in the repository's topic when it has one
+        let thread_id = self
+            .state
+            .get_repository_topic(chat_id, owner, repo)
+            .await
+            .unwrap_or_default();
+        let mut request = self.bot.send_message(Recipient::Id(ChatId(chat_id)), msg);
+        if let Some(thread_id) = thread_id {
+            request = request.message_thread_id(ThreadId(MessageId(thread_id)));
+        }
+        if let Some(buttons) = handlers::card_buttons(&self.state, chat_id).await {
+            request = request.reply_markup(buttons);
+        }
+        match request.retried().await {
+            Ok(sent_msg) => {
+                metrics::prs_announced(1);
+                if let Err(e) = self
+                    .state
+                    .set_card_message(chat_id, &full_name, pr.number, &sent_msg.id.0.to_string())
+                    .await
+                {
+                    error!(
+                        "Failed to record card for {}#{}: {}",
+                        full_name, pr.number, e
+                    );
+                }
+
+                // Fetch initial reviews (if any, though usually none on creation)
+                let mut approvals = vec![];
+                let mut changes_requested = vec![];
+                let mut comments = vec![];
+
+                if let Ok(reviews) = self.github.get_pr_reviews(owner, repo, pr.number).await {
+                    for review in reviews {
+                        if let Some(user) = review.user {
+                            let username = user.login;
+                            match review.state {
+                                Some(octocrab::models::pulls::ReviewState::Approved)
+                                    if !approvals.contains(&username) =>
+                                {
+                                    approvals.push(username);
+                                }
+                                Some(octocrab::models::pulls::ReviewState::ChangesRequested)
+                                    if !changes_requested.contains(&username) =>
+                                {
+                                    changes_requested.push(username);
+                                }
+                                Some(octocrab::models::pulls::ReviewState::Commented)
+                                    if !comments.contains(&username) =>
+                                {
+                                    comments.push(username);
+                                }
+                                _ => {}
+                            }
+                        }
+                    }
+                }
+
+                // We don't automatically track *messages* sent by this loop as "interactive" unless we want to.
+                // But the user requirements say "If it sees a new PR included, it will send a message... The review statuses are tracked using reactions"
+                // So YES, we must track this message in DB so reactions work.
+
+                let assignee = self
+                    .assign_reviewer(chat_id, owner, repo, pr.number, &author)
+                    .await;
+                // Everything on a new card comes from a GitHub review
+                let mut from_github: Vec<String> = vec![];
+                for user in approvals.iter().chain(&changes_requested).chain(&comments) {
+                    if !from_github.contains(user) {
+                        from_github.push(user.clone());
+                    }
+                }
+                let pr_data = PrData {
+                    pr_url,
+                    title,
+                    author,
+                    repo: format!("{}/{}", owner, repo),
+                    pr_number: pr.number,
+                    reviewers: assignee.iter().cloned().collect(),
+                    approvals,
+                    changes_requested,
+                    comments,
+                    from_github,
+                    status: PrStatus::from_github(
+                        pr.merged_at.is_some(),
+                        matches!(pr.state, Some(octocrab::models::IssueState::Closed)),
+                        pr.draft.unwrap_or(false),
+                    ),
+                    status_since: Utc::now().timestamp(),
+                    security: sensitive,
+                    labels: github::labels(&pr),
+                    head_sha: Some(pr.head.sha.clone()),
+                    requested_reviewers: Some(vec![]),
+                    thread_id,
+                    chat_id,
+                    ..Default::default()
+                };
+                self.state.audit(format!(
+                    "created {}: new PR",
+                    audit::card(&pr_data.repo, pr_data.pr_number, chat_id)
+                ));
+                if let Some(assignee) = &assignee {
+                    match self
+                        .bot
+                        .send_message(
+                            ChatId(chat_id),
+                            format!("🎯 @{}, you're up for this review", assignee),
+                        )
+                        .reply_parameters(ReplyParameters::new(sent_msg.id))
+                        .retried()
+                        .await
+                    {
+                        Ok(_) => self.state.audit(format!(
+                            "notified {}: assigned @{} from the reviewer pool",
+                            audit::card(&pr_data.repo, pr_data.pr_number, chat_id),
+                            assignee
+                        )),
+                        Err(e) => error!("Failed to mention the assigned reviewer: {}", e),
+                    }
+                }
+                self.state
+                    .record_event(
+                        &pr_data,
+                        &sent_msg.id.0.to_string(),
+                        &pr_data.author,
+                        "opened",
+                        "",
+                    )
+                    .await
+                    .ok();
+                let text = handlers::generate_message_text(&pr_data);
+                self.state
+                    .add_message(sent_msg.id.0.to_string(), pr_data.clone())
+                    .await
+                    .ok();
+                mirror::sync(&self.bot, &self.state, ChatId(chat_id), sent_msg.id, &text).await;
+                // Once the card is stored, so it takes reactions while GitHub is asked
+                codeowners::ping(self, &pr_data, sent_msg.id).await;
+            }
+            Err(e) => {
+                error!("Failed to send message: {}", e);
+                self.state
+                    .release_card(chat_id, &full_name, pr.number)
+                    .await
+                    .ok();
+            }
+        }
+    }
+
+    /// Picks the member of the repository's reviewer pool with the least recent assignments
+    /// and open reviews, the one assigned least recently among equals, skipping the PR's
+    /// author, and records the assignment. See `PoolMember::rank`.
+    async fn assign_reviewer(
+        &self,
+        chat_id: i64,
+        owner: &str,
+        repo: &str,
+        pr_number: u64,
+        author: &str,
+    ) -> Option<String> {
+        let pool = match self.state.get_reviewer_pool(chat_id, owner, repo).await {
+            Ok(pool) => pool,
+            Err(e) => {
+                error!("Failed to load reviewer pool of {}/{}: {}", owner, repo, e);
+                return None;
+            }
+        };
+        if pool.is_empty() {
+            return None;
+        }
+        // The author may be in the pool under their Telegram name
+        let identities = self.state.get_github_identities().await.unwrap_or_default();
+        let author_name = identities.get(&author.to_lowercase());
+        let assignee = pool
+            .into_iter()
+            .filter(|m| {
+                !m.username.eq_ignore_ascii_case(author) && author_name != Some(&m.username)
+            })
+            .min_by_key(|m| m.rank())?
+            .username;
+
+        if let Err(e) = self
+            .state
+            .record_assignment(
+                chat_id,
+                &format!("{}/{}", owner, repo),
+                pr_number,
+                &assignee,
+                Utc::now().timestamp(),
+            )
+            .await
+        {
+            error!("Failed to record reviewer assignment: {}", e);
+        }
+        Some(assignee)
+    }
+
+    /// Deletes extra cards of a PR left in a chat by earlier partial failures, keeping the
+    /// oldest, and registers existing cards so new announcements see them.
+    pub async fn repair_duplicate_cards(&self) {
+        let stale = (Utc::now() - chrono::Duration::days(1)).timestamp();
+        if let Ok(released) = self.state.release_stale_claims(stale).await {
+            if released > 0 {
+                info!("Released {} stale card claims", released);
+            }
+        }
+
+        let mut cards: HashMap<(i64, String, String, i64), Vec<PrMessage>> = HashMap::new();
+        for msg in self.state.get_all_messages().await.unwrap_or_default() {
+            cards
+                .entry((
+                    msg.chat_id,
+                    msg.repo_owner.clone(),
+                    msg.repo_name.clone(),
+                    msg.pr_number,
+                ))
+                .or_default()
+                .push(msg);
+        }
+        for mut msgs in cards.into_values().filter(|m| m.len() > 1) {
+            msgs.sort_by_key(|m| m.message_id.parse::<i64>().unwrap_or(i64::MAX));
+            for msg in msgs.into_iter().skip(1) {
+                let message_id = MessageId(msg.message_id.parse().unwrap_or(0));
+                if let Err(e) = self
+                    .bot
+                    .delete_message(ChatId(msg.chat_id), message_id)
+                    .retried()
+                    .await
+                {
+                    error!("Failed to delete duplicate card: {}", e);
+                }
+                if let Err(e) = self
+                    .state
+                    .remove_message(&msg.message_id, msg.chat_id)
+                    .await
+                {
+                    error!("Failed to remove duplicate card from DB: {}", e);
+                    continue;
+                }
+                self.state.audit(format!(
+                    "deleted {}: duplicate card",
+                    audit::card(
+                        &format!("{}/{}", msg.repo_owner, msg.repo_name),
+                        msg.pr_number,
+                        msg.chat_id
+                    )
+                ));
+            }
+        }
+
+        if let Err(e) = self.state.backfill_card_tracking().await {
+            error!("Failed to register existing cards: {}", e);
+        }
+    }
+
+    /// Checks that reactions on cards reach the bot in every chat it posts to, and switches
+    /// chats where they can't to buttons on the cards (and back once they can).
+    pub async fn check_reaction_support(self) {
+        let mut chats: BTreeSet<i64> = BTreeSet::from([self.config().chat_id]);
+        for (_, subscribers) in self.state.get_subscriptions().await.unwrap_or_default() {
+            chats.extend(subscribers);
+        }
+        for msg in self
+            .state
+            .get_all_active_messages()
+            .await
+            .unwrap_or_default()
+        {
+            chats.insert(msg.chat_id);
+        }
+
+        for chat_id in chats {
+            let Some(blocker) = self.reaction_blocker(ChatId(chat_id)).await else {
+                continue;
+            };
+            let mode = self
+                .state
+                .get_button_mode(chat_id)
+                .await
+                .unwrap_or(ButtonMode::Reactions);
+            // Chats that chose buttons keep them either way
+            if mode == ButtonMode::Chosen || blocker.is_some() == (mode == ButtonMode::Fallback) {
+                continue;
+            }
+            let mode = if blocker.is_some() {
+                ButtonMode::Fallback
+            } else {
+                ButtonMode::Reactions
+            };
+            if let Err(e) = self.state.set_button_mode(chat_id, mode).await {
+                error!("Failed to save button mode of {}: {}", chat_id, e);
+                continue;
+            }
+
+            let text = match blocker {
+                Some(reason) => {
+                    info!("Switching {} to button mode: {}", chat_id, reason);
+                    self.state
+                        .audit(format!("switched {} to button mode: {}", chat_id, reason));
+                    format!(
+                        "⚠️ Reactions on cards don't reach me here ({}). Cards now have buttons instead; replying /review, /approve, /comment, /giveup or /addressed to a card works too. I'll switch back to reactions once this is fixed and I restart.",
+                        reason
+                    )
+                }
+                None => {
+                    info!("Switching {} back to reactions", chat_id);
+                    self.state
+                        .audit(format!("switched {} back to reactions", chat_id));
+                    "✅ Reactions on cards reach me again, so the card buttons are gone."
+                        .to_string()
+                }
+            };
+            if let Err(e) = self.bot.send_message(ChatId(chat_id), text).retried().await {
+                error!(
+                    "Failed to announce button mode change in {}: {}",
+                    chat_id, e
+                );
+            }
+            self.refresh_card_buttons(chat_id).await;
+        }
+    }
+
+    /// Why reactions on cards in `chat_id` can't reach the bot; `Some(None)` when they can and
+    /// `None` when that couldn't be checked.
+    async fn reaction_blocker(&self, chat_id: ChatId) -> Option<Option<&'static str>> {
+        let chat = match self.bot.get_chat(chat_id).timed().await {
+            Ok(chat) => chat,
+            Err(e) => {
+                error!("Failed to check reactions in {}: {}", chat_id, e);
+                return None;
+            }
+        };
+        if let Some(available) = &chat.available_reactions {
+            let map = &self.config().reactions;
+            let usable = available
+                .iter()
+                .any(|r| matches!(r, ReactionType::Emoji { emoji } if map.action(emoji).is_some()));
+            if !usable {
+                return Some(Some("the chat doesn't allow the reactions I use"));
+            }
+        }
+        // Telegram only sends reactions in groups to bots that are admins
+        if chat.is_private() {
+            return Some(None);
+        }
+        let me = match self.bot.get_me().timed().await {
+            Ok(me) => me,
+            Err(e) => {
+                error!("Failed to look up the bot's own account: {}", e);
+                return None;
+            }
+        };
+        match self.bot.get_chat_member(chat_id, me.id).timed().await {
+            Ok(member) if member.is_privileged() => Some(None),
+            Ok(_) => Some(Some("I'm not an admin of this chat")),
+            Err(e) => {
+                error!("Failed to check the bot's rights in {}: {}", chat_id, e);
+                None
+            }
+        }
+    }
+
+    /// Adds or removes the buttons of the chat's active cards after its mode changed.
+    pub async fn refresh_card_buttons(&self, chat_id: i64) {
+        let buttons = handlers::card_buttons(&self.state, chat_id).await;
+        let mut throttle = Throttle::new(GROUP_SEND_INTERVAL);
+        for msg in self
+            .state
+            .get_chat_messages(chat_id)
+            .await
+            .unwrap_or_default()
+        {
+            if PrStatus::parse(&msg.status).is_terminal() {
+                continue;
+            }
+            throttle.wait().await;
+            let mut request = self.bot.edit_message_reply_markup(
+                ChatId(chat_id),
+                MessageId(msg.message_id.parse().unwrap_or(0)),
+            );
+            if let Some(buttons) = &buttons {
+                request = request.reply_markup(buttons.clone());
+            }
+            request.timed().await.ok();
+        }
+    }
+}
+
+/// The card of a closed PR boiled down to who merged it and when, the PR and who approved it
+/// (HTML).
+fn closed_summary(data: &PrData, pr: &PrSnapshot, is_merged: bool) -> String {
+    let mut text = if is_merged {
+        let date = pr.merged_at.unwrap_or_else(Utc::now).format("%Y-%m-%d");
+        match &pr.merged_by {
+            Some(login) => format!("✅ <b>Merged</b> by {} on {}", login, date),
+            None => format!("✅ <b>Merged</b> on {}", date),
+        }
+    } else {
+        format!("🚫 <b>Closed</b> on {}", Utc::now().format("%Y-%m-%d"))
+    };
+    text.push_str(&format!(
+        "\n<a href=\"{}\">{}#{}</a>: {}",
+        data.pr_url,
+        data.repo,
+        data.pr_number,
+        teloxide::utils::html::escape(&data.title)
+    ));
+    if !data.approvals.is_empty() {
+        text.push_str(&format!(
+            "\n👍 <b>Approved:</b> {}",
+            data.approvals.join(", ")
+        ));
+    }
+    text
+}
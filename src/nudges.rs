@@ -0,0 +1,149 @@
+use crate::db::UserLink;
+use crate::handlers;
+use crate::monitor::Monitor;
+use crate::sender::Retried;
+use crate::state::PrData;
+use chrono::Utc;
+use std::collections::BTreeMap;
+use teloxide::prelude::*;
+use teloxide::types::ParseMode;
+use tracing::{error, info};
+
+const CHECK_INTERVAL: tokio::time::Duration = tokio::time::Duration::from_secs(5 * 60);
+/// Held reminders listed per user, keeping the summary under Telegram's message size limit.
+const USER_LIMIT: usize = 10;
+
+/// Posts every chat's held reminders in one summary once their day is over.
+pub async fn run(monitor: Monitor) {
+    loop {
+        post_held(&monitor).await;
+        tokio::time::sleep(CHECK_INTERVAL).await;
+    }
+}
+
+/// The UTC day budgets are counted for, e.g. "2024-05-17".
+fn today() -> String {
+    Utc::now().format("%Y-%m-%d").to_string()
+}
+
+/// Reminder mentions a user can get in the chat per day: its own budget from `/nudges`, else
+/// `NUDGE_BUDGET`. 0 is unlimited.
+pub async fn budget(monitor: &Monitor, chat_id: i64) -> u32 {
+    match monitor.state.get_nudge_budget(chat_id).await {
+        Ok(Some(budget)) => budget,
+        _ => monitor.config().nudge_budget,
+    }
+}
+
+/// The name a linked user is mentioned and budgeted by.
+pub fn username(link: &UserLink) -> String {
+    link.telegram_username
+        .clone()
+        .unwrap_or_else(|| link.telegram_name.clone())
+}
+
+/// Which of `usernames` a reminder in the chat may mention, counting it against their budget.
+/// For everyone whose budget for today is used up, `line` (HTML) is held for the day's summary.
+pub async fn allowed(
+    monitor: &Monitor,
+    chat_id: i64,
+    usernames: &[String],
+    line: &str,
+) -> Vec<String> {
+    let budget = budget(monitor, chat_id).await;
+    if budget == 0 {
+        return usernames.to_vec();
+    }
+    let day = today();
+    let mut allowed = Vec::new();
+    for username in usernames {
+        let key = username.trim_start_matches('@').to_lowercase();
+        match monitor.state.take_nudge(chat_id, &key, &day, budget).await {
+            Ok(true) => allowed.push(username.clone()),
+            Ok(false) => {
+                info!(
+                    "Holding a reminder for {} in {}: budget used up",
+                    key, chat_id
+                );
+                if let Err(e) = monitor.state.hold_nudge(chat_id, &key, &day, line).await {
+                    error!("Failed to hold a reminder for {}: {}", key, e);
+                }
+            }
+            // A mention too many beats a lost reminder
+            Err(e) => {
+                error!("Failed to count a reminder for {}: {}", key, e);
+                allowed.push(username.clone());
+            }
+        }
+    }
+    allowed
+}
+
+/// Link to a card for held reminder lines, or to the PR where the card has no public link.
+pub fn card_link(data: &PrData, message_id: &str) -> String {
+    format!(
+        "<a href=\"{}\">{}#{}</a>",
+        handlers::message_link(data.chat_id, message_id).unwrap_or_else(|| data.pr_url.clone()),
+        data.repo,
+        data.pr_number
+    )
+}
+
+async fn post_held(monitor: &Monitor) {
+    let today = today();
+    let held = match monitor.state.get_held_nudges(&today).await {
+        Ok(held) => held,
+        Err(e) => {
+            error!("Failed to load held reminders: {}", e);
+            return;
+        }
+    };
+
+    let mut summaries: BTreeMap<(i64, String), BTreeMap<String, Vec<String>>> = BTreeMap::new();
+    for nudge in held {
+        summaries
+            .entry((nudge.chat_id, nudge.day))
+            .or_default()
+            .entry(nudge.username)
+            .or_default()
+            .push(nudge.line);
+    }
+
+    for ((chat_id, day), users) in summaries {
+        let count: usize = users.values().map(Vec::len).sum();
+        let mut text = format!("🌙 <b>Reminders held back on {}</b>\n", day);
+        for (username, lines) in &users {
+            text.push_str(&format!("\n@{}\n", teloxide::utils::html::escape(username)));
+            for line in lines.iter().take(USER_LIMIT) {
+                text.push_str(&format!("• {}\n", line));
+            }
+            if lines.len() > USER_LIMIT {
+                text.push_str(&format!("…and {} more\n", lines.len() - USER_LIMIT));
+            }
+        }
+
+        info!("Posting {} held reminders of {} in {}", count, day, chat_id);
+        match monitor
+            .bot
+            .send_message(ChatId(chat_id), text)
+            .parse_mode(ParseMode::Html)
+            .link_preview_options(handlers::no_link_preview())
+            .retried()
+            .await
+        {
+            Ok(_) => {
+                monitor.state.delete_held_nudges(chat_id, &day).await.ok();
+                monitor.state.audit(format!(
+                    "notified {}: {} reminders held back on {}",
+                    chat_id, count, day
+                ));
+            }
+            // Kept for the next check
+            Err(e) => error!("Failed to post held reminders in {}: {}", chat_id, e),
+        }
+    }
+
+    if let Err(e) = monitor.state.prune_nudges(&today).await {
+        error!("Failed to prune reminder counts: {}", e);
+    }
+}
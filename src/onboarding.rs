@@ -0,0 +1,133 @@
+use crate::db::RepoCapabilities;
+use crate::github::GithubClient;
+use crate::sender::Retried;
+use crate::state::StateManager;
+use teloxide::prelude::*;
+use tracing::{error, info};
+
+/// Probes what the bot's GitHub access allows on a repository and stores it, so features it
+/// can't use there aren't attempted. Returns the checklist to show in the chat.
+pub async fn probe(github: &GithubClient, state: &StateManager, owner: &str, repo: &str) -> String {
+    let capabilities = capabilities(github, owner, repo).await;
+    info!(
+        "Probed {}/{}: list_prs={:?} read_reviews={:?} read_checks={:?} write_labels={:?}",
+        owner,
+        repo,
+        capabilities.list_prs,
+        capabilities.read_reviews,
+        capabilities.read_checks,
+        capabilities.write_labels
+    );
+    if let Err(e) = state
+        .set_repo_capabilities(owner, repo, &capabilities)
+        .await
+    {
+        error!(
+            "Failed to store the capabilities of {}/{}: {}",
+            owner, repo, e
+        );
+    }
+    checklist(&capabilities)
+}
+
+/// Probes a repository first tracked from a PR link and posts the checklist; repositories
+/// probed before are left alone.
+pub async fn probe_new(
+    bot: &Bot,
+    github: &GithubClient,
+    state: &StateManager,
+    chat_id: ChatId,
+    owner: &str,
+    repo: &str,
+) {
+    if !matches!(state.get_repo_capabilities(owner, repo).await, Ok(None)) {
+        return;
+    }
+    let checklist = probe(github, state, owner, repo).await;
+    bot.send_message(chat_id, format!("🔎 {}/{}\n{}", owner, repo, checklist))
+        .disable_notification(true)
+        .retried()
+        .await
+        .ok();
+}
+
+/// The stored capabilities of a repository; unprobed ones allow everything.
+pub async fn of(state: &StateManager, owner: &str, repo: &str) -> RepoCapabilities {
+    state
+        .get_repo_capabilities(owner, repo)
+        .await
+        .ok()
+        .flatten()
+        .unwrap_or_default()
+}
+
+async fn capabilities(github: &GithubClient, owner: &str, repo: &str) -> RepoCapabilities {
+    let mut capabilities = RepoCapabilities::default();
+    // Installation tokens don't get their permissions back with the repository
+    if let Ok(repository) = github.get_repo(owner, repo).await {
+        capabilities.write_labels = repository
+            .permissions
+            .map(|p| (p.push || p.triage || p.maintain || p.admin) as i64);
+    }
+    let latest = github.get_latest_pr(owner, repo).await;
+    capabilities.list_prs = outcome(&latest);
+    // Reviews and checks can only be read off an actual PR
+    if let Ok(Some(pr)) = latest {
+        capabilities.read_reviews = outcome(&github.get_pr_reviews(owner, repo, pr.number).await);
+        capabilities.read_checks = outcome(&github.get_check_runs(owner, repo, &pr.head.sha).await);
+    }
+    capabilities
+}
+
+/// 1 when the call worked, 0 when GitHub denied it, `None` for any other failure.
+fn outcome<T>(result: &anyhow::Result<T>) -> Option<i64> {
+    match result {
+        Ok(_) => Some(1),
+        Err(e) => match e.downcast_ref::<octocrab::Error>() {
+            Some(octocrab::Error::GitHub { source, .. })
+                if matches!(source.status_code.as_u16(), 401 | 403 | 404) =>
+            {
+                Some(0)
+            }
+            _ => None,
+        },
+    }
+}
+
+fn checklist(capabilities: &RepoCapabilities) -> String {
+    let items = [
+        (
+            capabilities.list_prs,
+            "List pull requests",
+            "new PRs won't be announced",
+            "GitHub didn't answer",
+        ),
+        (
+            capabilities.read_reviews,
+            "Read reviews",
+            "GitHub reviews won't show on cards",
+            "couldn't be checked without a PR yet",
+        ),
+        (
+            capabilities.read_checks,
+            "Read checks",
+            "CI results and check gates won't show on cards",
+            "couldn't be checked without a PR yet",
+        ),
+        (
+            capabilities.write_labels,
+            "Change labels",
+            "/label won't work",
+            "tried when /label is used",
+        ),
+    ];
+    items
+        .iter()
+        .map(|(flag, name, denied, unknown)| match flag {
+            Some(1) => format!("✅ {}", name),
+            Some(_) => format!("⛔ {} — {}", name, denied),
+            None => format!("❔ {} — {}", name, unknown),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
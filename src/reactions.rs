@@ -0,0 +1,459 @@
+use anyhow::{bail, Result};
+
+/// What a reaction on a tracked card does.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReactionAction {
+    Review,
+    Approve,
+    RequestChanges,
+    Comment,
+    GiveUp,
+    Merge,
+    ReReview,
+    Draft,
+}
+
+impl ReactionAction {
+    pub const ALL: [Self; 8] = [
+        Self::Review,
+        Self::Approve,
+        Self::RequestChanges,
+        Self::Comment,
+        Self::GiveUp,
+        Self::Merge,
+        Self::ReReview,
+        Self::Draft,
+    ];
+
+    /// Name used in `REACTION_EMOJIS`.
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Review => "review",
+            Self::Approve => "approve",
+            Self::RequestChanges => "changes",
+            Self::Comment => "comment",
+            Self::GiveUp => "giveup",
+            Self::Merge => "merge",
+            Self::ReReview => "rereview",
+            Self::Draft => "draft",
+        }
+    }
+
+    /// Looks an action up by its `name`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|a| a.name() == name)
+    }
+
+    /// Text of the card button standing in for the reaction.
+    pub const fn button_label(self) -> &'static str {
+        match self {
+            Self::Review => "\u{2764} Review",
+            Self::Approve => "\u{1f44d} Approve",
+            Self::RequestChanges => "\u{1f44e} Changes",
+            Self::Comment => "\u{1f44c} Comment",
+            Self::GiveUp => "\u{1f62d} Give up",
+            Self::Merge => "\u{1f4af} Merged",
+            Self::ReReview => "\u{1f64f} Re-review",
+            Self::Draft => "\u{1f373} Draft",
+        }
+    }
+
+    const fn default_emoji(self) -> &'static str {
+        match self {
+            Self::Review => "\u{2764}",          // ❤
+            Self::Approve => "\u{1f44d}",        // 👍
+            Self::RequestChanges => "\u{1f44e}", // 👎
+            Self::Comment => "\u{1f44c}",        // 👌
+            Self::GiveUp => "\u{1f62d}",         // 😭
+            Self::Merge => "\u{1f4af}",          // 💯
+            Self::ReReview => "\u{1f64f}",       // 🙏
+            Self::Draft => "\u{1f373}",          // 🍳
+        }
+    }
+}
+
+/// Which review mark a user keeps when their reactions carry several, set by `REACTION_PRECEDENCE`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Precedence {
+    /// The mark just reacted with replaces the others
+    #[default]
+    Latest,
+    /// Approving outranks requesting changes, then commenting, then reviewing
+    Strongest,
+}
+
+impl Precedence {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "" | "latest" => Ok(Self::Latest),
+            "strongest" => Ok(Self::Strongest),
+            other => bail!(
+                "REACTION_PRECEDENCE must be 'latest' or 'strongest', not '{}'",
+                other
+            ),
+        }
+    }
+}
+
+/// How far a review mark got, for `Precedence::Strongest` and to break ties.
+fn strength(mark: ReactionAction) -> u8 {
+    match mark {
+        ReactionAction::Approve => 4,
+        ReactionAction::RequestChanges => 3,
+        ReactionAction::Comment => 2,
+        _ => 1,
+    }
+}
+
+/// Emoji → action mapping for reactions, loaded from `REACTION_EMOJIS`.
+#[derive(Clone, Debug)]
+pub struct ReactionMap {
+    entries: Vec<(ReactionAction, Vec<String>)>,
+}
+
+impl Default for ReactionMap {
+    fn default() -> Self {
+        Self {
+            entries: ReactionAction::ALL
+                .iter()
+                .map(|a| (*a, vec![a.default_emoji().to_string()]))
+                .collect(),
+        }
+    }
+}
+
+impl ReactionMap {
+    /// Parses overrides like `approve=👍|✅,merge=` on top of the defaults.
+    ///
+    /// Several emojis are separated by `|`; an empty value disables the action.
+    /// Unknown actions and emojis mapped to two actions are rejected.
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut map = Self::default();
+
+        for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let Some((name, emojis)) = part.split_once('=') else {
+                bail!(
+                    "REACTION_EMOJIS entry '{}' must look like action=emoji",
+                    part
+                );
+            };
+            let name = name.trim();
+            let Some(action) = ReactionAction::ALL.iter().find(|a| a.name() == name) else {
+                bail!("REACTION_EMOJIS has unknown action '{}'", name);
+            };
+            let emojis = emojis
+                .split('|')
+                .map(base_emoji)
+                .filter(|e| !e.is_empty())
+                .collect();
+            if let Some(entry) = map.entries.iter_mut().find(|(a, _)| a == action) {
+                entry.1 = emojis;
+            }
+        }
+
+        let mut seen: Vec<(&str, ReactionAction)> = Vec::new();
+        for (action, emojis) in &map.entries {
+            for emoji in emojis {
+                if let Some((_, other)) = seen.iter().find(|(e, _)| *e == emoji) {
+                    bail!(
+                        "REACTION_EMOJIS maps {} to both '{}' and '{}'",
+                        emoji,
+                        other.name(),
+                        action.name()
+                    );
+                }
+                seen.push((emoji, *action));
+            }
+        }
+
+        Ok(map)
+    }
+
+    /// Action for a reaction emoji; skin tones and variation selectors are ignored.
+    pub fn action(&self, emoji: &str) -> Option<ReactionAction> {
+        self.entries
+            .iter()
+            .find(|(_, emojis)| emojis.iter().any(|e| emoji.starts_with(e.as_str())))
+            .map(|(a, _)| *a)
+    }
+
+    pub fn is_enabled(&self, action: ReactionAction) -> bool {
+        self.entries
+            .iter()
+            .any(|(a, emojis)| *a == action && !emojis.is_empty())
+    }
+
+    /// The first emoji of the action, if it's enabled.
+    pub fn emoji(&self, action: ReactionAction) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(a, _)| *a == action)
+            .and_then(|(_, emojis)| emojis.first())
+            .map(String::as_str)
+    }
+
+    /// The review mark an emoji stands for: reviewing, approved, changes requested or
+    /// commented. Unmapped emojis count as a comment while comments are enabled.
+    pub fn mark(&self, emoji: &str) -> Option<ReactionAction> {
+        match self.action(emoji) {
+            Some(
+                a @ (ReactionAction::Review
+                | ReactionAction::Approve
+                | ReactionAction::RequestChanges
+                | ReactionAction::Comment),
+            ) => Some(a),
+            Some(_) => None,
+            None => self
+                .is_enabled(ReactionAction::Comment)
+                .then_some(ReactionAction::Comment),
+        }
+    }
+
+    /// The single review mark a user should have once their reactions changed from `old` to
+    /// `new`, given the marks they `held` on the card before. Returns `None` when the update
+    /// doesn't touch review marks, so marks from commands and GitHub are left alone.
+    ///
+    /// Marks whose emoji is still there count as held, so removing the winning emoji falls
+    /// back to the strongest remaining one.
+    pub fn resolve_mark(
+        &self,
+        precedence: Precedence,
+        held: &[ReactionAction],
+        old: &[String],
+        new: &[String],
+    ) -> Option<Option<ReactionAction>> {
+        let marks = |emojis: &[String]| -> Vec<ReactionAction> {
+            let mut marks: Vec<ReactionAction> =
+                emojis.iter().filter_map(|e| self.mark(e)).collect();
+            marks.sort_by_key(|m| strength(*m));
+            marks.dedup();
+            marks
+        };
+        let (before, after) = (marks(old), marks(new));
+        if before == after {
+            return None;
+        }
+
+        let added: Vec<ReactionAction> = after
+            .iter()
+            .filter(|m| !before.contains(m))
+            .copied()
+            .collect();
+        let mut holding: Vec<ReactionAction> = held
+            .iter()
+            .filter(|m| !before.contains(m) || after.contains(m))
+            .copied()
+            .collect();
+        holding.extend(after.iter().copied());
+
+        let candidates = match precedence {
+            Precedence::Latest if !added.is_empty() => added,
+            _ => holding,
+        };
+        Some(candidates.into_iter().max_by_key(|m| strength(*m)))
+    }
+
+    /// Help text suffix listing the action's emojis, e.g. " (👍)".
+    pub fn hint(&self, action: ReactionAction) -> String {
+        match self.entries.iter().find(|(a, _)| *a == action) {
+            Some((_, emojis)) if !emojis.is_empty() => format!(" ({})", emojis.join(" ")),
+            _ => String::new(),
+        }
+    }
+}
+
+/// Drops the emoji presentation selector so "❤️" and "❤" compare equal.
+fn base_emoji(s: &str) -> String {
+    s.trim().replace('\u{fe0f}', "")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MARKS: [ReactionAction; 4] = [
+        ReactionAction::Review,
+        ReactionAction::Approve,
+        ReactionAction::RequestChanges,
+        ReactionAction::Comment,
+    ];
+    const UNMAPPED: &str = "\u{1f525}"; // 🔥
+
+    /// The default emojis plus an unmapped one, which counts as a comment.
+    fn emojis() -> Vec<String> {
+        let mut emojis: Vec<String> = ReactionAction::ALL
+            .iter()
+            .map(|a| a.default_emoji().to_string())
+            .collect();
+        emojis.push(UNMAPPED.to_string());
+        emojis
+    }
+
+    fn subset(all: &[String], bits: usize) -> Vec<String> {
+        all.iter()
+            .enumerate()
+            .filter(|(i, _)| bits & (1 << i) != 0)
+            .map(|(_, e)| e.clone())
+            .collect()
+    }
+
+    fn marks_of(map: &ReactionMap, emojis: &[String]) -> Vec<ReactionAction> {
+        emojis.iter().filter_map(|e| map.mark(e)).collect()
+    }
+
+    #[test]
+    fn marks_of_the_default_emojis() {
+        let map = ReactionMap::default();
+        assert_eq!(map.mark("\u{2764}"), Some(ReactionAction::Review));
+        assert_eq!(map.mark("\u{2764}\u{fe0f}"), Some(ReactionAction::Review));
+        assert_eq!(map.mark("\u{1f44d}"), Some(ReactionAction::Approve));
+        assert_eq!(map.mark("\u{1f44e}"), Some(ReactionAction::RequestChanges));
+        assert_eq!(map.mark("\u{1f44c}"), Some(ReactionAction::Comment));
+        assert_eq!(map.mark(UNMAPPED), Some(ReactionAction::Comment));
+        for action in [
+            ReactionAction::GiveUp,
+            ReactionAction::Merge,
+            ReactionAction::ReReview,
+            ReactionAction::Draft,
+        ] {
+            assert_eq!(map.mark(action.default_emoji()), None);
+        }
+
+        let map = ReactionMap::parse("comment=").unwrap();
+        assert_eq!(map.mark(UNMAPPED), None);
+        assert_eq!(map.mark("\u{1f44c}"), None);
+    }
+
+    #[test]
+    fn precedence_parses() {
+        assert_eq!(Precedence::parse("").unwrap(), Precedence::Latest);
+        assert_eq!(Precedence::parse("Latest").unwrap(), Precedence::Latest);
+        assert_eq!(
+            Precedence::parse("strongest").unwrap(),
+            Precedence::Strongest
+        );
+        assert!(Precedence::parse("newest").is_err());
+    }
+
+    #[test]
+    fn thumbs_up_and_an_unmapped_emoji_resolve_to_one_mark() {
+        let map = ReactionMap::default();
+        let both = vec!["\u{1f44d}".to_string(), UNMAPPED.to_string()];
+        let latest = map.resolve_mark(
+            Precedence::Latest,
+            &[ReactionAction::Approve],
+            &both[..1],
+            &both,
+        );
+        assert_eq!(latest, Some(Some(ReactionAction::Comment)));
+        let strongest = map.resolve_mark(
+            Precedence::Strongest,
+            &[ReactionAction::Approve],
+            &both[..1],
+            &both,
+        );
+        assert_eq!(strongest, Some(Some(ReactionAction::Approve)));
+    }
+
+    #[test]
+    fn removing_the_winner_falls_back_to_the_remaining_emoji() {
+        let map = ReactionMap::default();
+        let review = "\u{2764}".to_string();
+        let approve = "\u{1f44d}".to_string();
+        let resolved = map.resolve_mark(
+            Precedence::Latest,
+            &[ReactionAction::Approve],
+            &[review.clone(), approve],
+            &[review],
+        );
+        assert_eq!(resolved, Some(Some(ReactionAction::Review)));
+    }
+
+    #[test]
+    fn non_mark_reactions_leave_marks_alone() {
+        let map = ReactionMap::default();
+        let new = vec![
+            "\u{1f44d}".to_string(),
+            ReactionAction::GiveUp.default_emoji().to_string(),
+        ];
+        let resolved = map.resolve_mark(
+            Precedence::Latest,
+            &[ReactionAction::Approve],
+            &new[..1],
+            &new,
+        );
+        assert_eq!(resolved, None);
+    }
+
+    /// Every change between two combinations of the mapped emojis, with every set of marks
+    /// held before, leaves the user with at most one mark chosen by the precedence rule.
+    #[test]
+    fn all_combinations_resolve_to_one_consistent_mark() {
+        let map = ReactionMap::default();
+        let all = emojis();
+        let combinations = 1 << all.len();
+        for precedence in [Precedence::Latest, Precedence::Strongest] {
+            for old_bits in 0..combinations {
+                let old = subset(&all, old_bits);
+                let before = marks_of(&map, &old);
+                for new_bits in 0..combinations {
+                    let new = subset(&all, new_bits);
+                    let after = marks_of(&map, &new);
+                    for held_bits in 0..(1 << MARKS.len()) {
+                        let held: Vec<ReactionAction> = MARKS
+                            .iter()
+                            .enumerate()
+                            .filter(|(i, _)| held_bits & (1 << i) != 0)
+                            .map(|(_, m)| *m)
+                            .collect();
+                        let resolved = map.resolve_mark(precedence, &held, &old, &new);
+
+                        let same = MARKS
+                            .iter()
+                            .all(|m| before.contains(m) == after.contains(m));
+                        if same {
+                            assert_eq!(resolved, None, "{:?} -> {:?}", old, new);
+                            continue;
+                        }
+                        let Some(mark) = resolved else {
+                            panic!("{:?} -> {:?} wasn't resolved", old, new);
+                        };
+
+                        let added: Vec<ReactionAction> = after
+                            .iter()
+                            .filter(|m| !before.contains(m))
+                            .copied()
+                            .collect();
+                        let kept: Vec<ReactionAction> = held
+                            .iter()
+                            .filter(|m| !before.contains(m) || after.contains(m))
+                            .chain(after.iter())
+                            .copied()
+                            .collect();
+                        let expected = match precedence {
+                            Precedence::Latest if !added.is_empty() => {
+                                added.iter().copied().max_by_key(|m| strength(*m))
+                            }
+                            _ => kept.iter().copied().max_by_key(|m| strength(*m)),
+                        };
+                        assert_eq!(
+                            mark, expected,
+                            "{:?}: {:?} -> {:?} holding {:?}",
+                            precedence, old, new, held
+                        );
+
+                        // A mark the user just took away never wins
+                        if let Some(mark) = mark {
+                            let removed = before.contains(&mark) && !after.contains(&mark);
+                            assert!(!removed, "{:?} -> {:?} kept {:?}", old, new, mark);
+                        }
+                        // A mark only disappears when no emoji or earlier mark backs any
+                        if mark.is_none() {
+                            assert!(after.is_empty(), "{:?} -> {:?}", old, new);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use crate::state::PrData;
+use octocrab::models::pulls::ReviewState;
+use std::collections::HashMap;
+
+/// A user whose Telegram-declared review state was overridden by GitHub.
+#[derive(Debug)]
+pub struct Demotion {
+    pub user: String,
+    pub from: &'static str,
+    pub to: &'static str,
+}
+
+fn declared_state(data: &PrData, user: &str) -> Option<&'static str> {
+    let user = user.to_string();
+    if data.approvals.contains(&user) {
+        Some("approval")
+    } else if data.changes_requested.contains(&user) {
+        Some("changes requested")
+    } else if data.comments.contains(&user) {
+        Some("comment")
+    } else {
+        None
+    }
+}
+
+/// Renames GitHub logins in the card's lists to their linked Telegram users (keys of
+/// `identities` are lowercased logins), so nobody is listed twice. Returns whether anything changed.
+pub fn merge_identities(data: &mut PrData, identities: &HashMap<String, String>) -> bool {
+    let mut changed = false;
+    for list in [
+        &mut data.reviewers,
+        &mut data.approvals,
+        &mut data.changes_requested,
+        &mut data.comments,
+        &mut data.from_github,
+    ] {
+        let mut merged: Vec<String> = Vec::with_capacity(list.len());
+        for name in list.iter() {
+            let name = identities.get(&name.to_lowercase()).unwrap_or(name).clone();
+            if !merged.contains(&name) {
+                merged.push(name);
+            }
+        }
+        if merged != *list {
+            *list = merged;
+            changed = true;
+        }
+    }
+    changed
+}
+
+/// Merges the latest GitHub review per user into the card's review lists.
+///
+/// Policy: a user's most recent submitted GitHub review is authoritative for that user
+/// and replaces whatever they declared in Telegram (a dismissed review clears it); their
+/// entry is then marked as coming from GitHub (`PrData::from_github`). Users who haven't
+/// reviewed on GitHub keep their Telegram state untouched, and reviewer claims (❤️) are
+/// never touched. Returns every entry that changed meaning.
+pub fn reconcile(data: &mut PrData, github: &HashMap<String, ReviewState>) -> Vec<Demotion> {
+    let mut demotions = Vec::new();
+
+    for (user, state) in github {
+        let to = match state {
+            ReviewState::Approved => Some("approval"),
+            ReviewState::ChangesRequested => Some("changes requested"),
+            ReviewState::Commented => Some("comment"),
+            ReviewState::Dismissed => None,
+            // Pending reviews aren't submitted yet, so they don't override anything
+            _ => continue,
+        };
+
+        let from = declared_state(data, user);
+        let sourced = data.from_github.contains(user);
+        if to.is_some() && !sourced {
+            data.from_github.push(user.clone());
+        } else if to.is_none() && sourced {
+            data.from_github.retain(|u| u != user);
+        }
+        if from == to {
+            continue;
+        }
+
+        data.approvals.retain(|u| u != user);
+        data.changes_requested.retain(|u| u != user);
+        data.comments.retain(|u| u != user);
+        match to {
+            Some("approval") => data.approvals.push(user.clone()),
+            Some("changes requested") => data.changes_requested.push(user.clone()),
+            Some(_) => data.comments.push(user.clone()),
+            None => {}
+        }
+
+        if let Some(from) = from {
+            demotions.push(Demotion {
+                user: user.clone(),
+                from,
+                to: to.unwrap_or("dismissed"),
+            });
+        }
+    }
+
+    demotions
+}
+
+/// Carries what changed on the card between `base`, the data a sync started from, and
+/// `theirs`, the data stored now, over to `ours`, the data the sync is about to save. A
+/// reaction, command or button handled while the sync talked to GitHub isn't lost that way;
+/// a verdict set meanwhile wins over the one the sync came to. Returns whether anything changed.
+pub fn merge_concurrent(ours: &mut PrData, base: &PrData, theirs: &PrData) -> bool {
+    let mut changed = false;
+    for (list, before, now) in [
+        (&mut ours.reviewers, &base.reviewers, &theirs.reviewers),
+        (&mut ours.approvals, &base.approvals, &theirs.approvals),
+        (
+            &mut ours.changes_requested,
+            &base.changes_requested,
+            &theirs.changes_requested,
+        ),
+        (&mut ours.comments, &base.comments, &theirs.comments),
+        (
+            &mut ours.from_github,
+            &base.from_github,
+            &theirs.from_github,
+        ),
+    ] {
+        let kept = list.clone();
+        list.retain(|u| !before.contains(u) || now.contains(u));
+        for user in now.iter().filter(|u| !before.contains(u)) {
+            if !list.contains(user) {
+                list.push(user.clone());
+            }
+        }
+        changed |= *list != kept;
+    }
+    let mut set: Vec<&String> = Vec::new();
+    for (before, now) in [
+        (&base.approvals, &theirs.approvals),
+        (&base.changes_requested, &theirs.changes_requested),
+        (&base.comments, &theirs.comments),
+    ] {
+        set.extend(now.iter().filter(|u| !before.contains(u)));
+    }
+    // Somebody holds one verdict at most
+    for user in set {
+        for (list, now) in [
+            (&mut ours.approvals, &theirs.approvals),
+            (&mut ours.changes_requested, &theirs.changes_requested),
+            (&mut ours.comments, &theirs.comments),
+        ] {
+            if !now.contains(user) && list.contains(user) {
+                list.retain(|u| u != user);
+                changed = true;
+            }
+        }
+    }
+    changed
+}
@@ -0,0 +1,130 @@
+use crate::handlers;
+use crate::monitor::Monitor;
+use crate::sender::Retried;
+use chrono::{DateTime, Utc};
+use octocrab::models::repos::Release;
+use teloxide::prelude::*;
+use teloxide::types::ParseMode;
+use teloxide::utils::html;
+use tracing::{error, info};
+
+/// Changelog lines quoted at most...
+const EXCERPT_LINES: usize = 8;
+/// ...and characters, so a long changelog doesn't flood the chat.
+const EXCERPT_CHARS: usize = 600;
+
+/// Announces the releases of a repository published after `since` in its chats, with
+/// `ANNOUNCE_RELEASES`. Returns whether they could be listed.
+pub async fn announce_new(
+    monitor: &Monitor,
+    owner: &str,
+    repo: &str,
+    since: DateTime<Utc>,
+    chats: &[i64],
+) -> bool {
+    let releases = match monitor.github.get_new_releases(owner, repo, since).await {
+        Ok(releases) => releases,
+        Err(e) => {
+            error!("Failed to fetch releases for {}/{}: {}", owner, repo, e);
+            monitor.counters.error();
+            return false;
+        }
+    };
+    for release in releases {
+        let text = announcement(owner, repo, &release);
+        for &chat_id in chats {
+            match monitor
+                .state
+                .mark_release_seen(chat_id, owner, repo, &release.tag_name)
+                .await
+            {
+                Ok(true) => {}
+                Ok(false) => continue,
+                Err(e) => {
+                    error!("Failed to record release {}: {}", release.tag_name, e);
+                    continue;
+                }
+            }
+            info!(
+                "Announcing release {} of {}/{} in {}",
+                release.tag_name, owner, repo, chat_id
+            );
+            match monitor
+                .bot
+                .send_message(ChatId(chat_id), &text)
+                .parse_mode(ParseMode::Html)
+                .link_preview_options(handlers::no_link_preview())
+                .retried()
+                .await
+            {
+                Ok(_) => monitor.state.audit(format!(
+                    "notified {}: release {} of {}/{}",
+                    chat_id, release.tag_name, owner, repo
+                )),
+                Err(e) => {
+                    error!(
+                        "Failed to announce release {} of {}/{} in {}: {}",
+                        release.tag_name, owner, repo, chat_id, e
+                    );
+                    monitor.counters.error();
+                }
+            }
+        }
+    }
+    true
+}
+
+/// "🚀 owner/repo released v1.2 (v1.2) by alice", then the start of the changelog.
+fn announcement(owner: &str, repo: &str, release: &Release) -> String {
+    let name = release
+        .name
+        .as_deref()
+        .map(str::trim)
+        .filter(|n| !n.is_empty())
+        .unwrap_or(&release.tag_name);
+    let mut text = format!(
+        "🚀 <b>{}/{}</b> released <a href=\"{}\">{}</a>",
+        html::escape(owner),
+        html::escape(repo),
+        release.html_url,
+        html::escape(name)
+    );
+    if name != release.tag_name {
+        text.push_str(&format!(
+            " (<code>{}</code>)",
+            html::escape(&release.tag_name)
+        ));
+    }
+    if release.prerelease {
+        text.push_str(" · pre-release");
+    }
+    if let Some(author) = &release.author {
+        text.push_str(&format!("\nBy {}", html::escape(&author.login)));
+    }
+    if let Some(excerpt) = release.body.as_deref().and_then(excerpt) {
+        text.push_str(&format!("\n\n{}", html::escape(&excerpt)));
+    }
+    text
+}
+
+/// The changelog's first lines, without blank ones, or `None` when it's empty.
+fn excerpt(body: &str) -> Option<String> {
+    let lines: Vec<&str> = body
+        .lines()
+        .map(str::trim_end)
+        .filter(|l| !l.trim().is_empty())
+        .collect();
+    if lines.is_empty() {
+        return None;
+    }
+    let mut excerpt = lines[..lines.len().min(EXCERPT_LINES)].join("\n");
+    let mut cut = lines.len() > EXCERPT_LINES;
+    if excerpt.chars().count() > EXCERPT_CHARS {
+        excerpt = excerpt.chars().take(EXCERPT_CHARS).collect();
+        cut = true;
+    }
+    if cut {
+        excerpt.push('…');
+    }
+    Some(excerpt)
+}
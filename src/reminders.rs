@@ -0,0 +1,100 @@
+use crate::audit;
+use crate::bulk;
+use crate::handlers;
+use crate::lifecycle::PrStatus;
+use crate::monitor::Monitor;
+use crate::sender::Retried;
+use crate::state::ItemType;
+use chrono::Utc;
+use teloxide::prelude::*;
+use teloxide::types::{MessageId, ParseMode, ReplyParameters};
+use tracing::{error, info};
+
+const CHECK_INTERVAL: tokio::time::Duration = tokio::time::Duration::from_secs(15 * 60);
+
+/// Periodically reminds chats of PRs waiting longer than `REVIEW_SLA_HOURS` for a reviewer
+/// or an approval.
+pub async fn run(monitor: Monitor) {
+    loop {
+        remind(&monitor).await;
+        tokio::time::sleep(CHECK_INTERVAL).await;
+    }
+}
+
+/// When the next reminder of a card waiting since `since` is due. The first comes one SLA
+/// in; every repeat waits twice as long as the previous one (1, 2, 4… SLAs apart).
+fn next_due(since: i64, last_reminded: Option<i64>, sla: i64) -> i64 {
+    // A reminder from before the status last changed belongs to an earlier wait
+    match last_reminded.filter(|t| *t >= since) {
+        Some(last) => last + (last - since) + sla,
+        None => since + sla,
+    }
+}
+
+async fn remind(monitor: &Monitor) {
+    let sla_hours = monitor.config().review_sla_hours;
+    if sla_hours == 0 {
+        return;
+    }
+    let sla = sla_hours as i64 * 3600;
+    let now = Utc::now().timestamp();
+
+    let messages = match monitor.state.get_all_active_messages().await {
+        Ok(messages) => messages,
+        Err(e) => {
+            error!("Failed to load cards for review reminders: {}", e);
+            return;
+        }
+    };
+    for msg in messages {
+        // Issues wait for an assignee, not a reviewer
+        if ItemType::parse(&msg.item_type) == ItemType::Issue {
+            continue;
+        }
+        let waiting_for = match PrStatus::parse(&msg.status) {
+            PrStatus::AwaitingReview => "a reviewer",
+            PrStatus::InReview => "an approval",
+            _ => continue,
+        };
+        if now < next_due(msg.status_since, msg.last_reminded_at, sla) {
+            continue;
+        }
+
+        let repo = format!("{}/{}", msg.repo_owner, msg.repo_name);
+        let waited = bulk::format_duration(now - msg.status_since);
+        info!(
+            "Reminding {} that {}#{} waits for {}",
+            msg.chat_id, repo, msg.pr_number, waiting_for
+        );
+        let text = format!(
+            "⏰ <a href=\"{}\">{}#{}</a> has been waiting {} for {}",
+            msg.pr_url, repo, msg.pr_number, waited, waiting_for
+        );
+        match monitor
+            .bot
+            .send_message(ChatId(msg.chat_id), text)
+            .reply_parameters(ReplyParameters::new(MessageId(
+                msg.message_id.parse().unwrap_or(0),
+            )))
+            .parse_mode(ParseMode::Html)
+            .link_preview_options(handlers::no_link_preview())
+            .retried()
+            .await
+        {
+            Ok(_) => {
+                monitor
+                    .state
+                    .set_last_reminded(&msg.message_id, msg.chat_id, now)
+                    .await
+                    .ok();
+                monitor.state.audit(format!(
+                    "notified {}: waiting {} for {}",
+                    audit::card(&repo, msg.pr_number, msg.chat_id),
+                    waited,
+                    waiting_for
+                ));
+            }
+            Err(e) => error!("Failed to send review reminder: {}", e),
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use crate::github::GithubClient;
+use crate::metrics;
+use crate::sender::Retried;
+use octocrab::models::{Rate, RateLimit};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+use teloxide::prelude::*;
+use teloxide::types::{MessageId, ThreadId};
+use tracing::{error, info};
+
+/// Card edits and failures of the background sync, tallied between two cycle reports.
+/// Webhook-triggered syncs in between count towards the next report.
+#[derive(Clone, Default)]
+pub struct SyncCounters {
+    updated: Arc<AtomicUsize>,
+    errors: Arc<AtomicUsize>,
+}
+
+impl SyncCounters {
+    pub fn card_updated(&self) {
+        self.updated.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn error(&self) {
+        self.errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// (cards updated, errors) since the last call.
+    fn take(&self) -> (usize, usize) {
+        (
+            self.updated.swap(0, Ordering::Relaxed),
+            self.errors.swap(0, Ordering::Relaxed),
+        )
+    }
+}
+
+/// What one polling cycle did, logged at info and optionally posted to `CYCLE_REPORT_CHAT_ID`.
+pub struct CycleReport {
+    started: Instant,
+    rate_before: Option<RateLimit>,
+    pub repos: usize,
+    pub announced: usize,
+}
+
+impl CycleReport {
+    pub async fn start(github: &GithubClient) -> Self {
+        Self {
+            started: Instant::now(),
+            rate_before: rate_limit(github).await,
+            repos: 0,
+            announced: 0,
+        }
+    }
+
+    pub async fn finish(
+        self,
+        bot: &Bot,
+        github: &GithubClient,
+        counters: &SyncCounters,
+        chat: Option<(i64, Option<i32>)>,
+    ) {
+        let (updated, errors) = counters.take();
+        let elapsed = self.started.elapsed();
+        metrics::poll_cycle_finished(elapsed);
+        let rate_after = rate_limit(github).await;
+        let (api_calls, remaining) = match (&self.rate_before, &rate_after) {
+            (Some(before), Some(after)) => (
+                Some(
+                    calls(&before.resources.core, &after.resources.core)
+                        + match (&before.resources.graphql, &after.resources.graphql) {
+                            (Some(before), Some(after)) => calls(before, after),
+                            _ => 0,
+                        },
+                ),
+                Some(after.resources.core.remaining),
+            ),
+            (None, Some(after)) => (None, Some(after.resources.core.remaining)),
+            _ => (None, None),
+        };
+        let show = |v: Option<usize>| v.map_or("?".to_string(), |v| v.to_string());
+
+        info!(
+            "cycle report: repos={} api_calls={} rate_remaining={} announced={} updated={} errors={} duration_ms={}",
+            self.repos,
+            show(api_calls),
+            show(remaining),
+            self.announced,
+            updated,
+            errors,
+            elapsed.as_millis()
+        );
+
+        let Some((chat_id, thread_id)) = chat else {
+            return;
+        };
+        let text = format!(
+            "🔄 Sync cycle: {} repos, {} API calls ({} left), {} announced, {} cards updated, {} errors, {:.1}s",
+            self.repos,
+            show(api_calls),
+            show(remaining),
+            self.announced,
+            updated,
+            errors,
+            elapsed.as_secs_f64()
+        );
+        let mut request = bot
+            .send_message(ChatId(chat_id), text)
+            .disable_notification(true);
+        if let Some(thread_id) = thread_id {
+            request = request.message_thread_id(ThreadId(MessageId(thread_id)));
+        }
+        if let Err(e) = request.retried().await {
+            error!("Failed to send cycle report: {}", e);
+        }
+    }
+}
+
+async fn rate_limit(github: &GithubClient) -> Option<RateLimit> {
+    match github.get_rate_limit().await {
+        Ok(rate) => Some(rate),
+        Err(e) => {
+            error!("Failed to check the GitHub rate limit: {}", e);
+            None
+        }
+    }
+}
+
+/// Requests made between two readings; after a reset only the new window's count is known.
+fn calls(before: &Rate, after: &Rate) -> usize {
+    if after.reset == before.reset {
+        after.used.saturating_sub(before.used)
+    } else {
+        after.used
+    }
+}
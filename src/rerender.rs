@@ -0,0 +1,84 @@
+use crate::db::PrMessage;
+use crate::handlers;
+use crate::monitor::Monitor;
+use crate::sender::{Throttle, GROUP_SEND_INTERVAL};
+use chrono::Utc;
+use teloxide::prelude::*;
+use teloxide::types::MessageId;
+use tracing::{error, info};
+
+/// Cards re-rendered per polling cycle at most, so the queue stays behind the sync's own edits.
+const BATCH: usize = 20;
+
+/// Re-renders the cards whose elapsed times ("no activity for 4 days", "reviewing for 1h 20m")
+/// were last rendered more than `RERENDER_INTERVAL_HOURS` ago, longest waiting first. Runs
+/// after each cycle's sync; a card edited since, by the sync or anything else, counts as fresh,
+/// so no card is edited twice in a cycle.
+pub async fn run_due(monitor: &Monitor) {
+    let hours = monitor.config().rerender_interval_hours;
+    if hours == 0 {
+        return;
+    }
+    let now = Utc::now().timestamp();
+    let cutoff = now - hours as i64 * 60 * 60;
+
+    let messages = match monitor.state.get_all_active_messages().await {
+        Ok(messages) => messages,
+        Err(e) => {
+            error!("Failed to load cards to re-render: {}", e);
+            return;
+        }
+    };
+    let mut due: Vec<PrMessage> = messages
+        .into_iter()
+        .filter(|m| m.stale_since.is_some() || m.review_sessions.is_some())
+        .filter(|m| m.rendered_at.unwrap_or(0) < cutoff)
+        .collect();
+    due.sort_by_key(|m| m.rendered_at.unwrap_or(0));
+
+    let mut throttle = Throttle::new(GROUP_SEND_INTERVAL);
+    let mut rendered = 0;
+    for msg in due {
+        if rendered == BATCH {
+            break;
+        }
+        let Ok(Some(data)) = monitor
+            .state
+            .get_pr_data(msg.message_id.clone(), msg.chat_id)
+            .await
+        else {
+            continue;
+        };
+        // Its timers stopped since; nothing to refresh until the card changes again
+        if !handlers::shows_elapsed_time(&data) {
+            monitor
+                .state
+                .set_rendered_at(&msg.message_id, msg.chat_id, now)
+                .await
+                .ok();
+            continue;
+        }
+        throttle.wait().await;
+        if let Err(e) = handlers::edit_card(
+            &monitor.bot,
+            &monitor.state,
+            ChatId(msg.chat_id),
+            MessageId(msg.message_id.parse().unwrap_or(0)),
+            &data,
+        )
+        .await
+        {
+            error!("Failed to re-render card {}: {}", msg.message_id, e);
+            // Tried again next interval rather than holding up the rest of the queue
+            monitor
+                .state
+                .set_rendered_at(&msg.message_id, msg.chat_id, now)
+                .await
+                .ok();
+        }
+        rendered += 1;
+    }
+    if rendered > 0 {
+        info!("Re-rendered {} cards with elapsed times", rendered);
+    }
+}
@@ -0,0 +1,337 @@
+use crate::bulk;
+use crate::lifecycle::PrStatus;
+use crate::state::PrData;
+use anyhow::{anyhow, bail, Result};
+use regex::Regex;
+
+/// A per-chat notification rule, e.g. `if label=db-migration and approvals<2 then mention @dba-team`.
+#[derive(Debug)]
+pub struct Rule {
+    conditions: Vec<Condition>,
+    pub action: RuleAction,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum RuleAction {
+    Mention(Vec<String>), // usernames without the `@`
+    Escalate,             // mention the chat's admins
+}
+
+#[derive(Debug)]
+struct Condition {
+    field: Field,
+    op: Op,
+    value: Value,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Field {
+    Label,
+    Author,
+    Repo,
+    Status,
+    Approvals,
+    Reviewers,
+    Changes,
+    Comments,
+    Age,  // since the PR was opened
+    Idle, // since the card's status last changed
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug)]
+enum Value {
+    Text(String),
+    Number(i64), // counts, or seconds for durations
+}
+
+/// What a rule is checked against.
+pub struct Facts<'a> {
+    pub data: &'a PrData,
+    pub labels: &'a [String],
+    pub opened_at: i64,
+    pub now: i64,
+}
+
+impl Field {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "label" => Some(Self::Label),
+            "author" => Some(Self::Author),
+            "repo" => Some(Self::Repo),
+            "status" => Some(Self::Status),
+            "approvals" => Some(Self::Approvals),
+            "reviewers" => Some(Self::Reviewers),
+            "changes" => Some(Self::Changes),
+            "comments" => Some(Self::Comments),
+            "age" => Some(Self::Age),
+            "idle" => Some(Self::Idle),
+            _ => None,
+        }
+    }
+
+    const fn is_text(self) -> bool {
+        matches!(self, Self::Label | Self::Author | Self::Repo | Self::Status)
+    }
+}
+
+impl Op {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "=" => Some(Self::Eq),
+            "!=" => Some(Self::Ne),
+            "<" => Some(Self::Lt),
+            "<=" => Some(Self::Le),
+            ">" => Some(Self::Gt),
+            ">=" => Some(Self::Ge),
+            _ => None,
+        }
+    }
+
+    fn compare(self, left: i64, right: i64) -> bool {
+        match self {
+            Self::Eq => left == right,
+            Self::Ne => left != right,
+            Self::Lt => left < right,
+            Self::Le => left <= right,
+            Self::Gt => left > right,
+            Self::Ge => left >= right,
+        }
+    }
+}
+
+impl Condition {
+    fn parse(s: &str) -> Result<Self> {
+        let re = Regex::new(r"^(\w+)\s*(<=|>=|!=|=|<|>)\s*(\S+)$").unwrap();
+        let captures = re
+            .captures(s.trim())
+            .ok_or_else(|| anyhow!("can't read condition \"{}\"", s.trim()))?;
+        let field = Field::parse(&captures[1])
+            .ok_or_else(|| anyhow!("unknown field \"{}\"", &captures[1]))?;
+        let op = Op::parse(&captures[2]).unwrap();
+        let raw = &captures[3];
+
+        let value = if field.is_text() {
+            if !matches!(op, Op::Eq | Op::Ne) {
+                bail!("{} only supports = and !=", &captures[1]);
+            }
+            if matches!(field, Field::Status) && PrStatus::parse(raw).as_str() != raw {
+                bail!("unknown status \"{}\"", raw);
+            }
+            Value::Text(raw.trim_start_matches('@').to_string())
+        } else if matches!(field, Field::Age | Field::Idle) {
+            Value::Number(
+                bulk::parse_duration(raw)
+                    .ok_or_else(|| anyhow!("\"{}\" isn't a duration like 5d or 12h", raw))?
+                    .num_seconds(),
+            )
+        } else {
+            Value::Number(
+                raw.parse()
+                    .map_err(|_| anyhow!("\"{}\" isn't a number", raw))?,
+            )
+        };
+
+        Ok(Self { field, op, value })
+    }
+
+    fn holds(&self, facts: &Facts) -> bool {
+        let data = facts.data;
+        match &self.value {
+            Value::Text(expected) => {
+                let equal = match self.field {
+                    Field::Label => facts
+                        .labels
+                        .iter()
+                        .any(|l| l.eq_ignore_ascii_case(expected)),
+                    Field::Author => data.author.eq_ignore_ascii_case(expected),
+                    Field::Repo => data.repo.eq_ignore_ascii_case(expected),
+                    _ => data.status.as_str() == expected,
+                };
+                equal == matches!(self.op, Op::Eq)
+            }
+            Value::Number(expected) => {
+                let actual = match self.field {
+                    Field::Approvals => data.counted_approvals().count() as i64,
+                    Field::Reviewers => data.reviewers.len() as i64,
+                    Field::Changes => data.changes_requested.len() as i64,
+                    Field::Comments => data.comments.len() as i64,
+                    Field::Age => facts.now - facts.opened_at,
+                    _ => facts.now - data.status_since,
+                };
+                self.op.compare(actual, *expected)
+            }
+        }
+    }
+}
+
+impl Rule {
+    /// Parses `if <condition> [and <condition>…] then <action>`.
+    pub fn parse(text: &str) -> Result<Self> {
+        let text = text.trim();
+        let body = text
+            .strip_prefix("if ")
+            .ok_or_else(|| anyhow!("a rule starts with \"if\""))?;
+        let (conditions, action) = body
+            .split_once(" then ")
+            .ok_or_else(|| anyhow!("a rule needs \"then <action>\""))?;
+
+        let conditions = conditions
+            .split(" and ")
+            .map(Condition::parse)
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut words = action.split_whitespace();
+        let action = match words.next() {
+            Some("mention") => {
+                let names: Vec<String> = words
+                    .map(|w| w.trim_start_matches('@').to_string())
+                    .filter(|w| !w.is_empty())
+                    .collect();
+                if names.is_empty() {
+                    bail!("mention needs at least one @username");
+                }
+                RuleAction::Mention(names)
+            }
+            Some("escalate") if words.next().is_none() => RuleAction::Escalate,
+            _ => bail!("the action must be \"mention @user…\" or \"escalate\""),
+        };
+
+        Ok(Self { conditions, action })
+    }
+
+    pub fn matches(&self, facts: &Facts) -> bool {
+        self.conditions.iter().all(|c| c.holds(facts))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: i64 = 3600;
+    const NOW: i64 = 1_000_000;
+
+    fn data() -> PrData {
+        PrData {
+            author: "alice".to_string(),
+            repo: "near/core".to_string(),
+            approvals: vec!["bob".to_string()],
+            reviewers: vec!["bob".to_string(), "carol".to_string()],
+            status: PrStatus::InReview,
+            status_since: NOW - 2 * HOUR,
+            ..Default::default()
+        }
+    }
+
+    fn matches(rule: &str, data: &PrData, labels: &[&str]) -> bool {
+        let labels: Vec<String> = labels.iter().map(|l| l.to_string()).collect();
+        Rule::parse(rule).unwrap().matches(&Facts {
+            data,
+            labels: &labels,
+            opened_at: NOW - 3 * 24 * HOUR,
+            now: NOW,
+        })
+    }
+
+    fn error(rule: &str) -> String {
+        Rule::parse(rule).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn actions_are_parsed() {
+        let rule = Rule::parse("if label=db-migration then mention @dba @ops").unwrap();
+        assert_eq!(
+            rule.action,
+            RuleAction::Mention(vec!["dba".to_string(), "ops".to_string()])
+        );
+        let rule = Rule::parse("  if age>2d and approvals<2 then escalate  ").unwrap();
+        assert_eq!(rule.action, RuleAction::Escalate);
+        assert_eq!(rule.conditions.len(), 2);
+    }
+
+    #[test]
+    fn malformed_rules_are_rejected() {
+        assert!(error("label=wip then escalate").contains("starts with \"if\""));
+        assert!(error("if label=wip").contains("then <action>"));
+        assert!(error("if colour=red then escalate").contains("unknown field \"colour\""));
+        assert!(error("if label wip then escalate").contains("can't read condition"));
+        assert!(error("if author>alice then escalate").contains("only supports = and !="));
+        assert!(error("if status=pending then escalate").contains("unknown status"));
+        assert!(error("if age>soon then escalate").contains("isn't a duration"));
+        assert!(error("if approvals<two then escalate").contains("isn't a number"));
+        assert!(error("if approvals<2 then mention @").contains("at least one @username"));
+        assert!(error("if approvals<2 then escalate now").contains("the action must be"));
+        assert!(error("if approvals<2 then ping @bob").contains("the action must be"));
+    }
+
+    #[test]
+    fn text_conditions_match_case_insensitively() {
+        let data = data();
+        assert!(matches(
+            "if label=DB-Migration then escalate",
+            &data,
+            &["db-migration"]
+        ));
+        assert!(!matches(
+            "if label=db-migration then escalate",
+            &data,
+            &["wip"]
+        ));
+        assert!(matches("if label!=wip then escalate", &data, &[]));
+        assert!(matches("if author=@Alice then escalate", &data, &[]));
+        assert!(matches("if repo=NEAR/core then escalate", &data, &[]));
+        assert!(matches("if status=in_review then escalate", &data, &[]));
+        assert!(!matches("if status!=in_review then escalate", &data, &[]));
+    }
+
+    #[test]
+    fn number_and_duration_conditions_compare() {
+        let data = data();
+        assert!(matches("if approvals<2 then escalate", &data, &[]));
+        assert!(!matches("if approvals>=2 then escalate", &data, &[]));
+        assert!(matches("if reviewers=2 then escalate", &data, &[]));
+        assert!(matches(
+            "if changes=0 and comments<=0 then escalate",
+            &data,
+            &[]
+        ));
+        assert!(matches("if age>2d then escalate", &data, &[]));
+        assert!(!matches("if age>3d then escalate", &data, &[]));
+        assert!(matches("if idle>=2h then escalate", &data, &[]));
+        assert!(!matches("if idle>2h then escalate", &data, &[]));
+    }
+
+    #[test]
+    fn every_condition_must_hold() {
+        let data = data();
+        assert!(matches(
+            "if approvals<2 and label=wip then escalate",
+            &data,
+            &["wip"]
+        ));
+        assert!(!matches(
+            "if approvals<2 and label=wip then escalate",
+            &data,
+            &[]
+        ));
+    }
+
+    #[test]
+    fn the_authors_own_approval_counts_only_with_self_approval() {
+        let mut data = data();
+        data.approvals.push("alice".to_string());
+        assert!(matches("if approvals=1 then escalate", &data, &[]));
+        data.self_approval = true;
+        assert!(matches("if approvals=2 then escalate", &data, &[]));
+    }
+}
@@ -0,0 +1,104 @@
+use crate::handlers;
+use crate::metrics::Timed;
+use crate::monitor::Monitor;
+use crate::sender::Retried;
+use teloxide::prelude::*;
+use teloxide::types::{ParseMode, ReplyParameters, UpdateKind};
+use tracing::warn;
+
+const REFUSAL: &str = "I'm in read-only safe mode: my database was set up by a newer version of me, so I can't change anything until that version is back. /list and /status still work.";
+
+/// Whether the configuration's database is newer than this build. It's then served in safe
+/// mode: nothing runs in the background, /list and /status answer from what's stored and
+/// everything else is refused, so the newer schema isn't written to by code that doesn't know it.
+pub fn is_on(monitor: Monitor) -> bool {
+    monitor.state.safe_mode().is_some()
+}
+
+/// Tells the main chat and `ADMIN_USER_IDS` that the configuration started in safe mode.
+pub async fn alert(monitor: &Monitor) {
+    let Some(versions) = monitor.state.safe_mode() else {
+        return;
+    };
+    let config = monitor.config();
+    let text = format!(
+        "🛟 Started in read-only safe mode: the database is at schema version {}, newer than this build's {}, probably after a rollback. Cards aren't synced and commands other than /list and /status are refused. Deploy the newer build again, or restore a backup made before it migrated the database.",
+        versions.database, versions.build
+    );
+    let chats = std::iter::once(config.chat_id).chain(config.admin_user_ids.iter().copied());
+    for chat_id in chats {
+        if let Err(e) = monitor
+            .bot
+            .send_message(ChatId(chat_id), &text)
+            .retried()
+            .await
+        {
+            warn!("Failed to announce safe mode to {}: {}", chat_id, e);
+        }
+    }
+}
+
+/// Handles an update in safe mode.
+pub async fn handle(bot: Bot, update: Update, monitor: Monitor) -> ResponseResult<()> {
+    match update.kind {
+        UpdateKind::Message(msg) => handle_message(&bot, &msg, &monitor).await,
+        UpdateKind::CallbackQuery(query) => {
+            bot.answer_callback_query(query.id)
+                .text(REFUSAL)
+                .show_alert(true)
+                .timed()
+                .await?;
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+async fn handle_message(bot: &Bot, msg: &Message, monitor: &Monitor) -> ResponseResult<()> {
+    // PR links and chatter get no card, and no reply either
+    let Some(command) = msg
+        .text()
+        .and_then(|text| text.split_whitespace().next())
+        .filter(|word| word.starts_with('/'))
+    else {
+        return Ok(());
+    };
+    let state = &monitor.state;
+    let ttl = monitor.config().error_reply_ttl_secs;
+    let command = command.split('@').next().unwrap_or_default();
+
+    let reply = match command {
+        "/list" => handlers::generate_list_text(state, msg.chat.id.0).await,
+        "/status" => {
+            let card = match msg.reply_to_message() {
+                Some(reply_to) => state
+                    .get_pr_data(reply_to.id.0.to_string(), msg.chat.id.0)
+                    .await
+                    .ok()
+                    .flatten(),
+                None => None,
+            };
+            let Some(data) = card else {
+                return handlers::reply_error(
+                    bot,
+                    msg,
+                    ttl,
+                    "Reply to a tracked PR card to use /status.",
+                )
+                .await;
+            };
+            format!(
+                "🛟 As last synced, before safe mode:\n\n{}",
+                handlers::generate_message_text(&data)
+            )
+        }
+        _ => return handlers::reply_error(bot, msg, ttl, REFUSAL).await,
+    };
+    bot.send_message(msg.chat.id, reply)
+        .parse_mode(ParseMode::Html)
+        .link_preview_options(handlers::no_link_preview())
+        .reply_parameters(ReplyParameters::new(msg.id))
+        .retried()
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,119 @@
+use crate::config::Settings;
+use crate::github::{self, GithubClient};
+use crate::state::PrData;
+use anyhow::Result;
+use octocrab::models::pulls::PullRequest;
+use regex::Regex;
+use tracing::error;
+
+/// Which PRs need a security review, who may give it and where they are announced.
+#[derive(Clone, Debug, Default)]
+pub struct SecurityPolicy {
+    pub labels: Vec<String>,
+    pub paths: Vec<Regex>,
+    pub reviewers: Vec<String>,
+    pub chat_id: Option<i64>,
+}
+
+impl SecurityPolicy {
+    pub fn from_settings(settings: &Settings) -> Result<Self> {
+        let list = |name: &str| -> Vec<String> {
+            settings
+                .get(name)
+                .unwrap_or_default()
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect()
+        };
+
+        let labels = match settings.get("SECURITY_LABELS") {
+            Some(_) => list("SECURITY_LABELS"),
+            None => vec!["security".to_string()],
+        };
+        let paths = list("SECURITY_PATHS")
+            .iter()
+            .map(|p| glob_to_regex(p))
+            .collect::<Result<_>>()?;
+        let reviewers = list("SECURITY_REVIEWERS")
+            .into_iter()
+            .map(|r| r.trim_start_matches('@').to_string())
+            .collect();
+        let chat_id = settings
+            .get("SECURITY_CHAT_ID")
+            .and_then(|v| v.parse::<i64>().ok());
+
+        Ok(Self {
+            labels,
+            paths,
+            reviewers,
+            chat_id,
+        })
+    }
+
+    /// Whether the PR carries a security label or touches a security-sensitive path.
+    pub async fn is_sensitive(
+        &self,
+        github: &GithubClient,
+        owner: &str,
+        repo: &str,
+        pr: &PullRequest,
+    ) -> bool {
+        if self.has_label(&github::labels(pr)) {
+            return true;
+        }
+        if self.paths.is_empty() {
+            return false;
+        }
+        match github.get_pr_files(owner, repo, pr.number).await {
+            Ok(files) => files
+                .iter()
+                .any(|f| self.paths.iter().any(|p| p.is_match(f))),
+            Err(e) => {
+                error!(
+                    "Failed to list files of {}/{}#{}: {}",
+                    owner, repo, pr.number, e
+                );
+                false
+            }
+        }
+    }
+
+    /// Label-only check, cheap enough to run on every sync.
+    pub fn has_label(&self, labels: &[String]) -> bool {
+        labels
+            .iter()
+            .any(|l| self.labels.iter().any(|s| s.eq_ignore_ascii_case(l)))
+    }
+
+    /// A sensitive PR can only become ready to merge once a security reviewer approved it.
+    pub fn is_satisfied(&self, data: &PrData) -> bool {
+        !data.security
+            || data
+                .counted_approvals()
+                .any(|a| self.reviewers.iter().any(|r| r.eq_ignore_ascii_case(a)))
+    }
+}
+
+/// Turns a path pattern into a regex: `**` matches across directories, `*` within one.
+pub fn glob_to_regex(glob: &str) -> Result<Regex> {
+    let mut re = String::from("^");
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                re.push_str(".*");
+            }
+            '*' => re.push_str("[^/]*"),
+            '?' => re.push_str("[^/]"),
+            c => re.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    // A bare directory like "auth/" covers everything below it
+    if glob.ends_with('/') {
+        re.push_str(".*");
+    }
+    re.push('$');
+    Ok(Regex::new(&re)?)
+}
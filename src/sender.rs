@@ -0,0 +1,91 @@
+use crate::metrics;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use teloxide::requests::{HasPayload, Output, Payload, Request};
+use teloxide::RequestError;
+use tokio::time::{sleep, sleep_until, Duration, Instant};
+use tracing::warn;
+
+/// Telegram allows roughly 20 messages per minute in a group; edits count too.
+pub const GROUP_SEND_INTERVAL: Duration = Duration::from_secs(3);
+
+/// Tries of a Telegram call that keeps failing for a transient reason.
+const RETRY_ATTEMPTS: u32 = 4;
+/// Wait before the first retry; doubled for each later one.
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+/// Flood-control waits longer than this are left to the caller instead of sat out.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(30);
+
+/// Spaces out consecutive Telegram calls so bulk operations stay under the rate limit.
+pub struct Throttle {
+    interval: Duration,
+    next: Instant,
+}
+
+impl Throttle {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            next: Instant::now(),
+        }
+    }
+
+    /// Waits until the next call is allowed.
+    pub async fn wait(&mut self) {
+        sleep_until(self.next).await;
+        self.next = Instant::now() + self.interval;
+    }
+}
+
+/// Sends a Telegram request like `Timed::timed`, retrying network failures with jittered
+/// exponential backoff and flood-control rejections after the wait Telegram asks for. API
+/// errors are final and returned at once.
+pub trait Retried: Request<Err = RequestError> {
+    fn retried(self) -> impl Future<Output = Result<Output<Self>, RequestError>> + Send;
+}
+
+impl<R> Retried for R
+where
+    R: Request<Err = RequestError> + Send + Sync,
+    R::SendRef: Send,
+    Output<R>: Send,
+{
+    async fn retried(self) -> Result<Output<Self>, RequestError> {
+        let mut attempt = 0;
+        loop {
+            let started = std::time::Instant::now();
+            let result = self.send_ref().await;
+            metrics::telegram::<<R as HasPayload>::Payload, _>(started, &result);
+            let wait = match &result {
+                Err(RequestError::Network(_) | RequestError::Io(_)) => backoff(attempt),
+                Err(RequestError::RetryAfter(seconds)) if seconds.duration() <= MAX_RETRY_AFTER => {
+                    seconds.duration()
+                }
+                _ => return result,
+            };
+            attempt += 1;
+            if attempt == RETRY_ATTEMPTS {
+                return result;
+            }
+            if let Err(e) = &result {
+                warn!(
+                    "{} failed ({}), retrying in {:?}",
+                    <R as HasPayload>::Payload::NAME,
+                    e,
+                    wait
+                );
+            }
+            sleep(wait).await;
+        }
+    }
+}
+
+/// `RETRY_BASE_DELAY` doubled per attempt, scaled by a random 50–150% so calls that failed
+/// together don't all retry together.
+fn backoff(attempt: u32) -> Duration {
+    let jitter = std::collections::hash_map::RandomState::new()
+        .build_hasher()
+        .finish()
+        % 1000;
+    RETRY_BASE_DELAY * 2u32.pow(attempt) * (500 + jitter as u32) / 1000
+}
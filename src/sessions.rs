@@ -0,0 +1,184 @@
+use crate::audit;
+use crate::handlers;
+use crate::lifecycle::PrEvent;
+use crate::monitor::Monitor;
+use crate::nudges;
+use crate::sender::{Retried, Throttle, GROUP_SEND_INTERVAL};
+use crate::state::PrData;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use teloxide::prelude::*;
+use teloxide::types::{MessageId, ReplyParameters};
+use tracing::{error, info};
+
+const CHECK_INTERVAL: tokio::time::Duration = tokio::time::Duration::from_secs(5 * 60);
+
+/// A reviewer's claim on a card, timed from ❤ or /review while `REVIEW_TIMEBOX_MINUTES` is set.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReviewSession {
+    pub reviewer: String,
+    pub started: i64,
+    pub pinged: bool, // the reviewer was reminded once the time box ran out
+}
+
+/// Starts timing `reviewer`'s review, when time-boxing is on.
+pub fn start(data: &mut PrData, reviewer: &str, timebox_minutes: u32) {
+    if timebox_minutes == 0 {
+        return;
+    }
+    data.review_sessions.retain(|s| s.reviewer != reviewer);
+    data.review_sessions.push(ReviewSession {
+        reviewer: reviewer.to_string(),
+        started: Utc::now().timestamp(),
+        pinged: false,
+    });
+}
+
+/// The reviewer is still on the card and hasn't approved, commented or requested changes.
+pub fn is_running(data: &PrData, session: &ReviewSession) -> bool {
+    let name = &session.reviewer;
+    data.reviewers.contains(name)
+        && !data.approvals.contains(name)
+        && !data.comments.contains(name)
+        && !data.changes_requested.contains(name)
+}
+
+/// How long a session has been running, e.g. "45m" or "1h 20m".
+pub fn elapsed(session: &ReviewSession) -> String {
+    let minutes = (Utc::now().timestamp() - session.started).max(0) / 60;
+    match minutes / 60 {
+        0 => format!("{}m", minutes),
+        hours => format!("{}h {}m", hours, minutes % 60),
+    }
+}
+
+/// Keeps the timers on cards current and handles reviews that outran their time box.
+pub async fn run(monitor: Monitor) {
+    loop {
+        check(&monitor).await;
+        tokio::time::sleep(CHECK_INTERVAL).await;
+    }
+}
+
+async fn check(monitor: &Monitor) {
+    let config = monitor.config();
+    let timebox = config.review_timebox_minutes as i64 * 60;
+    if timebox == 0 {
+        return;
+    }
+    let now = Utc::now().timestamp();
+
+    let messages = match monitor.state.get_all_active_messages().await {
+        Ok(messages) => messages,
+        Err(e) => {
+            error!("Failed to load cards for review timers: {}", e);
+            return;
+        }
+    };
+    let mut throttle = Throttle::new(GROUP_SEND_INTERVAL);
+    for msg in messages.into_iter().filter(|m| m.review_sessions.is_some()) {
+        let Ok(Some(mut data)) = monitor
+            .state
+            .get_pr_data(msg.message_id.clone(), msg.chat_id)
+            .await
+        else {
+            continue;
+        };
+        let before = data.review_sessions.clone();
+        let running: Vec<ReviewSession> = data
+            .review_sessions
+            .iter()
+            .filter(|s| is_running(&data, s))
+            .cloned()
+            .collect();
+        data.review_sessions = running;
+
+        let mut notices = Vec::new();
+        let mut released = Vec::new();
+        let card_link = nudges::card_link(&data, &msg.message_id);
+        for session in &mut data.review_sessions {
+            if session.pinged || now - session.started < timebox {
+                continue;
+            }
+            if config.review_timebox_release {
+                released.push(session.reviewer.clone());
+                notices.push(format!(
+                    "⏳ Released {}'s review claim after {} without an approval or comment.",
+                    session.reviewer,
+                    elapsed(session)
+                ));
+            } else {
+                session.pinged = true;
+                let line = format!("{} reviewing for {}", card_link, elapsed(session));
+                let reviewer = [session.reviewer.clone()];
+                // Past the reviewer's nudge budget the ping waits for the day's summary
+                if nudges::allowed(monitor, msg.chat_id, &reviewer, &line)
+                    .await
+                    .is_empty()
+                {
+                    continue;
+                }
+                notices.push(format!(
+                    "⏳ @{}, you've been reviewing this for {}. Still on it? Approve, comment or /giveup to let someone else take it.",
+                    session.reviewer,
+                    elapsed(session)
+                ));
+            }
+        }
+        if !released.is_empty() {
+            data.reviewers.retain(|r| !released.contains(r));
+            data.review_sessions
+                .retain(|s| !released.contains(&s.reviewer));
+            data.apply(PrEvent::ReviewsChanged);
+        }
+
+        if data.review_sessions != before {
+            if let Err(e) = monitor
+                .state
+                .update_pr_data(msg.message_id.clone(), data.clone())
+                .await
+            {
+                error!("Failed to save review timers: {}", e);
+                continue;
+            }
+        }
+        let message_id = MessageId(msg.message_id.parse().unwrap_or(0));
+        // Re-rendering refreshes the timers; a card whose last timer just stopped loses it
+        if !data.review_sessions.is_empty() || data.review_sessions != before {
+            throttle.wait().await;
+            handlers::edit_card(
+                &monitor.bot,
+                &monitor.state,
+                ChatId(msg.chat_id),
+                message_id,
+                &data,
+            )
+            .await
+            .ok();
+        }
+
+        let card = audit::card(&data.repo, data.pr_number, msg.chat_id);
+        for reviewer in &released {
+            info!("Released {}'s review claim on {}", reviewer, card);
+            monitor.state.audit(format!(
+                "edited {}: released {}'s review claim, time box ran out",
+                card, reviewer
+            ));
+        }
+        for text in notices {
+            throttle.wait().await;
+            match monitor
+                .bot
+                .send_message(ChatId(msg.chat_id), text)
+                .reply_parameters(ReplyParameters::new(message_id))
+                .retried()
+                .await
+            {
+                Ok(_) => monitor
+                    .state
+                    .audit(format!("notified {}: review time box ran out", card)),
+                Err(e) => error!("Failed to send review time box notice: {}", e),
+            }
+        }
+    }
+}
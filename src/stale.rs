@@ -0,0 +1,244 @@
+use crate::audit;
+use crate::handlers;
+use crate::lifecycle::PrStatus;
+use crate::monitor::Monitor;
+use crate::nudges;
+use crate::sender::Retried;
+use crate::state::{ItemType, PrData};
+use chrono::Utc;
+use std::collections::HashMap;
+use teloxide::prelude::*;
+use teloxide::types::{MessageId, ParseMode, ReplyParameters};
+use tracing::{error, info};
+
+const CHECK_INTERVAL: tokio::time::Duration = tokio::time::Duration::from_secs(6 * 60 * 60);
+const DAY: i64 = 24 * 60 * 60;
+/// Authors of a stale PR are nudged at most this often.
+const NUDGE_INTERVAL: i64 = 7 * DAY;
+
+/// Periodically flags PRs without GitHub activity for `STALE_AFTER_DAYS` and nudges their
+/// authors every week, more insistently the longer the PR stays stale.
+pub async fn run(monitor: Monitor) {
+    loop {
+        check(&monitor).await;
+        tokio::time::sleep(CHECK_INTERVAL).await;
+    }
+}
+
+async fn check(monitor: &Monitor) {
+    let days = monitor.config().stale_after_days;
+    if days == 0 {
+        return;
+    }
+    let threshold = days as i64 * DAY;
+    let now = Utc::now().timestamp();
+
+    let messages = match monitor.state.get_all_active_messages().await {
+        Ok(messages) => messages,
+        Err(e) => {
+            error!("Failed to load cards for stale checks: {}", e);
+            return;
+        }
+    };
+    // A PR with cards in several chats is looked up once
+    let mut activity: HashMap<(String, String, i64), Option<i64>> = HashMap::new();
+    for msg in messages {
+        // Issue cards have no commits or reviews to go stale on
+        if PrStatus::parse(&msg.status) == PrStatus::Snoozed
+            || ItemType::parse(&msg.item_type) == ItemType::Issue
+        {
+            continue;
+        }
+        let key = (msg.repo_owner.clone(), msg.repo_name.clone(), msg.pr_number);
+        let last = match activity.get(&key) {
+            Some(last) => *last,
+            None => match monitor
+                .github
+                .get_last_activity(&msg.repo_owner, &msg.repo_name, msg.pr_number as u64)
+                .await
+            {
+                Ok(last) => {
+                    let last = last.map(|t| t.timestamp());
+                    activity.insert(key, last);
+                    last
+                }
+                Err(e) => {
+                    error!(
+                        "Failed to check activity of {}/{}#{}: {}",
+                        msg.repo_owner, msg.repo_name, msg.pr_number, e
+                    );
+                    continue;
+                }
+            },
+        };
+        let stale_since = last.filter(|t| now - t > threshold);
+
+        let Ok(Some(mut data)) = monitor
+            .state
+            .get_pr_data(msg.message_id.clone(), msg.chat_id)
+            .await
+        else {
+            continue;
+        };
+        let card = audit::card(&data.repo, data.pr_number, msg.chat_id);
+        if data.stale_since != stale_since {
+            data.stale_since = stale_since;
+            if let Err(e) = monitor
+                .state
+                .update_pr_data(msg.message_id.clone(), data.clone())
+                .await
+            {
+                error!("Failed to save stale flag: {}", e);
+                continue;
+            }
+            handlers::edit_card(
+                &monitor.bot,
+                &monitor.state,
+                ChatId(msg.chat_id),
+                MessageId(msg.message_id.parse().unwrap_or(0)),
+                &data,
+            )
+            .await
+            .ok();
+            monitor.state.audit(format!(
+                "edited {}: {}",
+                card,
+                if stale_since.is_some() {
+                    "stale"
+                } else {
+                    "active again"
+                }
+            ));
+        }
+
+        let Some(since) = stale_since else {
+            continue;
+        };
+        // Nudges from before the latest activity belong to an earlier stale stretch
+        if msg
+            .last_nudged_at
+            .filter(|t| *t > since)
+            .is_some_and(|t| now - t < NUDGE_INTERVAL)
+        {
+            continue;
+        }
+        let weeks_stale = (now - since - threshold) / NUDGE_INTERVAL;
+        let Some(text) = nudge_text(
+            monitor,
+            &data,
+            &msg.message_id,
+            (now - since) / DAY,
+            weeks_stale,
+        )
+        .await
+        else {
+            // Held for the day's summary, which counts as this week's nudge
+            monitor
+                .state
+                .set_last_nudged(&msg.message_id, msg.chat_id, now)
+                .await
+                .ok();
+            continue;
+        };
+        info!("Nudging the author of stale {}", card);
+        match monitor
+            .bot
+            .send_message(ChatId(msg.chat_id), text)
+            .reply_parameters(ReplyParameters::new(MessageId(
+                msg.message_id.parse().unwrap_or(0),
+            )))
+            .parse_mode(ParseMode::Html)
+            .retried()
+            .await
+        {
+            Ok(_) => {
+                monitor
+                    .state
+                    .set_last_nudged(&msg.message_id, msg.chat_id, now)
+                    .await
+                    .ok();
+                monitor.state.audit(format!(
+                    "notified {}: stale for {} days",
+                    card,
+                    (now - since) / DAY
+                ));
+            }
+            Err(e) => error!("Failed to nudge about stale PR: {}", e),
+        }
+    }
+}
+
+/// The first nudge asks the author; later ones bring in the reviewers and then the chat's admins.
+/// `None` when everyone it would mention is past their nudge budget.
+async fn nudge_text(
+    monitor: &Monitor,
+    data: &PrData,
+    message_id: &str,
+    idle_days: i64,
+    weeks_stale: i64,
+) -> Option<String> {
+    let link = monitor
+        .state
+        .get_user_by_github(&data.author)
+        .await
+        .ok()
+        .flatten();
+    let admins = if weeks_stale >= 2 {
+        monitor.admin_usernames(ChatId(data.chat_id)).await
+    } else {
+        Vec::new()
+    };
+    let mut mentioned: Vec<String> = link.iter().map(nudges::username).collect();
+    mentioned.extend(admins);
+    let line = format!(
+        "{} no activity for {} days",
+        nudges::card_link(data, message_id),
+        idle_days
+    );
+    let allowed = nudges::allowed(monitor, data.chat_id, &mentioned, &line).await;
+    if !mentioned.is_empty() && allowed.is_empty() {
+        return None;
+    }
+
+    let author = match &link {
+        Some(link) if allowed.contains(&nudges::username(link)) => handlers::mention(link),
+        _ => teloxide::utils::html::escape(&data.author),
+    };
+    Some(match weeks_stale {
+        0 => format!(
+            "🕸 No activity here for {} days. {}, is this PR still alive? Push, comment or close it.",
+            idle_days, author
+        ),
+        1 => {
+            let reviewers = if data.reviewers.is_empty() {
+                String::new()
+            } else {
+                format!(
+                    " Reviewers: {}.",
+                    teloxide::utils::html::escape(&data.reviewers.join(", "))
+                )
+            };
+            format!(
+                "🕸🕸 Still no activity after {} days. {}, please move it forward or close it.{}",
+                idle_days, author, reviewers
+            )
+        }
+        _ => {
+            let admins: Vec<String> = allowed
+                .iter()
+                .filter(|name| link.as_ref().is_none_or(|l| nudges::username(l) != **name))
+                .map(|a| format!("@{}", a))
+                .collect();
+            format!(
+                "🪦 {} days without activity. {}, {} — time to close it or get it moving?",
+                idle_days,
+                author,
+                if admins.is_empty() {
+                    "admins".to_string()
+                } else {
+                    admins.join(" ")
+                }
+            )
+        }
+    })
+}
@@ -1,8 +1,19 @@
-use crate::db::Db;
+use crate::audit::Audit;
+use crate::checks::{CiSummary, GateState};
+use crate::config::ClosedCards;
+use crate::db::{
+    ArchiveCursor, ArchivedMerge, BranchAlert, BurstPr, ButtonMode, CheckGate, Db, Event,
+    GroupItem, HeldNudge, Lead, PoolMember, PrActivity, PrGroup, PrMessage, RepoCapabilities,
+    SchemaVersions, StoredRule, StoredSearch, TicketTracker, UserLink, ASSIGNMENT_WINDOW_DAYS,
+};
+use crate::lifecycle::PrStatus;
+use crate::sessions::ReviewSession;
+use crate::tickets::Ticket;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct PrData {
     pub pr_url: String,
     pub title: String,
@@ -13,20 +24,100 @@ pub struct PrData {
     pub approvals: Vec<String>,
     pub changes_requested: Vec<String>,
     pub comments: Vec<String>,
-    pub is_merged: bool,
-    pub is_draft: bool,
-    pub re_review_requested: bool,
+    pub from_github: Vec<String>, // whose approval, change request or comment is their GitHub review
+    pub status: PrStatus,
+    pub review_round: u32, // bumped on every re-review request
+    pub status_since: i64, // unix timestamp of the last status change
+    pub snoozed_until: Option<i64>,
+    pub gates: Vec<(String, GateState)>, // configured check gates, see checks.rs
+    pub security: bool,                  // security-sensitive, see security.rs
+    pub ci: Option<CiSummary>,           // all checks on the head commit, see checks.rs
+    pub conflicts: bool,                 // the PR can't be merged without resolving conflicts
+    pub labels: Vec<String>,             // GitHub labels
+    pub head_sha: Option<String>,        // head commit at the last sync
+    pub unresolved_threads: Option<u32>, // only when the base branch requires resolving them
+    pub requested_reviewers: Option<Vec<String>>, // logins, then teams as org/team; None until first synced
+    pub departed: Vec<String>, // the author's login or reviewers' names, once they left the chat
+    pub owner: Option<String>, // took the PR over with /takeover after the author left
+    pub review_sessions: Vec<ReviewSession>, // timed reviews, see sessions.rs
+    pub stale_since: Option<i64>, // last GitHub activity, set while the PR counts as stale
+    pub conflicted_since: Option<i64>, // set while the PR has merge conflicts
+    pub conflict_nudged_sha: Option<String>, // head commit when the author was last asked to rebase
+    pub thread_id: Option<i32>, // forum topic the card was posted in
+    pub maintainers: Option<Vec<String>>, // logins and card names; None without two-stage approval
+    pub suggestions: Vec<Suggestion>, // approvals that came with comments
+    pub tickets: Vec<Ticket>,  // ticket ids in the title or description, see tickets.rs
+    pub item_type: ItemType,
+    pub assignees: Vec<String>,    // GitHub logins, issues only
+    pub self_approval: bool,       // the chat's /selfapproval, not stored with the card
+    pub compacted: Option<String>, // the state the card was compacted in, see compact.rs
     pub chat_id: i64,
 }
 
+/// What a card tracks: a pull request, or with `TRACK_ISSUES` an issue. Issue cards reuse the
+/// reactions, /list and cleanup of PR cards, without reviews, CI or merge state.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ItemType {
+    #[default]
+    Pr,
+    Issue,
+}
+
+impl ItemType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ItemType::Pr => "pr",
+            ItemType::Issue => "issue",
+        }
+    }
+
+    pub fn parse(s: &str) -> Self {
+        match s {
+            "issue" => ItemType::Issue,
+            _ => ItemType::Pr,
+        }
+    }
+}
+
+/// A GitHub approval that came with comments, shown apart from plain approvals. With
+/// `HOLD_SUGGESTED_APPROVALS` it keeps the PR from counting as approved until the author
+/// acknowledges it with /ack.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Suggestion {
+    pub reviewer: String,
+    pub review_id: u64,
+    pub acked: bool,
+    pub holds: bool, // HOLD_SUGGESTED_APPROVALS was on when it came in
+}
+
 #[derive(Clone)]
 pub struct StateManager {
     db: Db,
+    audit: Audit,
 }
 
 impl StateManager {
-    pub const fn new(db: Db) -> Self {
-        Self { db }
+    pub fn new(db: Db, audit: Audit) -> Self {
+        Self { db, audit }
+    }
+
+    /// Set when the database schema is newer than this build, see `Db::new`.
+    pub fn safe_mode(&self) -> Option<SchemaVersions> {
+        self.db.safe_mode()
+    }
+
+    pub async fn ping(&self) -> Result<()> {
+        self.db.ping().await
+    }
+
+    /// Closes the database once in-flight writes are done; called on shutdown.
+    pub async fn close(&self) {
+        self.db.close().await
+    }
+
+    /// Mirrors an action the bot took into the audit log, if one is configured.
+    pub fn audit(&self, line: impl Into<String>) {
+        self.audit.record(line);
     }
 
     pub async fn add_message(&self, message_id: String, data: PrData) -> Result<()> {
@@ -44,9 +135,49 @@ impl StateManager {
             repo_owner: owner,
             repo_name: name,
             pr_number: data.pr_number as i64,
-            is_merged: data.is_merged,
-            is_draft: data.is_draft,
-            re_review_requested: data.re_review_requested,
+            status: data.status.as_str().to_string(),
+            review_round: data.review_round as i64,
+            status_since: data.status_since,
+            snoozed_until: data.snoozed_until,
+            gates: (!data.gates.is_empty())
+                .then(|| serde_json::to_string(&data.gates).unwrap_or_default()),
+            security: data.security as i64,
+            ci: data
+                .ci
+                .map(|ci| serde_json::to_string(&ci).unwrap_or_default()),
+            conflicts: data.conflicts as i64,
+            labels: (!data.labels.is_empty())
+                .then(|| serde_json::to_string(&data.labels).unwrap_or_default()),
+            head_sha: data.head_sha,
+            unresolved_threads: data.unresolved_threads.map(i64::from),
+            requested_reviewers: data
+                .requested_reviewers
+                .map(|r| serde_json::to_string(&r).unwrap_or_default()),
+            departed: (!data.departed.is_empty())
+                .then(|| serde_json::to_string(&data.departed).unwrap_or_default()),
+            owner: data.owner,
+            last_reminded_at: None,
+            stale_since: data.stale_since,
+            conflicted_since: data.conflicted_since,
+            conflict_nudged_sha: data.conflict_nudged_sha,
+            thread_id: data.thread_id.map(i64::from),
+            maintainers: data
+                .maintainers
+                .map(|m| serde_json::to_string(&m).unwrap_or_default()),
+            last_nudged_at: None,
+            rendered_at: None,
+            settled_key: None,
+            settled_since: None,
+            compact: 0,
+            suggestions: (!data.suggestions.is_empty())
+                .then(|| serde_json::to_string(&data.suggestions).unwrap_or_default()),
+            tickets: (!data.tickets.is_empty())
+                .then(|| serde_json::to_string(&data.tickets).unwrap_or_default()),
+            review_sessions: (!data.review_sessions.is_empty())
+                .then(|| serde_json::to_string(&data.review_sessions).unwrap_or_default()),
+            item_type: data.item_type.as_str().to_string(),
+            assignees: (!data.assignees.is_empty())
+                .then(|| serde_json::to_string(&data.assignees).unwrap_or_default()),
         };
 
         self.db.save_pr_message(&msg).await?;
@@ -55,19 +186,30 @@ impl StateManager {
                 &message_id,
                 data.chat_id,
                 &data.reviewers,
-                &data.approvals,
-                &data.changes_requested,
-                &data.comments,
+                [
+                    ("approval", &data.approvals),
+                    ("changes_requested", &data.changes_requested),
+                    ("comment", &data.comments),
+                ],
+                &data.from_github,
             )
             .await?;
-        
+
         // Mark seen
-        let key = format!("{}#{}", data.repo, data.pr_number);
+        let key = format!("{}:{}#{}", data.chat_id, data.repo, data.pr_number);
         self.db.mark_pr_seen(&key).await?;
-        
+
         Ok(())
     }
 
+    pub async fn get_pr_message(
+        &self,
+        message_id: &str,
+        chat_id: i64,
+    ) -> Result<Option<PrMessage>> {
+        self.db.get_pr_message(message_id, chat_id).await
+    }
+
     pub async fn get_pr_data(&self, message_id: String, chat_id: i64) -> Result<Option<PrData>> {
         let msg = self.db.get_pr_message(&message_id, chat_id).await?;
         if let Some(m) = msg {
@@ -83,9 +225,56 @@ impl StateManager {
                 approvals,
                 changes_requested,
                 comments,
-                is_merged: m.is_merged,
-                is_draft: m.is_draft,
-                re_review_requested: m.re_review_requested,
+                from_github: self.db.get_github_sourced(&message_id, chat_id).await?,
+                status: PrStatus::parse(&m.status),
+                review_round: m.review_round as u32,
+                status_since: m.status_since,
+                snoozed_until: m.snoozed_until,
+                gates: m
+                    .gates
+                    .and_then(|g| serde_json::from_str(&g).ok())
+                    .unwrap_or_default(),
+                security: m.security != 0,
+                ci: m.ci.and_then(|c| serde_json::from_str(&c).ok()),
+                conflicts: m.conflicts != 0,
+                labels: m
+                    .labels
+                    .and_then(|l| serde_json::from_str(&l).ok())
+                    .unwrap_or_default(),
+                head_sha: m.head_sha,
+                unresolved_threads: m.unresolved_threads.map(|n| n as u32),
+                requested_reviewers: m
+                    .requested_reviewers
+                    .and_then(|r| serde_json::from_str(&r).ok()),
+                departed: m
+                    .departed
+                    .and_then(|d| serde_json::from_str(&d).ok())
+                    .unwrap_or_default(),
+                owner: m.owner,
+                review_sessions: m
+                    .review_sessions
+                    .and_then(|s| serde_json::from_str(&s).ok())
+                    .unwrap_or_default(),
+                stale_since: m.stale_since,
+                conflicted_since: m.conflicted_since,
+                conflict_nudged_sha: m.conflict_nudged_sha,
+                thread_id: m.thread_id.map(|t| t as i32),
+                maintainers: m.maintainers.and_then(|l| serde_json::from_str(&l).ok()),
+                suggestions: m
+                    .suggestions
+                    .and_then(|s| serde_json::from_str(&s).ok())
+                    .unwrap_or_default(),
+                tickets: m
+                    .tickets
+                    .and_then(|t| serde_json::from_str(&t).ok())
+                    .unwrap_or_default(),
+                item_type: ItemType::parse(&m.item_type),
+                self_approval: self.db.get_self_approval(m.chat_id).await?,
+                compacted: m.settled_key.filter(|_| m.compact != 0),
+                assignees: m
+                    .assignees
+                    .and_then(|a| serde_json::from_str(&a).ok())
+                    .unwrap_or_default(),
                 chat_id: m.chat_id,
             }))
         } else {
@@ -93,29 +282,969 @@ impl StateManager {
         }
     }
 
+    /// Saves a changed card, recording who newly reviewed or approved it for the leaderboard.
     pub async fn update_pr_data(&self, message_id: String, data: PrData) -> Result<()> {
-        self.add_message(message_id, data).await
+        let before = self.get_pr_data(message_id.clone(), data.chat_id).await?;
+        self.add_message(message_id.clone(), data.clone()).await?;
+
+        let Some(before) = before else {
+            return Ok(());
+        };
+        let added = |now: &[String], was: &[String]| -> Vec<String> {
+            now.iter().filter(|n| !was.contains(n)).cloned().collect()
+        };
+        let mut events: Vec<(String, &str)> = Vec::new();
+        for name in added(&data.approvals, &before.approvals) {
+            events.push((name, "approved"));
+        }
+        for name in added(&data.comments, &before.comments)
+            .into_iter()
+            .chain(added(&data.changes_requested, &before.changes_requested))
+        {
+            events.push((name, "reviewed"));
+        }
+        for (name, kind) in events {
+            self.record_event(&data, &message_id, &name, kind, "")
+                .await?;
+        }
+        Ok(())
     }
 
-    pub async fn is_pr_seen(&self, repo: &str, pr_number: u64) -> Result<bool> {
-        let key = format!("{}#{}", repo, pr_number);
+    /// Whether the PR was already announced in `chat_id`.
+    pub async fn is_pr_seen(
+        &self,
+        chat_id: i64,
+        owner: &str,
+        repo: &str,
+        pr_number: u64,
+    ) -> Result<bool> {
+        let key = format!("{}:{}/{}#{}", chat_id, owner, repo, pr_number);
         self.db.is_pr_seen(&key).await
     }
-    
-    pub async fn add_repository(&self, owner: &str, name: &str) -> Result<()> {
-        self.db.add_repository(owner, name).await
+
+    /// The open alert about the default branch of `repo` ("owner/repo") in the chat.
+    pub async fn get_branch_alert(&self, chat_id: i64, repo: &str) -> Result<Option<BranchAlert>> {
+        self.db.get_branch_alert(chat_id, repo).await
+    }
+
+    pub async fn set_branch_alert(
+        &self,
+        chat_id: i64,
+        repo: &str,
+        alert: &BranchAlert,
+    ) -> Result<()> {
+        self.db.set_branch_alert(chat_id, repo, alert).await
+    }
+
+    pub async fn remove_branch_alert(&self, chat_id: i64, repo: &str) -> Result<()> {
+        self.db.remove_branch_alert(chat_id, repo).await
+    }
+
+    /// Remembers a release (by tag) as announced in the chat. Returns whether it wasn't already.
+    pub async fn mark_release_seen(
+        &self,
+        chat_id: i64,
+        owner: &str,
+        repo: &str,
+        tag: &str,
+    ) -> Result<bool> {
+        self.db
+            .mark_release_seen(chat_id, &format!("{}/{}", owner, repo), tag)
+            .await
+    }
+
+    pub async fn mark_repo_discovered(&self, owner: &str, name: &str) -> Result<bool> {
+        self.db.mark_repo_discovered(owner, name).await
+    }
+
+    pub async fn mark_pr_seen(
+        &self,
+        chat_id: i64,
+        owner: &str,
+        repo: &str,
+        pr_number: u64,
+    ) -> Result<()> {
+        let key = format!("{}:{}/{}#{}", chat_id, owner, repo, pr_number);
+        self.db.mark_pr_seen(&key).await
+    }
+
+    /// Remembers a draft in `chat_id` without announcing it, see `HOLD_DRAFTS`.
+    pub async fn hold_pr(
+        &self,
+        chat_id: i64,
+        owner: &str,
+        repo: &str,
+        pr_number: u64,
+    ) -> Result<()> {
+        let key = format!("{}:{}/{}#{}", chat_id, owner, repo, pr_number);
+        self.db.hold_pr(&key).await
+    }
+
+    /// Held drafts as (chat_id, owner, repo, pr_number).
+    pub async fn get_held_prs(&self) -> Result<Vec<(i64, String, String, u64)>> {
+        Ok(self
+            .db
+            .get_held_prs()
+            .await?
+            .iter()
+            .filter_map(|key| {
+                let (chat_id, pr) = key.split_once(':')?;
+                let (repo, number) = pr.rsplit_once('#')?;
+                let (owner, name) = repo.split_once('/')?;
+                Some((
+                    chat_id.parse().ok()?,
+                    owner.to_string(),
+                    name.to_string(),
+                    number.parse().ok()?,
+                ))
+            })
+            .collect())
+    }
+
+    /// Lets a held draft be announced. Returns false when it wasn't held (anymore).
+    pub async fn release_pr(
+        &self,
+        chat_id: i64,
+        owner: &str,
+        repo: &str,
+        pr_number: u64,
+    ) -> Result<bool> {
+        let key = format!("{}:{}/{}#{}", chat_id, owner, repo, pr_number);
+        self.db.release_pr(&key).await
+    }
+
+    pub async fn claim_card(&self, chat_id: i64, repo: &str, pr_number: u64) -> Result<bool> {
+        let (owner, name) = repo.split_once('/').unwrap_or((repo, ""));
+        self.db
+            .claim_card(chat_id, owner, name, pr_number as i64)
+            .await
+    }
+
+    pub async fn set_card_message(
+        &self,
+        chat_id: i64,
+        repo: &str,
+        pr_number: u64,
+        message_id: &str,
+    ) -> Result<()> {
+        let (owner, name) = repo.split_once('/').unwrap_or((repo, ""));
+        self.db
+            .set_card_message(chat_id, owner, name, pr_number as i64, message_id)
+            .await
+    }
+
+    pub async fn release_card(&self, chat_id: i64, repo: &str, pr_number: u64) -> Result<()> {
+        let (owner, name) = repo.split_once('/').unwrap_or((repo, ""));
+        self.db
+            .release_card(chat_id, owner, name, pr_number as i64)
+            .await
+    }
+
+    pub async fn find_card(
+        &self,
+        chat_id: i64,
+        owner: &str,
+        repo: &str,
+        pr_number: u64,
+    ) -> Result<Option<String>> {
+        self.db
+            .find_card(chat_id, owner, repo, pr_number as i64)
+            .await
+    }
+
+    pub async fn release_stale_claims(&self, before: i64) -> Result<u64> {
+        self.db.release_stale_claims(before).await
+    }
+
+    pub async fn backfill_card_tracking(&self) -> Result<()> {
+        self.db.backfill_card_tracking().await
+    }
+
+    pub async fn get_all_messages(&self) -> Result<Vec<crate::db::PrMessage>> {
+        self.db.get_all_messages().await
+    }
+
+    pub async fn add_repository(&self, chat_id: i64, owner: &str, name: &str) -> Result<()> {
+        self.db.add_repository(chat_id, owner, name).await
+    }
+
+    pub async fn add_config_repository(&self, chat_id: i64, owner: &str, name: &str) -> Result<()> {
+        self.db.add_config_repository(chat_id, owner, name).await
+    }
+
+    pub async fn remove_config_repository(
+        &self,
+        chat_id: i64,
+        owner: &str,
+        name: &str,
+    ) -> Result<bool> {
+        self.db.remove_config_repository(chat_id, owner, name).await
+    }
+
+    pub async fn set_repository_topic(
+        &self,
+        chat_id: i64,
+        owner: &str,
+        name: &str,
+        thread_id: Option<i32>,
+    ) -> Result<()> {
+        self.db
+            .set_repository_topic(chat_id, owner, name, thread_id)
+            .await
+    }
+
+    /// The forum topic the repository's cards go to in `chat_id`, if it has one.
+    pub async fn get_repository_topic(
+        &self,
+        chat_id: i64,
+        owner: &str,
+        name: &str,
+    ) -> Result<Option<i32>> {
+        let repos = self.db.get_repositories().await?;
+        Ok(repos
+            .into_iter()
+            .find(|r| r.chat_id == chat_id && r.owner == owner && r.name == name)
+            .and_then(|r| r.thread_id)
+            .map(|t| t as i32))
+    }
+
+    pub async fn remove_repository(&self, chat_id: i64, owner: &str, name: &str) -> Result<bool> {
+        self.db.remove_repository(chat_id, owner, name).await
+    }
+
+    /// Moves subscriptions created before per-chat support to `chat_id`.
+    pub async fn claim_unassigned_repositories(&self, chat_id: i64) -> Result<()> {
+        self.db.claim_unassigned_repositories(chat_id).await
+    }
+
+    /// Every subscribed repository with the chats subscribed to it.
+    /// Whether announcements may go to the chat, see verification.rs.
+    pub async fn is_chat_verified(&self, chat_id: i64) -> Result<bool> {
+        self.db.is_chat_verified(chat_id).await
+    }
+
+    pub async fn get_verification_code(&self, chat_id: i64) -> Result<Option<String>> {
+        self.db.get_verification_code(chat_id).await
+    }
+
+    pub async fn set_verification_code(&self, chat_id: i64, code: &str) -> Result<()> {
+        self.db.set_verification_code(chat_id, code).await
     }
-    
-    pub async fn get_repositories(&self) -> Result<Vec<(String, String)>> {
+
+    pub async fn find_chat_by_code(&self, code: &str) -> Result<Option<i64>> {
+        self.db.find_chat_by_code(code).await
+    }
+
+    pub async fn set_chat_verified(&self, chat_id: i64) -> Result<bool> {
+        self.db.set_chat_verified(chat_id).await
+    }
+
+    pub async fn get_subscriptions(&self) -> Result<Vec<((String, String), Vec<i64>)>> {
+        let mut subs: Vec<((String, String), Vec<i64>)> = Vec::new();
+        for r in self.db.get_repositories().await? {
+            match subs.last_mut() {
+                Some(((o, n), chats)) if *o == r.owner && *n == r.name => chats.push(r.chat_id),
+                _ => subs.push(((r.owner, r.name), vec![r.chat_id])),
+            }
+        }
+        Ok(subs)
+    }
+
+    pub async fn get_subscribers(&self, owner: &str, name: &str) -> Result<Vec<i64>> {
+        let repos = self.db.get_repositories().await?;
+        Ok(repos
+            .into_iter()
+            .filter(|r| r.owner == owner && r.name == name)
+            .map(|r| r.chat_id)
+            .collect())
+    }
+
+    pub async fn get_chat_repositories(&self, chat_id: i64) -> Result<Vec<(String, String)>> {
         let repos = self.db.get_repositories().await?;
-        Ok(repos.into_iter().map(|r| (r.owner, r.name)).collect())
+        Ok(repos
+            .into_iter()
+            .filter(|r| r.chat_id == chat_id)
+            .map(|r| (r.owner, r.name))
+            .collect())
+    }
+
+    pub async fn set_check_gates(
+        &self,
+        owner: &str,
+        name: &str,
+        gates: &[CheckGate],
+    ) -> Result<()> {
+        self.db.set_check_gates(owner, name, gates).await
+    }
+
+    pub async fn get_check_gates(&self, owner: &str, name: &str) -> Result<Vec<CheckGate>> {
+        self.db.get_check_gates(owner, name).await
     }
 
     pub async fn get_all_active_messages(&self) -> Result<Vec<crate::db::PrMessage>> {
         self.db.get_all_active_messages().await
     }
 
+    pub async fn get_chat_messages(&self, chat_id: i64) -> Result<Vec<crate::db::PrMessage>> {
+        self.db.get_chat_messages(chat_id).await
+    }
+
+    pub async fn get_messages_for_pr(
+        &self,
+        owner: &str,
+        repo: &str,
+        pr_number: u64,
+    ) -> Result<Vec<crate::db::PrMessage>> {
+        self.db
+            .get_messages_for_pr(owner, repo, pr_number as i64)
+            .await
+    }
+
+    pub async fn get_active_messages_for_repo(
+        &self,
+        owner: &str,
+        repo: &str,
+    ) -> Result<Vec<crate::db::PrMessage>> {
+        self.db.get_active_messages_for_repo(owner, repo).await
+    }
+
+    pub async fn record_event(
+        &self,
+        data: &PrData,
+        message_id: &str,
+        username: &str,
+        kind: &str,
+        detail: &str,
+    ) -> Result<()> {
+        let (owner, name) = data.repo.split_once('/').unwrap_or((&data.repo, ""));
+        self.db
+            .record_event(&Event {
+                chat_id: data.chat_id,
+                message_id: message_id.to_string(),
+                repo_owner: owner.to_string(),
+                repo_name: name.to_string(),
+                pr_number: data.pr_number as i64,
+                username: username.to_string(),
+                kind: kind.to_string(),
+                detail: detail.to_string(),
+                created_at: chrono::Utc::now().timestamp(),
+            })
+            .await
+    }
+
+    /// Stores the events of a `/backfill`, replacing those of an earlier one.
+    pub async fn import_events(
+        &self,
+        chat_id: i64,
+        owner: &str,
+        repo: &str,
+        events: &[Event],
+    ) -> Result<()> {
+        self.db.import_events(chat_id, owner, repo, events).await
+    }
+
+    pub async fn get_activity_counts(
+        &self,
+        chat_id: i64,
+        since: i64,
+    ) -> Result<HashMap<String, i64>> {
+        Ok(self
+            .db
+            .get_activity_counts(chat_id, since)
+            .await?
+            .into_iter()
+            .collect())
+    }
+
+    pub async fn get_event_details(
+        &self,
+        chat_id: i64,
+        kind: &str,
+        since: i64,
+    ) -> Result<Vec<String>> {
+        self.db.get_event_details(chat_id, kind, since).await
+    }
+
+    pub async fn get_events_since(
+        &self,
+        chat_id: i64,
+        kind: &str,
+        since: i64,
+    ) -> Result<Vec<(String, String)>> {
+        self.db.get_events_since(chat_id, kind, since).await
+    }
+
+    pub async fn get_card_events(
+        &self,
+        chat_id: i64,
+        message_id: &str,
+        kind: &str,
+    ) -> Result<Vec<(String, String)>> {
+        self.db.get_card_events(chat_id, message_id, kind).await
+    }
+
+    pub async fn get_review_counts(
+        &self,
+        chat_id: i64,
+        since: i64,
+    ) -> Result<Vec<(String, i64, i64)>> {
+        self.db.get_review_counts(chat_id, since).await
+    }
+
+    pub async fn get_review_pairs(
+        &self,
+        chat_id: i64,
+        since: i64,
+        until: i64,
+    ) -> Result<Vec<(String, String, String)>> {
+        self.db.get_review_pairs(chat_id, since, until).await
+    }
+
     pub async fn remove_message(&self, message_id: &str, chat_id: i64) -> Result<()> {
         self.db.remove_message(message_id, chat_id).await
     }
+
+    pub async fn forget_user(&self, username: &str) -> Result<Vec<(String, i64)>> {
+        self.db.forget_user(username).await
+    }
+
+    pub async fn wipe_chat(&self, chat_id: i64) -> Result<u64> {
+        self.db.wipe_chat(chat_id).await
+    }
+
+    pub async fn archive_merge(&self, entry: &ArchivedMerge) -> Result<()> {
+        self.db.archive_merge(entry).await
+    }
+
+    pub async fn get_archived_merges(
+        &self,
+        source: &str,
+        since: i64,
+        after: Option<&ArchiveCursor>,
+        limit: i64,
+    ) -> Result<Vec<ArchivedMerge>> {
+        self.db
+            .get_archived_merges(source, since, after, limit)
+            .await
+    }
+
+    pub async fn get_all_archived_merges(&self, since: i64) -> Result<Vec<ArchivedMerge>> {
+        self.db.get_all_archived_merges(since).await
+    }
+
+    pub async fn get_last_archived_merge(&self, source: &str) -> Result<Option<i64>> {
+        self.db.get_last_archived_merge(source).await
+    }
+
+    pub async fn link_user(&self, link: &UserLink) -> Result<()> {
+        self.db.link_user(link).await
+    }
+
+    pub async fn set_github_token(
+        &self,
+        telegram_id: i64,
+        login: &str,
+        sealed: &str,
+    ) -> Result<()> {
+        self.db.set_github_token(telegram_id, login, sealed).await
+    }
+
+    pub async fn remove_github_token(&self, telegram_id: i64) -> Result<bool> {
+        self.db.remove_github_token(telegram_id).await
+    }
+
+    pub async fn get_github_token(&self, telegram_id: i64) -> Result<Option<(String, String)>> {
+        self.db.get_github_token(telegram_id).await
+    }
+
+    pub async fn unlink_user(&self, telegram_id: i64) -> Result<bool> {
+        self.db.unlink_user(telegram_id).await
+    }
+
+    pub async fn get_user_link(&self, telegram_id: i64) -> Result<Option<UserLink>> {
+        self.db.get_user_link(telegram_id).await
+    }
+
+    pub async fn get_user_by_github(&self, login: &str) -> Result<Option<UserLink>> {
+        self.db.get_user_by_github(login).await
+    }
+
+    /// Linked users among `names`, which may be GitHub logins or names on cards.
+    pub async fn find_linked_users(&self, names: &[String]) -> Result<Vec<UserLink>> {
+        Ok(self
+            .db
+            .get_user_links()
+            .await?
+            .into_iter()
+            .filter(|l| {
+                names
+                    .iter()
+                    .any(|n| n.eq_ignore_ascii_case(&l.github_login) || *n == l.telegram_name)
+            })
+            .collect())
+    }
+
+    /// Lowercased GitHub login -> the linked user's name on cards.
+    pub async fn get_github_identities(&self) -> Result<HashMap<String, String>> {
+        Ok(self
+            .db
+            .get_user_links()
+            .await?
+            .into_iter()
+            .map(|l| (l.github_login.to_lowercase(), l.telegram_name))
+            .collect())
+    }
+
+    pub async fn get_pr_activity(&self) -> Result<Vec<PrActivity>> {
+        self.db.get_pr_activity().await
+    }
+
+    pub async fn set_pr_activity(&self, activity: &PrActivity) -> Result<()> {
+        self.db.set_pr_activity(activity).await
+    }
+
+    pub async fn prune_pr_activity(&self) -> Result<()> {
+        self.db.prune_pr_activity().await
+    }
+
+    pub async fn claim_update(&self, update_id: i64) -> Result<bool> {
+        self.db.claim_update(update_id).await
+    }
+
+    pub async fn get_last_update_id(&self) -> Result<Option<i64>> {
+        self.db.get_last_update_id().await
+    }
+
+    pub async fn prune_updates(&self, before: i64) -> Result<()> {
+        self.db.prune_updates(before).await
+    }
+
+    pub async fn get_repo_capabilities(
+        &self,
+        owner: &str,
+        name: &str,
+    ) -> Result<Option<RepoCapabilities>> {
+        self.db.get_repo_capabilities(owner, name).await
+    }
+
+    pub async fn set_repo_capabilities(
+        &self,
+        owner: &str,
+        name: &str,
+        capabilities: &RepoCapabilities,
+    ) -> Result<()> {
+        self.db
+            .set_repo_capabilities(owner, name, capabilities)
+            .await
+    }
+
+    pub async fn get_last_poll(&self, owner: &str, name: &str) -> Result<Option<i64>> {
+        self.db.get_last_poll(owner, name).await
+    }
+
+    pub async fn set_last_poll(&self, owner: &str, name: &str, last_check: i64) -> Result<()> {
+        self.db.set_last_poll(owner, name, last_check).await
+    }
+
+    pub async fn get_job_run(&self, chat_id: i64, job: &str) -> Result<Option<i64>> {
+        self.db.get_job_run(chat_id, job).await
+    }
+
+    pub async fn set_job_run(&self, chat_id: i64, job: &str, last_run: i64) -> Result<()> {
+        self.db.set_job_run(chat_id, job, last_run).await
+    }
+
+    pub async fn set_last_reminded(&self, message_id: &str, chat_id: i64, at: i64) -> Result<()> {
+        self.db.set_last_reminded(message_id, chat_id, at).await
+    }
+
+    pub async fn set_last_nudged(&self, message_id: &str, chat_id: i64, at: i64) -> Result<()> {
+        self.db.set_last_nudged(message_id, chat_id, at).await
+    }
+
+    pub async fn get_chat_tenant(&self, chat_id: i64) -> Result<Option<String>> {
+        self.db.get_chat_tenant(chat_id).await
+    }
+
+    pub async fn set_chat_tenant(&self, chat_id: i64, tenant_id: Option<&str>) -> Result<()> {
+        self.db.set_chat_tenant(chat_id, tenant_id).await
+    }
+
+    pub async fn set_settled(
+        &self,
+        message_id: &str,
+        chat_id: i64,
+        key: &str,
+        since: i64,
+        compact: bool,
+    ) -> Result<()> {
+        self.db
+            .set_settled(message_id, chat_id, key, since, compact)
+            .await
+    }
+
+    pub async fn set_rendered_at(&self, message_id: &str, chat_id: i64, at: i64) -> Result<()> {
+        self.db.set_rendered_at(message_id, chat_id, at).await
+    }
+
+    /// Whether the chat's cards carry buttons because reactions can't reach the bot there.
+    pub async fn get_button_mode(&self, chat_id: i64) -> Result<ButtonMode> {
+        self.db.get_button_mode(chat_id).await
+    }
+
+    pub async fn set_button_mode(&self, chat_id: i64, mode: ButtonMode) -> Result<()> {
+        self.db.set_button_mode(chat_id, mode).await
+    }
+
+    pub async fn get_dashboard(&self, chat_id: i64) -> Result<Option<String>> {
+        self.db.get_dashboard(chat_id).await
+    }
+
+    pub async fn get_dashboards(&self) -> Result<Vec<(i64, String)>> {
+        self.db.get_dashboards().await
+    }
+
+    pub async fn set_dashboard(&self, chat_id: i64, message_id: Option<&str>) -> Result<()> {
+        self.db.set_dashboard(chat_id, message_id).await
+    }
+
+    pub async fn get_nudge_budget(&self, chat_id: i64) -> Result<Option<u32>> {
+        self.db.get_nudge_budget(chat_id).await
+    }
+
+    pub async fn set_nudge_budget(&self, chat_id: i64, budget: Option<u32>) -> Result<()> {
+        self.db.set_nudge_budget(chat_id, budget).await
+    }
+
+    /// The chat's own policy for cards of closed PRs; one that no longer parses counts as unset.
+    pub async fn get_closed_cards(&self, chat_id: i64) -> Result<Option<ClosedCards>> {
+        Ok(self
+            .db
+            .get_closed_cards(chat_id)
+            .await?
+            .and_then(|p| ClosedCards::parse(&p)))
+    }
+
+    pub async fn set_closed_cards(&self, chat_id: i64, policy: Option<ClosedCards>) -> Result<()> {
+        self.db
+            .set_closed_cards(chat_id, policy.map(|p| p.to_string()).as_deref())
+            .await
+    }
+
+    /// Whether the chat lets authors approve their own PRs, see /selfapproval.
+    pub async fn get_self_approval(&self, chat_id: i64) -> Result<bool> {
+        self.db.get_self_approval(chat_id).await
+    }
+
+    pub async fn set_self_approval(&self, chat_id: i64, allowed: bool) -> Result<()> {
+        self.db.set_self_approval(chat_id, allowed).await
+    }
+
+    pub async fn add_kept_card(
+        &self,
+        chat_id: i64,
+        message_id: &str,
+        delete_at: i64,
+    ) -> Result<()> {
+        self.db.add_kept_card(chat_id, message_id, delete_at).await
+    }
+
+    pub async fn get_due_kept_cards(&self, now: i64) -> Result<Vec<(i64, String)>> {
+        self.db.get_due_kept_cards(now).await
+    }
+
+    pub async fn remove_kept_card(&self, chat_id: i64, message_id: &str) -> Result<()> {
+        self.db.remove_kept_card(chat_id, message_id).await
+    }
+
+    pub async fn take_nudge(
+        &self,
+        chat_id: i64,
+        username: &str,
+        day: &str,
+        budget: u32,
+    ) -> Result<bool> {
+        self.db.take_nudge(chat_id, username, day, budget).await
+    }
+
+    pub async fn hold_nudge(
+        &self,
+        chat_id: i64,
+        username: &str,
+        day: &str,
+        line: &str,
+    ) -> Result<()> {
+        self.db.hold_nudge(chat_id, username, day, line).await
+    }
+
+    pub async fn get_held_nudges(&self, day: &str) -> Result<Vec<HeldNudge>> {
+        self.db.get_held_nudges(day).await
+    }
+
+    pub async fn delete_held_nudges(&self, chat_id: i64, day: &str) -> Result<()> {
+        self.db.delete_held_nudges(chat_id, day).await
+    }
+
+    pub async fn prune_nudges(&self, day: &str) -> Result<()> {
+        self.db.prune_nudges(day).await
+    }
+
+    pub async fn set_mirror_chats(&self, mirrors: &[(i64, i64)]) -> Result<()> {
+        self.db.set_mirror_chats(mirrors).await
+    }
+
+    pub async fn get_mirror_chats(&self, source_chat_id: i64) -> Result<Vec<i64>> {
+        self.db.get_mirror_chats(source_chat_id).await
+    }
+
+    pub async fn is_mirror_chat(&self, chat_id: i64) -> Result<bool> {
+        self.db.is_mirror_chat(chat_id).await
+    }
+
+    pub async fn get_mirror_cards(
+        &self,
+        source_chat_id: i64,
+        source_message_id: &str,
+    ) -> Result<Vec<(i64, String)>> {
+        self.db
+            .get_mirror_cards(source_chat_id, source_message_id)
+            .await
+    }
+
+    pub async fn add_mirror_card(
+        &self,
+        source_chat_id: i64,
+        source_message_id: &str,
+        chat_id: i64,
+        message_id: &str,
+    ) -> Result<()> {
+        self.db
+            .add_mirror_card(source_chat_id, source_message_id, chat_id, message_id)
+            .await
+    }
+
+    pub async fn delete_mirror_cards(
+        &self,
+        source_chat_id: i64,
+        source_message_id: &str,
+    ) -> Result<()> {
+        self.db
+            .delete_mirror_cards(source_chat_id, source_message_id)
+            .await
+    }
+
+    pub async fn get_reviewer_pool(
+        &self,
+        chat_id: i64,
+        owner: &str,
+        repo: &str,
+    ) -> Result<Vec<PoolMember>> {
+        let since =
+            (chrono::Utc::now() - chrono::Duration::days(ASSIGNMENT_WINDOW_DAYS)).timestamp();
+        self.db.get_reviewer_pool(chat_id, owner, repo, since).await
+    }
+
+    pub async fn add_pool_member(
+        &self,
+        chat_id: i64,
+        owner: &str,
+        repo: &str,
+        username: &str,
+    ) -> Result<()> {
+        self.db
+            .add_pool_member(chat_id, owner, repo, username)
+            .await
+    }
+
+    pub async fn remove_pool_member(
+        &self,
+        chat_id: i64,
+        owner: &str,
+        repo: &str,
+        username: &str,
+    ) -> Result<bool> {
+        self.db
+            .remove_pool_member(chat_id, owner, repo, username)
+            .await
+    }
+
+    pub async fn record_assignment(
+        &self,
+        chat_id: i64,
+        repo: &str,
+        pr_number: u64,
+        username: &str,
+        at: i64,
+    ) -> Result<()> {
+        let (owner, name) = repo.split_once('/').unwrap_or((repo, ""));
+        self.db
+            .record_assignment(chat_id, owner, name, pr_number as i64, username, at)
+            .await
+    }
+
+    pub async fn add_lead(&self, lead: &Lead) -> Result<()> {
+        self.db.add_lead(lead).await
+    }
+
+    pub async fn remove_lead(&self, chat_id: i64, user_id: i64) -> Result<bool> {
+        self.db.remove_lead(chat_id, user_id).await
+    }
+
+    pub async fn get_leads(&self, chat_id: i64) -> Result<Vec<Lead>> {
+        self.db.get_leads(chat_id).await
+    }
+
+    pub async fn get_all_leads(&self) -> Result<Vec<Lead>> {
+        self.db.get_all_leads().await
+    }
+
+    pub async fn save_filter(
+        &self,
+        chat_id: i64,
+        user_id: i64,
+        name: &str,
+        query: &str,
+    ) -> Result<()> {
+        self.db.save_filter(chat_id, user_id, name, query).await
+    }
+
+    pub async fn remove_filter(&self, chat_id: i64, user_id: i64, name: &str) -> Result<bool> {
+        self.db.remove_filter(chat_id, user_id, name).await
+    }
+
+    pub async fn get_filters(&self, chat_id: i64, user_id: i64) -> Result<Vec<(String, String)>> {
+        self.db.get_filters(chat_id, user_id).await
+    }
+
+    pub async fn add_rule(&self, chat_id: i64, rule: &str, created_by: &str) -> Result<()> {
+        self.db.add_rule(chat_id, rule, created_by).await
+    }
+
+    pub async fn get_rules(&self, chat_id: i64) -> Result<Vec<StoredRule>> {
+        self.db.get_rules(chat_id).await
+    }
+
+    pub async fn remove_rule(&self, chat_id: i64, id: i64) -> Result<bool> {
+        self.db.remove_rule(chat_id, id).await
+    }
+
+    pub async fn add_ticket_tracker(
+        &self,
+        chat_id: i64,
+        pattern: &str,
+        url_template: &str,
+    ) -> Result<()> {
+        self.db
+            .add_ticket_tracker(chat_id, pattern, url_template)
+            .await
+    }
+
+    pub async fn get_ticket_trackers(&self, chat_id: i64) -> Result<Vec<TicketTracker>> {
+        self.db.get_ticket_trackers(chat_id).await
+    }
+
+    pub async fn remove_ticket_tracker(&self, chat_id: i64, id: i64) -> Result<bool> {
+        self.db.remove_ticket_tracker(chat_id, id).await
+    }
+
+    pub async fn get_reply_templates(&self, chat_id: i64) -> Result<Vec<(String, String)>> {
+        self.db.get_reply_templates(chat_id).await
+    }
+
+    pub async fn set_reply_template(&self, chat_id: i64, name: &str, body: &str) -> Result<()> {
+        self.db.set_reply_template(chat_id, name, body).await
+    }
+
+    pub async fn remove_reply_template(&self, chat_id: i64, name: &str) -> Result<bool> {
+        self.db.remove_reply_template(chat_id, name).await
+    }
+
+    pub async fn add_search(&self, chat_id: i64, query: &str, created_by: &str) -> Result<()> {
+        self.db.add_search(chat_id, query, created_by).await
+    }
+
+    pub async fn get_searches(&self, chat_id: i64) -> Result<Vec<StoredSearch>> {
+        self.db.get_searches(chat_id).await
+    }
+
+    pub async fn get_all_searches(&self) -> Result<Vec<StoredSearch>> {
+        self.db.get_all_searches().await
+    }
+
+    pub async fn remove_search(&self, chat_id: i64, id: i64) -> Result<bool> {
+        self.db.remove_search(chat_id, id).await
+    }
+
+    pub async fn is_rule_hit(&self, rule_id: i64, chat_id: i64, message_id: &str) -> Result<bool> {
+        self.db.is_rule_hit(rule_id, chat_id, message_id).await
+    }
+
+    pub async fn set_rule_hit(
+        &self,
+        rule_id: i64,
+        chat_id: i64,
+        message_id: &str,
+        hit: bool,
+    ) -> Result<()> {
+        self.db
+            .set_rule_hit(rule_id, chat_id, message_id, hit)
+            .await
+    }
+
+    pub async fn save_group(&self, group: &PrGroup, items: &[GroupItem]) -> Result<()> {
+        self.db.save_group(group, items).await?;
+
+        for item in items {
+            let key = format!(
+                "{}:{}/{}#{}",
+                group.chat_id, item.repo_owner, item.repo_name, item.pr_number
+            );
+            self.db.mark_pr_seen(&key).await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn get_group(
+        &self,
+        message_id: &str,
+        chat_id: i64,
+    ) -> Result<Option<(PrGroup, Vec<GroupItem>)>> {
+        match self.db.get_group(message_id, chat_id).await? {
+            Some(group) => {
+                let items = self.db.get_group_items(message_id, chat_id).await?;
+                Ok(Some((group, items)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub async fn get_all_groups(&self) -> Result<Vec<PrGroup>> {
+        self.db.get_all_groups().await
+    }
+
+    pub async fn find_recent_group(
+        &self,
+        chat_id: i64,
+        author: &str,
+        since: i64,
+    ) -> Result<Option<PrGroup>> {
+        self.db.find_recent_group(chat_id, author, since).await
+    }
+
+    pub async fn hold_burst_pr(&self, pr: &BurstPr) -> Result<()> {
+        self.db.hold_burst_pr(pr).await
+    }
+
+    pub async fn get_burst_prs(&self) -> Result<Vec<BurstPr>> {
+        self.db.get_burst_prs().await
+    }
+
+    pub async fn release_burst_pr(&self, pr: &BurstPr) -> Result<bool> {
+        self.db.release_burst_pr(pr).await
+    }
+
+    pub async fn remove_group(&self, message_id: &str, chat_id: i64) -> Result<()> {
+        self.db.remove_group(message_id, chat_id).await
+    }
 }
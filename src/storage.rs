@@ -0,0 +1,203 @@
+use sqlx::migrate::Migrator;
+use std::borrow::Cow;
+
+// Each engine has its own copy of every migration; keep the two directories in step.
+static SQLITE_MIGRATIONS: Migrator = sqlx::migrate!("migrations/sqlite");
+static POSTGRES_MIGRATIONS: Migrator = sqlx::migrate!("migrations/postgres");
+
+/// A database engine `Db` can keep its data in, chosen by the `DATABASE_URL` scheme.
+///
+/// `Db` goes through sqlx's Any driver and writes each query once, with `?` placeholders and
+/// SQL both engines understand (`ON CONFLICT` upserts, `BIGINT` columns). What differs between
+/// the engines is implemented here, and in the per-engine `migrations/` directories.
+pub trait Storage: Send + Sync {
+    fn migrator(&self) -> &'static Migrator;
+
+    /// Tables only the operator's database has, created after the migrations ran.
+    fn operator_schema(&self) -> &'static str;
+
+    /// Rewrites a query's `?` placeholders into the form the engine expects.
+    fn sql<'a>(&self, query: &'a str) -> Cow<'a, str>;
+
+    /// Column declaration of an auto-incrementing primary key.
+    fn id_column(&self) -> &'static str;
+
+    /// Makes a connection refuse writes, for safe mode.
+    fn read_only_statement(&self) -> &'static str;
+
+    /// Counts the tables named like the single bound parameter.
+    fn table_count_query(&self) -> &'static str;
+
+    /// Counts the columns of `table` named like the single bound parameter.
+    fn column_count_query(&self, table: &str) -> String;
+}
+
+/// A local SQLite file, the default.
+pub struct Sqlite;
+
+/// A PostgreSQL server, for several instances or heavier concurrent use.
+pub struct Postgres;
+
+/// The engine behind `database_url`.
+pub fn for_url(database_url: &str) -> &'static dyn Storage {
+    if database_url.starts_with("postgres://") || database_url.starts_with("postgresql://") {
+        &Postgres
+    } else {
+        &Sqlite
+    }
+}
+
+impl Storage for Sqlite {
+    fn migrator(&self) -> &'static Migrator {
+        &SQLITE_MIGRATIONS
+    }
+
+    fn operator_schema(&self) -> &'static str {
+        include_str!("../migrations/operator/sqlite/github_tokens.sql")
+    }
+
+    fn sql<'a>(&self, query: &'a str) -> Cow<'a, str> {
+        Cow::Borrowed(query)
+    }
+
+    fn id_column(&self) -> &'static str {
+        "id INTEGER PRIMARY KEY AUTOINCREMENT"
+    }
+
+    fn read_only_statement(&self) -> &'static str {
+        "PRAGMA query_only = ON"
+    }
+
+    fn table_count_query(&self) -> &'static str {
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?"
+    }
+
+    fn column_count_query(&self, table: &str) -> String {
+        format!(
+            "SELECT COUNT(*) FROM pragma_table_info('{}') WHERE name = ?",
+            table
+        )
+    }
+}
+
+impl Storage for Postgres {
+    fn migrator(&self) -> &'static Migrator {
+        &POSTGRES_MIGRATIONS
+    }
+
+    fn operator_schema(&self) -> &'static str {
+        include_str!("../migrations/operator/postgres/github_tokens.sql")
+    }
+
+    fn sql<'a>(&self, query: &'a str) -> Cow<'a, str> {
+        Cow::Owned(numbered_placeholders(query))
+    }
+
+    fn id_column(&self) -> &'static str {
+        "id BIGSERIAL PRIMARY KEY"
+    }
+
+    fn read_only_statement(&self) -> &'static str {
+        "SET default_transaction_read_only = on"
+    }
+
+    // Only the search path's first schema: a same-named table elsewhere isn't ours
+    fn table_count_query(&self) -> &'static str {
+        "SELECT COUNT(*) FROM information_schema.tables
+         WHERE table_schema = current_schema() AND table_name = ?"
+    }
+
+    fn column_count_query(&self, table: &str) -> String {
+        format!(
+            "SELECT COUNT(*) FROM information_schema.columns
+             WHERE table_schema = current_schema() AND table_name = '{}' AND column_name = ?",
+            table
+        )
+    }
+}
+
+/// `query` with every `?` placeholder numbered as `$1`, `$2`, … in order. A `?` inside a quoted
+/// string or identifier is left alone.
+fn numbered_placeholders(query: &str) -> String {
+    let mut out = String::with_capacity(query.len() + 8);
+    let mut n = 0;
+    let mut quote = None;
+    for c in query.chars() {
+        match (c, quote) {
+            // A doubled quote inside a literal closes and reopens it, which comes out the same
+            ('\'' | '"', None) => quote = Some(c),
+            (c, Some(q)) if c == q => quote = None,
+            ('?', None) => {
+                n += 1;
+                out.push_str(&format!("${}", n));
+                continue;
+            }
+            _ => {}
+        }
+        out.push(c);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn placeholders_are_numbered_in_order() {
+        assert_eq!(
+            numbered_placeholders("SELECT a FROM t WHERE b = ? AND c = ? OR d = ?"),
+            "SELECT a FROM t WHERE b = $1 AND c = $2 OR d = $3"
+        );
+        assert_eq!(
+            numbered_placeholders("INSERT INTO t (a, b) VALUES (?,?)"),
+            "INSERT INTO t (a, b) VALUES ($1,$2)"
+        );
+        assert_eq!(numbered_placeholders("SELECT 1"), "SELECT 1");
+    }
+
+    #[test]
+    fn repeated_placeholders_get_their_own_numbers() {
+        assert_eq!(
+            numbered_placeholders("UPDATE t SET a = ? WHERE a = ? OR b = ?"),
+            "UPDATE t SET a = $1 WHERE a = $2 OR b = $3"
+        );
+    }
+
+    #[test]
+    fn quoted_question_marks_are_kept() {
+        assert_eq!(
+            numbered_placeholders("SELECT '?' FROM t WHERE a = ? AND b LIKE 'what?%' AND c = ?"),
+            "SELECT '?' FROM t WHERE a = $1 AND b LIKE 'what?%' AND c = $2"
+        );
+        assert_eq!(
+            numbered_placeholders("SELECT 'it''s ?', \"odd?\" FROM t WHERE a = ?"),
+            "SELECT 'it''s ?', \"odd?\" FROM t WHERE a = $1"
+        );
+        assert_eq!(
+            numbered_placeholders("SELECT '\"?' FROM t WHERE a = ?"),
+            "SELECT '\"?' FROM t WHERE a = $1"
+        );
+    }
+
+    #[test]
+    fn the_url_scheme_picks_the_engine() {
+        let query = "SELECT a FROM t WHERE b = ?";
+        for url in ["postgres://bot@db/prs", "postgresql://bot@db/prs"] {
+            assert_eq!(for_url(url).sql(query), "SELECT a FROM t WHERE b = $1");
+        }
+        for url in ["sqlite:prs.db?mode=rwc", "sqlite::memory:"] {
+            assert_eq!(for_url(url).sql(query), query);
+        }
+    }
+
+    #[test]
+    fn postgres_introspection_stays_in_the_current_schema() {
+        assert!(Postgres
+            .table_count_query()
+            .contains("table_schema = current_schema()"));
+        assert!(Postgres
+            .column_count_query("users")
+            .contains("table_schema = current_schema()"));
+    }
+}
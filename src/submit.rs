@@ -0,0 +1,85 @@
+use crate::github::GithubClient;
+use crate::monitor::Monitor;
+use crate::reactions::ReactionAction;
+use crate::state::PrData;
+use crate::tokens;
+use octocrab::models::pulls::ReviewAction;
+use teloxide::types::Message;
+use tracing::error;
+
+/// Submits the review `/approve` or `/changes` stands for on the card's PR, as the sender:
+/// with the personal access token they gave with `/token`, kept in the `operator`'s database
+/// whichever tenant serves the chat, so it's their review and not the bot's. The text after
+/// the command is its body. Nothing is submitted for senders without a token. The error is
+/// the reason to tell the sender.
+pub async fn review(
+    github: &GithubClient,
+    operator: &Monitor,
+    msg: &Message,
+    data: &PrData,
+    action: ReactionAction,
+    text: &str,
+) -> Result<(), String> {
+    let (event, default_body) = match action {
+        ReactionAction::Approve => (ReviewAction::Approve, ""),
+        // GitHub wants a reason with every change request
+        ReactionAction::RequestChanges => (ReviewAction::RequestChanges, "Changes requested."),
+        _ => return Ok(()),
+    };
+    let Some(user) = msg.from.as_ref() else {
+        return Ok(());
+    };
+    let Some(key) = operator.config().user_token_key else {
+        return Ok(());
+    };
+    let telegram_id = user.id.0 as i64;
+    let sealed = match operator.state.get_github_token(telegram_id).await {
+        Ok(Some((_, sealed))) => sealed,
+        Ok(None) => return Ok(()),
+        Err(e) => {
+            error!("Failed to load GitHub token: {}", e);
+            return Err("Couldn't look up your GitHub token, try again.".to_string());
+        }
+    };
+    let token = match tokens::open(&key, telegram_id, &sealed) {
+        Ok(token) => token,
+        // Sealed under a key the operator has since replaced
+        Err(e) => {
+            error!(
+                "Failed to decrypt the GitHub token of {}: {}",
+                telegram_id, e
+            );
+            return Err(
+                "Couldn't read your stored GitHub token; send me a new one with /token in a private message."
+                    .to_string(),
+            );
+        }
+    };
+    let Some((owner, repo)) = data.repo.split_once('/') else {
+        return Err("This card has no repository.".to_string());
+    };
+    let body = text
+        .split_once(char::is_whitespace)
+        .map_or("", |(_, rest)| rest.trim());
+    let body = if body.is_empty() { default_body } else { body };
+
+    github
+        .submit_review(&token, owner, repo, data.pr_number, event, body)
+        .await
+        .map_err(|e| describe_error(&e, &data.repo))
+}
+
+/// Why GitHub refused a review submitted with the user's own token.
+fn describe_error(e: &anyhow::Error, repo: &str) -> String {
+    match e.downcast_ref::<octocrab::Error>() {
+        Some(octocrab::Error::GitHub { source, .. }) => match source.status_code.as_u16() {
+            401 => {
+                "GitHub rejected your token; send me a new one with /token in a private message."
+                    .to_string()
+            }
+            403 | 404 => format!("Your GitHub token can't review PRs in {}.", repo),
+            _ => format!("GitHub refused the review: {}.", source.message),
+        },
+        _ => "Couldn't reach GitHub, try again later.".to_string(),
+    }
+}
@@ -0,0 +1,105 @@
+use crate::db::RepoCapabilities;
+use crate::github::{self, GithubClient};
+use crate::onboarding;
+use crate::state::{PrData, StateManager};
+use teloxide::types::Message;
+use tracing::error;
+
+/// Quick-reply templates for common review feedback, (name, default body). `/<name>` in reply
+/// to a card marks the sender as having commented and, once they linked their GitHub account,
+/// posts the body on the PR. Chats replace the bodies with `/templates`.
+pub const TEMPLATES: [(&str, &str); 3] = [
+    (
+        "nitpick",
+        "A few nitpicks below, none of them blocking. Take them or leave them.",
+    ),
+    (
+        "needs-tests",
+        "This needs tests covering the new behaviour before it can be merged.",
+    ),
+    (
+        "needs-docs",
+        "This needs documentation (user docs, README or doc comments) before it can be merged.",
+    ),
+];
+
+/// The template a reply command names, e.g. "needs-tests" for `/needs-tests`.
+pub fn command(text: &str) -> Option<&'static str> {
+    let command = text.split_whitespace().next()?.split('@').next()?;
+    find(command.strip_prefix('/')?)
+}
+
+/// The template of that name.
+pub fn find(name: &str) -> Option<&'static str> {
+    TEMPLATES.iter().map(|(n, _)| *n).find(|n| *n == name)
+}
+
+/// Every template with the chat's body, its own where it set one. An empty body posts nothing.
+pub async fn bodies(state: &StateManager, chat_id: i64) -> Vec<(&'static str, String)> {
+    let own = state.get_reply_templates(chat_id).await.unwrap_or_default();
+    TEMPLATES
+        .iter()
+        .map(|(name, default)| {
+            let body = own
+                .iter()
+                .find(|(n, _)| n == name)
+                .map_or(default.to_string(), |(_, body)| body.clone());
+            (*name, body)
+        })
+        .collect()
+}
+
+/// Posts the chat's body of `template` on the card's PR, signed with the sender's GitHub login.
+/// Nothing is posted for senders without a linked account (`/link`) or when the chat emptied
+/// the body. The error is the reason to tell the sender.
+pub async fn post(
+    github: &GithubClient,
+    state: &StateManager,
+    msg: &Message,
+    data: &PrData,
+    template: &str,
+) -> Result<(), String> {
+    let Some(user) = msg.from.as_ref() else {
+        return Ok(());
+    };
+    let link = match state.get_user_link(user.id.0 as i64).await {
+        Ok(Some(link)) => link,
+        Ok(None) => return Ok(()),
+        Err(e) => {
+            error!("Failed to load user link: {}", e);
+            return Err("Couldn't look up your GitHub account, try again.".to_string());
+        }
+    };
+    let body = bodies(state, msg.chat.id.0)
+        .await
+        .into_iter()
+        .find(|(name, _)| *name == template)
+        .map(|(_, body)| body)
+        .unwrap_or_default();
+    if body.is_empty() {
+        return Ok(());
+    }
+    let Some((owner, repo)) = data.repo.split_once('/') else {
+        return Err("This card has no repository.".to_string());
+    };
+    // Commenting takes the same issues write access as changing labels
+    if RepoCapabilities::denies(onboarding::of(state, owner, repo).await.write_labels) {
+        return Err(format!(
+            "The bot's GitHub access can't comment in {}; use /comment instead.",
+            data.repo
+        ));
+    }
+    let comment = format!(
+        "{}\n\n<sub>— {} via Telegram</sub>",
+        body, link.github_login
+    );
+    github
+        .post_comment(owner, repo, data.pr_number, &comment)
+        .await
+        .map_err(|e| {
+            format!(
+                "Couldn't comment on GitHub: {}.",
+                github::describe_error(&e)
+            )
+        })
+}
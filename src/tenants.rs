@@ -0,0 +1,256 @@
+use crate::db::UserLink;
+use crate::handlers;
+use crate::monitor::Monitor;
+use crate::sender::Retried;
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use teloxide::prelude::*;
+use teloxide::types::{ReplyParameters, User};
+use tracing::{error, info, warn};
+
+/// The configurations one process serves: the operator's, and isolated tenants with their own
+/// GitHub access, database, repositories, chats and settings. Each Telegram update goes to the
+/// tenant its chat is bound to, or to the operator's when it isn't bound. Bindings live in the
+/// operator's database.
+pub struct Tenants {
+    operator: Monitor,
+    tenants: BTreeMap<String, Monitor>,
+}
+
+impl Tenants {
+    pub fn new(operator: Monitor, tenants: BTreeMap<String, Monitor>) -> Self {
+        Self { operator, tenants }
+    }
+
+    /// The operator's configuration, whose database also keeps what belongs to a Telegram user
+    /// rather than to a chat: their `/token`.
+    pub fn operator(&self) -> &Monitor {
+        &self.operator
+    }
+
+    /// The operator's configuration and every tenant's.
+    pub fn all(&self) -> impl Iterator<Item = &Monitor> {
+        std::iter::once(&self.operator).chain(self.tenants.values())
+    }
+
+    /// The configuration serving the chat.
+    pub async fn for_chat(&self, chat_id: i64) -> &Monitor {
+        match self.operator.state.get_chat_tenant(chat_id).await {
+            Ok(Some(id)) => self.tenants.get(&id).unwrap_or(&self.operator),
+            Ok(None) => &self.operator,
+            // Every chat starts out with the operator's
+            Err(e) => {
+                error!("Failed to look up the tenant of {}: {}", chat_id, e);
+                &self.operator
+            }
+        }
+    }
+
+    /// Binds the chats a tenant's configuration names to it, unless they're bound already.
+    pub async fn bind_configured_chats(&self) {
+        for (id, monitor) in &self.tenants {
+            let config = monitor.config();
+            let mut chats = vec![config.chat_id];
+            chats.extend(config.repo_options.iter().flat_map(|o| o.chats.clone()));
+            chats.extend(config.mirror_chats.iter().flat_map(|(s, m)| [*s, *m]));
+            chats.sort();
+            chats.dedup();
+            for chat_id in chats {
+                match self.operator.state.get_chat_tenant(chat_id).await {
+                    Ok(None) => {
+                        info!("Binding chat {} to tenant {}", chat_id, id);
+                        if let Err(e) = self.operator.state.set_chat_tenant(chat_id, Some(id)).await
+                        {
+                            error!("Failed to bind chat {} to tenant {}: {}", chat_id, id, e);
+                        }
+                    }
+                    Ok(Some(bound)) if bound != *id => warn!(
+                        "Tenant {} names chat {}, which belongs to tenant {}",
+                        id, chat_id, bound
+                    ),
+                    Ok(Some(_)) => {}
+                    Err(e) => error!("Failed to look up the tenant of {}: {}", chat_id, e),
+                }
+            }
+        }
+    }
+
+    /// Every configuration that posted a verification code, with the chat waiting for it. A
+    /// private chat is routed to one configuration, but the code may come from any tenant's
+    /// database, and codes are only unique within one.
+    pub async fn find_verification_code(&self, code: &str) -> Result<Vec<(&Monitor, i64)>> {
+        let mut found = Vec::new();
+        let mut failed = None;
+        for monitor in self.all() {
+            match monitor.state.find_chat_by_code(code).await {
+                Ok(Some(chat_id)) => found.push((monitor, chat_id)),
+                Ok(None) => {}
+                // The code may still be in another tenant's database
+                Err(e) => failed = Some(e),
+            }
+        }
+        match failed {
+            Some(e) if found.is_empty() => Err(e),
+            _ => Ok(found),
+        }
+    }
+
+    /// The user's `/link`s in the operator's and every tenant's database.
+    pub async fn user_links(&self, telegram_id: i64) -> Result<Vec<UserLink>> {
+        let mut links = Vec::new();
+        for monitor in self.all() {
+            links.extend(monitor.state.get_user_link(telegram_id).await?);
+        }
+        Ok(links)
+    }
+
+    /// Forgets the user's `/token` once it's for none of the GitHub accounts they're linked to,
+    /// after `/unlink`, `/forgetme` or linking to another account.
+    pub async fn forget_unlinked_token(&self, telegram_id: i64) {
+        let state = &self.operator.state;
+        let login = match state.get_github_token(telegram_id).await {
+            Ok(Some((login, _))) => login,
+            Ok(None) => return,
+            Err(e) => {
+                error!("Failed to load GitHub token: {}", e);
+                return;
+            }
+        };
+        match self.user_links(telegram_id).await {
+            Ok(links)
+                if links
+                    .iter()
+                    .any(|l| l.github_login.eq_ignore_ascii_case(&login)) => {}
+            Ok(_) => match state.remove_github_token(telegram_id).await {
+                Ok(_) => state.audit(format!("forgot the GitHub token of {}", login)),
+                Err(e) => error!("Failed to remove GitHub token: {}", e),
+            },
+            Err(e) => error!("Failed to load user links: {}", e),
+        }
+    }
+
+    /// Whether the user is on `ADMIN_USER_IDS` of the tenant, or of the operator for `None`.
+    fn is_admin_of(&self, tenant: Option<&str>, user: &User) -> bool {
+        let monitor = match tenant {
+            Some(id) => match self.tenants.get(id) {
+                Some(monitor) => monitor,
+                None => return false,
+            },
+            None => &self.operator,
+        };
+        monitor
+            .config()
+            .admin_user_ids
+            .contains(&(user.id.0 as i64))
+    }
+}
+
+/// The configuration an update is handled with, injected in place of the operator's.
+pub async fn route(update: Update, tenants: Arc<Tenants>) -> Monitor {
+    match update.chat() {
+        Some(chat) => tenants.for_chat(chat.id.0).await.clone(),
+        None => tenants.operator.clone(),
+    }
+}
+
+/// `/tenant` is handled before routing, since it changes where the chat is routed.
+pub fn is_command(msg: Message) -> bool {
+    msg.text()
+        .and_then(|text| text.split_whitespace().next())
+        .is_some_and(|command| command == "/tenant" || command.starts_with("/tenant@"))
+}
+
+/// `/tenant` shows which tenant serves the chat, `/tenant <id>` binds it to a tenant and
+/// `/tenant leave` hands it back to the operator. Binding takes an admin of the tenant (on its
+/// `ADMIN_USER_IDS`) who is also a chat admin; taking a chat from another tenant also takes one
+/// of that tenant's admins. The operator's `ADMIN_USER_IDS` may do either.
+pub async fn handle_command(bot: Bot, msg: Message, tenants: Arc<Tenants>) -> ResponseResult<()> {
+    let ttl = tenants.operator.config().error_reply_ttl_secs;
+    let text = msg.text().unwrap_or_default();
+    let chat_id = msg.chat.id.0;
+    let current = match tenants.operator.state.get_chat_tenant(chat_id).await {
+        Ok(current) => current,
+        Err(e) => {
+            error!("Failed to look up the tenant of {}: {}", chat_id, e);
+            return handlers::reply_error(
+                &bot,
+                &msg,
+                ttl,
+                "Something went wrong, try again later.",
+            )
+            .await;
+        }
+    };
+
+    let Some(arg) = text.split_whitespace().nth(1) else {
+        let reply = match &current {
+            Some(id) => format!("🏢 This chat belongs to tenant {}.", id),
+            None => "🏢 This chat is served by the main configuration.".to_string(),
+        };
+        bot.send_message(msg.chat.id, reply)
+            .reply_parameters(ReplyParameters::new(msg.id))
+            .retried()
+            .await?;
+        return Ok(());
+    };
+    let target = (arg != "leave").then_some(arg);
+    if current.as_deref() == target {
+        return handlers::reply_error(&bot, &msg, ttl, "This chat is already served there.").await;
+    }
+    if target.is_some_and(|id| !tenants.tenants.contains_key(id)) {
+        let reason = format!("There's no tenant {}.", arg);
+        return handlers::reply_error(&bot, &msg, ttl, reason).await;
+    }
+
+    let allowed = match msg.from.as_ref() {
+        Some(user) if tenants.is_admin_of(None, user) => true,
+        Some(user) => {
+            // Both the tenant giving the chat up and the one taking it over must agree
+            [current.as_deref(), target]
+                .into_iter()
+                .flatten()
+                .all(|id| tenants.is_admin_of(Some(id), user))
+                && handlers::is_admin(&bot, &msg).await
+        }
+        None => false,
+    };
+    if !allowed {
+        return handlers::reply_error(
+            &bot,
+            &msg,
+            ttl,
+            "Sorry, only the tenant's administrators can move this chat, and only if they're admins here too.",
+        )
+        .await;
+    }
+
+    if let Err(e) = tenants
+        .operator
+        .state
+        .set_chat_tenant(chat_id, target)
+        .await
+    {
+        error!(
+            "Failed to bind chat {} to tenant {:?}: {}",
+            chat_id, target, e
+        );
+        return handlers::reply_error(&bot, &msg, ttl, "Couldn't move this chat, try again later.")
+            .await;
+    }
+    tenants.operator.state.audit(format!(
+        "moved chat {} from {} to {}",
+        chat_id,
+        current.as_deref().unwrap_or("the main configuration"),
+        target.unwrap_or("the main configuration")
+    ));
+    let reply = match target {
+        Some(id) => format!(
+            "🏢 This chat now belongs to tenant {}: its repositories, cards and settings are {}'s from here on. Cards posted before stay with the previous configuration.",
+            id, id
+        ),
+        None => "🏢 This chat is served by the main configuration again.".to_string(),
+    };
+    bot.send_message(msg.chat.id, reply).retried().await?;
+    Ok(())
+}
@@ -0,0 +1,54 @@
+use crate::db::TicketTracker;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// Tickets linked per card; a release PR listing dozens would crowd it.
+const CARD_LIMIT: usize = 5;
+
+/// A ticket id found in a PR's title or description, e.g. "JIRA-1234", and where it's tracked.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Ticket {
+    pub id: String,
+    pub url: String,
+}
+
+/// Checks a `/tickets add` pattern and URL template before they're saved.
+pub fn validate(pattern: &str, url_template: &str) -> Result<(), String> {
+    Regex::new(pattern).map_err(|e| format!("the pattern isn't a valid regex: {}", e))?;
+    if !url_template.starts_with("https://") && !url_template.starts_with("http://") {
+        return Err("the URL must start with https:// or http://".to_string());
+    }
+    if !url_template.contains("{id}") {
+        return Err("the URL needs an {id} placeholder for the ticket id".to_string());
+    }
+    Ok(())
+}
+
+/// The tickets the chat's trackers find in `texts`, by tracker and then in order of mention,
+/// each once. A pattern with a capture group fills `{id}` with the group, so `GH-(\d+)` can link
+/// "GH-12" to `.../issues/12`.
+pub fn find(trackers: &[TicketTracker], texts: &[&str]) -> Vec<Ticket> {
+    let mut tickets: Vec<Ticket> = Vec::new();
+    for tracker in trackers {
+        let Ok(pattern) = Regex::new(&tracker.pattern) else {
+            continue;
+        };
+        for text in texts {
+            for found in pattern.captures_iter(text) {
+                let id = &found[0];
+                if id.is_empty() || tickets.iter().any(|t| t.id == id) {
+                    continue;
+                }
+                let key = found.get(1).map_or(id, |group| group.as_str());
+                tickets.push(Ticket {
+                    id: id.to_string(),
+                    url: tracker.url_template.replace("{id}", key),
+                });
+                if tickets.len() == CARD_LIMIT {
+                    return tickets;
+                }
+            }
+        }
+    }
+    tickets
+}
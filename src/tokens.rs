@@ -0,0 +1,93 @@
+use anyhow::{anyhow, bail, Result};
+use openssl::symm::{decrypt_aead, encrypt_aead, Cipher};
+
+/// Stored tokens are `v1:<nonce>:<ciphertext><tag>` in hex, AES-256-GCM under
+/// `GITHUB_USER_TOKEN_KEY`.
+const VERSION: &str = "v1";
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+
+/// Encrypts a user's personal access token for the database. The Telegram id is authenticated
+/// with it, so a token copied to another user's row doesn't decrypt.
+pub fn seal(key: &[u8; 32], telegram_id: i64, token: &str) -> Result<String> {
+    let mut nonce = [0u8; NONCE_LEN];
+    openssl::rand::rand_bytes(&mut nonce)?;
+    let mut tag = [0u8; TAG_LEN];
+    let mut sealed = encrypt_aead(
+        Cipher::aes_256_gcm(),
+        key,
+        Some(&nonce),
+        &telegram_id.to_be_bytes(),
+        token.as_bytes(),
+        &mut tag,
+    )?;
+    sealed.extend_from_slice(&tag);
+    Ok(format!(
+        "{}:{}:{}",
+        VERSION,
+        hex::encode(nonce),
+        hex::encode(sealed)
+    ))
+}
+
+/// The token `seal` stored for the user. Fails for another key or user, or a tampered value.
+pub fn open(key: &[u8; 32], telegram_id: i64, stored: &str) -> Result<String> {
+    let mut parts = stored.splitn(3, ':');
+    let (Some(VERSION), Some(nonce), Some(sealed)) = (parts.next(), parts.next(), parts.next())
+    else {
+        bail!("not a sealed token");
+    };
+    let nonce = hex::decode(nonce)?;
+    let sealed = hex::decode(sealed)?;
+    if sealed.len() < TAG_LEN {
+        bail!("sealed token is truncated");
+    }
+    let (ciphertext, tag) = sealed.split_at(sealed.len() - TAG_LEN);
+    let token = decrypt_aead(
+        Cipher::aes_256_gcm(),
+        key,
+        Some(&nonce),
+        &telegram_id.to_be_bytes(),
+        ciphertext,
+        tag,
+    )
+    .map_err(|_| anyhow!("the token doesn't decrypt with GITHUB_USER_TOKEN_KEY"))?;
+    Ok(String::from_utf8(token)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: [u8; 32] = [7; 32];
+    const TOKEN: &str = "github_pat_11ABCDEFG0123456789";
+
+    #[test]
+    fn sealed_tokens_open_again() {
+        let sealed = seal(&KEY, 42, TOKEN).unwrap();
+        assert!(sealed.starts_with("v1:"));
+        assert!(!sealed.contains(TOKEN));
+        assert_eq!(open(&KEY, 42, &sealed).unwrap(), TOKEN);
+        // Every seal has its own nonce
+        assert_ne!(seal(&KEY, 42, TOKEN).unwrap(), sealed);
+    }
+
+    #[test]
+    fn tokens_only_open_for_their_key_and_user() {
+        let sealed = seal(&KEY, 42, TOKEN).unwrap();
+        assert!(open(&[8; 32], 42, &sealed).is_err());
+        assert!(open(&KEY, 43, &sealed).is_err());
+    }
+
+    #[test]
+    fn tampered_or_plain_values_are_rejected() {
+        let sealed = seal(&KEY, 42, TOKEN).unwrap();
+        let mut flipped = sealed.clone().into_bytes();
+        let last = flipped.len() - 1;
+        flipped[last] = if flipped[last] == b'0' { b'1' } else { b'0' };
+        assert!(open(&KEY, 42, std::str::from_utf8(&flipped).unwrap()).is_err());
+        assert!(open(&KEY, 42, &sealed[..sealed.len() - 40]).is_err());
+        assert!(open(&KEY, 42, TOKEN).is_err());
+        assert!(open(&KEY, 42, "v1:00:00").is_err());
+    }
+}
@@ -0,0 +1,47 @@
+use crate::metrics::Timed;
+use crate::state::StateManager;
+use std::sync::Arc;
+use teloxide::prelude::*;
+use tracing::{error, info};
+
+/// Seconds handled updates are remembered. Telegram keeps undelivered updates for 24 hours,
+/// so none older can come again.
+pub const RETENTION_SECS: i64 = 2 * 24 * 60 * 60;
+
+/// Lets an update through once: a redelivered one, after a restart or a retried webhook
+/// delivery, is dropped before it can mark a card or send a reply twice.
+pub async fn claim(update: Update, state: Arc<StateManager>) -> bool {
+    // Nothing a safe mode reply does would be done twice
+    if state.safe_mode().is_some() {
+        return true;
+    }
+    match state.claim_update(update.id.0 as i64).await {
+        Ok(claimed) => claimed,
+        // Handling twice beats dropping the update
+        Err(e) => {
+            error!("Failed to record update {}: {}", update.id.0, e);
+            true
+        }
+    }
+}
+
+/// Confirms to Telegram every update handled before the last restart. Long polling only
+/// confirms a batch with the next request, so updates handled right before a stop would
+/// otherwise come again.
+pub async fn resume_polling(bot: &Bot, state: &StateManager) {
+    let Some(last) = state.get_last_update_id().await.ok().flatten() else {
+        return;
+    };
+    // Asking from past the last handled update confirms everything before it
+    match bot
+        .get_updates()
+        .offset(last as i32 + 1)
+        .limit(1)
+        .timeout(0)
+        .timed()
+        .await
+    {
+        Ok(_) => info!("Resuming long polling after update {}", last),
+        Err(e) => error!("Failed to resume long polling after update {}: {}", last, e),
+    }
+}
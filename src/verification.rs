@@ -0,0 +1,112 @@
+use crate::monitor::Monitor;
+use crate::sender::Retried;
+use std::collections::BTreeSet;
+use std::hash::{BuildHasher, Hasher};
+use teloxide::prelude::*;
+use tracing::{error, info, warn};
+
+/// Asks every chat routed from the config that hasn't been verified yet to confirm it's the
+/// intended one, by posting a code there. Chats whose code is in `CHAT_VERIFICATION_CODES`
+/// are verified instead. Announcements skip unverified chats.
+pub async fn request_pending(monitor: &Monitor) {
+    let mut chats = BTreeSet::new();
+    for (_, subscribers) in monitor.state.get_subscriptions().await.unwrap_or_default() {
+        chats.extend(subscribers);
+    }
+    let confirmed = &monitor.config().chat_verification_codes;
+
+    for chat_id in chats {
+        if monitor
+            .state
+            .is_chat_verified(chat_id)
+            .await
+            .unwrap_or(true)
+        {
+            continue;
+        }
+        let code = monitor
+            .state
+            .get_verification_code(chat_id)
+            .await
+            .ok()
+            .flatten();
+        match code {
+            Some(code) if confirmed.contains(&code) => {
+                confirm(monitor, chat_id, "the configuration").await;
+            }
+            // Already asked; posting again on every reload would only spam the chat
+            Some(_) => {}
+            None => ask(monitor, chat_id).await,
+        }
+    }
+}
+
+async fn ask(monitor: &Monitor, chat_id: i64) {
+    let code = new_code();
+    if let Err(e) = monitor.state.set_verification_code(chat_id, &code).await {
+        error!(
+            "Failed to store the verification code of {}: {}",
+            chat_id, e
+        );
+        return;
+    }
+    let text = format!(
+        "🔐 This chat was set up to receive pull request announcements. To confirm it's the right chat, \
+         a chat admin can send me /verify {} in a private message, or the bot's operator can add {} \
+         to CHAT_VERIFICATION_CODES. Nothing is announced here until then.",
+        code, code
+    );
+    match monitor.bot.send_message(ChatId(chat_id), text).retried().await {
+        Ok(_) => {
+            info!("Asked {} to verify with code {}", chat_id, code);
+            monitor
+                .state
+                .audit(format!("notified {}: verification code", chat_id));
+        }
+        // A mistyped id usually isn't a chat the bot is in
+        Err(e) => warn!(
+            "Can't post the verification code to {}, check that the chat id is right and the bot is a member: {}",
+            chat_id, e
+        ),
+    }
+}
+
+/// Marks the chat verified and tells it announcements start now.
+pub async fn confirm(monitor: &Monitor, chat_id: i64, by: &str) {
+    match monitor.state.set_chat_verified(chat_id).await {
+        Ok(true) => {}
+        Ok(false) => return,
+        Err(e) => {
+            error!("Failed to verify {}: {}", chat_id, e);
+            return;
+        }
+    }
+    info!("Chat {} verified by {}", chat_id, by);
+    monitor
+        .state
+        .audit(format!("verified {} (confirmed by {})", chat_id, by));
+    monitor
+        .bot
+        .send_message(
+            ChatId(chat_id),
+            format!(
+                "✅ This chat was confirmed by {}. New pull requests will be announced here.",
+                by
+            ),
+        )
+        .retried()
+        .await
+        .ok();
+}
+
+/// A six-digit code. `RandomState` is seeded randomly, so codes can't be guessed from the chat.
+fn new_code() -> String {
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u128(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos(),
+    );
+    format!("{:06}", hasher.finish() % 1_000_000)
+}
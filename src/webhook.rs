@@ -0,0 +1,225 @@
+use crate::monitor::Monitor;
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::routing::post;
+use axum::Router;
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tracing::{error, info, warn};
+
+#[derive(Deserialize)]
+struct Owner {
+    login: String,
+}
+
+#[derive(Deserialize)]
+struct Repository {
+    name: String,
+    owner: Owner,
+}
+
+#[derive(Deserialize)]
+struct PullRequestRef {
+    number: u64,
+}
+
+/// The subset of a `pull_request` / `pull_request_review` / `push` / `check_run` / `status`
+/// payload we act on.
+#[derive(Deserialize)]
+struct Delivery {
+    action: Option<String>,
+    repository: Option<Repository>,
+    pull_request: Option<PullRequestRef>,
+}
+
+/// Deliveries synced at once. Beyond that GitHub gets a 503, and the next poll catches up.
+const MAX_CONCURRENT_DELIVERIES: usize = 32;
+
+struct WebhookState {
+    monitor: Monitor,
+    secret: String,
+    in_flight: Arc<Semaphore>,
+}
+
+/// Router for the GitHub webhook endpoint at `POST /github/webhook`. Deliveries without a
+/// valid signature made with `secret` are rejected.
+pub fn router(secret: String, monitor: Monitor) -> Router {
+    Router::new()
+        .route("/github/webhook", post(handle_delivery))
+        .with_state(Arc::new(WebhookState {
+            monitor,
+            secret,
+            in_flight: Arc::new(Semaphore::new(MAX_CONCURRENT_DELIVERIES)),
+        }))
+}
+
+fn verify_signature(secret: &str, headers: &HeaderMap, body: &[u8]) -> bool {
+    let Some(signature) = headers
+        .get("x-hub-signature-256")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("sha256="))
+        .and_then(|v| hex::decode(v).ok())
+    else {
+        return false;
+    };
+
+    let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(secret.as_bytes()) else {
+        return false;
+    };
+    mac.update(body);
+    mac.verify_slice(&signature).is_ok()
+}
+
+async fn handle_delivery(
+    State(ctx): State<Arc<WebhookState>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> StatusCode {
+    if !verify_signature(&ctx.secret, &headers, &body) {
+        warn!("Rejected webhook delivery with invalid signature");
+        return StatusCode::UNAUTHORIZED;
+    }
+
+    let event = headers
+        .get("x-github-event")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+        .to_string();
+
+    if event == "ping" {
+        return StatusCode::OK;
+    }
+
+    let delivery: Delivery = match serde_json::from_slice(&body) {
+        Ok(d) => d,
+        Err(e) => {
+            error!("Failed to parse {} webhook payload: {}", event, e);
+            return StatusCode::BAD_REQUEST;
+        }
+    };
+
+    let Some(repository) = delivery.repository else {
+        return StatusCode::ACCEPTED;
+    };
+    let owner = repository.owner.login;
+    let repo = repository.name;
+
+    let Ok(permit) = ctx.in_flight.clone().try_acquire_owned() else {
+        warn!(
+            "Dropped {} webhook for {}/{}: too many deliveries in flight",
+            event, owner, repo
+        );
+        return StatusCode::SERVICE_UNAVAILABLE;
+    };
+
+    // Answer GitHub right away; syncing talks to both GitHub and Telegram.
+    let monitor = ctx.monitor.clone();
+    tokio::spawn(async move {
+        let _permit = permit;
+        match (event.as_str(), delivery.pull_request) {
+            ("pull_request", Some(pr)) => {
+                let action = delivery.action.unwrap_or_default();
+                info!(
+                    "Webhook: pull_request {} for {}/{}#{}",
+                    action, owner, repo, pr.number
+                );
+                if action == "opened" || action == "reopened" {
+                    announce_if_new(&monitor, &owner, &repo, pr.number).await;
+                }
+                if action == "ready_for_review" {
+                    for (chat_id, pr) in monitor
+                        .release_ready_drafts(Some((&owner, &repo, pr.number)))
+                        .await
+                    {
+                        monitor.announce_new_prs(chat_id, vec![pr]).await;
+                    }
+                }
+                monitor.sync_pr(&owner, &repo, pr.number).await;
+            }
+            ("pull_request_review" | "pull_request_review_thread", Some(pr)) => {
+                info!("Webhook: {} for {}/{}#{}", event, owner, repo, pr.number);
+                monitor.sync_pr(&owner, &repo, pr.number).await;
+            }
+            ("check_run", _) if delivery.action.as_deref() != Some("completed") => {}
+            ("push" | "check_run" | "status", _) => {
+                // These don't reliably name the PR, so refresh every tracked PR of the repo.
+                info!("Webhook: {} for {}/{}", event, owner, repo);
+                match monitor
+                    .state
+                    .get_active_messages_for_repo(&owner, &repo)
+                    .await
+                {
+                    Ok(msgs) => {
+                        for msg in msgs {
+                            monitor.sync_message(msg).await;
+                        }
+                    }
+                    Err(e) => error!(
+                        "Failed to load tracked messages for {}/{}: {}",
+                        owner, repo, e
+                    ),
+                }
+            }
+            _ => {}
+        }
+    });
+
+    StatusCode::ACCEPTED
+}
+
+async fn announce_if_new(monitor: &Monitor, owner: &str, repo: &str, pr_number: u64) {
+    if monitor.is_ignored(owner, repo) {
+        return;
+    }
+    let mut chats = Vec::new();
+    for chat_id in monitor
+        .state
+        .get_subscribers(owner, repo)
+        .await
+        .unwrap_or_default()
+    {
+        if !monitor
+            .state
+            .is_pr_seen(chat_id, owner, repo, pr_number)
+            .await
+            .unwrap_or(false)
+        {
+            chats.push(chat_id);
+        }
+    }
+    if chats.is_empty() {
+        return;
+    }
+
+    match monitor.github.get_pr_details(owner, repo, pr_number).await {
+        Ok(pr) => {
+            if !monitor.pr_filter(owner, repo).allows(&pr) {
+                return;
+            }
+            if let Some(author) = &pr.user {
+                if monitor.skips_author(owner, repo, &author.login) {
+                    return;
+                }
+            }
+            if monitor.holds_draft(&pr) {
+                for chat_id in chats {
+                    monitor.hold_draft(chat_id, owner, repo, pr_number).await;
+                }
+                return;
+            }
+            for chat_id in chats {
+                monitor
+                    .announce_new_prs(
+                        chat_id,
+                        vec![(owner.to_string(), repo.to_string(), pr.clone())],
+                    )
+                    .await
+            }
+        }
+        Err(e) => error!("Failed to fetch PR {}/{}#{}: {}", owner, repo, pr_number, e),
+    }
+}